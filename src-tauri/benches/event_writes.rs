@@ -0,0 +1,172 @@
+//! Events/sec for persisting streamed session events, comparing the old path, where every event
+//! was written with its own transactions on the one shared connection, against queuing them on
+//! the writer thread.
+//!
+//! Run with `cargo bench --bench event_writes`; `LULU_BENCH_EVENTS` and `LULU_BENCH_SESSIONS`
+//! change the load.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rusqlite::{params, Connection, TransactionBehavior};
+use tauri_app_lib::db::{init_database, Database, QueuedSessionEvent, Session};
+
+fn env_or(name: &str, default: u64) -> u64 {
+    std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+}
+
+fn setup(dir: &std::path::Path, sessions: u64) -> Arc<Database> {
+    let db = init_database(&dir.join("lulu.db")).expect("database should initialize");
+    for index in 0..sessions {
+        let now = chrono::Utc::now().to_rfc3339();
+        db.create_session(&Session {
+            id: format!("session-{}", index),
+            name: format!("session {}", index),
+            status: "running".to_string(),
+            working_dir: "/tmp".to_string(),
+            created_at: now.clone(),
+            updated_at: now,
+        })
+        .expect("session should persist");
+    }
+    Arc::new(db)
+}
+
+fn event(session_id: &str, seq: u64) -> QueuedSessionEvent {
+    let content = format!("line {} of streamed assistant output", seq);
+    let payload = serde_json::json!({ "type": "message", "data": { "content": content } });
+    QueuedSessionEvent {
+        session_id: session_id.to_string(),
+        run_id: "run-1".to_string(),
+        seq,
+        event_type: "message".to_string(),
+        payload_json: payload.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        activity: true,
+        assistant_message: Some(content),
+    }
+}
+
+/// The connection every write used to go through, opened the way `init_database` opened it.
+fn open_shared_connection(dir: &std::path::Path) -> Arc<Mutex<Connection>> {
+    let conn = Connection::open(dir.join("lulu.db")).expect("connection should open");
+    conn.execute_batch(
+        "PRAGMA journal_mode=WAL;
+         PRAGMA synchronous=NORMAL;
+         PRAGMA busy_timeout=5000;
+         PRAGMA foreign_keys=ON;",
+    )
+    .expect("pragmas should apply");
+    Arc::new(Mutex::new(conn))
+}
+
+/// Runs one statement in its own immediate transaction, as each of the old helpers did.
+fn execute_alone(conn: &Mutex<Connection>, sql: &str, params: impl rusqlite::Params) {
+    let mut conn = conn.lock().expect("connection lock");
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .expect("transaction should begin");
+    tx.execute(sql, params).expect("statement should run");
+    tx.commit().expect("transaction should commit");
+}
+
+/// What the event loop used to do per message: four writes, each committed on its own.
+fn write_each(conn: &Mutex<Connection>, event: QueuedSessionEvent) {
+    execute_alone(
+        conn,
+        "INSERT INTO session_events (id, session_id, run_id, seq, event_type, payload_json, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(session_id, run_id, seq) DO NOTHING",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            event.session_id,
+            event.run_id,
+            event.seq as i64,
+            event.event_type,
+            event.payload_json,
+            event.timestamp,
+        ],
+    );
+    execute_alone(
+        conn,
+        "UPDATE sessions
+         SET restored = 0,
+             restored_at = NULL,
+             recovery_hint = 0,
+             updated_at = ?1
+         WHERE id = ?2 AND (restored = 1 OR recovery_hint = 1)",
+        params![chrono::Utc::now().to_rfc3339(), event.session_id],
+    );
+    execute_alone(
+        conn,
+        "UPDATE sessions SET last_activity_at = ?1, updated_at = ?2 WHERE id = ?3",
+        params![event.timestamp, chrono::Utc::now().to_rfc3339(), event.session_id],
+    );
+    execute_alone(
+        conn,
+        "INSERT INTO messages (id, session_id, role, content, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            event.session_id,
+            "assistant",
+            event.assistant_message.unwrap_or_default(),
+            event.timestamp,
+        ],
+    );
+}
+
+/// Streams `events` per session from one thread per session and returns the elapsed time once
+/// everything is committed.
+fn run(sessions: u64, events: u64, queued: bool) -> Duration {
+    let dir = tempfile::tempdir().expect("tempdir should be created");
+    let db = setup(dir.path(), sessions);
+    let shared = open_shared_connection(dir.path());
+
+    let started = Instant::now();
+    let writers: Vec<_> = (0..sessions)
+        .map(|index| {
+            let db = db.clone();
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                let session_id = format!("session-{}", index);
+                for seq in 1..=events {
+                    if queued {
+                        db.queue_session_event(event(&session_id, seq))
+                            .expect("event should queue");
+                    } else {
+                        write_each(&shared, event(&session_id, seq));
+                    }
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().expect("writer thread should finish");
+    }
+    db.flush_writes().expect("flush should succeed");
+    let elapsed = started.elapsed();
+
+    let persisted: usize = (0..sessions)
+        .map(|index| db.list_session_history(&format!("session-{}", index)).expect("history").len())
+        .sum();
+    assert_eq!(persisted as u64, sessions * events, "every event should be persisted");
+    elapsed
+}
+
+fn main() {
+    let sessions = env_or("LULU_BENCH_SESSIONS", 4);
+    let events = env_or("LULU_BENCH_EVENTS", 2_000);
+    let total = (sessions * events) as f64;
+
+    println!("{} sessions x {} message events", sessions, events);
+    for (label, queued) in [("per-event transactions", false), ("writer thread", true)] {
+        let elapsed = run(sessions, events, queued);
+        println!(
+            "{:<24} {:>10.0} events/sec ({:.2?})",
+            label,
+            total / elapsed.as_secs_f64(),
+            elapsed
+        );
+    }
+}
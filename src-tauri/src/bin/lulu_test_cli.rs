@@ -30,16 +30,53 @@ fn main() {
         r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"tool-1","content":{"ok":true}}]}}"#
     );
 
+    let turns = args.iter().find_map(|arg| extract_number(arg, "turns=")).unwrap_or(0);
+    let turn_delay_ms = args
+        .iter()
+        .find_map(|arg| extract_number(arg, "turn-delay-ms="))
+        .unwrap_or(0);
+    for turn in 1..=turns {
+        println!(
+            r#"{{"type":"assistant","message":{{"id":"msg-{}","model":"claude-sonnet-4-5","content":[{{"type":"text","text":"turn {}"}}],"usage":{{"input_tokens":1000,"output_tokens":500}}}}}}"#,
+            turn, turn
+        );
+        std::thread::sleep(std::time::Duration::from_millis(turn_delay_ms));
+    }
+
     if delay_ms > 0 {
         std::thread::sleep(std::time::Duration::from_millis(delay_ms));
     }
 
+    let cost_usd = args
+        .iter()
+        .find_map(|arg| extract_cost_usd(arg))
+        .unwrap_or(0.01);
+    let usage = r#"{"input_tokens":120,"output_tokens":48}"#;
+
     if should_fail {
-        println!("{}", r#"{"type":"result","subtype":"error","is_error":true}"#);
+        println!(
+            r#"{{"type":"result","subtype":"error","is_error":true,"total_cost_usd":{},"usage":{}}}"#,
+            cost_usd, usage
+        );
         std::process::exit(1);
     }
 
-    println!("{}", r#"{"type":"result","subtype":"success","is_error":false}"#);
+    println!(
+        r#"{{"type":"result","subtype":"success","is_error":false,"total_cost_usd":{},"usage":{}}}"#,
+        cost_usd, usage
+    );
+}
+
+fn extract_cost_usd(arg: &str) -> Option<f64> {
+    arg.split_whitespace()
+        .find_map(|token| token.strip_prefix("cost-usd="))
+        .and_then(|raw| raw.parse::<f64>().ok())
+}
+
+fn extract_number(arg: &str, prefix: &str) -> Option<u64> {
+    arg.split_whitespace()
+        .find_map(|token| token.strip_prefix(prefix))
+        .and_then(|raw| raw.parse::<u64>().ok())
 }
 
 fn extract_delay_ms(arg: &str) -> Option<u64> {
@@ -0,0 +1,101 @@
+use crate::db::{Budget, Database};
+use crate::session::budgets::{
+    is_budget_unit, session_budget_id, BUDGET_SCOPE_GLOBAL, BUDGET_SCOPE_SESSION, BUDGET_UNITS,
+    GLOBAL_BUDGET_ID,
+};
+use tauri::State;
+
+fn validated_budget(
+    id: String,
+    scope: &str,
+    session_id: Option<String>,
+    unit: String,
+    hard_limit: f64,
+    soft_limit: Option<f64>,
+) -> Result<Budget, String> {
+    let unit = unit.trim().to_lowercase();
+    if !is_budget_unit(&unit) {
+        return Err(format!(
+            "Unknown budget unit '{}'. Expected one of: {}",
+            unit,
+            BUDGET_UNITS.join(", ")
+        ));
+    }
+
+    if !hard_limit.is_finite() || hard_limit <= 0.0 {
+        return Err("Budget limit must be greater than zero".to_string());
+    }
+
+    if let Some(soft_limit) = soft_limit {
+        if !soft_limit.is_finite() || soft_limit <= 0.0 || soft_limit >= hard_limit {
+            return Err("Soft limit must be greater than zero and below the hard limit".to_string());
+        }
+    }
+
+    Ok(Budget {
+        id,
+        scope: scope.to_string(),
+        session_id,
+        unit,
+        soft_limit,
+        hard_limit,
+        updated_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+#[tauri::command]
+pub async fn set_global_budget(
+    db: State<'_, Database>,
+    unit: String,
+    hard_limit: f64,
+    soft_limit: Option<f64>,
+) -> Result<Budget, String> {
+    let budget = validated_budget(
+        GLOBAL_BUDGET_ID.to_string(),
+        BUDGET_SCOPE_GLOBAL,
+        None,
+        unit,
+        hard_limit,
+        soft_limit,
+    )?;
+
+    db.save_budget(&budget).map_err(|e| format!("Failed to save global budget: {}", e))?;
+
+    Ok(budget)
+}
+
+#[tauri::command]
+pub async fn set_session_budget(
+    db: State<'_, Database>,
+    session_id: String,
+    unit: String,
+    hard_limit: f64,
+    soft_limit: Option<f64>,
+) -> Result<Budget, String> {
+    db.get_session(&session_id)
+        .map_err(|e| format!("Failed to load session: {}", e))?
+        .ok_or_else(|| format!("Session {} not found", session_id))?;
+
+    let budget = validated_budget(
+        session_budget_id(&session_id),
+        BUDGET_SCOPE_SESSION,
+        Some(session_id),
+        unit,
+        hard_limit,
+        soft_limit,
+    )?;
+
+    db.save_budget(&budget).map_err(|e| format!("Failed to save session budget: {}", e))?;
+
+    Ok(budget)
+}
+
+#[tauri::command]
+pub async fn list_budgets(db: State<'_, Database>) -> Result<Vec<Budget>, String> {
+    db.list_budgets().map_err(|e| format!("Failed to list budgets: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_budget(db: State<'_, Database>, id: String) -> Result<(), String> {
+    db.delete_budget(&id).map_err(|e| format!("Failed to delete budget: {}", e))
+}
@@ -0,0 +1,84 @@
+use crate::db::{Database, LifecycleHook};
+use crate::session::hooks::{is_hook_event, DEFAULT_HOOK_TIMEOUT_MS, HOOK_EVENTS};
+use crate::session::WorktreeService;
+use tauri::State;
+
+fn resolve_hook_scope(repo_path: Option<String>) -> Result<Option<String>, String> {
+    let Some(repo_path) = repo_path.filter(|value| !value.trim().is_empty()) else {
+        return Ok(None);
+    };
+
+    let service = WorktreeService::from_working_dir(repo_path.trim())?;
+    Ok(Some(service.repo_root().display().to_string()))
+}
+
+#[tauri::command]
+pub async fn create_lifecycle_hook(
+    db: State<'_, Database>,
+    event: String,
+    command: String,
+    repo_path: Option<String>,
+    timeout_ms: Option<i64>,
+) -> Result<LifecycleHook, String> {
+    let event = event.trim().to_lowercase();
+    if !is_hook_event(&event) {
+        return Err(format!(
+            "Unknown hook event '{}'. Expected one of: {}",
+            event,
+            HOOK_EVENTS.join(", ")
+        ));
+    }
+
+    let command = command.trim();
+    if command.is_empty() {
+        return Err("Hook command cannot be empty".to_string());
+    }
+
+    let timeout_ms = timeout_ms.unwrap_or(DEFAULT_HOOK_TIMEOUT_MS);
+    if timeout_ms <= 0 {
+        return Err("Hook timeout must be greater than zero".to_string());
+    }
+
+    let hook = LifecycleHook {
+        id: uuid::Uuid::new_v4().to_string(),
+        repo_root: resolve_hook_scope(repo_path)?,
+        event,
+        command: command.to_string(),
+        timeout_ms,
+        enabled: true,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    db.create_lifecycle_hook(&hook)
+        .map_err(|e| format!("Failed to create lifecycle hook: {}", e))?;
+
+    Ok(hook)
+}
+
+#[tauri::command]
+pub async fn list_lifecycle_hooks(db: State<'_, Database>) -> Result<Vec<LifecycleHook>, String> {
+    db.list_lifecycle_hooks()
+        .map_err(|e| format!("Failed to list lifecycle hooks: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_lifecycle_hook_enabled(
+    db: State<'_, Database>,
+    id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let updated = db
+        .set_lifecycle_hook_enabled(&id, enabled)
+        .map_err(|e| format!("Failed to update lifecycle hook: {}", e))?;
+    if !updated {
+        return Err(format!("Lifecycle hook {} not found", id));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_lifecycle_hook(db: State<'_, Database>, id: String) -> Result<(), String> {
+    db.delete_lifecycle_hook(&id)
+        .map_err(|e| format!("Failed to delete lifecycle hook: {}", e))
+}
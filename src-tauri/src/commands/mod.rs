@@ -1,3 +1,15 @@
+pub mod budgets;
+pub mod hooks;
+pub mod repositories;
 pub mod session;
+pub mod storage;
+pub mod webhooks;
+pub mod worktree;
 
+pub use budgets::*;
+pub use hooks::*;
+pub use repositories::*;
 pub use session::*;
+pub use storage::*;
+pub use webhooks::*;
+pub use worktree::*;
@@ -0,0 +1,158 @@
+use crate::commands::session::{configured_worktree_location, migrate_repository_worktrees};
+use crate::db::{Database, RepositorySettings};
+use crate::session::sync::{
+    SyncConflictPolicy, SyncStrategy, SYNC_CONFLICT_POLICIES, SYNC_STRATEGIES,
+};
+use crate::session::worktree::{
+    render_branch_name, validate_branch_name, AppDataDir, WorktreeLocation, WORKTREE_LOCATIONS,
+};
+use crate::session::WorktreeService;
+use std::path::Path;
+use tauri::State;
+
+fn resolve_repo_root(repo_path: &str) -> Result<String, String> {
+    let service = WorktreeService::from_working_dir(repo_path.trim())?;
+    Ok(service.repo_root().display().to_string())
+}
+
+fn normalize_patterns(patterns: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = patterns
+        .into_iter()
+        .map(|pattern| pattern.trim().trim_start_matches("./").to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect();
+    normalized.dedup();
+    normalized
+}
+
+fn repository_has_active_sessions(db: &Database, repo_root: &str) -> Result<bool, String> {
+    let sessions =
+        db.list_sessions().map_err(|e| format!("Failed to list sessions: {}", e))?;
+
+    Ok(sessions.iter().any(|session| {
+        matches!(session.status.as_str(), "starting" | "running" | "interrupting" | "resuming")
+            && WorktreeService::from_working_dir(&session.working_dir)
+                .is_ok_and(|service| service.repo_root() == Path::new(repo_root))
+    }))
+}
+
+#[tauri::command]
+pub async fn get_repository_settings(
+    db: State<'_, Database>,
+    repo_path: String,
+) -> Result<RepositorySettings, String> {
+    let repo_root = resolve_repo_root(&repo_path)?;
+    let settings = db
+        .get_repository_settings(&repo_root)
+        .map_err(|e| format!("Failed to load repository settings: {}", e))?;
+
+    Ok(settings.unwrap_or(RepositorySettings { repo_root, ..Default::default() }))
+}
+
+#[tauri::command]
+pub async fn save_repository_settings(
+    db: State<'_, Database>,
+    app_data_dir: State<'_, AppDataDir>,
+    repo_path: String,
+    settings: RepositorySettings,
+) -> Result<RepositorySettings, String> {
+    if settings.verification_timeout_ms.is_some_and(|value| value <= 0) {
+        return Err("Verification timeout must be greater than zero".to_string());
+    }
+    if settings.retention_max_age_days.is_some_and(|days| days < 0) {
+        return Err("Retention age must not be negative".to_string());
+    }
+    if settings.retention_max_bytes.is_some_and(|bytes| bytes < 0) {
+        return Err("Retention size cap must not be negative".to_string());
+    }
+
+    let branch_template = settings
+        .branch_template
+        .map(|template| template.trim().to_string())
+        .filter(|template| !template.is_empty());
+    if let Some(template) = branch_template.as_deref() {
+        validate_branch_name(&render_branch_name(template, "Example session", "0123456789abcdef"))
+            .map_err(|e| format!("Branch template does not produce a valid branch: {}", e))?;
+    }
+
+    let worktree_location = settings
+        .worktree_location
+        .map(|location| location.trim().to_string())
+        .filter(|location| !location.is_empty());
+    if let Some(location) = worktree_location.as_deref() {
+        if WorktreeLocation::parse(location).is_none() {
+            return Err(format!(
+                "Worktree location must be one of: {}",
+                WORKTREE_LOCATIONS.join(", ")
+            ));
+        }
+    }
+
+    let sync_strategy = settings
+        .sync_strategy
+        .map(|strategy| strategy.trim().to_string())
+        .filter(|strategy| !strategy.is_empty());
+    if sync_strategy.as_deref().is_some_and(|strategy| SyncStrategy::parse(strategy).is_none()) {
+        return Err(format!("Sync strategy must be one of: {}", SYNC_STRATEGIES.join(", ")));
+    }
+    let sync_on_conflict = settings
+        .sync_on_conflict
+        .map(|policy| policy.trim().to_string())
+        .filter(|policy| !policy.is_empty());
+    if sync_on_conflict.as_deref().is_some_and(|policy| SyncConflictPolicy::parse(policy).is_none())
+    {
+        return Err(format!(
+            "Sync conflict policy must be one of: {}",
+            SYNC_CONFLICT_POLICIES.join(", ")
+        ));
+    }
+
+    let repo_root = resolve_repo_root(&repo_path)?;
+    let previous = db
+        .get_repository_settings(&repo_root)
+        .map_err(|e| format!("Failed to load repository settings: {}", e))?
+        .unwrap_or_default();
+    let location_changed = configured_worktree_location(&previous)
+        != worktree_location.as_deref().and_then(WorktreeLocation::parse).unwrap_or_default();
+    if location_changed && repository_has_active_sessions(&db, &repo_root)? {
+        return Err(
+            "Stop or wait for this repository's running sessions before moving its worktrees"
+                .to_string(),
+        );
+    }
+
+    let settings = RepositorySettings {
+        repo_root,
+        verification_command: settings
+            .verification_command
+            .map(|command| command.trim().to_string())
+            .filter(|command| !command.is_empty()),
+        branch_template,
+        auto_commit_trailer: settings
+            .auto_commit_trailer
+            .map(|trailer| trailer.trim().to_string())
+            .filter(|trailer| !trailer.is_empty()),
+        worktree_location,
+        sync_strategy,
+        sync_on_conflict,
+        provision_copy: normalize_patterns(settings.provision_copy),
+        provision_symlink: normalize_patterns(settings.provision_symlink),
+        setup_command: settings
+            .setup_command
+            .map(|command| command.trim().to_string())
+            .filter(|command| !command.is_empty()),
+        ..settings
+    };
+
+    db.save_repository_settings(&settings)
+        .map_err(|e| format!("Failed to save repository settings: {}", e))?;
+
+    // Existing session worktrees follow the new location; anything left behind is retried at
+    // the next startup.
+    if location_changed {
+        migrate_repository_worktrees(&db, Path::new(&settings.repo_root), &app_data_dir.0)
+            .map_err(|e| format!("Settings saved, but moving worktrees failed: {}", e))?;
+    }
+
+    Ok(settings)
+}
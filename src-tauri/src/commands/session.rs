@@ -1,6 +1,36 @@
-use crate::db::{Database, Session, SessionDashboardRow, SessionHistoryEvent, SessionMessage};
+use crate::db::search::DEFAULT_SEARCH_LIMIT;
+use crate::db::{
+    Database, HistoryPageQuery, MessagePageQuery, QueuedSessionEvent, RepositorySettings, Session,
+    SessionBase, SessionCheckpoint, SessionDashboardRow, SessionHistoryEvent, SessionHistoryPage,
+    SessionMessage, SessionMessagePage, SessionRun, SessionSearchFilters, SessionSearchHit,
+};
 use crate::session::projection::{normalize_failure_reason, project_dashboard_row, DashboardSessionProjection};
 use crate::session::{ClaudeCli, SessionManager, SessionRuntime, SessionSupervisor, WorktreeService};
+use crate::session::auto_commit::auto_commit_session;
+use crate::session::budgets::{budget_block_reason, enforce_budgets, BUDGET_EXCEEDED_REASON};
+use crate::session::checkpoints::{
+    create_checkpoint, remove_checkpoint_refs, rewind_to_checkpoint, spawn_checkpoint_worker,
+    READ_ONLY_TOOLS,
+};
+use crate::session::history_retention::{archive_path, HISTORY_ARCHIVE_DIR};
+use crate::session::hooks::run_lifecycle_hooks;
+use crate::session::overlap::{refresh_diff_paths, track_tool_call};
+use crate::session::provisioning::provision_session_worktree;
+use crate::session::quarantine::{reconcile_managed_worktrees, ReconcileReport};
+use crate::session::retention::record_worktree_usage;
+use crate::session::sandbox::{sandbox_tree_for, SessionSandbox};
+use crate::session::sync::{configured_sync, sync_session_worktree};
+use crate::session::verification::run_session_verification;
+use crate::session::worktree::{
+    render_branch_name, AppDataDir, WorktreeLocation, DEFAULT_BRANCH_TEMPLATE,
+};
+use crate::session::webhooks::{
+    webhook_event_for_status, WebhookDispatcher, WEBHOOK_STALL_CHECK_INTERVAL,
+};
+use crate::session::{
+    LifecycleAdmission, LifecycleOperationKind, LifecycleOperationSnapshot, TerminalTransitionNotice,
+};
+use crate::session::supervisor::INTERRUPT_DEADLINE;
 use crate::session::{SessionEvent, SessionEventPayload};
 use serde_json::json;
 use std::collections::HashMap;
@@ -50,51 +80,264 @@ fn resolve_working_dir(working_dir: &str) -> Result<String, String> {
     Ok(trimmed.to_string())
 }
 
+struct ExecutionTarget {
+    worktree_service: Option<WorktreeService>,
+    worktree_path: Option<PathBuf>,
+    branch_name: Option<String>,
+    base: Option<SessionBase>,
+    sandbox: Option<SessionSandbox>,
+    execution_dir: String,
+    fallback_message: Option<String>,
+}
+
+impl ExecutionTarget {
+    fn fallback(working_dir: &str, message: String) -> Self {
+        Self {
+            worktree_service: None,
+            worktree_path: None,
+            branch_name: None,
+            base: None,
+            sandbox: None,
+            execution_dir: working_dir.to_string(),
+            fallback_message: Some(message),
+        }
+    }
+
+    fn sandboxed(sandbox: SessionSandbox) -> Self {
+        Self {
+            worktree_service: None,
+            worktree_path: None,
+            branch_name: None,
+            base: None,
+            execution_dir: sandbox.tree().display().to_string(),
+            sandbox: Some(sandbox),
+            fallback_message: None,
+        }
+    }
+}
+
+/// The repository's settings, or the defaults outside git or when none are saved.
+fn repository_settings_for(db: &Database, working_dir: &str) -> RepositorySettings {
+    WorktreeService::from_working_dir(working_dir)
+        .ok()
+        .and_then(|service| {
+            db.get_repository_settings(&service.repo_root().display().to_string()).ok().flatten()
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) fn configured_worktree_location(settings: &RepositorySettings) -> WorktreeLocation {
+    settings
+        .worktree_location
+        .as_deref()
+        .and_then(WorktreeLocation::parse)
+        .unwrap_or_default()
+}
+
+/// Worktree service for `repo_root` rooted wherever the repository's settings put worktrees.
+pub(crate) fn configured_worktree_service(
+    db: &Database,
+    repo_root: &Path,
+    app_data_dir: &Path,
+) -> WorktreeService {
+    let settings = db
+        .get_repository_settings(&repo_root.display().to_string())
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    WorktreeService::with_location(repo_root, configured_worktree_location(&settings), app_data_dir)
+}
+
+/// Session ids and worktree paths of every session that has a worktree, grouped by repository.
+fn session_worktrees_by_repo(
+    db: &Database,
+) -> Result<HashMap<PathBuf, Vec<(String, PathBuf)>>, String> {
+    let sessions = db
+        .list_sessions()
+        .map_err(|e| format!("Failed to list sessions for worktree reconciliation: {}", e))?;
+
+    let mut worktrees_by_repo: HashMap<PathBuf, Vec<(String, PathBuf)>> = HashMap::new();
+    for session in sessions {
+        let worktree_path = db
+            .get_session_worktree_path(&session.id)
+            .map_err(|e| format!("Failed to fetch worktree metadata for session {}: {}", session.id, e))?;
+
+        let Some(worktree_path) = worktree_path else {
+            continue;
+        };
+
+        let service = match WorktreeService::from_working_dir(&session.working_dir) {
+            Ok(service) => service,
+            Err(_) => continue,
+        };
+
+        worktrees_by_repo
+            .entry(service.repo_root().to_path_buf())
+            .or_default()
+            .push((session.id, PathBuf::from(worktree_path)));
+    }
+
+    Ok(worktrees_by_repo)
+}
+
+/// Moves a session worktree into `service`'s worktrees root and points the session row at the
+/// new path. Worktrees already there, missing on disk, or in use by an active session are
+/// returned unchanged.
+fn migrate_session_worktree(
+    db: &Database,
+    service: &WorktreeService,
+    session_id: &str,
+    worktree_path: &Path,
+) -> Result<PathBuf, String> {
+    if worktree_path.starts_with(service.worktrees_root()) || !worktree_path.exists() {
+        return Ok(worktree_path.to_path_buf());
+    }
+    // A session that is starting or running has its CLI inside the worktree; it moves once the
+    // session has stopped.
+    let status = db
+        .get_session(session_id)
+        .map_err(|e| format!("Failed to get session {}: {}", session_id, e))?
+        .map(|session| session.status);
+    if matches!(status.as_deref(), Some("starting" | "running" | "interrupting" | "resuming")) {
+        return Ok(worktree_path.to_path_buf());
+    }
+
+    let relocated = service.relocate_worktree(worktree_path, session_id)?;
+    db.update_worktree_path(session_id, Some(&relocated.display().to_string()))
+        .map_err(|e| format!("Failed to record moved worktree for session {}: {}", session_id, e))?;
+    Ok(relocated)
+}
+
+/// Moves every session worktree of `repo_root` that lives outside the configured worktrees root
+/// into it. Returns how many worktrees moved; stops at the first failure, leaving the rest
+/// where they were (their rows still point at them).
+pub fn migrate_repository_worktrees(
+    db: &Database,
+    repo_root: &Path,
+    app_data_dir: &Path,
+) -> Result<usize, String> {
+    let service = configured_worktree_service(db, repo_root, app_data_dir);
+    let worktrees = session_worktrees_by_repo(db)?.remove(repo_root).unwrap_or_default();
+
+    let mut moved = 0;
+    for (session_id, worktree_path) in worktrees {
+        let relocated = migrate_session_worktree(db, &service, &session_id, &worktree_path)
+            .map_err(|e| {
+                format!("Moved {} worktree(s), then failed on session {}: {}", moved, session_id, e)
+            })?;
+        if relocated != worktree_path {
+            moved += 1;
+        }
+    }
+
+    Ok(moved)
+}
+
+/// Picks where the session runs. Without `base_ref` a repository that can't host a worktree
+/// falls back to the working directory; an explicit base ref must resolve or the spawn fails.
 fn resolve_execution_dir_with_worktree(
     working_dir: &str,
     session_id: &str,
-) -> (Option<WorktreeService>, Option<PathBuf>, String, Option<String>) {
-    match WorktreeService::from_working_dir(working_dir) {
-        Ok(service) => match service.create_worktree(session_id) {
-            Ok(path) => {
-                let execution_dir = path.display().to_string();
-                (Some(service), Some(path), execution_dir, None)
+    session_name: &str,
+    settings: &RepositorySettings,
+    app_data_dir: &Path,
+    base_ref: Option<&str>,
+) -> Result<ExecutionTarget, String> {
+    let service = match WorktreeService::from_working_dir(working_dir) {
+        Ok(service) => WorktreeService::with_location(
+            service.repo_root(),
+            configured_worktree_location(settings),
+            app_data_dir,
+        )
+        .with_lfs_smudge_skipped(settings.lfs_checkout),
+        Err(err) if base_ref.is_some() => {
+            return Err(format!("A base ref needs a git repository: {}", err))
+        }
+        Err(err) => {
+            let tree = sandbox_tree_for(app_data_dir, session_id);
+            return Ok(match SessionSandbox::create(Path::new(working_dir), &tree) {
+                Ok(sandbox) => ExecutionTarget::sandboxed(sandbox),
+                Err(sandbox_err) => ExecutionTarget::fallback(
+                    working_dir,
+                    format!(
+                        "No git repository detected ({}) and the folder could not be sandboxed, \
+                         using working directory directly: {}",
+                        err, sandbox_err
+                    ),
+                ),
+            });
+        }
+    };
+
+    let base = match base_ref {
+        Some(base_ref) => SessionBase {
+            base_ref: base_ref.to_string(),
+            base_sha: service.resolve_commit(base_ref)?,
+        },
+        None => match (service.current_ref(), service.resolve_commit("HEAD")) {
+            (Ok(base_ref), Ok(base_sha)) => SessionBase { base_ref, base_sha },
+            (_, Err(err)) | (Err(err), _) => {
+                return Ok(ExecutionTarget::fallback(
+                    working_dir,
+                    format!("Worktree creation failed, using working directory directly: {}", err),
+                ))
             }
-            Err(err) => (
-                None,
-                None,
-                working_dir.to_string(),
-                Some(format!(
-                    "Worktree creation failed, using working directory directly: {}",
-                    err
-                )),
-            ),
         },
-        Err(err) => (
-            None,
-            None,
-            working_dir.to_string(),
-            Some(format!(
-                "No git repository detected, using working directory directly: {}",
-                err
-            )),
-        ),
+    };
+
+    let branch_template = settings.branch_template.as_deref().unwrap_or(DEFAULT_BRANCH_TEMPLATE);
+    let branch = service.unique_branch_name(&render_branch_name(
+        branch_template,
+        session_name,
+        session_id,
+    ));
+    match service.create_worktree_at(session_id, &branch, &base.base_sha) {
+        Ok(path) => Ok(ExecutionTarget {
+            execution_dir: path.display().to_string(),
+            worktree_service: Some(service),
+            worktree_path: Some(path),
+            branch_name: Some(branch),
+            base: Some(base),
+            sandbox: None,
+            fallback_message: None,
+        }),
+        Err(err) if base_ref.is_some() => Err(format!("Worktree creation failed: {}", err)),
+        Err(err) => Ok(ExecutionTarget::fallback(
+            working_dir,
+            format!("Worktree creation failed, using working directory directly: {}", err),
+        )),
     }
 }
 
 fn cleanup_failed_spawn_attempt(
     db: &Database,
-    worktree_service: Option<&WorktreeService>,
+    target: &ExecutionTarget,
     session_id: &str,
 ) {
     let _ = db.delete_session(session_id);
 
-    if let Some(service) = worktree_service {
+    if let Some(sandbox) = target.sandbox.as_ref() {
+        let _ = sandbox.remove();
+    }
+    if let Some(service) = target.worktree_service.as_ref() {
         let _ = service.remove_worktree_for_session(session_id);
         let _ = service.prune_worktrees();
+        if let Some(branch) = target.branch_name.as_deref() {
+            let _ = service.delete_branch(branch);
+        }
     }
 }
 
+/// Fails a session whose worktree could not be prepared. Unlike a CLI launch failure the session
+/// is kept, so its history shows the provisioning output; the worktree stays for inspection
+/// until the session is deleted.
+fn fail_session_before_launch(app: &AppHandle, db: &Database, session_id: &str, reason: &str) {
+    let _ = db.update_session_status(session_id, "failed");
+    let _ = db.update_failure_reason(session_id, Some(reason));
+    let _ = app.emit("session-error", (session_id, reason.to_string()));
+    dispatch_lifecycle_hooks(app, session_id, "failed");
+}
+
 fn normalize_spawn_session_error(error: &str, execution_dir: &str) -> String {
     if error.starts_with("Working directory ") {
         return error.to_string();
@@ -125,6 +368,18 @@ fn event_type(payload: &SessionEventPayload) -> &'static str {
         SessionEventPayload::ToolResult { .. } => "tool_result",
         SessionEventPayload::Status { .. } => "status",
         SessionEventPayload::Error { .. } => "error",
+        SessionEventPayload::Hook { .. } => "hook",
+        SessionEventPayload::Usage { .. } => "usage",
+        SessionEventPayload::VerificationOutput { .. } => "verification_output",
+        SessionEventPayload::Verification { .. } => "verification",
+        SessionEventPayload::ProvisionStep { .. } => "provision_step",
+        SessionEventPayload::ProvisionOutput { .. } => "provision_output",
+        SessionEventPayload::BudgetWarning { .. } => "budget_warning",
+        SessionEventPayload::BudgetExceeded { .. } => "budget_exceeded",
+        SessionEventPayload::ConflictWarning { .. } => "conflict_warning",
+        SessionEventPayload::WorktreeSync { .. } => "worktree_sync",
+        SessionEventPayload::Rewind { .. } => "rewind",
+        SessionEventPayload::HistoryCompacted { .. } => "history_compacted",
     }
 }
 
@@ -148,6 +403,8 @@ async fn finalize_session_once(
 ) {
     let supervisor = session_supervisor(manager).await;
     let db = app.state::<Database>();
+    // Webhooks, hooks and the dashboard read what the run streamed; let it land first.
+    let _ = db.flush_writes();
     let transition = match supervisor
         .finalize_terminal_transition_and_emit(
             app,
@@ -206,49 +463,66 @@ fn launch_session_event_tasks(
     runtime: Arc<SessionRuntime>,
     mut event_rx: mpsc::Receiver<SessionEvent>,
 ) {
+    // Events Lulu records mid-run draw from the stream's counter instead of racing it.
+    app.state::<Database>().register_run_sequence(&session_id, &run_id, sequence.clone());
+
     let app_event = app.clone();
     let session_id_for_events = session_id.clone();
     let manager_for_events = manager.clone();
     let seq_for_events = sequence.clone();
 
+    let app_for_checkpoints = app.clone();
+    let (checkpoint_session_id, checkpoint_run_id) = (session_id.clone(), run_id.clone());
+    let checkpoints = spawn_checkpoint_worker(move |seq| {
+        let db = app_for_checkpoints.state::<Database>();
+        if let Err(message) =
+            create_checkpoint(db.inner(), &checkpoint_session_id, &checkpoint_run_id, seq)
+        {
+            let _ = app_for_checkpoints.emit(
+                "session-debug",
+                json!({
+                    "session_id": checkpoint_session_id,
+                    "kind": "checkpoint-error",
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                    "message": message,
+                }),
+            );
+        }
+    });
+
     tokio::spawn(async move {
+        // Tool names by call id, since results from the CLI don't carry them.
+        let mut tool_calls: HashMap<String, String> = HashMap::new();
         while let Some(event) = event_rx.recv().await {
             let payload_json = match serde_json::to_value(&event.payload) {
                 Ok(value) => value,
                 Err(err) => json!({ "serialization_error": err.to_string() }),
             };
 
-            if app_event
-                .state::<Database>()
-                .insert_session_event(
-                    &event.session_id,
-                    &run_id,
-                    event.seq,
-                    event_type(&event.payload),
-                    &payload_json,
-                    &event.timestamp,
-                )
-                .is_ok()
-            {
-                let _ = app_event
-                    .state::<Database>()
-                    .clear_restored_metadata(&event.session_id);
-            }
+            // One queued job per event: the writer thread commits it with its neighbours, so
+            // this task never waits on SQLite.
+            let _ = app_event.state::<Database>().queue_session_event(QueuedSessionEvent {
+                session_id: event.session_id.clone(),
+                run_id: run_id.clone(),
+                seq: event.seq,
+                event_type: event_type(&event.payload).to_string(),
+                payload_json: payload_json.to_string(),
+                timestamp: event.timestamp.clone(),
+                activity: matches!(
+                    event.payload,
+                    SessionEventPayload::Message { .. } | SessionEventPayload::Status { .. }
+                ),
+                assistant_message: match &event.payload {
+                    SessionEventPayload::Message { content } => Some(content.clone()),
+                    _ => None,
+                },
+            });
 
             let frontend_event = to_frontend_session_event(&event);
             let _ = app_event.emit("session-event", frontend_event);
 
             match &event.payload {
                 SessionEventPayload::Message { content } => {
-                    let _ = app_event
-                        .state::<Database>()
-                        .update_last_activity(&event.session_id, &event.timestamp);
-                    let _ = app_event.state::<Database>().insert_session_message(
-                        &event.session_id,
-                        "assistant",
-                        content,
-                        &event.timestamp,
-                    );
                     let _ = app_event.emit(
                         "session-output",
                         SessionOutput {
@@ -257,22 +531,17 @@ fn launch_session_event_tasks(
                         },
                     );
                 }
-                SessionEventPayload::Status { status } => {
-                    let _ = app_event
-                        .state::<Database>()
-                        .update_last_activity(&event.session_id, &event.timestamp);
-                    if is_terminal_status(status) {
-                        finalize_session_once(
-                            &app_event,
-                            &manager_for_events,
-                            &session_id_for_events,
-                            status,
-                            &seq_for_events,
-                            false,
-                            None,
-                        )
-                        .await;
-                    }
+                SessionEventPayload::Status { status } if is_terminal_status(status) => {
+                    finalize_session_once(
+                        &app_event,
+                        &manager_for_events,
+                        &session_id_for_events,
+                        status,
+                        &seq_for_events,
+                        false,
+                        None,
+                    )
+                    .await;
                 }
                 SessionEventPayload::Error { message } => {
                     let _ = app_event
@@ -292,6 +561,57 @@ fn launch_session_event_tasks(
                     );
                     let _ = app_event.emit("session-error", (&event.session_id, message));
                 }
+                SessionEventPayload::Usage {
+                    input_tokens,
+                    output_tokens,
+                    cost_usd,
+                } => {
+                    let recorded = app_event
+                        .state::<Database>()
+                        .record_run_usage(
+                            &event.session_id,
+                            &run_id,
+                            *input_tokens,
+                            *output_tokens,
+                            *cost_usd,
+                        )
+                        .is_ok();
+                    if recorded {
+                        enforce_session_budgets(&app_event, &manager_for_events, &event.session_id)
+                            .await;
+                    }
+                    if recorded && cost_usd.is_some() {
+                        dispatch_cost_threshold_webhooks(&app_event, &event.session_id);
+                    }
+                }
+                SessionEventPayload::ToolCall { call_id, tool_name, args } => {
+                    if let Some(call_id) = call_id {
+                        tool_calls.insert(call_id.clone(), tool_name.clone());
+                    }
+                    let (session_id, tool_name, args) =
+                        (event.session_id.clone(), tool_name.clone(), args.clone());
+                    track_overlaps(&app_event, &event.session_id, move |db| {
+                        track_tool_call(db, &session_id, &tool_name, &args)
+                    })
+                    .await;
+                }
+                SessionEventPayload::ToolResult { call_id, tool_name, .. } => {
+                    if matches!(tool_name.as_deref(), None | Some("Bash")) {
+                        let session_id = event.session_id.clone();
+                        track_overlaps(&app_event, &event.session_id, move |db| {
+                            refresh_diff_paths(db, &session_id)
+                        })
+                        .await;
+                    }
+                    let read_only = call_id
+                        .as_ref()
+                        .and_then(|call_id| tool_calls.remove(call_id))
+                        .or_else(|| tool_name.clone())
+                        .is_some_and(|tool_name| READ_ONLY_TOOLS.contains(&tool_name.as_str()));
+                    if !read_only {
+                        let _ = checkpoints.send(event.seq);
+                    }
+                }
                 _ => {}
             }
         }
@@ -306,15 +626,20 @@ fn launch_session_event_tasks(
     tokio::spawn(async move {
         match wait_for_runtime_exit(runtime_for_wait.clone()).await {
             Ok(exit_status) => {
-                let terminal = if runtime_for_wait.was_interrupt_requested() {
-                    "interrupted"
+                let terminal = if runtime_for_wait.was_budget_exceeded() {
+                    "failed"
                 } else if runtime_for_wait.was_killed() {
                     "killed"
+                } else if runtime_for_wait.was_interrupt_requested() {
+                    "interrupted"
                 } else if exit_status.success() {
                     "completed"
                 } else {
                     "failed"
                 };
+                let failure_message = runtime_for_wait
+                    .was_budget_exceeded()
+                    .then(|| BUDGET_EXCEEDED_REASON.to_string());
 
                 finalize_session_once(
                     &app_for_wait,
@@ -323,7 +648,7 @@ fn launch_session_event_tasks(
                     terminal,
                     &seq_for_wait,
                     true,
-                    None,
+                    failure_message,
                 )
                 .await;
             }
@@ -344,60 +669,440 @@ fn launch_session_event_tasks(
     });
 }
 
-pub fn reconcile_sessions_on_startup(db: &Database) -> Result<(), String> {
-    db.reconcile_stale_inflight_sessions()
-        .map_err(|e| format!("Failed to reconcile stale sessions: {}", e))?;
+fn emit_budget_error(app: &AppHandle, session_id: &str, message: &str) {
+    let _ = app.emit(
+        "session-debug",
+        json!({
+            "session_id": session_id,
+            "kind": "budget-error",
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "message": message,
+        }),
+    );
+}
 
-    let sessions = db
-        .list_sessions()
-        .map_err(|e| format!("Failed to list sessions for worktree reconciliation: {}", e))?;
+/// Enforces the session's budgets after new usage arrives and stops the run in the background
+/// once a hard limit is reached.
+async fn enforce_session_budgets(
+    app: &AppHandle,
+    manager: &Arc<Mutex<SessionManager>>,
+    session_id: &str,
+) {
+    let db = app.state::<Database>();
+    let supervisor = session_supervisor(manager).await;
+    let enforcement = match enforce_budgets(db.inner(), &supervisor, session_id).await {
+        Ok(enforcement) => enforcement,
+        Err(message) => {
+            emit_budget_error(app, session_id, &message);
+            return;
+        }
+    };
 
-    let mut expected_by_repo: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
-    for session in sessions {
-        let worktree_path = db
-            .get_session_worktree_path(&session.id)
-            .map_err(|e| format!("Failed to fetch worktree metadata for session {}: {}", session.id, e))?;
+    emit_recorded_events(app, &enforcement.events);
+    for message in &enforcement.errors {
+        emit_budget_error(app, session_id, message);
+    }
+    if !enforcement.stop {
+        return;
+    }
 
-        let Some(worktree_path) = worktree_path else {
-            continue;
-        };
+    let app = app.clone();
+    let session_id = session_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<Database>();
+        if let Err(message) = supervisor
+            .stop_session_for_budget(db.inner(), &session_id, INTERRUPT_DEADLINE)
+            .await
+        {
+            emit_budget_error(&app, &session_id, &message);
+        }
+    });
+}
 
-        let service = match WorktreeService::from_working_dir(&session.working_dir) {
-            Ok(service) => service,
-            Err(_) => continue,
-        };
+/// Runs overlap tracking on the blocking pool, since it shells out to git, then emits its
+/// warnings.
+async fn track_overlaps<F>(app: &AppHandle, session_id: &str, track: F)
+where
+    F: FnOnce(&Database) -> Result<Vec<SessionEvent>, String> + Send + 'static,
+{
+    let app_for_task = app.clone();
+    let result =
+        tokio::task::spawn_blocking(move || track(app_for_task.state::<Database>().inner()))
+            .await
+            .unwrap_or_else(|e| Err(format!("Overlap tracking did not finish: {}", e)));
+    emit_overlap_events(app, session_id, result);
+}
 
-        expected_by_repo
-            .entry(service.repo_root().to_path_buf())
-            .or_default()
-            .push(PathBuf::from(worktree_path));
+/// Emits the conflict warnings from tracking a session's edits; tracking failures only reach
+/// the debug stream, they never interrupt the run.
+fn emit_overlap_events(
+    app: &AppHandle,
+    session_id: &str,
+    result: Result<Vec<SessionEvent>, String>,
+) {
+    match result {
+        Ok(events) => emit_recorded_events(app, &events),
+        Err(message) => {
+            let _ = app.emit(
+                "session-debug",
+                json!({
+                    "session_id": session_id,
+                    "kind": "overlap-error",
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                    "message": message,
+                }),
+            );
+        }
     }
+}
 
-    for (repo_root, expected_paths) in expected_by_repo {
-        let service = WorktreeService::new(repo_root);
-        service.reconcile_managed_worktrees(&expected_paths)?;
+/// Applies the repository's sync-before-resume policy to a session worktree. Returns the
+/// prompt for conflicts the sync left behind, which goes ahead of the user's resume prompt. A
+/// sync that fails outright is reported and the resume goes ahead without it.
+async fn sync_before_resume(
+    app: &AppHandle,
+    db: &Database,
+    session_id: &str,
+    working_dir: &str,
+) -> Option<String> {
+    let settings = repository_settings_for(db, working_dir);
+    if !settings.auto_sync || db.get_session_worktree_path(session_id).ok().flatten().is_none() {
+        return None;
     }
 
-    Ok(())
+    let (strategy, on_conflict) = configured_sync(&settings);
+    let (app_for_sync, id) = (app.clone(), session_id.to_string());
+    let synced = tokio::task::spawn_blocking(move || {
+        let db = app_for_sync.state::<Database>();
+        sync_session_worktree(db.inner(), &id, strategy, on_conflict)
+    })
+    .await
+    .unwrap_or_else(|e| Err(format!("Worktree sync did not finish: {}", e)));
+    match synced {
+        Ok((outcome, events)) => {
+            emit_recorded_events(app, &events);
+            outcome.resume_prompt
+        }
+        Err(message) => {
+            let _ = app.emit(
+                "session-debug",
+                json!({
+                    "session_id": session_id,
+                    "kind": "sync-error",
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                    "message": message,
+                }),
+            );
+            None
+        }
+    }
+}
+
+pub(crate) fn emit_recorded_events(app: &AppHandle, events: &[SessionEvent]) {
+    for event in events {
+        let _ = app.emit("session-event", to_frontend_session_event(event));
+    }
+}
+
+fn dispatch_lifecycle_hooks(app: &AppHandle, session_id: &str, event: &str) {
+    let app = app.clone();
+    let session_id = session_id.to_string();
+    let event = event.to_string();
+
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<Database>();
+        match run_lifecycle_hooks(db.inner(), &session_id, &event).await {
+            Ok(events) => emit_recorded_events(&app, &events),
+            Err(message) => {
+                let _ = app.emit(
+                    "session-debug",
+                    json!({
+                        "session_id": session_id,
+                        "kind": "hook-error",
+                        "timestamp": chrono::Utc::now().to_rfc3339(),
+                        "message": message,
+                    }),
+                );
+            }
+        }
+    });
+}
+
+fn emit_webhook_error(app: &AppHandle, session_id: &str, message: &str) {
+    let _ = app.emit(
+        "session-debug",
+        json!({
+            "session_id": session_id,
+            "kind": "webhook-error",
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "message": message,
+        }),
+    );
+}
+
+async fn notify_webhooks(
+    app: &AppHandle,
+    session_id: &str,
+    event: &str,
+    details: serde_json::Value,
+) {
+    let Some(dispatcher) = app.try_state::<WebhookDispatcher>() else {
+        return;
+    };
+    let dispatcher = dispatcher.inner().clone();
+    let db = app.state::<Database>();
+    if let Err(message) = dispatcher.notify(db.inner(), session_id, event, details).await {
+        emit_webhook_error(app, session_id, &message);
+    }
+}
+
+async fn run_post_run_verification(app: &AppHandle, session_id: &str) {
+    let db = app.state::<Database>();
+    let result = run_session_verification(db.inner(), session_id, |event| {
+        let _ = app.emit("session-event", to_frontend_session_event(event));
+    })
+    .await;
+
+    if let Err(message) = result {
+        let _ = app.emit(
+            "session-debug",
+            json!({
+                "session_id": session_id,
+                "kind": "verification-error",
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+                "message": message,
+            }),
+        );
+    }
+}
+
+async fn run_post_run_auto_commit(app: &AppHandle, session_id: &str) {
+    let (app_for_commit, id) = (app.clone(), session_id.to_string());
+    let result = tokio::task::spawn_blocking(move || {
+        auto_commit_session(app_for_commit.state::<Database>().inner(), &id)
+    })
+    .await
+    .unwrap_or_else(|e| Err(format!("Auto-commit did not finish: {}", e)));
+    let (kind, detail) = match result {
+        Ok(Some(commit_sha)) => ("auto-commit", json!({ "commit_sha": commit_sha })),
+        Ok(None) => return,
+        Err(message) => ("auto-commit-error", json!({ "message": message })),
+    };
+
+    let _ = app.emit(
+        "session-debug",
+        json!({
+            "session_id": session_id,
+            "kind": kind,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "detail": detail,
+        }),
+    );
+}
+
+/// Completed runs are committed (when the repository opted in) and then verified before
+/// webhooks fire, so receivers see both the commit and the verification status. The worktree
+/// is measured once the run is done so the dashboard shows its current disk usage.
+fn dispatch_post_run_tasks(app: &AppHandle, notice: TerminalTransitionNotice) {
+    let app = app.clone();
+
+    tauri::async_runtime::spawn(async move {
+        if notice.final_status == "completed" {
+            run_post_run_auto_commit(&app, &notice.session_id).await;
+            run_post_run_verification(&app, &notice.session_id).await;
+        }
+        let (app_for_usage, session_id) = (app.clone(), notice.session_id.clone());
+        let _ = tokio::task::spawn_blocking(move || {
+            record_worktree_usage(app_for_usage.state::<Database>().inner(), &session_id)
+        })
+        .await;
+
+        if let Some(event) = webhook_event_for_status(&notice.final_status) {
+            notify_webhooks(
+                &app,
+                &notice.session_id,
+                event,
+                json!({
+                    "status": notice.final_status,
+                    "failure_message": notice.failure_message,
+                }),
+            )
+            .await;
+        }
+    });
+}
+
+fn dispatch_cost_threshold_webhooks(app: &AppHandle, session_id: &str) {
+    let Some(dispatcher) = app.try_state::<WebhookDispatcher>() else {
+        return;
+    };
+    let dispatcher = dispatcher.inner().clone();
+    let app = app.clone();
+    let session_id = session_id.to_string();
+
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<Database>();
+        if let Err(message) = dispatcher
+            .notify_cost_threshold(db.inner(), &session_id)
+            .await
+        {
+            emit_webhook_error(&app, &session_id, &message);
+        }
+    });
+}
+
+/// Periodically reports running sessions that have gone quiet to `stalled` webhook targets.
+pub fn start_webhook_stall_watcher(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            sleep(WEBHOOK_STALL_CHECK_INTERVAL).await;
+
+            let Some(dispatcher) = app.try_state::<WebhookDispatcher>() else {
+                continue;
+            };
+            let dispatcher = dispatcher.inner().clone();
+            let db = app.state::<Database>();
+            if let Err(message) = dispatcher
+                .notify_stalled(db.inner(), chrono::Utc::now())
+                .await
+            {
+                let _ = app.emit(
+                    "session-debug",
+                    json!({
+                        "kind": "webhook-error",
+                        "timestamp": chrono::Utc::now().to_rfc3339(),
+                        "message": message,
+                    }),
+                );
+            }
+        }
+    });
+}
+
+/// Reports streamed events and messages that failed to persist as `session-debug` events.
+pub fn start_write_failure_listener(app: AppHandle) {
+    let mut failures = app.state::<Database>().subscribe_write_failures();
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let failure = match failures.recv().await {
+                Ok(failure) => failure,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+
+            let _ = app.emit(
+                "session-debug",
+                json!({
+                    "session_id": failure.session_id,
+                    "kind": "db-write-error",
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                    "message": failure.message,
+                }),
+            );
+        }
+    });
+}
+
+/// Reacts to every terminal transition the supervisor applies, regardless of whether it came
+/// from the CLI stream, process exit, interrupt or kill.
+pub fn start_terminal_transition_listener(app: AppHandle, supervisor: Arc<SessionSupervisor>) {
+    let mut transitions = supervisor.subscribe_terminal_transitions();
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let notice = match transitions.recv().await {
+                Ok(notice) => notice,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+
+            dispatch_lifecycle_hooks(&app, &notice.session_id, &notice.final_status);
+            dispatch_post_run_tasks(&app, notice);
+        }
+    });
+}
+
+/// Moves worktrees whose repository changed its worktree location, then moves managed
+/// worktrees no session owns into the repository's quarantine. With `dry_run` nothing moves
+/// and the reports list what would be quarantined.
+pub fn reconcile_session_worktrees(
+    db: &Database,
+    app_data_dir: &Path,
+    dry_run: bool,
+) -> Result<Vec<ReconcileReport>, String> {
+    let mut reports = Vec::new();
+    for (repo_root, worktrees) in session_worktrees_by_repo(db)? {
+        let service = configured_worktree_service(db, &repo_root, app_data_dir);
+        let expected_paths: Vec<PathBuf> = worktrees
+            .into_iter()
+            .map(|(session_id, worktree_path)| {
+                if dry_run {
+                    return worktree_path;
+                }
+                migrate_session_worktree(db, &service, &session_id, &worktree_path)
+                    .unwrap_or(worktree_path)
+            })
+            .collect();
+        reports.push(reconcile_managed_worktrees(&service, &expected_paths, dry_run)?);
+    }
+
+    Ok(reports)
+}
+
+/// Marks sessions left in flight by a previous run as restored and reconciles session
+/// worktrees. A worktree that can't be moved stays where it is; its session keeps working from
+/// there. Worktrees no session owns are quarantined, never deleted.
+pub fn reconcile_sessions_on_startup(db: &Database, app_data_dir: &Path) -> Result<(), String> {
+    db.reconcile_stale_inflight_sessions()
+        .map_err(|e| format!("Failed to reconcile stale sessions: {}", e))?;
+
+    reconcile_session_worktrees(db, app_data_dir, false).map(|_| ())
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn spawn_session(
     app: AppHandle,
     db: State<'_, Database>,
     manager: State<'_, Arc<Mutex<SessionManager>>>,
+    app_data_dir: State<'_, AppDataDir>,
     name: String,
     prompt: String,
     working_dir: String,
     cli_path_override: Option<String>,
+    base_ref: Option<String>,
 ) -> Result<String, String> {
     let working_dir = resolve_working_dir(&working_dir)?;
     validate_working_dir(&working_dir)?;
+    if let Some(reason) = budget_block_reason(&db, None, chrono::Utc::now())? {
+        return Err(reason);
+    }
     let session_id = uuid::Uuid::new_v4().to_string();
-    let (worktree_service, worktree_path, execution_dir, fallback_message) =
-        resolve_execution_dir_with_worktree(&working_dir, &session_id);
+    let supervisor = session_supervisor(manager.inner()).await;
+    let worktree_creation = supervisor.lock_worktree_creation().await;
+    // Creating a worktree or copying a folder into a sandbox can take a while.
+    let target = {
+        let (working_dir, session_id, name) =
+            (working_dir.clone(), session_id.clone(), name.clone());
+        let settings = repository_settings_for(&db, &working_dir);
+        let app_data_dir = app_data_dir.0.clone();
+        tokio::task::spawn_blocking(move || {
+            resolve_execution_dir_with_worktree(
+                &working_dir,
+                &session_id,
+                &name,
+                &settings,
+                &app_data_dir,
+                base_ref.as_deref().map(str::trim).filter(|value| !value.is_empty()),
+            )
+        })
+        .await
+        .map_err(|e| format!("Preparing the session directory did not finish: {}", e))??
+    };
+    let execution_dir = target.execution_dir.clone();
 
-    if let Some(message) = fallback_message {
+    if let Some(message) = target.fallback_message.clone() {
         let _ = app.emit(
             "session-debug",
             json!({
@@ -425,7 +1130,7 @@ pub async fn spawn_session(
     ];
 
     let now = chrono::Utc::now().to_rfc3339();
-    let worktree_path_str = worktree_path.as_ref().map(|path| path.display().to_string());
+    let worktree_path_str = target.worktree_path.as_ref().map(|path| path.display().to_string());
 
     let session = Session {
         id: session_id.clone(),
@@ -438,13 +1143,47 @@ pub async fn spawn_session(
 
     db.create_session(&session).map_err(|e| format!("Failed to create session: {}", e))?;
     if let Err(err) = db.update_worktree_path(&session_id, worktree_path_str.as_deref()) {
-        cleanup_failed_spawn_attempt(&db, worktree_service.as_ref(), &session_id);
+        cleanup_failed_spawn_attempt(&db, &target, &session_id);
         return Err(format!("Failed to persist session worktree path: {}", err));
     }
+    drop(worktree_creation);
+    let sandbox_path_str =
+        target.sandbox.as_ref().map(|sandbox| sandbox.tree().display().to_string());
+    if let Err(err) = db.update_sandbox_path(&session_id, sandbox_path_str.as_deref()) {
+        cleanup_failed_spawn_attempt(&db, &target, &session_id);
+        return Err(format!("Failed to persist session sandbox path: {}", err));
+    }
+    if let Err(err) = db.update_session_branch(&session_id, target.branch_name.as_deref()) {
+        cleanup_failed_spawn_attempt(&db, &target, &session_id);
+        return Err(format!("Failed to persist session branch: {}", err));
+    }
+    if let Err(err) = db.update_session_base(
+        &session_id,
+        target.base.as_ref().map(|base| base.base_ref.as_str()),
+        target.base.as_ref().map(|base| base.base_sha.as_str()),
+    ) {
+        cleanup_failed_spawn_attempt(&db, &target, &session_id);
+        return Err(format!("Failed to persist session base commit: {}", err));
+    }
+    if let Err(err) = db.update_session_prompt(&session_id, &prompt) {
+        cleanup_failed_spawn_attempt(&db, &target, &session_id);
+        return Err(format!("Failed to persist session prompt: {}", err));
+    }
+
+    if target.worktree_path.is_some() {
+        let provisioned = provision_session_worktree(db.inner(), &session_id, |event| {
+            let _ = app.emit("session-event", to_frontend_session_event(event));
+        })
+        .await;
+        if let Err(reason) = provisioned {
+            fail_session_before_launch(&app, &db, &session_id, &reason);
+            return Err(reason);
+        }
+    }
 
     let run_id = uuid::Uuid::new_v4().to_string();
     if let Err(err) = db.begin_run_attempt(&session_id, &run_id) {
-        cleanup_failed_spawn_attempt(&db, worktree_service.as_ref(), &session_id);
+        cleanup_failed_spawn_attempt(&db, &target, &session_id);
         return Err(format!("Failed to persist session run metadata: {}", err));
     }
 
@@ -458,6 +1197,9 @@ pub async fn spawn_session(
             "args": spawn_args,
             "working_dir": working_dir.clone(),
             "worktree_path": worktree_path_str,
+            "sandbox_path": sandbox_path_str,
+            "branch": target.branch_name.clone(),
+            "base_sha": target.base.as_ref().map(|base| base.base_sha.clone()),
         }),
     );
 
@@ -471,13 +1213,12 @@ pub async fn spawn_session(
         Err(err) => {
             let normalized = normalize_spawn_session_error(&err, &execution_dir);
             let _ = app.emit("session-error", (&session_id, normalized.clone()));
-            cleanup_failed_spawn_attempt(&db, worktree_service.as_ref(), &session_id);
+            cleanup_failed_spawn_attempt(&db, &target, &session_id);
             return Err(normalized);
         }
     };
 
     let sequence = spawned.seq.clone();
-    let supervisor = session_supervisor(manager.inner()).await;
     let runtime = supervisor
         .register(session_id.clone(), name.clone(), spawned.child)
         .await;
@@ -493,6 +1234,7 @@ pub async fn spawn_session(
     );
 
     let _ = app.emit("session-started", &session_id);
+    dispatch_lifecycle_hooks(&app, &session_id, "started");
 
     Ok(session_id)
 }
@@ -578,6 +1320,242 @@ fn to_frontend_session_event(event: &SessionEvent) -> serde_json::Value {
                 }
             })
         }
+        SessionEventPayload::Hook {
+            hook_id,
+            event: hook_event,
+            command,
+            exit_code,
+            timed_out,
+            stdout,
+            stderr,
+            duration_ms,
+        } => {
+            json!({
+                "type": "hook",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "hook_id": hook_id,
+                    "event": hook_event,
+                    "command": command,
+                    "exit_code": exit_code,
+                    "timed_out": timed_out,
+                    "stdout": stdout,
+                    "stderr": stderr,
+                    "duration_ms": duration_ms
+                }
+            })
+        }
+        SessionEventPayload::Usage {
+            input_tokens,
+            output_tokens,
+            cost_usd,
+        } => {
+            json!({
+                "type": "usage",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "input_tokens": input_tokens,
+                    "output_tokens": output_tokens,
+                    "cost_usd": cost_usd
+                }
+            })
+        }
+        SessionEventPayload::VerificationOutput { stream, line } => {
+            json!({
+                "type": "verification_output",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "stream": stream,
+                    "line": line
+                }
+            })
+        }
+        SessionEventPayload::Verification {
+            status,
+            command,
+            exit_code,
+            timed_out,
+            duration_ms,
+        } => {
+            json!({
+                "type": "verification",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "status": status,
+                    "command": command,
+                    "exit_code": exit_code,
+                    "timed_out": timed_out,
+                    "duration_ms": duration_ms
+                }
+            })
+        }
+        SessionEventPayload::ProvisionStep {
+            step,
+            status,
+            detail,
+            duration_ms,
+        } => {
+            json!({
+                "type": "provision_step",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "step": step,
+                    "status": status,
+                    "detail": detail,
+                    "duration_ms": duration_ms
+                }
+            })
+        }
+        SessionEventPayload::ProvisionOutput { stream, line } => {
+            json!({
+                "type": "provision_output",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "stream": stream,
+                    "line": line
+                }
+            })
+        }
+        SessionEventPayload::BudgetWarning {
+            budget_id,
+            scope,
+            unit,
+            spent,
+            soft_limit,
+            hard_limit,
+        } => {
+            json!({
+                "type": "budget_warning",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "budget_id": budget_id,
+                    "scope": scope,
+                    "unit": unit,
+                    "spent": spent,
+                    "soft_limit": soft_limit,
+                    "hard_limit": hard_limit
+                }
+            })
+        }
+        SessionEventPayload::BudgetExceeded {
+            budget_id,
+            scope,
+            unit,
+            spent,
+            hard_limit,
+        } => {
+            json!({
+                "type": "budget_exceeded",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "budget_id": budget_id,
+                    "scope": scope,
+                    "unit": unit,
+                    "spent": spent,
+                    "hard_limit": hard_limit
+                }
+            })
+        }
+        SessionEventPayload::ConflictWarning {
+            path,
+            other_session_id,
+            other_session_name,
+        } => {
+            json!({
+                "type": "conflict_warning",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "path": path,
+                    "other_session_id": other_session_id,
+                    "other_session_name": other_session_name
+                }
+            })
+        }
+        SessionEventPayload::WorktreeSync {
+            strategy,
+            base_ref,
+            base_sha,
+            status,
+            conflicts,
+            summary,
+        } => {
+            json!({
+                "type": "worktree_sync",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "strategy": strategy,
+                    "base_ref": base_ref,
+                    "base_sha": base_sha,
+                    "status": status,
+                    "conflicts": conflicts,
+                    "summary": summary
+                }
+            })
+        }
+        SessionEventPayload::Rewind {
+            checkpoint_id,
+            run_id,
+            seq,
+            commit_sha,
+        } => {
+            json!({
+                "type": "rewind",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "checkpoint_id": checkpoint_id,
+                    "checkpoint_run_id": run_id,
+                    "checkpoint_seq": seq,
+                    "commit_sha": commit_sha
+                }
+            })
+        }
+        SessionEventPayload::HistoryCompacted {
+            run_id,
+            event_count,
+            event_counts,
+            tools,
+            first_at,
+            last_at,
+            archive_path,
+        } => {
+            json!({
+                "type": "history_compacted",
+                "data": {
+                    "session_id": &event.session_id,
+                    "seq": event.seq,
+                    "timestamp": &event.timestamp,
+                    "run_id": run_id,
+                    "event_count": event_count,
+                    "event_counts": event_counts,
+                    "tools": tools,
+                    "first_at": first_at,
+                    "last_at": last_at,
+                    "archive_path": archive_path
+                }
+            })
+        }
     }
 }
 
@@ -633,6 +1611,67 @@ pub async fn list_session_history(
         .map_err(|e| format!("Failed to list session history: {}", e))
 }
 
+#[tauri::command]
+pub async fn list_session_history_page(
+    db: State<'_, Database>,
+    id: String,
+    query: Option<HistoryPageQuery>,
+) -> Result<SessionHistoryPage, String> {
+    db.list_session_history_page(&id, &query.unwrap_or_default())
+        .map_err(|e| format!("Failed to list session history: {}", e))
+}
+
+#[tauri::command]
+pub async fn count_session_history(
+    db: State<'_, Database>,
+    id: String,
+    run_id: Option<String>,
+    event_types: Option<Vec<String>>,
+) -> Result<u64, String> {
+    db.count_session_history(&id, run_id.as_deref(), &event_types.unwrap_or_default())
+        .map_err(|e| format!("Failed to count session history: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_session_messages_page(
+    db: State<'_, Database>,
+    id: String,
+    query: Option<MessagePageQuery>,
+) -> Result<SessionMessagePage, String> {
+    db.list_session_messages_page(&id, &query.unwrap_or_default())
+        .map_err(|e| format!("Failed to list session messages: {}", e))
+}
+
+#[tauri::command]
+pub async fn count_session_messages(db: State<'_, Database>, id: String) -> Result<u64, String> {
+    db.count_session_messages(&id).map_err(|e| format!("Failed to count session messages: {}", e))
+}
+
+#[tauri::command]
+pub async fn search_sessions(
+    db: State<'_, Database>,
+    query: String,
+    filters: Option<SessionSearchFilters>,
+    limit: Option<u32>,
+) -> Result<Vec<SessionSearchHit>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    db.search_sessions(query, &filters.unwrap_or_default(), limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .map_err(|e| format!("Failed to search sessions: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_session_runs(
+    db: State<'_, Database>,
+    id: String,
+) -> Result<Vec<SessionRun>, String> {
+    db.list_session_runs(&id)
+        .map_err(|e| format!("Failed to list session runs: {}", e))
+}
+
 #[tauri::command]
 pub async fn interrupt_session(
     db: State<'_, Database>,
@@ -641,10 +1680,18 @@ pub async fn interrupt_session(
 ) -> Result<(), String> {
     let supervisor = session_supervisor(manager.inner()).await;
     supervisor
-        .interrupt_session_with_deadline(db.inner(), &id, Duration::from_secs(10))
+        .interrupt_session_with_deadline(db.inner(), &id, INTERRUPT_DEADLINE)
         .await
 }
 
+#[tauri::command]
+pub async fn list_lifecycle_operations(
+    manager: State<'_, Arc<Mutex<SessionManager>>>,
+) -> Result<Vec<LifecycleOperationSnapshot>, String> {
+    let supervisor = session_supervisor(manager.inner()).await;
+    Ok(supervisor.list_lifecycle_operations())
+}
+
 #[tauri::command]
 pub async fn resume_session(
     app: AppHandle,
@@ -659,6 +1706,38 @@ pub async fn resume_session(
         return Err("Resume prompt cannot be empty".to_string());
     }
 
+    let supervisor = session_supervisor(manager.inner()).await;
+    let gate = match supervisor
+        .acquire_lifecycle_operation(&id, LifecycleOperationKind::Resume)
+        .await?
+    {
+        LifecycleAdmission::Acquired(guard) => guard,
+        LifecycleAdmission::Joined(result) => return result,
+    };
+
+    let result = resume_session_locked(
+        app,
+        db.inner(),
+        manager.inner().clone(),
+        &supervisor,
+        id,
+        prompt,
+        cli_path_override,
+    )
+    .await;
+    gate.complete(&result);
+    result
+}
+
+async fn resume_session_locked(
+    app: AppHandle,
+    db: &Database,
+    manager: Arc<Mutex<SessionManager>>,
+    supervisor: &SessionSupervisor,
+    id: String,
+    prompt: &str,
+    cli_path_override: Option<String>,
+) -> Result<(), String> {
     let session = db
         .get_session(&id)
         .map_err(|e| format!("Failed to load session for resume: {}", e))?
@@ -668,22 +1747,43 @@ pub async fn resume_session(
         return Err("Only completed or interrupted sessions can be resumed".to_string());
     }
 
-    let supervisor = session_supervisor(manager.inner()).await;
-    let _gate = supervisor.acquire_lifecycle_operation(&id, "resume")?;
-
     if supervisor.get(&id).await.is_some() {
         return Err("Session runtime is already active".to_string());
     }
 
+    if let Some(reason) = budget_block_reason(db, Some(&id), chrono::Utc::now())? {
+        return Err(reason);
+    }
+
+    let pruned_at = db
+        .get_dashboard_session(&id)
+        .map_err(|e| format!("Failed to load session metadata for resume: {}", e))?
+        .and_then(|row| row.worktree_pruned_at);
+    if let Some(pruned_at) = pruned_at {
+        return Err(format!(
+            "The session's worktree was removed by the retention policy on {}; its branch is kept",
+            pruned_at
+        ));
+    }
+
+    let sandbox_path = db
+        .get_session_sandbox_path(&id)
+        .map_err(|e| format!("Failed to resolve session sandbox path: {}", e))?;
     let execution_dir = db
         .get_session_worktree_path(&id)
         .map_err(|e| format!("Failed to resolve session worktree path: {}", e))?
+        .or(sandbox_path)
         .unwrap_or_else(|| session.working_dir.clone());
 
     let cli_override_path =
         cli_path_override.filter(|value| !value.trim().is_empty()).map(PathBuf::from);
     let cli = ClaudeCli::find_with_override(cli_override_path)?;
 
+    let prompt = match sync_before_resume(&app, db, &id, &session.working_dir).await {
+        Some(sync_prompt) => format!("{}\n\n{}", sync_prompt, prompt),
+        None => prompt.to_string(),
+    };
+
     let resumed_at = chrono::Utc::now().to_rfc3339();
     let run_id = uuid::Uuid::new_v4().to_string();
     let resumed = db
@@ -695,7 +1795,7 @@ pub async fn resume_session(
 
     let (event_tx, event_rx) = mpsc::channel::<SessionEvent>(256);
     let spawned = match cli
-        .spawn_resume_with_events(prompt, &execution_dir, &id, event_tx)
+        .spawn_resume_with_events(&prompt, &execution_dir, &id, event_tx)
         .await
     {
         Ok(spawned) => spawned,
@@ -713,18 +1813,78 @@ pub async fn resume_session(
         .await;
 
     launch_session_event_tasks(
-        app,
-        manager.inner().clone(),
-        id,
+        app.clone(),
+        manager,
+        id.clone(),
         run_id,
         spawned.seq,
         runtime,
         event_rx,
     );
+    dispatch_lifecycle_hooks(&app, &id, "started");
 
     Ok(())
 }
 
+/// Rewinds the session's worktree to one of its checkpoints. With a `prompt` the CLI is resumed
+/// right away, told that its files now match the checkpoint.
+#[tauri::command]
+pub async fn rewind_session(
+    app: AppHandle,
+    db: State<'_, Database>,
+    manager: State<'_, Arc<Mutex<SessionManager>>>,
+    id: String,
+    checkpoint_id: String,
+    prompt: Option<String>,
+    cli_path_override: Option<String>,
+) -> Result<SessionCheckpoint, String> {
+    let supervisor = session_supervisor(manager.inner()).await;
+    let gate = match supervisor
+        .acquire_lifecycle_operation(&id, LifecycleOperationKind::Rewind)
+        .await?
+    {
+        LifecycleAdmission::Acquired(guard) => guard,
+        LifecycleAdmission::Joined(_) => {
+            return Err("Another lifecycle operation on the session is in progress".to_string())
+        }
+    };
+
+    let session = db
+        .get_session(&id)
+        .map_err(|e| format!("Failed to get session: {}", e))?
+        .ok_or_else(|| format!("Session not found: {}", id))?;
+    if matches!(session.status.as_str(), "starting" | "running" | "interrupting" | "resuming") {
+        return Err("Stop or wait for the session before rewinding it".to_string());
+    }
+    if supervisor.get(&id).await.is_some() {
+        return Err("Session runtime is already active".to_string());
+    }
+
+    let rewind_app = app.clone();
+    let rewind_id = id.clone();
+    let (checkpoint, events) = tokio::task::spawn_blocking(move || {
+        rewind_to_checkpoint(&rewind_app.state::<Database>(), &rewind_id, &checkpoint_id)
+    })
+    .await
+    .map_err(|e| format!("Rewind did not finish: {}", e))??;
+    emit_recorded_events(&app, &events);
+    // The resume below takes the gate for itself.
+    drop(gate);
+
+    let prompt = prompt.map(|prompt| prompt.trim().to_string()).filter(|prompt| !prompt.is_empty());
+    if let Some(prompt) = prompt {
+        let prompt = format!(
+            "The files in your working directory were rewound to how they were after event {} \
+             of this session. Anything changed after that point is gone, whatever the \
+             conversation says.\n\n{}",
+            checkpoint.seq, prompt
+        );
+        resume_session(app, db, manager, id, prompt, cli_path_override).await?;
+    }
+
+    Ok(checkpoint)
+}
+
 #[tauri::command]
 pub async fn kill_session(
     manager: State<'_, Arc<Mutex<SessionManager>>>,
@@ -740,7 +1900,9 @@ pub async fn kill_session(
 pub async fn delete_session(
     manager: State<'_, Arc<Mutex<SessionManager>>>,
     db: State<'_, Database>,
+    app_data_dir: State<'_, AppDataDir>,
     id: String,
+    delete_branch: Option<bool>,
 ) -> Result<(), String> {
     let session = db
         .get_session(&id)
@@ -748,6 +1910,12 @@ pub async fn delete_session(
     let worktree_path = db
         .get_session_worktree_path(&id)
         .map_err(|e| format!("Failed to get session worktree path: {}", e))?;
+    let branch_name = db
+        .get_session_branch(&id)
+        .map_err(|e| format!("Failed to get session branch: {}", e))?;
+    let sandbox_path = db
+        .get_session_sandbox_path(&id)
+        .map_err(|e| format!("Failed to get session sandbox path: {}", e))?;
 
     let supervisor = session_supervisor(manager.inner()).await;
     if let Some(runtime) = supervisor.remove(&id).await {
@@ -756,13 +1924,27 @@ pub async fn delete_session(
         let _ = child.kill().await;
     }
 
-    if let (Some(session_record), Some(path)) = (session, worktree_path) {
+    if let Some(session_record) = session {
         if let Ok(worktree_service) = WorktreeService::from_working_dir(&session_record.working_dir) {
-            let _ = worktree_service.remove_worktree_at_path(Path::new(&path), true);
-            let _ = worktree_service.prune_worktrees();
+            if let Some(path) = worktree_path {
+                let _ = worktree_service.remove_worktree_at_path(Path::new(&path), true);
+                let _ = worktree_service.prune_worktrees();
+            }
+            let _ = remove_checkpoint_refs(worktree_service.repo_root(), &id);
+            // The branch outlives the session unless the user asked to drop it.
+            if let (true, Some(branch)) = (delete_branch.unwrap_or(false), branch_name) {
+                worktree_service
+                    .delete_branch(&branch)
+                    .map_err(|e| format!("Failed to delete session branch: {}", e))?;
+            }
         }
     }
 
+    if let Some(path) = sandbox_path {
+        let _ = SessionSandbox::open(Path::new(&path)).remove();
+    }
+    let _ = std::fs::remove_file(archive_path(&app_data_dir.0.join(HISTORY_ARCHIVE_DIR), &id));
+
     db.delete_session(&id)
         .map_err(|e| format!("Failed to delete session: {}", e))?;
 
@@ -775,7 +1957,7 @@ mod tests {
         normalize_spawn_session_error, project_dashboard_rows, resolve_execution_dir_with_worktree,
         resolve_working_dir,
     };
-    use crate::db::SessionDashboardRow;
+    use crate::db::{RepositorySettings, SessionDashboardRow};
     use crate::session::projection::DASHBOARD_STATUS_FAILED;
     use tempfile::tempdir;
 
@@ -823,6 +2005,7 @@ mod tests {
             restored: false,
             restored_at: None,
             recovery_hint: false,
+            ..Default::default()
         }];
 
         let projected = project_dashboard_rows(rows);
@@ -834,19 +2017,60 @@ mod tests {
     fn resolve_execution_dir_falls_back_for_non_git_folder() {
         let temp = tempdir().expect("tempdir should be created");
         let working_dir = temp.path().display().to_string();
+        // A file where the app data dir should be, so the sandbox cannot be created.
+        let app_data = tempdir().expect("app data tempdir should be created");
+        let app_data_dir = app_data.path().join("not-a-dir");
+        std::fs::write(&app_data_dir, "").expect("file should write");
+
+        let target = resolve_execution_dir_with_worktree(
+            &working_dir,
+            "session-non-git",
+            "Non git",
+            &RepositorySettings::default(),
+            &app_data_dir,
+            None,
+        )
+        .expect("non-git folders should fall back");
+
+        assert!(target.worktree_service.is_none());
+        assert!(target.worktree_path.is_none());
+        assert!(target.branch_name.is_none());
+        assert!(target.sandbox.is_none());
+        assert_eq!(target.execution_dir, working_dir);
+        let message = target.fallback_message.as_deref().unwrap_or_default();
+        assert!(
+            message.contains("No git repository detected")
+                && message.contains("could not be sandboxed"),
+            "expected explicit non-git fallback message, got {:?}",
+            message
+        );
+    }
 
-        let (service, worktree_path, execution_dir, fallback_message) =
-            resolve_execution_dir_with_worktree(&working_dir, "session-non-git");
+    #[test]
+    fn resolve_execution_dir_sandboxes_non_git_folder() {
+        let temp = tempdir().expect("tempdir should be created");
+        let app_data = tempdir().expect("app data tempdir should be created");
+        std::fs::write(temp.path().join("notes.txt"), "draft\n").expect("file should write");
+        let working_dir = temp.path().display().to_string();
 
-        assert!(service.is_none());
-        assert!(worktree_path.is_none());
-        assert_eq!(execution_dir, working_dir);
-        assert!(
-            fallback_message
-                .as_deref()
-                .unwrap_or_default()
-                .contains("No git repository detected"),
-            "expected explicit non-git fallback message"
+        let target = resolve_execution_dir_with_worktree(
+            &working_dir,
+            "session-sandbox",
+            "Sandbox",
+            &RepositorySettings::default(),
+            app_data.path(),
+            None,
+        )
+        .expect("non-git folders should be sandboxed");
+
+        let sandbox = target.sandbox.expect("expected a sandbox");
+        assert!(target.worktree_path.is_none());
+        assert!(target.fallback_message.is_none());
+        assert_eq!(target.execution_dir, sandbox.tree().display().to_string());
+        assert!(sandbox.tree().starts_with(app_data.path()));
+        assert_eq!(
+            std::fs::read_to_string(sandbox.tree().join("notes.txt")).expect("copy should read"),
+            "draft\n"
         );
     }
 
@@ -862,15 +2086,27 @@ mod tests {
 
         let working_dir = temp.path().display().to_string();
         let session_id = "session-git";
-        let (service, worktree_path, execution_dir, fallback_message) =
-            resolve_execution_dir_with_worktree(&working_dir, session_id);
+        let target = resolve_execution_dir_with_worktree(
+            &working_dir,
+            session_id,
+            "Fix login bug",
+            &RepositorySettings::default(),
+            temp.path(),
+            None,
+        )
+        .expect("worktree should resolve");
 
-        assert!(fallback_message.is_none());
-        let service = service.expect("expected worktree service for git repo");
-        let worktree_path = worktree_path.expect("expected created worktree path");
+        assert!(target.fallback_message.is_none());
+        let service = target.worktree_service.expect("expected worktree service for git repo");
+        let worktree_path = target.worktree_path.expect("expected created worktree path");
         assert!(worktree_path.exists());
         assert!(worktree_path.ends_with(session_id));
-        assert_eq!(execution_dir, worktree_path.display().to_string());
+        assert_eq!(target.execution_dir, worktree_path.display().to_string());
+        assert_eq!(target.branch_name.as_deref(), Some("lulu/fix-login-bug-session-"));
+        assert!(service.branch_exists("lulu/fix-login-bug-session-"));
+        let base = target.base.expect("expected recorded base commit");
+        assert_eq!(base.base_ref, "main");
+        assert_eq!(base.base_sha, service.resolve_commit("HEAD").expect("HEAD should resolve"));
 
         service
             .remove_worktree_for_session(session_id)
@@ -878,20 +2114,79 @@ mod tests {
         service.prune_worktrees().expect("worktree prune should succeed");
     }
 
+    #[test]
+    fn resolve_execution_dir_starts_worktree_from_explicit_base_ref() {
+        let temp = tempdir().expect("tempdir should be created");
+        run_git(temp.path(), &["init", "--initial-branch=main"]);
+        run_git(temp.path(), &["config", "user.name", "Lulu Test"]);
+        run_git(temp.path(), &["config", "user.email", "lulu@example.com"]);
+        std::fs::write(temp.path().join("README.md"), "# v1\n").expect("seed file should write");
+        run_git(temp.path(), &["add", "README.md"]);
+        run_git(temp.path(), &["commit", "-m", "initial"]);
+        run_git(temp.path(), &["tag", "v1"]);
+        run_git(temp.path(), &["checkout", "-b", "feature"]);
+        std::fs::write(temp.path().join("README.md"), "# feature\n").expect("file should write");
+        run_git(temp.path(), &["commit", "-am", "feature work"]);
+
+        let working_dir = temp.path().display().to_string();
+        let target = resolve_execution_dir_with_worktree(
+            &working_dir,
+            "session-base",
+            "Based",
+            &RepositorySettings::default(),
+            temp.path(),
+            Some("v1"),
+        )
+        .expect("tag base ref should resolve");
+
+        let service = target.worktree_service.expect("expected worktree service");
+        let worktree_path = target.worktree_path.expect("expected created worktree path");
+        let base = target.base.expect("expected recorded base commit");
+        assert_eq!(base.base_ref, "v1");
+        assert_eq!(base.base_sha, service.resolve_commit("v1").expect("tag should resolve"));
+        assert_eq!(
+            std::fs::read_to_string(worktree_path.join("README.md")).expect("file should read"),
+            "# v1\n"
+        );
+
+        let missing = resolve_execution_dir_with_worktree(
+            &working_dir,
+            "session-missing-base",
+            "Missing",
+            &RepositorySettings::default(),
+            temp.path(),
+            Some("no-such-branch"),
+        );
+        assert!(missing.is_err(), "an unknown base ref must fail the spawn");
+
+        service
+            .remove_worktree_for_session("session-base")
+            .expect("worktree cleanup should succeed");
+        service.prune_worktrees().expect("worktree prune should succeed");
+    }
+
     #[test]
     fn resolve_execution_dir_falls_back_when_worktree_creation_fails() {
         let temp = tempdir().expect("tempdir should be created");
         run_git(temp.path(), &["init", "--initial-branch=main"]);
 
         let working_dir = temp.path().display().to_string();
-        let (service, worktree_path, execution_dir, fallback_message) =
-            resolve_execution_dir_with_worktree(&working_dir, "session-no-head");
+        let target = resolve_execution_dir_with_worktree(
+            &working_dir,
+            "session-no-head",
+            "No head",
+            &RepositorySettings::default(),
+            temp.path(),
+            None,
+        )
+        .expect("missing HEAD should fall back");
 
-        assert!(service.is_none());
-        assert!(worktree_path.is_none());
-        assert_eq!(execution_dir, working_dir);
+        assert!(target.worktree_service.is_none());
+        assert!(target.worktree_path.is_none());
+        assert_eq!(target.execution_dir, working_dir);
         assert!(
-            fallback_message
+            target
+                .fallback_message
                 .as_deref()
                 .unwrap_or_default()
                 .contains("Worktree creation failed"),
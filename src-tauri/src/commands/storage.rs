@@ -0,0 +1,86 @@
+use crate::db::{Database, HistoryRetentionPolicy};
+use crate::session::history_retention::{
+    apply_history_retention, apply_startup_history_retention,
+    rehydrate_session_history as rehydrate_history, storage_usage_report,
+    validate_history_retention_policy, HistoryRetentionReport, StorageUsageReport,
+    HISTORY_ARCHIVE_DIR,
+};
+use crate::session::worktree::AppDataDir;
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+#[tauri::command]
+pub async fn get_history_retention_policy(
+    db: State<'_, Database>,
+) -> Result<HistoryRetentionPolicy, String> {
+    db.get_history_retention_policy()
+        .map_err(|e| format!("Failed to load history retention policy: {}", e))
+}
+
+#[tauri::command]
+pub async fn save_history_retention_policy(
+    db: State<'_, Database>,
+    policy: HistoryRetentionPolicy,
+) -> Result<HistoryRetentionPolicy, String> {
+    validate_history_retention_policy(&policy)?;
+    db.save_history_retention_policy(&policy)
+        .map_err(|e| format!("Failed to save history retention policy: {}", e))?;
+    Ok(policy)
+}
+
+/// Compacts or archives expired session history now, the same pass that runs after startup,
+/// but this one also converts an old database to incremental vacuuming. With `dry_run` the
+/// report lists what would be removed without touching anything.
+#[tauri::command]
+pub async fn run_history_retention(
+    db: State<'_, Database>,
+    app_data_dir: State<'_, AppDataDir>,
+    dry_run: Option<bool>,
+) -> Result<HistoryRetentionReport, String> {
+    apply_history_retention(
+        &db,
+        &app_data_dir.0.join(HISTORY_ARCHIVE_DIR),
+        dry_run.unwrap_or(false),
+    )
+}
+
+/// Applies history retention once the app is up, off the startup path: compacting and vacuuming
+/// a large history takes a while. Failures only reach the debug stream.
+pub fn start_history_retention(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let _ = tokio::task::spawn_blocking(move || {
+            let db = app.state::<Database>();
+            let archive_dir = app.state::<AppDataDir>().0.join(HISTORY_ARCHIVE_DIR);
+            if let Err(message) = apply_startup_history_retention(db.inner(), &archive_dir) {
+                let _ = app.emit(
+                    "session-debug",
+                    json!({
+                        "kind": "retention-error",
+                        "timestamp": chrono::Utc::now().to_rfc3339(),
+                        "message": message,
+                    }),
+                );
+            }
+        })
+        .await;
+    });
+}
+
+/// Restores a session's archived events so its full history can be viewed. Returns the number
+/// of events restored.
+#[tauri::command]
+pub async fn rehydrate_session_history(
+    db: State<'_, Database>,
+    app_data_dir: State<'_, AppDataDir>,
+    id: String,
+) -> Result<u64, String> {
+    rehydrate_history(&db, &app_data_dir.0.join(HISTORY_ARCHIVE_DIR), &id)
+}
+
+#[tauri::command]
+pub async fn get_storage_usage(
+    db: State<'_, Database>,
+    app_data_dir: State<'_, AppDataDir>,
+) -> Result<StorageUsageReport, String> {
+    storage_usage_report(&db, &app_data_dir.0.join(HISTORY_ARCHIVE_DIR))
+}
@@ -0,0 +1,114 @@
+use crate::db::{Database, WebhookDelivery, WebhookTarget};
+use crate::session::webhooks::{is_webhook_event, DEFAULT_WEBHOOK_MAX_ATTEMPTS, WEBHOOK_EVENTS};
+use tauri::State;
+
+const DEFAULT_DELIVERY_LIMIT: i64 = 100;
+
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn create_webhook_target(
+    db: State<'_, Database>,
+    name: String,
+    url: String,
+    events: Vec<String>,
+    secret: Option<String>,
+    cost_threshold_usd: Option<f64>,
+    stall_after_secs: Option<i64>,
+    max_attempts: Option<i64>,
+) -> Result<WebhookTarget, String> {
+    let url = url.trim();
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err("Webhook URL must start with http:// or https://".to_string());
+    }
+
+    let mut normalized_events = Vec::new();
+    for event in events {
+        let event = event.trim().to_lowercase();
+        if !is_webhook_event(&event) {
+            return Err(format!(
+                "Unknown webhook event '{}'. Expected one of: {}",
+                event,
+                WEBHOOK_EVENTS.join(", ")
+            ));
+        }
+        if !normalized_events.contains(&event) {
+            normalized_events.push(event);
+        }
+    }
+    if normalized_events.is_empty() {
+        return Err("Webhook must subscribe to at least one event".to_string());
+    }
+
+    let subscribes = |event: &str| normalized_events.iter().any(|e| e == event);
+    if subscribes("cost_threshold") && !cost_threshold_usd.is_some_and(|value| value > 0.0) {
+        return Err("cost_threshold webhooks need a cost threshold greater than zero".to_string());
+    }
+    if subscribes("stalled") && stall_after_secs.is_none_or(|value| value <= 0) {
+        return Err("stalled webhooks need a stall timeout greater than zero".to_string());
+    }
+
+    let max_attempts = max_attempts.unwrap_or(DEFAULT_WEBHOOK_MAX_ATTEMPTS);
+    if max_attempts <= 0 {
+        return Err("Webhook max attempts must be greater than zero".to_string());
+    }
+
+    let name = name.trim();
+    let target = WebhookTarget {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: if name.is_empty() { url.to_string() } else { name.to_string() },
+        url: url.to_string(),
+        secret: secret.filter(|value| !value.is_empty()),
+        events: normalized_events,
+        cost_threshold_usd,
+        stall_after_secs,
+        max_attempts,
+        enabled: true,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    db.create_webhook_target(&target)
+        .map_err(|e| format!("Failed to create webhook target: {}", e))?;
+
+    Ok(target)
+}
+
+#[tauri::command]
+pub async fn list_webhook_targets(db: State<'_, Database>) -> Result<Vec<WebhookTarget>, String> {
+    db.list_webhook_targets()
+        .map_err(|e| format!("Failed to list webhook targets: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_webhook_target_enabled(
+    db: State<'_, Database>,
+    id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let updated = db
+        .set_webhook_target_enabled(&id, enabled)
+        .map_err(|e| format!("Failed to update webhook target: {}", e))?;
+    if !updated {
+        return Err(format!("Webhook target {} not found", id));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_webhook_target(db: State<'_, Database>, id: String) -> Result<(), String> {
+    db.delete_webhook_target(&id)
+        .map_err(|e| format!("Failed to delete webhook target: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_webhook_deliveries(
+    db: State<'_, Database>,
+    target_id: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<WebhookDelivery>, String> {
+    db.list_webhook_deliveries(
+        target_id.as_deref(),
+        limit.unwrap_or(DEFAULT_DELIVERY_LIMIT).max(1),
+    )
+    .map_err(|e| format!("Failed to list webhook deliveries: {}", e))
+}
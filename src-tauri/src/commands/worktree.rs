@@ -0,0 +1,407 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::commands::session::{
+    configured_worktree_service, emit_recorded_events, reconcile_session_worktrees,
+};
+use crate::db::{Database, SessionCheckpoint, SessionIntegration};
+use crate::session::diff::{DiffLimits, WorktreeDiff};
+use crate::session::export::{write_session_export, ExportFormat, ExportRequest, ExportResult};
+use crate::session::integration::{
+    integrate_session_changes, IntegrationRequest, IntegrationStrategy,
+};
+use crate::session::overlap::{self, refresh_repo_diff_paths, RepoConflict};
+use crate::session::quarantine::{self, PurgeReport, QuarantinedWorktree, ReconcileReport};
+use crate::session::retention::{
+    apply_startup_worktree_retention, apply_worktree_retention, RetentionReport,
+};
+use crate::session::sandbox::{SandboxApplyReport, SessionSandbox};
+use crate::session::sync::{self, SyncConflictPolicy, SyncOutcome, SyncStrategy};
+use crate::session::worktree::AppDataDir;
+use crate::session::{
+    LifecycleAdmission, LifecycleOperationKind, SessionManager, WorktreeService,
+};
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Mutex;
+
+#[tauri::command]
+pub async fn get_session_diff(
+    db: State<'_, Database>,
+    id: String,
+    max_file_bytes: Option<usize>,
+) -> Result<WorktreeDiff, String> {
+    let session = db
+        .get_session(&id)
+        .map_err(|e| format!("Failed to get session: {}", e))?
+        .ok_or_else(|| format!("Session not found: {}", id))?;
+    let limits = DiffLimits {
+        max_file_bytes: max_file_bytes.unwrap_or(DiffLimits::default().max_file_bytes),
+        ..DiffLimits::default()
+    };
+    let sandbox_path = db
+        .get_session_sandbox_path(&id)
+        .map_err(|e| format!("Failed to get session sandbox path: {}", e))?;
+    if let Some(sandbox_path) = sandbox_path {
+        return SessionSandbox::open(Path::new(&sandbox_path)).diff(limits);
+    }
+
+    let worktree_path = db
+        .get_session_worktree_path(&id)
+        .map_err(|e| format!("Failed to get session worktree path: {}", e))?
+        .ok_or_else(|| format!("Session {} has no worktree", id))?;
+    let base = db
+        .get_session_base(&id)
+        .map_err(|e| format!("Failed to get session base commit: {}", e))?
+        .ok_or_else(|| format!("Session {} has no recorded base commit", id))?;
+
+    let service = WorktreeService::from_working_dir(&session.working_dir)?;
+    service.diff_against_base(Path::new(&worktree_path), &base.base_sha, limits)
+}
+
+/// Copies a sandboxed session's changes back into its original folder. Files that were also
+/// changed there since the sandbox was made are reported as conflicts and skipped unless
+/// `force` is set.
+#[tauri::command]
+pub async fn apply_sandbox_changes(
+    db: State<'_, Database>,
+    id: String,
+    force: Option<bool>,
+) -> Result<SandboxApplyReport, String> {
+    let session = db
+        .get_session(&id)
+        .map_err(|e| format!("Failed to get session: {}", e))?
+        .ok_or_else(|| format!("Session not found: {}", id))?;
+    if matches!(session.status.as_str(), "starting" | "running" | "interrupting" | "resuming") {
+        return Err("Stop or wait for the session before applying its changes".to_string());
+    }
+    let sandbox_path = db
+        .get_session_sandbox_path(&id)
+        .map_err(|e| format!("Failed to get session sandbox path: {}", e))?
+        .ok_or_else(|| format!("Session {} has no sandbox", id))?;
+
+    SessionSandbox::open(Path::new(&sandbox_path)).apply(force.unwrap_or(false))
+}
+
+/// Lands a stopped session's changes on `target_branch`. The session's lifecycle gate is held
+/// throughout, so it cannot be resumed or synced while the merge reads its branch and worktree.
+#[tauri::command]
+pub async fn merge_session_changes(
+    db: State<'_, Database>,
+    manager: State<'_, Arc<Mutex<SessionManager>>>,
+    id: String,
+    strategy: IntegrationStrategy,
+    target_branch: Option<String>,
+) -> Result<SessionIntegration, String> {
+    let supervisor = manager.lock().await.supervisor.clone();
+    let _gate = match supervisor
+        .acquire_lifecycle_operation(&id, LifecycleOperationKind::Merge)
+        .await?
+    {
+        LifecycleAdmission::Acquired(guard) => guard,
+        LifecycleAdmission::Joined(_) => {
+            return Err("Another lifecycle operation on the session is in progress".to_string())
+        }
+    };
+
+    let session = db
+        .get_session(&id)
+        .map_err(|e| format!("Failed to get session: {}", e))?
+        .ok_or_else(|| format!("Session not found: {}", id))?;
+    if matches!(session.status.as_str(), "starting" | "running" | "interrupting" | "resuming") {
+        return Err("Stop or wait for the session before merging its changes".to_string());
+    }
+
+    let worktree_path = db
+        .get_session_worktree_path(&id)
+        .map_err(|e| format!("Failed to get session worktree path: {}", e))?
+        .ok_or_else(|| format!("Session {} has no worktree", id))?;
+    let session_branch = db
+        .get_session_branch(&id)
+        .map_err(|e| format!("Failed to get session branch: {}", e))?
+        .ok_or_else(|| format!("Session {} has no branch", id))?;
+    let base = db
+        .get_session_base(&id)
+        .map_err(|e| format!("Failed to get session base commit: {}", e))?
+        .ok_or_else(|| format!("Session {} has no recorded base commit", id))?;
+
+    let target_branch = target_branch
+        .map(|branch| branch.trim().to_string())
+        .filter(|branch| !branch.is_empty())
+        .unwrap_or_else(|| base.base_ref.clone());
+
+    let service = WorktreeService::from_working_dir(&session.working_dir)?;
+    let request = IntegrationRequest {
+        session_name: session.name,
+        session_branch,
+        worktree_path: PathBuf::from(worktree_path),
+        base_sha: base.base_sha,
+        target_branch: target_branch.clone(),
+        strategy,
+    };
+    let outcome =
+        tokio::task::spawn_blocking(move || integrate_session_changes(&service, &request))
+            .await
+            .map_err(|e| format!("Merge did not finish: {}", e))??;
+
+    let integration = SessionIntegration {
+        id: uuid::Uuid::new_v4().to_string(),
+        session_id: id,
+        strategy: strategy.as_str().to_string(),
+        target_branch,
+        status: outcome.status,
+        result_sha: outcome.result_sha,
+        conflicts: outcome.conflicts,
+        summary: outcome.summary,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    db.insert_session_integration(&integration)
+        .map_err(|e| format!("Failed to record merge outcome: {}", e))?;
+
+    Ok(integration)
+}
+
+/// Rebases or merges the session branch onto the latest commit of its base ref. A strategy or
+/// conflict policy left out comes from the repository settings. The session's lifecycle gate is
+/// held throughout, so it cannot be resumed while its worktree is being rewritten.
+#[tauri::command]
+pub async fn sync_session_worktree(
+    app: AppHandle,
+    db: State<'_, Database>,
+    manager: State<'_, Arc<Mutex<SessionManager>>>,
+    id: String,
+    strategy: Option<SyncStrategy>,
+    on_conflict: Option<SyncConflictPolicy>,
+) -> Result<SyncOutcome, String> {
+    let supervisor = manager.lock().await.supervisor.clone();
+    let _gate = match supervisor
+        .acquire_lifecycle_operation(&id, LifecycleOperationKind::Sync)
+        .await?
+    {
+        LifecycleAdmission::Acquired(guard) => guard,
+        LifecycleAdmission::Joined(_) => {
+            return Err("Another lifecycle operation on the session is in progress".to_string())
+        }
+    };
+
+    let session = db
+        .get_session(&id)
+        .map_err(|e| format!("Failed to get session: {}", e))?
+        .ok_or_else(|| format!("Session not found: {}", id))?;
+    if matches!(session.status.as_str(), "starting" | "running" | "interrupting" | "resuming") {
+        return Err("Stop or wait for the session before syncing its worktree".to_string());
+    }
+
+    let service = WorktreeService::from_working_dir(&session.working_dir)?;
+    let settings = db
+        .get_repository_settings(&service.repo_root().display().to_string())
+        .map_err(|e| format!("Failed to load repository settings: {}", e))?
+        .unwrap_or_default();
+    let (default_strategy, default_on_conflict) = sync::configured_sync(&settings);
+    let strategy = strategy.unwrap_or(default_strategy);
+    let on_conflict = on_conflict.unwrap_or(default_on_conflict);
+
+    let app_for_sync = app.clone();
+    let (outcome, events) = tokio::task::spawn_blocking(move || {
+        let db = app_for_sync.state::<Database>();
+        sync::sync_session_worktree(db.inner(), &id, strategy, on_conflict)
+    })
+    .await
+    .map_err(|e| format!("Worktree sync did not finish: {}", e))??;
+    emit_recorded_events(&app, &events);
+    Ok(outcome)
+}
+
+/// The session's worktree checkpoints, oldest first.
+#[tauri::command]
+pub async fn list_checkpoints(
+    db: State<'_, Database>,
+    id: String,
+) -> Result<Vec<SessionCheckpoint>, String> {
+    db.list_session_checkpoints(&id).map_err(|e| format!("Failed to list checkpoints: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_session_integrations(
+    db: State<'_, Database>,
+    id: String,
+) -> Result<Vec<SessionIntegration>, String> {
+    db.list_session_integrations(&id)
+        .map_err(|e| format!("Failed to list merge history: {}", e))
+}
+
+#[tauri::command]
+pub async fn export_session_changes(
+    db: State<'_, Database>,
+    id: String,
+    format: ExportFormat,
+    destination: String,
+) -> Result<ExportResult, String> {
+    let destination = destination.trim();
+    if destination.is_empty() {
+        return Err("Export destination cannot be empty".to_string());
+    }
+
+    let session = db
+        .get_session(&id)
+        .map_err(|e| format!("Failed to get session: {}", e))?
+        .ok_or_else(|| format!("Session not found: {}", id))?;
+    let worktree_path = db
+        .get_session_worktree_path(&id)
+        .map_err(|e| format!("Failed to get session worktree path: {}", e))?
+        .ok_or_else(|| format!("Session {} has no worktree", id))?;
+    let session_branch = db
+        .get_session_branch(&id)
+        .map_err(|e| format!("Failed to get session branch: {}", e))?
+        .ok_or_else(|| format!("Session {} has no branch", id))?;
+    let base = db
+        .get_session_base(&id)
+        .map_err(|e| format!("Failed to get session base commit: {}", e))?
+        .ok_or_else(|| format!("Session {} has no recorded base commit", id))?;
+    let prompt = db
+        .get_session_prompt(&id)
+        .map_err(|e| format!("Failed to get session prompt: {}", e))?;
+    let runs = db
+        .list_session_runs(&id)
+        .map_err(|e| format!("Failed to list session runs: {}", e))?;
+    let run_metadata = db
+        .get_session_run_metadata(&id)
+        .map_err(|e| format!("Failed to get session run metadata: {}", e))?;
+    let usage = db
+        .get_session_usage(&id)
+        .map_err(|e| format!("Failed to get session usage: {}", e))?;
+
+    let manifest = json!({
+        "session": {
+            "id": session.id,
+            "name": session.name,
+            "prompt": prompt,
+            "status": session.status,
+            "working_dir": session.working_dir,
+            "created_at": session.created_at,
+            "base_ref": base.base_ref,
+        },
+        "runs": runs,
+        "resume_count": run_metadata.as_ref().map(|metadata| metadata.resume_count),
+        "usage": usage,
+    });
+
+    let service = WorktreeService::from_working_dir(&session.working_dir)?;
+    write_session_export(
+        &service,
+        &ExportRequest {
+            session_branch,
+            worktree_path: PathBuf::from(worktree_path),
+            base_sha: base.base_sha,
+            format,
+            destination: PathBuf::from(destination),
+        },
+        manifest,
+    )
+}
+
+/// Applies each repository's worktree retention policy now. With `dry_run` the report lists
+/// what would be removed without touching anything.
+#[tauri::command]
+pub async fn run_worktree_retention(
+    app: AppHandle,
+    dry_run: Option<bool>,
+) -> Result<RetentionReport, String> {
+    tokio::task::spawn_blocking(move || {
+        apply_worktree_retention(app.state::<Database>().inner(), dry_run.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| format!("Worktree retention did not finish: {}", e))?
+}
+
+/// Applies worktree retention once the app is up, off the startup path: measuring worktrees
+/// walks every file in them. Failures only reach the debug stream.
+pub fn start_worktree_retention(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let _ = tokio::task::spawn_blocking(move || {
+            let db = app.state::<Database>();
+            if let Err(message) = apply_startup_worktree_retention(db.inner()) {
+                let _ = app.emit(
+                    "session-debug",
+                    json!({
+                        "kind": "retention-error",
+                        "timestamp": chrono::Utc::now().to_rfc3339(),
+                        "message": message,
+                    }),
+                );
+            }
+        })
+        .await;
+    });
+}
+
+/// Files of the repository that more than one active session has changed. Worktree diffs are
+/// re-read first, so edits made through shell commands are included.
+#[tauri::command]
+pub async fn list_repo_conflicts(
+    app: AppHandle,
+    db: State<'_, Database>,
+    repo_path: String,
+) -> Result<Vec<RepoConflict>, String> {
+    let service = WorktreeService::from_working_dir(&repo_path)?;
+    let repo_root = service.repo_root().display().to_string();
+
+    let (app_for_refresh, root) = (app.clone(), repo_root.clone());
+    let warnings = tokio::task::spawn_blocking(move || {
+        refresh_repo_diff_paths(app_for_refresh.state::<Database>().inner(), &root)
+    })
+    .await
+    .map_err(|e| format!("Diff refresh did not finish: {}", e))??;
+    emit_recorded_events(&app, &warnings);
+    overlap::list_repo_conflicts(&db, &repo_root)
+}
+
+/// Quarantines managed worktrees no session owns, the same pass that runs at startup. With
+/// `dry_run` nothing moves and the reports list what would be quarantined. Spawns that are
+/// still creating a worktree finish recording it first.
+#[tauri::command]
+pub async fn reconcile_worktrees(
+    app: AppHandle,
+    manager: State<'_, Arc<Mutex<SessionManager>>>,
+    dry_run: Option<bool>,
+) -> Result<Vec<ReconcileReport>, String> {
+    let supervisor = manager.lock().await.supervisor.clone();
+    let _sweep = supervisor.lock_worktree_sweep().await;
+
+    tokio::task::spawn_blocking(move || {
+        let app_data_dir = app.state::<AppDataDir>().0.clone();
+        reconcile_session_worktrees(
+            app.state::<Database>().inner(),
+            &app_data_dir,
+            dry_run.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| format!("Worktree reconciliation did not finish: {}", e))?
+}
+
+#[tauri::command]
+pub async fn list_quarantined_worktrees(
+    db: State<'_, Database>,
+    app_data_dir: State<'_, AppDataDir>,
+    repo_path: String,
+) -> Result<Vec<QuarantinedWorktree>, String> {
+    let repo_root = WorktreeService::from_working_dir(&repo_path)?.repo_root().to_path_buf();
+    let service = configured_worktree_service(&db, &repo_root, &app_data_dir.0);
+    quarantine::list_quarantined_worktrees(&service)
+}
+
+/// Deletes quarantined worktrees of the repository, all of them or the ones in `names`.
+/// Worktrees with uncommitted or unmerged work are skipped unless `confirm` is set.
+#[tauri::command]
+pub async fn purge_quarantined_worktrees(
+    db: State<'_, Database>,
+    app_data_dir: State<'_, AppDataDir>,
+    repo_path: String,
+    names: Option<Vec<String>>,
+    confirm: Option<bool>,
+) -> Result<PurgeReport, String> {
+    let repo_root = WorktreeService::from_working_dir(&repo_path)?.repo_root().to_path_buf();
+    let service = configured_worktree_service(&db, &repo_root, &app_data_dir.0);
+    quarantine::purge_quarantined_worktrees(&service, names.as_deref(), confirm.unwrap_or(false))
+}
@@ -0,0 +1,129 @@
+use crate::db::{Database, DbError};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// A spend or token limit. `scope` is `global` (per UTC day, across all sessions) or `session`
+/// (over the session's lifetime, including resumes).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    pub id: String,
+    pub scope: String,
+    pub session_id: Option<String>,
+    pub unit: String,
+    pub soft_limit: Option<f64>,
+    pub hard_limit: f64,
+    pub updated_at: String,
+}
+
+fn budget_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Budget> {
+    Ok(Budget {
+        id: row.get(0)?,
+        scope: row.get(1)?,
+        session_id: row.get(2)?,
+        unit: row.get(3)?,
+        soft_limit: row.get(4)?,
+        hard_limit: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+impl Database {
+    /// Creates or replaces a budget. Alerts already raised against it are cleared so the new
+    /// limits start from a clean slate.
+    pub fn save_budget(&self, budget: &Budget) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "INSERT INTO budgets (id, scope, session_id, unit, soft_limit, hard_limit, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(id) DO UPDATE SET
+                unit = excluded.unit,
+                soft_limit = excluded.soft_limit,
+                hard_limit = excluded.hard_limit,
+                updated_at = excluded.updated_at",
+            params![
+                budget.id,
+                budget.scope,
+                budget.session_id,
+                budget.unit,
+                budget.soft_limit,
+                budget.hard_limit,
+                budget.updated_at,
+            ],
+        )?;
+        tx.execute("DELETE FROM budget_alerts WHERE budget_id = ?1", params![budget.id])?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn list_budgets(&self) -> Result<Vec<Budget>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, scope, session_id, unit, soft_limit, hard_limit, updated_at
+             FROM budgets
+             ORDER BY scope ASC, updated_at ASC",
+        )?;
+        let rows = stmt.query_map([], budget_from_row)?;
+
+        let mut budgets = Vec::new();
+        for budget in rows {
+            budgets.push(budget?);
+        }
+
+        Ok(budgets)
+    }
+
+    /// The global budget (if any) followed by the session's own budget (if any).
+    pub fn list_budgets_for_session(&self, session_id: &str) -> Result<Vec<Budget>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, scope, session_id, unit, soft_limit, hard_limit, updated_at
+             FROM budgets
+             WHERE scope = 'global' OR session_id = ?1
+             ORDER BY scope = 'session' ASC",
+        )?;
+        let rows = stmt.query_map(params![session_id], budget_from_row)?;
+
+        let mut budgets = Vec::new();
+        for budget in rows {
+            budgets.push(budget?);
+        }
+
+        Ok(budgets)
+    }
+
+    pub fn delete_budget(&self, id: &str) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute("DELETE FROM budget_alerts WHERE budget_id = ?1", params![id])?;
+        tx.execute("DELETE FROM budgets WHERE id = ?1", params![id])?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Claims the `level` alert for a budget period. Returns false if it was already raised.
+    /// Budgets are checked from the CLI event task, so this goes through the writer.
+    pub fn record_budget_alert(
+        &self,
+        budget_id: &str,
+        period: &str,
+        level: &str,
+    ) -> Result<bool, DbError> {
+        let (budget_id, period, level) =
+            (budget_id.to_string(), period.to_string(), level.to_string());
+        self.writer.execute(move |conn| {
+            let inserted = conn.execute(
+                "INSERT OR IGNORE INTO budget_alerts (budget_id, period, level, created_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![budget_id, period, level, chrono::Utc::now().to_rfc3339()],
+            )?;
+            Ok(inserted > 0)
+        })
+    }
+}
@@ -0,0 +1,158 @@
+use crate::db::{Database, DbError};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// The state of a session worktree after one of the session's events.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionCheckpoint {
+    pub id: String,
+    pub session_id: String,
+    /// Run and seq of the `session_events` row the checkpoint was taken after.
+    pub run_id: String,
+    pub seq: u64,
+    /// The branch commit the worktree was on.
+    pub head_sha: String,
+    /// Commit holding the full worktree state; `head_sha` itself when nothing was uncommitted.
+    pub commit_sha: String,
+    pub tree_sha: String,
+    pub ref_name: String,
+    /// Files that differ from the previous checkpoint.
+    pub changed_paths: Vec<String>,
+    pub created_at: String,
+}
+
+fn checkpoint_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SessionCheckpoint> {
+    let changed_paths_json: String = row.get(8)?;
+    Ok(SessionCheckpoint {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        run_id: row.get(2)?,
+        seq: row.get(3)?,
+        head_sha: row.get(4)?,
+        commit_sha: row.get(5)?,
+        tree_sha: row.get(6)?,
+        ref_name: row.get(7)?,
+        changed_paths: serde_json::from_str(&changed_paths_json).unwrap_or_default(),
+        created_at: row.get(9)?,
+    })
+}
+
+const CHECKPOINT_COLUMNS: &str = "id, session_id, run_id, seq, head_sha, commit_sha, tree_sha,
+    ref_name, changed_paths_json, created_at";
+
+impl Database {
+    pub fn insert_session_checkpoint(&self, checkpoint: &SessionCheckpoint) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            &format!(
+                "INSERT INTO session_checkpoints ({})
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                CHECKPOINT_COLUMNS
+            ),
+            params![
+                checkpoint.id,
+                checkpoint.session_id,
+                checkpoint.run_id,
+                checkpoint.seq as i64,
+                checkpoint.head_sha,
+                checkpoint.commit_sha,
+                checkpoint.tree_sha,
+                checkpoint.ref_name,
+                serde_json::Value::from(checkpoint.changed_paths.clone()).to_string(),
+                checkpoint.created_at,
+            ],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Oldest first.
+    pub fn list_session_checkpoints(
+        &self,
+        session_id: &str,
+    ) -> Result<Vec<SessionCheckpoint>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM session_checkpoints
+             WHERE session_id = ?1
+             ORDER BY created_at ASC, rowid ASC",
+            CHECKPOINT_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![session_id], checkpoint_from_row)?;
+
+        let mut checkpoints = Vec::new();
+        for row in rows {
+            checkpoints.push(row?);
+        }
+
+        Ok(checkpoints)
+    }
+
+    pub fn get_session_checkpoint(
+        &self,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<Option<SessionCheckpoint>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM session_checkpoints WHERE session_id = ?1 AND id = ?2",
+            CHECKPOINT_COLUMNS
+        ))?;
+        let mut rows = stmt.query(params![session_id, checkpoint_id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(checkpoint_from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn latest_session_checkpoint(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<SessionCheckpoint>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM session_checkpoints
+             WHERE session_id = ?1
+             ORDER BY created_at DESC, rowid DESC
+             LIMIT 1",
+            CHECKPOINT_COLUMNS
+        ))?;
+        let mut rows = stmt.query(params![session_id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(checkpoint_from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Run id and seq of the session's most recent event.
+    pub fn latest_session_event_position(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<(String, u64)>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT run_id, seq FROM session_events
+             WHERE session_id = ?1
+             ORDER BY timestamp DESC, seq DESC, id DESC
+             LIMIT 1",
+        )?;
+        let mut rows = stmt.query(params![session_id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some((row.get(0)?, row.get(1)?)))
+        } else {
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use crate::db::session::{history_event_from_row, message_from_row};
+use crate::db::{Database, DbError, SessionHistoryEvent, SessionMessage};
+use rusqlite::types::ToSql;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_PAGE_SIZE: u32 = 200;
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+/// Position in a session's history, in its `timestamp, seq, id` order; build one from the first
+/// or last event of a page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryCursor {
+    pub timestamp: String,
+    pub seq: i64,
+    pub id: String,
+}
+
+impl From<&SessionHistoryEvent> for HistoryCursor {
+    fn from(event: &SessionHistoryEvent) -> Self {
+        Self { timestamp: event.timestamp.clone(), seq: event.seq, id: event.id.clone() }
+    }
+}
+
+/// Position among a session's messages, in their `timestamp, id` order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageCursor {
+    pub timestamp: String,
+    pub id: String,
+}
+
+impl From<&SessionMessage> for MessageCursor {
+    fn from(message: &SessionMessage) -> Self {
+        Self { timestamp: message.timestamp.clone(), id: message.id.clone() }
+    }
+}
+
+/// Which slice of a session's history to load. With `after` the page starts right after that
+/// event, with only `before` it ends right before it, and with neither it starts at the oldest
+/// event, or ends at the newest one when `tail` is set. Pages are always oldest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryPageQuery {
+    pub after: Option<HistoryCursor>,
+    pub before: Option<HistoryCursor>,
+    pub tail: bool,
+    pub limit: Option<u32>,
+    pub run_id: Option<String>,
+    pub event_types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MessagePageQuery {
+    pub after: Option<MessageCursor>,
+    pub before: Option<MessageCursor>,
+    pub tail: bool,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionHistoryPage {
+    pub events: Vec<SessionHistoryEvent>,
+    /// Whether more events lie beyond the page in the direction it was loaded.
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMessagePage {
+    pub messages: Vec<SessionMessage>,
+    pub has_more: bool,
+}
+
+/// A page's SQL: filters and keyset bounds are only added when set, so the timeline index
+/// serves every variant as a range scan.
+struct PageSql {
+    conditions: Vec<String>,
+    params: Vec<Box<dyn ToSql>>,
+}
+
+impl PageSql {
+    fn new(session_id: &str) -> Self {
+        Self {
+            conditions: vec!["session_id = ?1".to_string()],
+            params: vec![Box::new(session_id.to_string())],
+        }
+    }
+
+    fn push(&mut self, condition: &str, params: Vec<Box<dyn ToSql>>) {
+        let mut condition = condition.to_string();
+        for param in params {
+            self.params.push(param);
+            condition = condition.replacen("{}", &format!("?{}", self.params.len()), 1);
+        }
+        self.conditions.push(condition);
+    }
+
+    fn event_types(&mut self, event_types: &[String]) {
+        if !event_types.is_empty() {
+            let list = serde_json::Value::from(event_types.to_vec()).to_string();
+            self.push("event_type IN (SELECT value FROM json_each({}))", vec![Box::new(list)]);
+        }
+    }
+
+    fn where_clause(&self) -> String {
+        self.conditions.join(" AND ")
+    }
+
+    fn params(&self) -> Vec<&dyn ToSql> {
+        self.params.iter().map(|param| param.as_ref()).collect()
+    }
+}
+
+fn page_limit(limit: Option<u32>) -> u32 {
+    limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Keeps `limit` rows of a page loaded with one row to spare, restoring oldest-first order for
+/// pages read backwards.
+fn finish_page<T>(mut rows: Vec<T>, limit: u32, backwards: bool) -> (Vec<T>, bool) {
+    let has_more = rows.len() > limit as usize;
+    rows.truncate(limit as usize);
+    if backwards {
+        rows.reverse();
+    }
+    (rows, has_more)
+}
+
+impl Database {
+    pub fn list_session_history_page(
+        &self,
+        session_id: &str,
+        query: &HistoryPageQuery,
+    ) -> Result<SessionHistoryPage, DbError> {
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
+
+        let mut sql = PageSql::new(session_id);
+        if let Some(run_id) = &query.run_id {
+            sql.push("run_id = {}", vec![Box::new(run_id.clone())]);
+        }
+        sql.event_types(&query.event_types);
+        for (cursor, operator) in [(&query.after, ">"), (&query.before, "<")] {
+            if let Some(cursor) = cursor {
+                sql.push(
+                    &format!("(timestamp, seq, id) {} ({{}}, {{}}, {{}})", operator),
+                    vec![
+                        Box::new(cursor.timestamp.clone()),
+                        Box::new(cursor.seq),
+                        Box::new(cursor.id.clone()),
+                    ],
+                );
+            }
+        }
+
+        let backwards = query.after.is_none() && (query.before.is_some() || query.tail);
+        let order = if backwards { "DESC" } else { "ASC" };
+        let limit = page_limit(query.limit);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, session_id, run_id, seq, event_type, payload_json, timestamp
+             FROM session_events
+             WHERE {}
+             ORDER BY timestamp {order}, seq {order}, id {order}
+             LIMIT {}",
+            sql.where_clause(),
+            limit + 1,
+            order = order
+        ))?;
+        let rows = stmt.query_map(sql.params().as_slice(), history_event_from_row)?;
+
+        let mut events = Vec::new();
+        for event in rows {
+            events.push(event?);
+        }
+
+        let (events, has_more) = finish_page(events, limit, backwards);
+        Ok(SessionHistoryPage { events, has_more })
+    }
+
+    pub fn list_session_messages_page(
+        &self,
+        session_id: &str,
+        query: &MessagePageQuery,
+    ) -> Result<SessionMessagePage, DbError> {
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
+
+        let mut sql = PageSql::new(session_id);
+        for (cursor, operator) in [(&query.after, ">"), (&query.before, "<")] {
+            if let Some(cursor) = cursor {
+                sql.push(
+                    &format!("(timestamp, id) {} ({{}}, {{}})", operator),
+                    vec![Box::new(cursor.timestamp.clone()), Box::new(cursor.id.clone())],
+                );
+            }
+        }
+
+        let backwards = query.after.is_none() && (query.before.is_some() || query.tail);
+        let order = if backwards { "DESC" } else { "ASC" };
+        let limit = page_limit(query.limit);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, session_id, role, content, timestamp
+             FROM messages
+             WHERE {}
+             ORDER BY timestamp {order}, id {order}
+             LIMIT {}",
+            sql.where_clause(),
+            limit + 1,
+            order = order
+        ))?;
+        let rows = stmt.query_map(sql.params().as_slice(), message_from_row)?;
+
+        let mut messages = Vec::new();
+        for message in rows {
+            messages.push(message?);
+        }
+
+        let (messages, has_more) = finish_page(messages, limit, backwards);
+        Ok(SessionMessagePage { messages, has_more })
+    }
+
+    /// Number of events matching the same filters as `list_session_history_page`.
+    pub fn count_session_history(
+        &self,
+        session_id: &str,
+        run_id: Option<&str>,
+        event_types: &[String],
+    ) -> Result<u64, DbError> {
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
+
+        let mut sql = PageSql::new(session_id);
+        if let Some(run_id) = run_id {
+            sql.push("run_id = {}", vec![Box::new(run_id.to_string())]);
+        }
+        sql.event_types(event_types);
+        let count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM session_events WHERE {}", sql.where_clause()),
+            sql.params().as_slice(),
+            |row| row.get(0),
+        )?;
+
+        Ok(count as u64)
+    }
+
+    pub fn count_session_messages(&self, session_id: &str) -> Result<u64, DbError> {
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
+
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM messages WHERE session_id = ?1",
+            [session_id],
+            |row| row.get(0),
+        )?;
+
+        Ok(count as u64)
+    }
+}
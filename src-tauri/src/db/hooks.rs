@@ -0,0 +1,119 @@
+use crate::db::{Database, DbError};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleHook {
+    pub id: String,
+    pub repo_root: Option<String>,
+    pub event: String,
+    pub command: String,
+    pub timeout_ms: i64,
+    pub enabled: bool,
+    pub created_at: String,
+}
+
+fn hook_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<LifecycleHook> {
+    Ok(LifecycleHook {
+        id: row.get(0)?,
+        repo_root: row.get(1)?,
+        event: row.get(2)?,
+        command: row.get(3)?,
+        timeout_ms: row.get(4)?,
+        enabled: row.get::<_, i64>(5)? != 0,
+        created_at: row.get(6)?,
+    })
+}
+
+impl Database {
+    pub fn create_lifecycle_hook(&self, hook: &LifecycleHook) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "INSERT INTO lifecycle_hooks (id, repo_root, event, command, timeout_ms, enabled, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                hook.id,
+                hook.repo_root,
+                hook.event,
+                hook.command,
+                hook.timeout_ms,
+                hook.enabled as i64,
+                hook.created_at,
+            ],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn list_lifecycle_hooks(&self) -> Result<Vec<LifecycleHook>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, repo_root, event, command, timeout_ms, enabled, created_at
+             FROM lifecycle_hooks
+             ORDER BY created_at ASC, id ASC",
+        )?;
+
+        let rows = stmt.query_map([], hook_from_row)?;
+
+        let mut hooks = Vec::new();
+        for hook in rows {
+            hooks.push(hook?);
+        }
+
+        Ok(hooks)
+    }
+
+    /// Enabled hooks for `event`: global hooks first, then hooks scoped to `repo_root`.
+    pub fn list_hooks_for_event(
+        &self,
+        event: &str,
+        repo_root: Option<&str>,
+    ) -> Result<Vec<LifecycleHook>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, repo_root, event, command, timeout_ms, enabled, created_at
+             FROM lifecycle_hooks
+             WHERE event = ?1
+               AND enabled = 1
+               AND (repo_root IS NULL OR repo_root = ?2)
+             ORDER BY repo_root IS NOT NULL, created_at ASC, id ASC",
+        )?;
+
+        let rows = stmt.query_map(params![event, repo_root], hook_from_row)?;
+
+        let mut hooks = Vec::new();
+        for hook in rows {
+            hooks.push(hook?);
+        }
+
+        Ok(hooks)
+    }
+
+    pub fn set_lifecycle_hook_enabled(&self, id: &str, enabled: bool) -> Result<bool, DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        let updated = tx.execute(
+            "UPDATE lifecycle_hooks SET enabled = ?1 WHERE id = ?2",
+            params![enabled as i64, id],
+        )?;
+
+        tx.commit()?;
+        Ok(updated > 0)
+    }
+
+    pub fn delete_lifecycle_hook(&self, id: &str) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute("DELETE FROM lifecycle_hooks WHERE id = ?1", params![id])?;
+
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use crate::db::{Database, DbError};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// One attempt to land a session's changes on a target branch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionIntegration {
+    pub id: String,
+    pub session_id: String,
+    pub strategy: String,
+    pub target_branch: String,
+    /// `merged` or `conflicted`.
+    pub status: String,
+    pub result_sha: Option<String>,
+    pub conflicts: Vec<String>,
+    pub summary: String,
+    pub created_at: String,
+}
+
+fn integration_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SessionIntegration> {
+    let conflicts_json: String = row.get(6)?;
+    Ok(SessionIntegration {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        strategy: row.get(2)?,
+        target_branch: row.get(3)?,
+        status: row.get(4)?,
+        result_sha: row.get(5)?,
+        conflicts: serde_json::from_str(&conflicts_json).unwrap_or_default(),
+        summary: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+impl Database {
+    pub fn insert_session_integration(
+        &self,
+        integration: &SessionIntegration,
+    ) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "INSERT INTO session_integrations (
+                id, session_id, strategy, target_branch, status, result_sha, conflicts_json,
+                summary, created_at
+             )
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                integration.id,
+                integration.session_id,
+                integration.strategy,
+                integration.target_branch,
+                integration.status,
+                integration.result_sha,
+                serde_json::Value::from(integration.conflicts.clone()).to_string(),
+                integration.summary,
+                integration.created_at,
+            ],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Newest first.
+    pub fn list_session_integrations(
+        &self,
+        session_id: &str,
+    ) -> Result<Vec<SessionIntegration>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, strategy, target_branch, status, result_sha, conflicts_json,
+                    summary, created_at
+             FROM session_integrations
+             WHERE session_id = ?1
+             ORDER BY created_at DESC, rowid DESC",
+        )?;
+        let rows = stmt.query_map(params![session_id], integration_from_row)?;
+
+        let mut integrations = Vec::new();
+        for row in rows {
+            integrations.push(row?);
+        }
+
+        Ok(integrations)
+    }
+}
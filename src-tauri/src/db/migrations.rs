@@ -0,0 +1,527 @@
+//! Schema migrations, tracked in `PRAGMA user_version`. Each migration runs in its own
+//! transaction together with the version bump, so a failing step leaves the database at the
+//! previous version. Migrations are append-only: never edit one that has shipped, add a new one.
+
+use rusqlite::{Connection, Result};
+use std::path::{Path, PathBuf};
+
+use crate::db::DbError;
+
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub apply: fn(&Connection) -> Result<()>,
+}
+
+/// Every migration, in the order they run; versions count up from 1 without gaps.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline schema, including the columns earlier versions added ad hoc",
+        apply: baseline,
+    },
+    Migration {
+        version: 2,
+        description: "drop the session_events index duplicating its unique constraint",
+        apply: drop_duplicate_session_events_index,
+    },
+    Migration {
+        version: 3,
+        description: "full-text index over session events and messages",
+        apply: create_session_search_index,
+    },
+    Migration {
+        version: 4,
+        description: "index messages for paging through a session in order",
+        apply: create_messages_timeline_index,
+    },
+    Migration {
+        version: 5,
+        description: "history retention policy",
+        apply: create_history_retention,
+    },
+];
+
+pub const LATEST_SCHEMA_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Applies the `migrations` newer than the database's version, one transaction each.
+pub fn apply_migrations(conn: &mut Connection, migrations: &[Migration]) -> Result<u32> {
+    let mut version = schema_version(conn)?;
+    for migration in migrations {
+        if migration.version <= version {
+            continue;
+        }
+        let tx = conn.transaction()?;
+        (migration.apply)(&tx)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+        version = migration.version;
+    }
+    Ok(version)
+}
+
+/// Where the copy taken before migrating `db_path` from `version` goes.
+pub fn backup_path(db_path: &Path, version: u32) -> PathBuf {
+    let file_name = db_path.file_name().map(|name| name.to_string_lossy().into_owned());
+    db_path.with_file_name(format!(
+        "{}.v{}-{}.bak",
+        file_name.unwrap_or_else(|| "lulu.db".to_string()),
+        version,
+        chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+    ))
+}
+
+/// Brings the database at `db_path` to `LATEST_SCHEMA_VERSION`. A database that already has
+/// tables is copied next to itself first; one from a newer Lulu is refused without changes.
+pub fn run_migrations(conn: &mut Connection, db_path: &Path) -> Result<(), DbError> {
+    let version = schema_version(conn)?;
+    if version > LATEST_SCHEMA_VERSION {
+        return Err(DbError::NewerSchema { found: version, supported: LATEST_SCHEMA_VERSION });
+    }
+    if version == LATEST_SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let table_count: i64 =
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'", [], |row| {
+            row.get(0)
+        })?;
+    if table_count > 0 {
+        let backup = backup_path(db_path, version);
+        conn.execute("VACUUM INTO ?1", [backup.display().to_string()])
+            .map_err(|e| DbError::Backup(format!("{}: {}", backup.display(), e)))?;
+    }
+
+    apply_migrations(conn, MIGRATIONS)?;
+    Ok(())
+}
+
+fn baseline(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS sessions (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'created',
+            working_dir TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            last_activity_at TEXT,
+            failure_reason TEXT,
+            worktree_path TEXT,
+            resume_count INTEGER NOT NULL DEFAULT 0,
+            active_run_id TEXT,
+            last_resume_at TEXT,
+            restored INTEGER NOT NULL DEFAULT 0,
+            restored_at TEXT,
+            recovery_hint INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE TABLE IF NOT EXISTS messages (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            role TEXT NOT NULL,
+            content TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_messages_session_id ON messages(session_id);",
+    )?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_events (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            run_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            event_type TEXT NOT NULL,
+            payload_json TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+            UNIQUE(session_id, run_id, seq)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_session_events_session_id_timestamp
+            ON session_events(session_id, timestamp, seq, id);
+
+        CREATE INDEX IF NOT EXISTS idx_session_events_session_id_run_id_seq
+            ON session_events(session_id, run_id, seq);",
+    )?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS lifecycle_hooks (
+            id TEXT PRIMARY KEY,
+            repo_root TEXT,
+            event TEXT NOT NULL,
+            command TEXT NOT NULL,
+            timeout_ms INTEGER NOT NULL DEFAULT 30000,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_lifecycle_hooks_event
+            ON lifecycle_hooks(event, repo_root);",
+    )?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_run_usage (
+            session_id TEXT NOT NULL,
+            run_id TEXT NOT NULL,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            cost_usd REAL,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (session_id, run_id),
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS webhook_targets (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            url TEXT NOT NULL,
+            secret TEXT,
+            events TEXT NOT NULL,
+            cost_threshold_usd REAL,
+            stall_after_secs INTEGER,
+            max_attempts INTEGER NOT NULL DEFAULT 4,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS webhook_deliveries (
+            id TEXT PRIMARY KEY,
+            target_id TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            event TEXT NOT NULL,
+            attempt INTEGER NOT NULL,
+            status_code INTEGER,
+            success INTEGER NOT NULL,
+            error TEXT,
+            payload_json TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_target_created_at
+            ON webhook_deliveries(target_id, created_at);
+
+        CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_session_event
+            ON webhook_deliveries(session_id, event);",
+    )?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS repository_settings (
+            repo_root TEXT PRIMARY KEY,
+            verification_command TEXT,
+            verification_timeout_ms INTEGER,
+            updated_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS session_runs (
+            session_id TEXT NOT NULL,
+            run_id TEXT NOT NULL,
+            verification_status TEXT,
+            verification_command TEXT,
+            verification_exit_code INTEGER,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (session_id, run_id),
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        );",
+    )?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS daily_usage (
+            day TEXT PRIMARY KEY,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            cost_usd REAL NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS budgets (
+            id TEXT PRIMARY KEY,
+            scope TEXT NOT NULL,
+            session_id TEXT,
+            unit TEXT NOT NULL,
+            soft_limit REAL,
+            hard_limit REAL NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS budget_alerts (
+            budget_id TEXT NOT NULL,
+            period TEXT NOT NULL,
+            level TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (budget_id, period, level)
+        );",
+    )?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_integrations (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            strategy TEXT NOT NULL,
+            target_branch TEXT NOT NULL,
+            status TEXT NOT NULL,
+            result_sha TEXT,
+            conflicts_json TEXT NOT NULL DEFAULT '[]',
+            summary TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_session_integrations_session_created_at
+            ON session_integrations(session_id, created_at);",
+    )?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_checkpoints (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            run_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            head_sha TEXT NOT NULL,
+            commit_sha TEXT NOT NULL,
+            tree_sha TEXT NOT NULL,
+            ref_name TEXT NOT NULL,
+            changed_paths_json TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_session_checkpoints_session_created_at
+            ON session_checkpoints(session_id, created_at);",
+    )?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_touched_files (
+            session_id TEXT NOT NULL,
+            repo_root TEXT NOT NULL,
+            path TEXT NOT NULL,
+            from_tool INTEGER NOT NULL DEFAULT 0,
+            from_diff INTEGER NOT NULL DEFAULT 0,
+            first_seen_at TEXT NOT NULL,
+            last_seen_at TEXT NOT NULL,
+            PRIMARY KEY (session_id, path),
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_session_touched_files_repo_path
+            ON session_touched_files(repo_root, path);
+
+        CREATE TABLE IF NOT EXISTS session_overlap_warnings (
+            session_id TEXT NOT NULL,
+            other_session_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (session_id, other_session_id, path),
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+            FOREIGN KEY (other_session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        );",
+    )?;
+
+    ensure_session_column(conn, "last_activity_at", "TEXT")?;
+    ensure_session_column(conn, "failure_reason", "TEXT")?;
+    ensure_session_column(conn, "worktree_path", "TEXT")?;
+    ensure_session_column(conn, "resume_count", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_session_column(conn, "active_run_id", "TEXT")?;
+    ensure_session_column(conn, "last_resume_at", "TEXT")?;
+    ensure_session_column(conn, "restored", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_session_column(conn, "restored_at", "TEXT")?;
+    ensure_session_column(conn, "recovery_hint", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_session_column(conn, "branch_name", "TEXT")?;
+    ensure_session_column(conn, "base_ref", "TEXT")?;
+    ensure_session_column(conn, "base_sha", "TEXT")?;
+    ensure_session_column(conn, "prompt", "TEXT")?;
+    ensure_session_column(conn, "worktree_bytes", "INTEGER")?;
+    ensure_session_column(conn, "worktree_pruned_at", "TEXT")?;
+    ensure_session_column(conn, "sandbox_path", "TEXT")?;
+    ensure_table_column(conn, "repository_settings", "branch_template", "TEXT")?;
+    ensure_table_column(conn, "repository_settings", "auto_commit", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_table_column(conn, "repository_settings", "auto_commit_trailer", "TEXT")?;
+    ensure_table_column(conn, "repository_settings", "worktree_location", "TEXT")?;
+    ensure_table_column(
+        conn,
+        "repository_settings",
+        "provision_copy",
+        "TEXT NOT NULL DEFAULT '[]'",
+    )?;
+    ensure_table_column(
+        conn,
+        "repository_settings",
+        "provision_symlink",
+        "TEXT NOT NULL DEFAULT '[]'",
+    )?;
+    ensure_table_column(conn, "repository_settings", "setup_command", "TEXT")?;
+    ensure_table_column(conn, "repository_settings", "retention_max_age_days", "INTEGER")?;
+    ensure_table_column(conn, "repository_settings", "retention_max_bytes", "INTEGER")?;
+    ensure_table_column(
+        conn,
+        "repository_settings",
+        "retention_keep_unmerged",
+        "INTEGER NOT NULL DEFAULT 1",
+    )?;
+    ensure_table_column(
+        conn,
+        "repository_settings",
+        "init_submodules",
+        "INTEGER NOT NULL DEFAULT 1",
+    )?;
+    ensure_table_column(conn, "repository_settings", "lfs_checkout", "INTEGER NOT NULL DEFAULT 1")?;
+    ensure_table_column(conn, "repository_settings", "auto_sync", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_table_column(conn, "repository_settings", "sync_strategy", "TEXT")?;
+    ensure_table_column(conn, "repository_settings", "sync_on_conflict", "TEXT")?;
+    ensure_table_column(conn, "session_runs", "commit_sha", "TEXT")?;
+
+    Ok(())
+}
+
+fn drop_duplicate_session_events_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch("DROP INDEX IF EXISTS idx_session_events_session_id_run_id_seq;")
+}
+
+/// The searchable text of an event payload: every string in it except the variant tag.
+fn payload_text(payload: &str) -> String {
+    format!(
+        "(SELECT group_concat(value, ' ') FROM json_tree({}) \
+          WHERE type = 'text' AND fullkey <> '$.type')",
+        payload
+    )
+}
+
+/// `session_search` holds the text, `session_search_docs` what each row points at. Docs have an
+/// INTEGER PRIMARY KEY, so the rowids linking the two survive a VACUUM. Assistant messages are
+/// mirrored from `message` events, which are indexed with their seq, so only other messages are
+/// indexed from `messages`.
+fn create_session_search_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch(&format!(
+        "CREATE TABLE session_search_docs (
+            doc_id INTEGER PRIMARY KEY,
+            source TEXT NOT NULL,
+            source_id TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            run_id TEXT,
+            seq INTEGER,
+            event_type TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            UNIQUE(source, source_id)
+        );
+
+        CREATE INDEX idx_session_search_docs_session_id ON session_search_docs(session_id);
+
+        CREATE VIRTUAL TABLE session_search USING fts5(body, tokenize = 'porter unicode61');
+
+        CREATE TRIGGER session_events_search_insert AFTER INSERT ON session_events BEGIN
+            INSERT INTO session_search_docs
+                (source, source_id, session_id, run_id, seq, event_type, timestamp)
+            VALUES ('event', NEW.id, NEW.session_id, NEW.run_id, NEW.seq, NEW.event_type,
+                    NEW.timestamp);
+            INSERT INTO session_search (rowid, body) VALUES (last_insert_rowid(), {new_text});
+        END;
+
+        CREATE TRIGGER session_events_search_update AFTER UPDATE OF payload_json ON session_events
+        BEGIN
+            UPDATE session_search SET body = {new_text}
+            WHERE rowid = (SELECT doc_id FROM session_search_docs
+                           WHERE source = 'event' AND source_id = NEW.id);
+        END;
+
+        CREATE TRIGGER session_events_search_delete AFTER DELETE ON session_events BEGIN
+            DELETE FROM session_search
+            WHERE rowid = (SELECT doc_id FROM session_search_docs
+                           WHERE source = 'event' AND source_id = OLD.id);
+            DELETE FROM session_search_docs WHERE source = 'event' AND source_id = OLD.id;
+        END;
+
+        CREATE TRIGGER messages_search_insert AFTER INSERT ON messages
+        WHEN NEW.role <> 'assistant'
+        BEGIN
+            INSERT INTO session_search_docs
+                (source, source_id, session_id, run_id, seq, event_type, timestamp)
+            VALUES ('message', NEW.id, NEW.session_id, NULL, NULL, 'message', NEW.timestamp);
+            INSERT INTO session_search (rowid, body) VALUES (last_insert_rowid(), NEW.content);
+        END;
+
+        CREATE TRIGGER messages_search_delete AFTER DELETE ON messages BEGIN
+            DELETE FROM session_search
+            WHERE rowid = (SELECT doc_id FROM session_search_docs
+                           WHERE source = 'message' AND source_id = OLD.id);
+            DELETE FROM session_search_docs WHERE source = 'message' AND source_id = OLD.id;
+        END;
+
+        INSERT INTO session_search_docs
+            (source, source_id, session_id, run_id, seq, event_type, timestamp)
+        SELECT 'event', id, session_id, run_id, seq, event_type, timestamp FROM session_events;
+        INSERT INTO session_search_docs
+            (source, source_id, session_id, run_id, seq, event_type, timestamp)
+        SELECT 'message', id, session_id, NULL, NULL, 'message', timestamp FROM messages
+        WHERE role <> 'assistant';
+        INSERT INTO session_search (rowid, body)
+        SELECT docs.doc_id, {event_text}
+        FROM session_search_docs docs
+        JOIN session_events ON session_events.id = docs.source_id
+        WHERE docs.source = 'event';
+        INSERT INTO session_search (rowid, body)
+        SELECT docs.doc_id, messages.content
+        FROM session_search_docs docs
+        JOIN messages ON messages.id = docs.source_id
+        WHERE docs.source = 'message';",
+        new_text = payload_text("NEW.payload_json"),
+        event_text = payload_text("session_events.payload_json"),
+    ))
+}
+
+fn create_messages_timeline_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_messages_session_id_timestamp
+            ON messages(session_id, timestamp, id);",
+    )
+}
+
+fn create_history_retention(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS history_retention (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            keep_days INTEGER,
+            mode TEXT NOT NULL DEFAULT 'compact',
+            updated_at TEXT NOT NULL
+        );",
+    )
+}
+
+fn ensure_session_column(
+    conn: &Connection,
+    column_name: &str,
+    column_definition: &str,
+) -> Result<()> {
+    ensure_table_column(conn, "sessions", column_name, column_definition)
+}
+
+fn ensure_table_column(
+    conn: &Connection,
+    table_name: &str,
+    column_name: &str,
+    column_definition: &str,
+) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table_name))?;
+    let mut rows = stmt.query([])?;
+
+    while let Some(row) = rows.next()? {
+        let existing_name: String = row.get(1)?;
+        if existing_name == column_name {
+            return Ok(());
+        }
+    }
+
+    conn.execute(
+        &format!("ALTER TABLE {} ADD COLUMN {} {}", table_name, column_name, column_definition),
+        [],
+    )?;
+
+    Ok(())
+}
@@ -1,110 +1,94 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OpenFlags, Result};
 use std::path::Path;
-use std::sync::Mutex;
-
+use std::sync::{Arc, Mutex};
+
+pub mod budgets;
+pub mod checkpoints;
+pub mod history;
+pub mod hooks;
+pub mod integrations;
+pub mod migrations;
+pub mod overlaps;
+pub mod repositories;
+pub mod runs;
+pub mod search;
 pub mod session;
+pub mod storage;
+pub mod usage;
+pub mod webhooks;
+pub mod writer;
+pub use budgets::Budget;
+pub use checkpoints::SessionCheckpoint;
+pub use history::{
+    HistoryCursor, HistoryPageQuery, MessageCursor, MessagePageQuery, SessionHistoryPage,
+    SessionMessagePage,
+};
+pub use hooks::LifecycleHook;
+pub use integrations::SessionIntegration;
+pub use overlaps::TouchedFile;
+pub use repositories::RepositorySettings;
+pub use runs::SessionRun;
+pub use search::{SessionSearchFilters, SessionSearchHit};
 pub use session::{
-    Session, SessionDashboardRow, SessionHistoryEvent, SessionMessage, SessionRunMetadata,
+    QueuedSessionEvent, Session, SessionBase, SessionDashboardRow, SessionHistoryEvent,
+    SessionMessage, SessionRunMetadata,
 };
+pub use storage::{DatabaseFileUsage, ExpiredRun, HistoryRetentionPolicy, SessionStorageUsage};
+pub use usage::SessionUsage;
+pub use webhooks::{WebhookDelivery, WebhookTarget};
+pub use writer::{DbWriter, WriteFailure};
 
 pub struct Database {
     pub conn: Mutex<Connection>,
+    /// Read-only connection for the history, message and dashboard queries, so they do not
+    /// queue up behind writes.
+    reader: Mutex<Connection>,
+    /// Owns the writes of session events and messages; see `writer`.
+    writer: DbWriter,
+    /// Sequence counter of each session's streaming run; see `register_run_sequence`.
+    run_sequences: session::RunSequences,
 }
 
-pub fn init_database(db_path: &Path) -> Result<Database> {
-    let conn = Connection::open(db_path)?;
+/// Opens the database and brings its schema up to date; see `migrations`. A database written by
+/// a newer Lulu is refused rather than touched.
+pub fn init_database(db_path: &Path) -> Result<Database, DbError> {
+    let mut conn = Connection::open(db_path)?;
 
+    // Only takes effect for new databases; history retention converts older ones.
     conn.execute_batch(
-        "PRAGMA journal_mode=WAL;
+        "PRAGMA auto_vacuum=INCREMENTAL;
+         PRAGMA journal_mode=WAL;
          PRAGMA synchronous=NORMAL;
          PRAGMA busy_timeout=5000;
          PRAGMA foreign_keys=ON;",
     )?;
 
-    conn.execute_batch(
-        "CREATE TABLE IF NOT EXISTS sessions (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            status TEXT NOT NULL DEFAULT 'created',
-            working_dir TEXT NOT NULL,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            last_activity_at TEXT,
-            failure_reason TEXT,
-            worktree_path TEXT,
-            resume_count INTEGER NOT NULL DEFAULT 0,
-            active_run_id TEXT,
-            last_resume_at TEXT,
-            restored INTEGER NOT NULL DEFAULT 0,
-            restored_at TEXT,
-            recovery_hint INTEGER NOT NULL DEFAULT 0
-        );
-
-        CREATE TABLE IF NOT EXISTS messages (
-            id TEXT PRIMARY KEY,
-            session_id TEXT NOT NULL,
-            role TEXT NOT NULL,
-            content TEXT NOT NULL,
-            timestamp TEXT NOT NULL,
-            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
-        );
-
-        CREATE INDEX IF NOT EXISTS idx_messages_session_id ON messages(session_id);",
-    )?;
-
-    conn.execute_batch(
-        "CREATE TABLE IF NOT EXISTS session_events (
-            id TEXT PRIMARY KEY,
-            session_id TEXT NOT NULL,
-            run_id TEXT NOT NULL,
-            seq INTEGER NOT NULL,
-            event_type TEXT NOT NULL,
-            payload_json TEXT NOT NULL,
-            timestamp TEXT NOT NULL,
-            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
-            UNIQUE(session_id, run_id, seq)
-        );
-
-        CREATE INDEX IF NOT EXISTS idx_session_events_session_id_timestamp
-            ON session_events(session_id, timestamp, seq, id);
+    migrations::run_migrations(&mut conn, db_path)?;
 
-        CREATE INDEX IF NOT EXISTS idx_session_events_session_id_run_id_seq
-            ON session_events(session_id, run_id, seq);",
+    let writer = DbWriter::spawn(writer::open_connection(db_path, OpenFlags::default())?)?;
+    let reader = writer::open_connection(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
     )?;
 
-    ensure_session_column(&conn, "last_activity_at", "TEXT")?;
-    ensure_session_column(&conn, "failure_reason", "TEXT")?;
-    ensure_session_column(&conn, "worktree_path", "TEXT")?;
-    ensure_session_column(&conn, "resume_count", "INTEGER NOT NULL DEFAULT 0")?;
-    ensure_session_column(&conn, "active_run_id", "TEXT")?;
-    ensure_session_column(&conn, "last_resume_at", "TEXT")?;
-    ensure_session_column(&conn, "restored", "INTEGER NOT NULL DEFAULT 0")?;
-    ensure_session_column(&conn, "restored_at", "TEXT")?;
-    ensure_session_column(&conn, "recovery_hint", "INTEGER NOT NULL DEFAULT 0")?;
-
-    Ok(Database { conn: Mutex::new(conn) })
+    Ok(Database {
+        conn: Mutex::new(conn),
+        reader: Mutex::new(reader),
+        writer,
+        run_sequences: Arc::default(),
+    })
 }
 
-fn ensure_session_column(conn: &Connection, column_name: &str, column_definition: &str) -> Result<()> {
-    let mut stmt = conn.prepare("PRAGMA table_info(sessions)")?;
-    let mut rows = stmt.query([])?;
-
-    while let Some(row) = rows.next()? {
-        let existing_name: String = row.get(1)?;
-        if existing_name == column_name {
-            return Ok(());
-        }
+impl Database {
+    /// Waits until every queued event and message write has been committed.
+    pub fn flush_writes(&self) -> Result<(), DbError> {
+        self.writer.flush()
     }
 
-    conn.execute(
-        &format!(
-            "ALTER TABLE sessions ADD COLUMN {} {}",
-            column_name, column_definition
-        ),
-        [],
-    )?;
-
-    Ok(())
+    /// Queued event writes that failed from now on; nobody else hears about them.
+    pub fn subscribe_write_failures(&self) -> tokio::sync::broadcast::Receiver<WriteFailure> {
+        self.writer.subscribe_failures()
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -113,6 +97,17 @@ pub enum DbError {
     Sqlite(#[from] rusqlite::Error),
     #[error("Lock error")]
     Lock,
+    #[error(
+        "Database schema version {found} is newer than this version of Lulu supports \
+         ({supported}); update Lulu to open it"
+    )]
+    NewerSchema { found: u32, supported: u32 },
+    #[error("Failed to back up the database before migrating: {0}")]
+    Backup(String),
+    #[error("Database writer error: {0}")]
+    Writer(String),
+    #[error("Invalid search query: {0}")]
+    InvalidSearchQuery(String),
 }
 
 impl serde::Serialize for DbError {
@@ -130,7 +125,7 @@ mod tests {
     use tempfile::tempdir;
 
     #[test]
-    fn init_database_creates_file() -> Result<()> {
+    fn init_database_creates_file() -> Result<(), DbError> {
         let dir = tempdir().expect("failed to create temp dir");
         let db_path = dir.path().join("lulu-test.db");
 
@@ -0,0 +1,192 @@
+use crate::db::{Database, DbError};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// A repository file a session changed, as seen through its edit tool calls and/or its
+/// worktree diff.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TouchedFile {
+    pub session_id: String,
+    pub repo_root: String,
+    pub path: String,
+    pub from_tool: bool,
+    pub from_diff: bool,
+    pub last_seen_at: String,
+}
+
+fn touched_file_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<TouchedFile> {
+    Ok(TouchedFile {
+        session_id: row.get(0)?,
+        repo_root: row.get(1)?,
+        path: row.get(2)?,
+        from_tool: row.get::<_, i64>(3)? != 0,
+        from_diff: row.get::<_, i64>(4)? != 0,
+        last_seen_at: row.get(5)?,
+    })
+}
+
+fn status_placeholders(statuses: &[&str], first_index: usize) -> String {
+    (0..statuses.len())
+        .map(|offset| format!("?{}", first_index + offset))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Database {
+    pub fn record_tool_touched_file(
+        &self,
+        session_id: &str,
+        repo_root: &str,
+        path: &str,
+    ) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        tx.execute(
+            "INSERT INTO session_touched_files (
+                session_id, repo_root, path, from_tool, from_diff, first_seen_at, last_seen_at
+             )
+             VALUES (?1, ?2, ?3, 1, 0, ?4, ?4)
+             ON CONFLICT(session_id, path) DO UPDATE SET
+                from_tool = 1,
+                last_seen_at = excluded.last_seen_at",
+            params![session_id, repo_root, path, now],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Makes `paths` the session's diff-derived files. Files that dropped out of the diff and
+    /// were never edited through a tool are forgotten.
+    pub fn replace_diff_touched_files(
+        &self,
+        session_id: &str,
+        repo_root: &str,
+        paths: &[String],
+    ) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        tx.execute(
+            "UPDATE session_touched_files SET from_diff = 0 WHERE session_id = ?1",
+            params![session_id],
+        )?;
+        for path in paths {
+            tx.execute(
+                "INSERT INTO session_touched_files (
+                    session_id, repo_root, path, from_tool, from_diff, first_seen_at, last_seen_at
+                 )
+                 VALUES (?1, ?2, ?3, 0, 1, ?4, ?4)
+                 ON CONFLICT(session_id, path) DO UPDATE SET
+                    from_diff = 1,
+                    last_seen_at = excluded.last_seen_at",
+                params![session_id, repo_root, path, now],
+            )?;
+        }
+        tx.execute(
+            "DELETE FROM session_touched_files
+             WHERE session_id = ?1 AND from_tool = 0 AND from_diff = 0",
+            params![session_id],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// `(path, other_session_id)` for every file this session shares with another session of
+    /// the same repository whose status is one of `statuses`.
+    pub fn list_session_overlaps(
+        &self,
+        session_id: &str,
+        statuses: &[&str],
+    ) -> Result<Vec<(String, String)>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let sql = format!(
+            "SELECT mine.path, other.session_id
+             FROM session_touched_files mine
+             JOIN session_touched_files other
+                ON other.repo_root = mine.repo_root
+               AND other.path = mine.path
+               AND other.session_id != mine.session_id
+             JOIN sessions ON sessions.id = other.session_id
+             WHERE mine.session_id = ?1 AND sessions.status IN ({})
+             ORDER BY mine.path, other.session_id",
+            status_placeholders(statuses, 2)
+        );
+        let mut values: Vec<&dyn rusqlite::ToSql> = vec![&session_id];
+        values.extend(statuses.iter().map(|status| status as &dyn rusqlite::ToSql));
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(values.as_slice(), |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut overlaps = Vec::new();
+        for row in rows {
+            overlaps.push(row?);
+        }
+
+        Ok(overlaps)
+    }
+
+    /// Records that a warning for this pair of sessions and path went out. Returns `false` when
+    /// one already had, in either direction.
+    pub fn record_overlap_warning(
+        &self,
+        session_id: &str,
+        other_session_id: &str,
+        path: &str,
+    ) -> Result<bool, DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        let (first, second) = if session_id <= other_session_id {
+            (session_id, other_session_id)
+        } else {
+            (other_session_id, session_id)
+        };
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO session_overlap_warnings (
+                session_id, other_session_id, path, created_at
+             )
+             VALUES (?1, ?2, ?3, ?4)",
+            params![first, second, path, chrono::Utc::now().to_rfc3339()],
+        )?;
+
+        tx.commit()?;
+        Ok(inserted > 0)
+    }
+
+    /// Files touched in `repo_root` by sessions whose status is one of `statuses`.
+    pub fn list_repo_touched_files(
+        &self,
+        repo_root: &str,
+        statuses: &[&str],
+    ) -> Result<Vec<TouchedFile>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let sql = format!(
+            "SELECT touched.session_id, touched.repo_root, touched.path, touched.from_tool,
+                    touched.from_diff, touched.last_seen_at
+             FROM session_touched_files touched
+             JOIN sessions ON sessions.id = touched.session_id
+             WHERE touched.repo_root = ?1 AND sessions.status IN ({})
+             ORDER BY touched.path, sessions.created_at",
+            status_placeholders(statuses, 2)
+        );
+        let mut values: Vec<&dyn rusqlite::ToSql> = vec![&repo_root];
+        values.extend(statuses.iter().map(|status| status as &dyn rusqlite::ToSql));
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(values.as_slice(), touched_file_from_row)?;
+
+        let mut files = Vec::new();
+        for row in rows {
+            files.push(row?);
+        }
+
+        Ok(files)
+    }
+}
@@ -0,0 +1,182 @@
+use crate::db::{Database, DbError};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// Per-repository options, keyed by the canonical repository root.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepositorySettings {
+    pub repo_root: String,
+    pub verification_command: Option<String>,
+    pub verification_timeout_ms: Option<i64>,
+    /// Template for session branch names; see `render_branch_name`.
+    pub branch_template: Option<String>,
+    /// Commit everything left in the worktree when a session completes.
+    pub auto_commit: bool,
+    /// Trailer appended to auto-commit messages; `{session-id}` is substituted.
+    pub auto_commit_trailer: Option<String>,
+    /// `in_repo`, `sibling` or `app_data`; see `WorktreeLocation`. Unset means in-repo.
+    pub worktree_location: Option<String>,
+    /// Globs of untracked or ignored files copied from the main checkout into new worktrees.
+    pub provision_copy: Vec<String>,
+    /// Globs of untracked or ignored files symlinked from the main checkout instead.
+    pub provision_symlink: Vec<String>,
+    /// Runs in a new worktree before the CLI starts; a failure fails the spawn.
+    pub setup_command: Option<String>,
+    /// Remove worktrees of sessions that finished more than this many days ago.
+    pub retention_max_age_days: Option<i64>,
+    /// Cap on the total size of the repository's session worktrees.
+    pub retention_max_bytes: Option<i64>,
+    /// Never let the retention policy remove a worktree with unmerged or uncommitted work.
+    pub retention_keep_unmerged: bool,
+    /// Initialise submodules in new worktrees, cloning from the main checkout's copies.
+    pub init_submodules: bool,
+    /// Check out Git LFS files in new worktrees from the local LFS cache.
+    pub lfs_checkout: bool,
+    /// Sync the session worktree onto its base ref before every resume.
+    pub auto_sync: bool,
+    /// `rebase` or `merge`; see `SyncStrategy`. Unset means rebase.
+    pub sync_strategy: Option<String>,
+    /// `abort` or `leave`; see `SyncConflictPolicy`. Unset means abort.
+    pub sync_on_conflict: Option<String>,
+}
+
+impl Default for RepositorySettings {
+    fn default() -> Self {
+        Self {
+            repo_root: String::new(),
+            verification_command: None,
+            verification_timeout_ms: None,
+            branch_template: None,
+            auto_commit: false,
+            auto_commit_trailer: None,
+            worktree_location: None,
+            provision_copy: Vec::new(),
+            provision_symlink: Vec::new(),
+            setup_command: None,
+            retention_max_age_days: None,
+            retention_max_bytes: None,
+            retention_keep_unmerged: true,
+            init_submodules: true,
+            lfs_checkout: true,
+            auto_sync: false,
+            sync_strategy: None,
+            sync_on_conflict: None,
+        }
+    }
+}
+
+fn json_conversion_error(raw: &str, err: serde_json::Error) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(raw.len(), rusqlite::types::Type::Text, Box::new(err))
+}
+
+fn patterns_from_column(row: &rusqlite::Row<'_>, index: usize) -> rusqlite::Result<Vec<String>> {
+    let raw: String = row.get(index)?;
+    serde_json::from_str(&raw).map_err(|err| json_conversion_error(&raw, err))
+}
+
+impl Database {
+    pub fn get_repository_settings(
+        &self,
+        repo_root: &str,
+    ) -> Result<Option<RepositorySettings>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT repo_root, verification_command, verification_timeout_ms, branch_template,
+                    auto_commit, auto_commit_trailer, worktree_location, provision_copy,
+                    provision_symlink, setup_command, retention_max_age_days, retention_max_bytes,
+                    retention_keep_unmerged, init_submodules, lfs_checkout,
+                    auto_sync, sync_strategy, sync_on_conflict
+             FROM repository_settings
+             WHERE repo_root = ?1",
+        )?;
+        let mut rows = stmt.query(params![repo_root])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(RepositorySettings {
+                repo_root: row.get(0)?,
+                verification_command: row.get(1)?,
+                verification_timeout_ms: row.get(2)?,
+                branch_template: row.get(3)?,
+                auto_commit: row.get(4)?,
+                auto_commit_trailer: row.get(5)?,
+                worktree_location: row.get(6)?,
+                provision_copy: patterns_from_column(row, 7)?,
+                provision_symlink: patterns_from_column(row, 8)?,
+                setup_command: row.get(9)?,
+                retention_max_age_days: row.get(10)?,
+                retention_max_bytes: row.get(11)?,
+                retention_keep_unmerged: row.get(12)?,
+                init_submodules: row.get(13)?,
+                lfs_checkout: row.get(14)?,
+                auto_sync: row.get(15)?,
+                sync_strategy: row.get(16)?,
+                sync_on_conflict: row.get(17)?,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn save_repository_settings(&self, settings: &RepositorySettings) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "INSERT INTO repository_settings (
+                repo_root, verification_command, verification_timeout_ms, branch_template,
+                auto_commit, auto_commit_trailer, worktree_location, provision_copy,
+                provision_symlink, setup_command, retention_max_age_days, retention_max_bytes,
+                retention_keep_unmerged, init_submodules, lfs_checkout, auto_sync,
+                sync_strategy, sync_on_conflict, updated_at
+             )
+             VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19
+             )
+             ON CONFLICT(repo_root) DO UPDATE SET
+                verification_command = excluded.verification_command,
+                verification_timeout_ms = excluded.verification_timeout_ms,
+                branch_template = excluded.branch_template,
+                auto_commit = excluded.auto_commit,
+                auto_commit_trailer = excluded.auto_commit_trailer,
+                worktree_location = excluded.worktree_location,
+                provision_copy = excluded.provision_copy,
+                provision_symlink = excluded.provision_symlink,
+                setup_command = excluded.setup_command,
+                retention_max_age_days = excluded.retention_max_age_days,
+                retention_max_bytes = excluded.retention_max_bytes,
+                retention_keep_unmerged = excluded.retention_keep_unmerged,
+                init_submodules = excluded.init_submodules,
+                lfs_checkout = excluded.lfs_checkout,
+                auto_sync = excluded.auto_sync,
+                sync_strategy = excluded.sync_strategy,
+                sync_on_conflict = excluded.sync_on_conflict,
+                updated_at = excluded.updated_at",
+            params![
+                settings.repo_root,
+                settings.verification_command,
+                settings.verification_timeout_ms,
+                settings.branch_template,
+                settings.auto_commit,
+                settings.auto_commit_trailer,
+                settings.worktree_location,
+                serde_json::Value::from(settings.provision_copy.clone()).to_string(),
+                serde_json::Value::from(settings.provision_symlink.clone()).to_string(),
+                settings.setup_command,
+                settings.retention_max_age_days,
+                settings.retention_max_bytes,
+                settings.retention_keep_unmerged,
+                settings.init_submodules,
+                settings.lfs_checkout,
+                settings.auto_sync,
+                settings.sync_strategy,
+                settings.sync_on_conflict,
+                chrono::Utc::now().to_rfc3339(),
+            ],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+}
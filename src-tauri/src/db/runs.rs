@@ -0,0 +1,130 @@
+use crate::db::{Database, DbError};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// Outcomes recorded against a single run of a session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionRun {
+    pub session_id: String,
+    pub run_id: String,
+    pub verification_status: Option<String>,
+    pub verification_command: Option<String>,
+    pub verification_exit_code: Option<i32>,
+    /// Commit created by auto-commit when the run completed.
+    pub commit_sha: Option<String>,
+    pub updated_at: String,
+}
+
+fn run_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SessionRun> {
+    Ok(SessionRun {
+        session_id: row.get(0)?,
+        run_id: row.get(1)?,
+        verification_status: row.get(2)?,
+        verification_command: row.get(3)?,
+        verification_exit_code: row.get(4)?,
+        commit_sha: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+impl Database {
+    pub fn update_run_verification(
+        &self,
+        session_id: &str,
+        run_id: &str,
+        status: &str,
+        command: Option<&str>,
+        exit_code: Option<i32>,
+    ) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "INSERT INTO session_runs (
+                session_id, run_id, verification_status, verification_command,
+                verification_exit_code, updated_at
+             )
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(session_id, run_id) DO UPDATE SET
+                verification_status = excluded.verification_status,
+                verification_command = excluded.verification_command,
+                verification_exit_code = excluded.verification_exit_code,
+                updated_at = excluded.updated_at",
+            params![
+                session_id,
+                run_id,
+                status,
+                command,
+                exit_code,
+                chrono::Utc::now().to_rfc3339(),
+            ],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn update_run_commit(
+        &self,
+        session_id: &str,
+        run_id: &str,
+        commit_sha: &str,
+    ) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "INSERT INTO session_runs (session_id, run_id, commit_sha, updated_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(session_id, run_id) DO UPDATE SET
+                commit_sha = excluded.commit_sha,
+                updated_at = excluded.updated_at",
+            params![session_id, run_id, commit_sha, chrono::Utc::now().to_rfc3339()],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_session_run(
+        &self,
+        session_id: &str,
+        run_id: &str,
+    ) -> Result<Option<SessionRun>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT session_id, run_id, verification_status, verification_command,
+                    verification_exit_code, commit_sha, updated_at
+             FROM session_runs
+             WHERE session_id = ?1 AND run_id = ?2",
+        )?;
+        let mut rows = stmt.query(params![session_id, run_id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(run_from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn list_session_runs(&self, session_id: &str) -> Result<Vec<SessionRun>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT session_id, run_id, verification_status, verification_command,
+                    verification_exit_code, commit_sha, updated_at
+             FROM session_runs
+             WHERE session_id = ?1
+             ORDER BY updated_at ASC, run_id ASC",
+        )?;
+        let rows = stmt.query_map(params![session_id], run_from_row)?;
+
+        let mut runs = Vec::new();
+        for run in rows {
+            runs.push(run?);
+        }
+
+        Ok(runs)
+    }
+}
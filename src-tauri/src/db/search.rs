@@ -0,0 +1,123 @@
+use crate::db::{Database, DbError};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// Wrapped around every matched term in `SessionSearchHit::snippet`. Control characters, so
+/// the snippet can be rendered without treating any of the session's own text as markup.
+pub const SNIPPET_MATCH_START: &str = "\u{2}";
+pub const SNIPPET_MATCH_END: &str = "\u{3}";
+pub const DEFAULT_SEARCH_LIMIT: u32 = 50;
+pub const MAX_SEARCH_LIMIT: u32 = 200;
+
+/// Narrows a search; unset fields match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionSearchFilters {
+    /// Repository root the sessions ran in.
+    pub repo: Option<String>,
+    pub statuses: Vec<String>,
+    /// Inclusive lower bound on the matching event's timestamp (RFC 3339).
+    pub from: Option<String>,
+    /// Exclusive upper bound on the matching event's timestamp (RFC 3339).
+    pub to: Option<String>,
+    pub event_types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSearchHit {
+    pub session_id: String,
+    pub session_name: String,
+    pub session_status: String,
+    pub working_dir: String,
+    /// Run and seq of the matching event; `None` for hits in stored messages.
+    pub run_id: Option<String>,
+    pub seq: Option<i64>,
+    pub event_type: String,
+    pub timestamp: String,
+    pub snippet: String,
+}
+
+impl Database {
+    /// Full-text search over session events and messages, best match first. `query` uses FTS5
+    /// syntax: terms are ANDed, and `OR`, `NOT`, `"phrases"`, `prefix*` and `NEAR(...)` work.
+    pub fn search_sessions(
+        &self,
+        query: &str,
+        filters: &SessionSearchFilters,
+        limit: u32,
+    ) -> Result<Vec<SessionSearchHit>, DbError> {
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT docs.session_id,
+                    sessions.name,
+                    sessions.status,
+                    sessions.working_dir,
+                    docs.run_id,
+                    docs.seq,
+                    docs.event_type,
+                    docs.timestamp,
+                    snippet(session_search, 0, ?2, ?3, '…', 16)
+             FROM session_search
+             JOIN session_search_docs docs ON docs.doc_id = session_search.rowid
+             JOIN sessions ON sessions.id = docs.session_id
+             WHERE session_search MATCH ?1
+               AND (?4 IS NULL OR sessions.working_dir = ?4)
+               AND (?5 IS NULL OR sessions.status IN (SELECT value FROM json_each(?5)))
+               AND (?6 IS NULL OR docs.timestamp >= ?6)
+               AND (?7 IS NULL OR docs.timestamp < ?7)
+               AND (?8 IS NULL OR docs.event_type IN (SELECT value FROM json_each(?8)))
+             ORDER BY session_search.rank, docs.timestamp DESC
+             LIMIT ?9",
+        )?;
+
+        let json_list = |values: &[String]| {
+            (!values.is_empty()).then(|| serde_json::Value::from(values.to_vec()).to_string())
+        };
+        // The statement itself is known to be valid, so a plain SQLITE_ERROR while stepping it
+        // comes from parsing the MATCH expression.
+        let invalid_query = |err: rusqlite::Error| match err {
+            rusqlite::Error::SqliteFailure(failure, message)
+                if failure.code == rusqlite::ErrorCode::Unknown =>
+            {
+                DbError::InvalidSearchQuery(message.unwrap_or_else(|| failure.to_string()))
+            }
+            other => DbError::Sqlite(other),
+        };
+        let rows = stmt
+            .query_map(
+                params![
+                    query,
+                    SNIPPET_MATCH_START,
+                    SNIPPET_MATCH_END,
+                    filters.repo,
+                    json_list(&filters.statuses),
+                    filters.from,
+                    filters.to,
+                    json_list(&filters.event_types),
+                    limit.min(MAX_SEARCH_LIMIT),
+                ],
+                |row| {
+                    Ok(SessionSearchHit {
+                        session_id: row.get(0)?,
+                        session_name: row.get(1)?,
+                        session_status: row.get(2)?,
+                        working_dir: row.get(3)?,
+                        run_id: row.get(4)?,
+                        seq: row.get(5)?,
+                        event_type: row.get(6)?,
+                        timestamp: row.get(7)?,
+                        snippet: row.get(8)?,
+                    })
+                },
+            )
+            .map_err(invalid_query)?;
+
+        let mut hits = Vec::new();
+        for hit in rows {
+            hits.push(hit.map_err(invalid_query)?);
+        }
+
+        Ok(hits)
+    }
+}
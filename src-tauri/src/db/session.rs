@@ -1,6 +1,12 @@
 use crate::db::{Database, DbError};
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The CLI stream's sequence counter for the run each session is streaming, keyed by session.
+pub(crate) type RunSequences = Arc<Mutex<HashMap<String, (String, Arc<AtomicU64>)>>>;
 
 fn is_terminal_status(status: &str) -> bool {
     matches!(status, "completed" | "failed" | "killed" | "interrupted")
@@ -29,7 +35,7 @@ pub struct SessionMessage {
     pub timestamp: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SessionDashboardRow {
     pub id: String,
     pub name: String,
@@ -41,6 +47,27 @@ pub struct SessionDashboardRow {
     pub restored: bool,
     pub restored_at: Option<String>,
     pub recovery_hint: bool,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: Option<f64>,
+    pub verification_status: Option<String>,
+    pub branch_name: Option<String>,
+    pub base_ref: Option<String>,
+    pub base_sha: Option<String>,
+    pub integration_summary: Option<String>,
+    pub commit_sha: Option<String>,
+    /// Disk usage of the worktree when it was last measured.
+    pub worktree_bytes: Option<i64>,
+    /// Set when the retention policy removed the worktree; the branch is kept.
+    pub worktree_pruned_at: Option<String>,
+    /// Copy of a non-git working directory the session runs in instead of the original.
+    pub sandbox_path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionBase {
+    pub base_ref: String,
+    pub base_sha: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +81,21 @@ pub struct SessionHistoryEvent {
     pub timestamp: String,
 }
 
+/// An event from a session's CLI stream on its way to the writer thread.
+#[derive(Debug, Clone)]
+pub struct QueuedSessionEvent {
+    pub session_id: String,
+    pub run_id: String,
+    pub seq: u64,
+    pub event_type: String,
+    pub payload_json: String,
+    pub timestamp: String,
+    /// Whether the event moves the session's `last_activity_at`.
+    pub activity: bool,
+    /// Assistant output to store in `messages` as well.
+    pub assistant_message: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionRunMetadata {
     pub resume_count: i64,
@@ -61,6 +103,170 @@ pub struct SessionRunMetadata {
     pub last_resume_at: Option<String>,
 }
 
+const DASHBOARD_SELECT: &str = "SELECT sessions.id,
+            sessions.name,
+            sessions.status,
+            sessions.created_at,
+            sessions.last_activity_at,
+            sessions.failure_reason,
+            sessions.worktree_path,
+            sessions.restored,
+            sessions.restored_at,
+            sessions.recovery_hint,
+            COALESCE(usage.input_tokens, 0),
+            COALESCE(usage.output_tokens, 0),
+            usage.cost_usd,
+            run.verification_status,
+            sessions.branch_name,
+            sessions.base_ref,
+            sessions.base_sha,
+            (
+                SELECT integration.summary
+                FROM session_integrations integration
+                WHERE integration.session_id = sessions.id
+                ORDER BY integration.created_at DESC, integration.rowid DESC
+                LIMIT 1
+            ) AS integration_summary,
+            run.commit_sha,
+            sessions.worktree_bytes,
+            sessions.worktree_pruned_at,
+            sessions.sandbox_path
+     FROM sessions
+     LEFT JOIN (
+        SELECT session_id,
+               SUM(input_tokens) AS input_tokens,
+               SUM(output_tokens) AS output_tokens,
+               SUM(cost_usd) AS cost_usd
+        FROM session_run_usage
+        GROUP BY session_id
+     ) AS usage ON usage.session_id = sessions.id
+     LEFT JOIN session_runs AS run
+        ON run.session_id = sessions.id AND run.run_id = sessions.active_run_id";
+
+fn dashboard_row_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SessionDashboardRow> {
+    Ok(SessionDashboardRow {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        status: row.get(2)?,
+        created_at: row.get(3)?,
+        last_activity_at: row.get(4)?,
+        failure_reason: row.get(5)?,
+        worktree_path: row.get(6)?,
+        restored: row.get::<_, i64>(7)? != 0,
+        restored_at: row.get(8)?,
+        recovery_hint: row.get::<_, i64>(9)? != 0,
+        input_tokens: row.get(10)?,
+        output_tokens: row.get(11)?,
+        cost_usd: row.get(12)?,
+        verification_status: row.get(13)?,
+        branch_name: row.get(14)?,
+        base_ref: row.get(15)?,
+        base_sha: row.get(16)?,
+        integration_summary: row.get(17)?,
+        commit_sha: row.get(18)?,
+        worktree_bytes: row.get(19)?,
+        worktree_pruned_at: row.get(20)?,
+        sandbox_path: row.get(21)?,
+    })
+}
+
+/// Maps `id, session_id, role, content, timestamp`.
+pub(crate) fn message_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SessionMessage> {
+    Ok(SessionMessage {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        role: row.get(2)?,
+        content: row.get(3)?,
+        timestamp: row.get(4)?,
+    })
+}
+
+/// Maps `id, session_id, run_id, seq, event_type, payload_json, timestamp`.
+pub(crate) fn history_event_from_row(
+    row: &rusqlite::Row<'_>,
+) -> rusqlite::Result<SessionHistoryEvent> {
+    let payload_raw: String = row.get(5)?;
+    let payload_json = serde_json::from_str(&payload_raw).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(
+            payload_raw.len(),
+            rusqlite::types::Type::Text,
+            Box::new(err),
+        )
+    })?;
+
+    Ok(SessionHistoryEvent {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        run_id: row.get(2)?,
+        seq: row.get(3)?,
+        event_type: row.get(4)?,
+        payload_json,
+        timestamp: row.get(6)?,
+    })
+}
+
+fn insert_event_row(conn: &rusqlite::Connection, event: &QueuedSessionEvent) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT INTO session_events (id, session_id, run_id, seq, event_type, payload_json, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(session_id, run_id, seq) DO NOTHING",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            event.session_id,
+            event.run_id,
+            event.seq as i64,
+            event.event_type,
+            event.payload_json,
+            event.timestamp,
+        ],
+    )
+}
+
+/// Picks the seq for an event written outside the CLI stream. While the run is streaming, the
+/// stream's own counter hands it out, so the event can never take a seq the stream is about to
+/// use; otherwise it goes after the highest persisted one. Runs on the writer, behind every
+/// queued event.
+pub(crate) fn next_event_seq(
+    conn: &rusqlite::Connection,
+    sequences: &RunSequences,
+    session_id: &str,
+    run_id: &str,
+) -> rusqlite::Result<i64> {
+    let next_persisted: i64 = conn.query_row(
+        "SELECT COALESCE(MAX(seq), 0) + 1 FROM session_events WHERE session_id = ?1 AND run_id = ?2",
+        params![session_id, run_id],
+        |row| row.get(0),
+    )?;
+
+    let live = sequences
+        .lock()
+        .ok()
+        .and_then(|sequences| sequences.get(session_id).cloned())
+        .filter(|(live_run_id, _)| live_run_id == run_id)
+        .map(|(_, sequence)| sequence);
+    let Some(sequence) = live else {
+        return Ok(next_persisted);
+    };
+
+    sequence.fetch_max(next_persisted as u64, Ordering::SeqCst);
+    Ok(sequence.fetch_add(1, Ordering::SeqCst) as i64)
+}
+
+fn insert_message_row(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+    role: &str,
+    content: &str,
+    timestamp: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO messages (id, session_id, role, content, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![uuid::Uuid::new_v4().to_string(), session_id, role, content, timestamp],
+    )?;
+    Ok(())
+}
+
 impl Database {
     pub fn create_session(&self, session: &Session) -> Result<(), DbError> {
         let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
@@ -85,7 +291,7 @@ impl Database {
     }
 
     pub fn get_session(&self, id: &str) -> Result<Option<Session>, DbError> {
-        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
 
         let mut stmt = conn.prepare(
             "SELECT id, name, status, working_dir, created_at, updated_at
@@ -109,7 +315,7 @@ impl Database {
     }
 
     pub fn list_sessions(&self) -> Result<Vec<Session>, DbError> {
-        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
 
         let mut stmt = conn.prepare(
             "SELECT id, name, status, working_dir, created_at, updated_at
@@ -191,6 +397,9 @@ impl Database {
         tx.execute("DELETE FROM sessions WHERE id = ?1", params![id])?;
 
         tx.commit()?;
+        if let Ok(mut sequences) = self.run_sequences.lock() {
+            sequences.remove(id);
+        }
         Ok(())
     }
 
@@ -207,64 +416,78 @@ impl Database {
         Ok(())
     }
 
+    /// Goes through the writer, since the CLI event task reports failures as they stream in.
     pub fn update_failure_reason(&self, id: &str, reason: Option<&str>) -> Result<(), DbError> {
+        let (id, reason) = (id.to_string(), reason.map(str::to_string));
+        self.writer.execute(move |conn| {
+            conn.execute(
+                "UPDATE sessions SET failure_reason = ?1, updated_at = ?2 WHERE id = ?3",
+                params![reason, chrono::Utc::now().to_rfc3339(), id],
+            )?;
+            Ok(())
+        })
+    }
+
+    pub fn update_worktree_path(&self, id: &str, worktree_path: Option<&str>) -> Result<(), DbError> {
         let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
         let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
 
         tx.execute(
-            "UPDATE sessions SET failure_reason = ?1, updated_at = ?2 WHERE id = ?3",
-            params![reason, chrono::Utc::now().to_rfc3339(), id],
+            "UPDATE sessions SET worktree_path = ?1, updated_at = ?2 WHERE id = ?3",
+            params![worktree_path, chrono::Utc::now().to_rfc3339(), id],
         )?;
 
         tx.commit()?;
         Ok(())
     }
 
-    pub fn update_worktree_path(&self, id: &str, worktree_path: Option<&str>) -> Result<(), DbError> {
+    pub fn update_sandbox_path(&self, id: &str, sandbox_path: Option<&str>) -> Result<(), DbError> {
         let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
         let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
 
         tx.execute(
-            "UPDATE sessions SET worktree_path = ?1, updated_at = ?2 WHERE id = ?3",
-            params![worktree_path, chrono::Utc::now().to_rfc3339(), id],
+            "UPDATE sessions SET sandbox_path = ?1, updated_at = ?2 WHERE id = ?3",
+            params![sandbox_path, chrono::Utc::now().to_rfc3339(), id],
         )?;
 
         tx.commit()?;
         Ok(())
     }
 
-    pub fn list_dashboard_sessions(&self) -> Result<Vec<SessionDashboardRow>, DbError> {
-        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+    /// Stores a fresh disk-usage measurement; `updated_at` is left alone since nothing about the
+    /// session itself changed.
+    pub fn update_worktree_bytes(&self, id: &str, bytes: i64) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
 
-        let mut stmt = conn.prepare(
-            "SELECT id,
-                    name,
-                    status,
-                    created_at,
-                    last_activity_at,
-                    failure_reason,
-                    worktree_path,
-                    restored,
-                    restored_at,
-                    recovery_hint
-             FROM sessions
-             ORDER BY created_at DESC",
+        tx.execute("UPDATE sessions SET worktree_bytes = ?1 WHERE id = ?2", params![bytes, id])?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Records that the session's worktree was removed by the retention policy.
+    pub fn mark_worktree_pruned(&self, id: &str) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "UPDATE sessions
+             SET worktree_path = NULL, worktree_bytes = NULL, worktree_pruned_at = ?1
+             WHERE id = ?2",
+            params![chrono::Utc::now().to_rfc3339(), id],
         )?;
 
-        let rows = stmt.query_map([], |row| {
-            Ok(SessionDashboardRow {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                status: row.get(2)?,
-                created_at: row.get(3)?,
-                last_activity_at: row.get(4)?,
-                failure_reason: row.get(5)?,
-                worktree_path: row.get(6)?,
-                restored: row.get::<_, i64>(7)? != 0,
-                restored_at: row.get(8)?,
-                recovery_hint: row.get::<_, i64>(9)? != 0,
-            })
-        })?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn list_dashboard_sessions(&self) -> Result<Vec<SessionDashboardRow>, DbError> {
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt =
+            conn.prepare(&format!("{} ORDER BY sessions.created_at DESC", DASHBOARD_SELECT))?;
+        let rows = stmt.query_map([], dashboard_row_from_row)?;
 
         let mut sessions = Vec::new();
         for session in rows {
@@ -274,6 +497,110 @@ impl Database {
         Ok(sessions)
     }
 
+    pub fn get_dashboard_session(&self, id: &str) -> Result<Option<SessionDashboardRow>, DbError> {
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(&format!("{} WHERE sessions.id = ?1", DASHBOARD_SELECT))?;
+        let mut rows = stmt.query(params![id])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(Some(dashboard_row_from_row(row)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn update_session_branch(&self, id: &str, branch_name: Option<&str>) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "UPDATE sessions SET branch_name = ?1, updated_at = ?2 WHERE id = ?3",
+            params![branch_name, chrono::Utc::now().to_rfc3339(), id],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_session_branch(&self, id: &str) -> Result<Option<String>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare("SELECT branch_name FROM sessions WHERE id = ?1")?;
+        let mut rows = stmt.query(params![id])?;
+
+        if let Some(row) = rows.next()? {
+            let branch_name: Option<String> = row.get(0)?;
+            Ok(branch_name)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn update_session_prompt(&self, id: &str, prompt: &str) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "UPDATE sessions SET prompt = ?1, updated_at = ?2 WHERE id = ?3",
+            params![prompt, chrono::Utc::now().to_rfc3339(), id],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_session_prompt(&self, id: &str) -> Result<Option<String>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare("SELECT prompt FROM sessions WHERE id = ?1")?;
+        let mut rows = stmt.query(params![id])?;
+
+        if let Some(row) = rows.next()? {
+            let prompt: Option<String> = row.get(0)?;
+            Ok(prompt)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn update_session_base(
+        &self,
+        id: &str,
+        base_ref: Option<&str>,
+        base_sha: Option<&str>,
+    ) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "UPDATE sessions SET base_ref = ?1, base_sha = ?2, updated_at = ?3 WHERE id = ?4",
+            params![base_ref, base_sha, chrono::Utc::now().to_rfc3339(), id],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The ref and commit the session's worktree started from, if it has one.
+    pub fn get_session_base(&self, id: &str) -> Result<Option<SessionBase>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare("SELECT base_ref, base_sha FROM sessions WHERE id = ?1")?;
+        let mut rows = stmt.query(params![id])?;
+
+        if let Some(row) = rows.next()? {
+            let base_ref: Option<String> = row.get(0)?;
+            let base_sha: Option<String> = row.get(1)?;
+            Ok(base_sha.map(|base_sha| SessionBase {
+                base_ref: base_ref.unwrap_or_else(|| base_sha.clone()),
+                base_sha,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
     pub fn get_session_worktree_path(&self, id: &str) -> Result<Option<String>, DbError> {
         let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
 
@@ -288,6 +615,20 @@ impl Database {
         }
     }
 
+    pub fn get_session_sandbox_path(&self, id: &str) -> Result<Option<String>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare("SELECT sandbox_path FROM sessions WHERE id = ?1")?;
+        let mut rows = stmt.query(params![id])?;
+
+        if let Some(row) = rows.next()? {
+            let sandbox_path: Option<String> = row.get(0)?;
+            Ok(sandbox_path)
+        } else {
+            Ok(None)
+        }
+    }
+
     pub fn reconcile_stale_inflight_sessions(&self) -> Result<Vec<String>, DbError> {
         let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
         let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
@@ -458,27 +799,14 @@ impl Database {
         content: &str,
         timestamp: &str,
     ) -> Result<(), DbError> {
-        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
-        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
-
-        tx.execute(
-            "INSERT INTO messages (id, session_id, role, content, timestamp)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![
-                uuid::Uuid::new_v4().to_string(),
-                session_id,
-                role,
-                content,
-                timestamp,
-            ],
-        )?;
-
-        tx.commit()?;
-        Ok(())
+        let (session_id, role, content, timestamp) =
+            (session_id.to_string(), role.to_string(), content.to_string(), timestamp.to_string());
+        self.writer
+            .execute(move |conn| insert_message_row(conn, &session_id, &role, &content, &timestamp))
     }
 
     pub fn list_session_messages(&self, session_id: &str) -> Result<Vec<SessionMessage>, DbError> {
-        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
 
         let mut stmt = conn.prepare(
             "SELECT id, session_id, role, content, timestamp
@@ -487,15 +815,7 @@ impl Database {
              ORDER BY timestamp ASC",
         )?;
 
-        let rows = stmt.query_map(params![session_id], |row| {
-            Ok(SessionMessage {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                role: row.get(2)?,
-                content: row.get(3)?,
-                timestamp: row.get(4)?,
-            })
-        })?;
+        let rows = stmt.query_map(params![session_id], message_from_row)?;
 
         let mut messages = Vec::new();
         for message in rows {
@@ -514,30 +834,106 @@ impl Database {
         payload_json: &serde_json::Value,
         timestamp: &str,
     ) -> Result<(), DbError> {
-        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
-        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let event = QueuedSessionEvent {
+            session_id: session_id.to_string(),
+            run_id: run_id.to_string(),
+            seq,
+            event_type: event_type.to_string(),
+            payload_json: payload_json.to_string(),
+            timestamp: timestamp.to_string(),
+            activity: false,
+            assistant_message: None,
+        };
+        self.writer.execute(move |conn| insert_event_row(conn, &event).map(|_| ()))
+    }
 
-        tx.execute(
-            "INSERT INTO session_events (id, session_id, run_id, seq, event_type, payload_json, timestamp)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
-             ON CONFLICT(session_id, run_id, seq) DO NOTHING",
-            params![
-                uuid::Uuid::new_v4().to_string(),
-                session_id,
-                run_id,
-                seq as i64,
-                event_type,
-                payload_json.to_string(),
-                timestamp,
-            ],
+    /// Hands an event from the live CLI stream to the writer thread without waiting for it. It
+    /// is committed with the next batch, together with the session bookkeeping it implies.
+    pub fn queue_session_event(&self, event: QueuedSessionEvent) -> Result<(), DbError> {
+        let session_id = event.session_id.clone();
+        self.writer.queue(&session_id, move |conn| {
+            insert_event_row(conn, &event)?;
+            conn.execute(
+                "UPDATE sessions
+                 SET restored = 0,
+                     restored_at = NULL,
+                     recovery_hint = 0,
+                     updated_at = ?1
+                 WHERE id = ?2 AND (restored = 1 OR recovery_hint = 1)",
+                params![chrono::Utc::now().to_rfc3339(), event.session_id],
+            )?;
+            if event.activity {
+                conn.execute(
+                    "UPDATE sessions SET last_activity_at = ?1, updated_at = ?2 WHERE id = ?3",
+                    params![event.timestamp, chrono::Utc::now().to_rfc3339(), event.session_id],
+                )?;
+            }
+            if let Some(content) = &event.assistant_message {
+                insert_message_row(conn, &event.session_id, "assistant", content, &event.timestamp)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Shares the CLI stream's sequence counter for `run_id` with events recorded outside the
+    /// stream, such as hook results and warnings, until the session's next run replaces it.
+    pub fn register_run_sequence(&self, session_id: &str, run_id: &str, sequence: Arc<AtomicU64>) {
+        if let Ok(mut sequences) = self.run_sequences.lock() {
+            sequences.insert(session_id.to_string(), (run_id.to_string(), sequence));
+        }
+    }
+
+    /// Appends an event raised outside the CLI stream to a run; see `next_event_seq` for how
+    /// its seq is picked. Returns the seq.
+    pub fn append_session_event(
+        &self,
+        session_id: &str,
+        run_id: &str,
+        event_type: &str,
+        payload_json: &serde_json::Value,
+        timestamp: &str,
+    ) -> Result<u64, DbError> {
+        let seqs = self.append_session_events(
+            session_id,
+            run_id,
+            vec![(event_type.to_string(), payload_json.to_string(), timestamp.to_string())],
         )?;
+        Ok(seqs[0])
+    }
 
-        tx.commit()?;
-        Ok(())
+    /// Appends several `(event_type, payload_json, timestamp)` events to a run in one writer
+    /// job, like `append_session_event`. Returns their seqs in order.
+    pub fn append_session_events(
+        &self,
+        session_id: &str,
+        run_id: &str,
+        events: Vec<(String, String, String)>,
+    ) -> Result<Vec<u64>, DbError> {
+        let session_id = session_id.to_string();
+        let run_id = run_id.to_string();
+        let sequences = self.run_sequences.clone();
+        self.writer.execute(move |conn| {
+            let mut seqs = Vec::with_capacity(events.len());
+            for (event_type, payload_json, timestamp) in events {
+                let event = QueuedSessionEvent {
+                    session_id: session_id.clone(),
+                    run_id: run_id.clone(),
+                    seq: next_event_seq(conn, &sequences, &session_id, &run_id)? as u64,
+                    event_type,
+                    payload_json,
+                    timestamp,
+                    activity: false,
+                    assistant_message: None,
+                };
+                insert_event_row(conn, &event)?;
+                seqs.push(event.seq);
+            }
+            Ok(seqs)
+        })
     }
 
     pub fn list_session_history(&self, session_id: &str) -> Result<Vec<SessionHistoryEvent>, DbError> {
-        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
 
         let mut stmt = conn.prepare(
             "SELECT id, session_id, run_id, seq, event_type, payload_json, timestamp
@@ -546,26 +942,7 @@ impl Database {
              ORDER BY timestamp ASC, seq ASC, id ASC",
         )?;
 
-        let rows = stmt.query_map(params![session_id], |row| {
-            let payload_raw: String = row.get(5)?;
-            let payload_json = serde_json::from_str(&payload_raw).map_err(|err| {
-                rusqlite::Error::FromSqlConversionFailure(
-                    payload_raw.len(),
-                    rusqlite::types::Type::Text,
-                    Box::new(err),
-                )
-            })?;
-
-            Ok(SessionHistoryEvent {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                run_id: row.get(2)?,
-                seq: row.get(3)?,
-                event_type: row.get(4)?,
-                payload_json,
-                timestamp: row.get(6)?,
-            })
-        })?;
+        let rows = stmt.query_map(params![session_id], history_event_from_row)?;
 
         let mut events = Vec::new();
         for event in rows {
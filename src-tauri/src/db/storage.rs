@@ -0,0 +1,393 @@
+use crate::db::session::{history_event_from_row, next_event_seq};
+use crate::db::{Database, DbError, SessionHistoryEvent};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+/// Event type of the summary left in place of a compacted or archived run.
+pub const HISTORY_SUMMARY_EVENT_TYPE: &str = "history_compacted";
+
+/// How long full session history is kept; see `session::history_retention`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryRetentionPolicy {
+    /// Runs whose newest event is older than this are compacted; `None` keeps everything.
+    pub keep_days: Option<i64>,
+    /// `compact` or `archive`.
+    pub mode: String,
+}
+
+impl Default for HistoryRetentionPolicy {
+    fn default() -> Self {
+        Self { keep_days: None, mode: "compact".to_string() }
+    }
+}
+
+/// A run of a finished session that is due for compaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExpiredRun {
+    pub session_id: String,
+    pub run_id: String,
+    pub event_count: u64,
+    pub event_bytes: i64,
+    pub first_at: String,
+    pub last_at: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionStorageUsage {
+    pub session_id: String,
+    pub name: String,
+    pub status: String,
+    pub event_count: u64,
+    /// Size of the stored event payloads.
+    pub event_bytes: i64,
+    pub message_count: u64,
+    pub message_bytes: i64,
+    pub compacted_runs: u64,
+    /// Size of the session's history archive on disk.
+    pub archived_bytes: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseFileUsage {
+    /// Size of the database file.
+    pub database_bytes: i64,
+    /// Part of it that is unused and can be reclaimed by vacuuming.
+    pub free_bytes: i64,
+}
+
+impl Database {
+    pub fn get_history_retention_policy(&self) -> Result<HistoryRetentionPolicy, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt =
+            conn.prepare("SELECT keep_days, mode FROM history_retention WHERE id = 1")?;
+        let mut rows = stmt.query([])?;
+
+        if let Some(row) = rows.next()? {
+            Ok(HistoryRetentionPolicy { keep_days: row.get(0)?, mode: row.get(1)? })
+        } else {
+            Ok(HistoryRetentionPolicy::default())
+        }
+    }
+
+    pub fn save_history_retention_policy(
+        &self,
+        policy: &HistoryRetentionPolicy,
+    ) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "INSERT INTO history_retention (id, keep_days, mode, updated_at)
+             VALUES (1, ?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET
+                keep_days = excluded.keep_days,
+                mode = excluded.mode,
+                updated_at = excluded.updated_at",
+            params![policy.keep_days, policy.mode, chrono::Utc::now().to_rfc3339()],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Runs of sessions that are not running whose newest event is older than `cutoff` and that
+    /// still hold events to remove, oldest first. Counts and sizes cover only those events:
+    /// everything but the summary, and but messages when `keep_messages` is set.
+    pub fn list_expired_runs(
+        &self,
+        cutoff: &str,
+        keep_messages: bool,
+    ) -> Result<Vec<ExpiredRun>, DbError> {
+        self.flush_writes()?;
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT session_id, run_id, SUM(removable), SUM(removable * bytes), MIN(timestamp),
+                    MAX(timestamp)
+             FROM (
+                SELECT events.session_id,
+                       events.run_id,
+                       events.timestamp,
+                       (events.event_type <> 'message' OR ?2 = 0) AS removable,
+                       length(CAST(events.payload_json AS BLOB)) AS bytes
+                FROM session_events events
+                JOIN sessions ON sessions.id = events.session_id
+                WHERE sessions.status NOT IN ('starting', 'running', 'interrupting', 'resuming')
+                  AND events.event_type <> ?3
+             )
+             GROUP BY session_id, run_id
+             HAVING MAX(timestamp) < ?1 AND SUM(removable) > 0
+             ORDER BY MIN(timestamp) ASC",
+        )?;
+        let rows =
+            stmt.query_map(params![cutoff, keep_messages, HISTORY_SUMMARY_EVENT_TYPE], |row| {
+                Ok(ExpiredRun {
+                    session_id: row.get(0)?,
+                    run_id: row.get(1)?,
+                    event_count: row.get(2)?,
+                    event_bytes: row.get(3)?,
+                    first_at: row.get(4)?,
+                    last_at: row.get(5)?,
+                })
+            })?;
+
+        let mut runs = Vec::new();
+        for run in rows {
+            runs.push(run?);
+        }
+
+        Ok(runs)
+    }
+
+    /// Every event of a run except retention summaries, in history order.
+    pub fn list_run_events(
+        &self,
+        session_id: &str,
+        run_id: &str,
+    ) -> Result<Vec<SessionHistoryEvent>, DbError> {
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, run_id, seq, event_type, payload_json, timestamp
+             FROM session_events
+             WHERE session_id = ?1 AND run_id = ?2 AND event_type <> ?3
+             ORDER BY timestamp ASC, seq ASC, id ASC",
+        )?;
+        let rows = stmt.query_map(
+            params![session_id, run_id, HISTORY_SUMMARY_EVENT_TYPE],
+            history_event_from_row,
+        )?;
+
+        let mut events = Vec::new();
+        for event in rows {
+            events.push(event?);
+        }
+
+        Ok(events)
+    }
+
+    /// Deletes the events of `run` up to its `last_at`, keeping messages when asked, and
+    /// appends `summary` to the run in the same transaction. Assistant messages whose events go
+    /// stay searchable through their `messages` rows. Returns the number of events removed.
+    pub fn compact_run(
+        &self,
+        run: &ExpiredRun,
+        keep_messages: bool,
+        summary: &serde_json::Value,
+    ) -> Result<u64, DbError> {
+        let run = run.clone();
+        let summary = summary.to_string();
+        let sequences = self.run_sequences.clone();
+        self.writer.execute(move |conn| {
+            let next_seq = next_event_seq(conn, &sequences, &run.session_id, &run.run_id)?;
+            if !keep_messages {
+                index_archived_messages(conn, &run)?;
+            }
+            let removed = conn.execute(
+                "DELETE FROM session_events
+                 WHERE session_id = ?1 AND run_id = ?2 AND timestamp <= ?3
+                   AND event_type <> ?4
+                   AND (?5 = 0 OR event_type <> 'message')",
+                params![
+                    run.session_id,
+                    run.run_id,
+                    run.last_at,
+                    HISTORY_SUMMARY_EVENT_TYPE,
+                    keep_messages
+                ],
+            )?;
+            conn.execute(
+                "INSERT INTO session_events
+                    (id, session_id, run_id, seq, event_type, payload_json, timestamp)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    uuid::Uuid::new_v4().to_string(),
+                    run.session_id,
+                    run.run_id,
+                    next_seq,
+                    HISTORY_SUMMARY_EVENT_TYPE,
+                    summary,
+                    run.last_at,
+                ],
+            )?;
+            Ok(removed as u64)
+        })
+    }
+
+    /// Puts archived events back and drops the summaries that pointed at the archive, and the
+    /// search entries that stood in for its messages. Events that are already present are
+    /// skipped. Returns the number of events restored.
+    pub fn restore_archived_events(
+        &self,
+        session_id: &str,
+        events: Vec<SessionHistoryEvent>,
+    ) -> Result<u64, DbError> {
+        let session_id = session_id.to_string();
+        self.writer.execute(move |conn| {
+            let mut restored = 0;
+            for event in events.iter().filter(|event| event.session_id == session_id) {
+                restored += conn.execute(
+                    "INSERT OR IGNORE INTO session_events
+                        (id, session_id, run_id, seq, event_type, payload_json, timestamp)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        event.id,
+                        event.session_id,
+                        event.run_id,
+                        event.seq,
+                        event.event_type,
+                        event.payload_json.to_string(),
+                        event.timestamp,
+                    ],
+                )? as u64;
+            }
+            conn.execute(
+                "DELETE FROM session_events
+                 WHERE session_id = ?1 AND event_type = ?2
+                   AND json_extract(payload_json, '$.data.archive_path') IS NOT NULL",
+                params![session_id, HISTORY_SUMMARY_EVENT_TYPE],
+            )?;
+            conn.execute(
+                "DELETE FROM session_search
+                 WHERE rowid IN (SELECT doc_id FROM session_search_docs
+                                 WHERE source = 'message' AND session_id = ?1
+                                   AND source_id IN (SELECT id FROM messages
+                                                     WHERE role = 'assistant'))",
+                params![session_id],
+            )?;
+            conn.execute(
+                "DELETE FROM session_search_docs
+                 WHERE source = 'message' AND session_id = ?1
+                   AND source_id IN (SELECT id FROM messages WHERE role = 'assistant')",
+                params![session_id],
+            )?;
+            Ok(restored)
+        })
+    }
+
+    /// Stored history per session, largest first. `archived_bytes` is left at zero; archives
+    /// live outside the database.
+    pub fn list_session_storage_usage(&self) -> Result<Vec<SessionStorageUsage>, DbError> {
+        let conn = self.reader.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT sessions.id,
+                    sessions.name,
+                    sessions.status,
+                    COALESCE(events.event_count, 0),
+                    COALESCE(events.event_bytes, 0),
+                    COALESCE(messages.message_count, 0),
+                    COALESCE(messages.message_bytes, 0),
+                    COALESCE(events.compacted_runs, 0)
+             FROM sessions
+             LEFT JOIN (
+                SELECT session_id,
+                       COUNT(*) AS event_count,
+                       SUM(length(CAST(payload_json AS BLOB))) AS event_bytes,
+                       SUM(event_type = ?1) AS compacted_runs
+                FROM session_events
+                GROUP BY session_id
+             ) AS events ON events.session_id = sessions.id
+             LEFT JOIN (
+                SELECT session_id,
+                       COUNT(*) AS message_count,
+                       SUM(length(CAST(content AS BLOB))) AS message_bytes
+                FROM messages
+                GROUP BY session_id
+             ) AS messages ON messages.session_id = sessions.id
+             ORDER BY COALESCE(events.event_bytes, 0) + COALESCE(messages.message_bytes, 0) DESC,
+                      sessions.created_at DESC",
+        )?;
+        let rows = stmt.query_map(params![HISTORY_SUMMARY_EVENT_TYPE], |row| {
+            Ok(SessionStorageUsage {
+                session_id: row.get(0)?,
+                name: row.get(1)?,
+                status: row.get(2)?,
+                event_count: row.get(3)?,
+                event_bytes: row.get(4)?,
+                message_count: row.get(5)?,
+                message_bytes: row.get(6)?,
+                compacted_runs: row.get(7)?,
+                archived_bytes: 0,
+            })
+        })?;
+
+        let mut usage = Vec::new();
+        for session in rows {
+            usage.push(session?);
+        }
+
+        Ok(usage)
+    }
+
+    pub fn database_file_usage(&self) -> Result<DatabaseFileUsage, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let pragma = |name: &str| -> rusqlite::Result<i64> {
+            conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))
+        };
+        let page_size = pragma("page_size")?;
+        Ok(DatabaseFileUsage {
+            database_bytes: pragma("page_count")? * page_size,
+            free_bytes: pragma("freelist_count")? * page_size,
+        })
+    }
+
+    /// Gives pages freed by compaction back to the file system. Databases created before
+    /// incremental vacuuming was enabled need one full `VACUUM` to switch them over, which
+    /// rewrites the whole file; without `full_vacuum` they are left as they are.
+    pub fn reclaim_free_space(&self, full_vacuum: bool) -> Result<(), DbError> {
+        self.flush_writes()?;
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        conn.execute_batch("INSERT INTO session_search (session_search) VALUES ('optimize');")?;
+        let auto_vacuum: i64 = conn.query_row("PRAGMA auto_vacuum", [], |row| row.get(0))?;
+        if auto_vacuum == 2 {
+            // Each step frees one page, so the pragma has to be run to completion.
+            let mut stmt = conn.prepare("PRAGMA incremental_vacuum")?;
+            let mut rows = stmt.query([])?;
+            while rows.next()?.is_some() {}
+        } else if full_vacuum {
+            conn.execute_batch("PRAGMA auto_vacuum=INCREMENTAL; VACUUM;")?;
+        }
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+        Ok(())
+    }
+}
+
+/// Assistant messages are indexed through their `message` events. When archiving takes those
+/// events out of the database, their `messages` rows are indexed in their place, like any other
+/// stored message.
+fn index_archived_messages(conn: &Connection, run: &ExpiredRun) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT messages.id, messages.content, messages.timestamp
+         FROM session_events events
+         JOIN messages ON messages.session_id = events.session_id
+                      AND messages.role = 'assistant'
+                      AND messages.timestamp = events.timestamp
+         WHERE events.session_id = ?1 AND events.run_id = ?2 AND events.timestamp <= ?3
+           AND events.event_type = 'message'",
+    )?;
+    let messages = stmt
+        .query_map(params![run.session_id, run.run_id, run.last_at], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (id, content, timestamp) in messages {
+        let added = conn.execute(
+            "INSERT OR IGNORE INTO session_search_docs
+                (source, source_id, session_id, run_id, seq, event_type, timestamp)
+             VALUES ('message', ?1, ?2, NULL, NULL, 'message', ?3)",
+            params![id, run.session_id, timestamp],
+        )?;
+        if added == 1 {
+            conn.execute(
+                "INSERT INTO session_search (rowid, body) VALUES (last_insert_rowid(), ?1)",
+                params![content],
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use crate::db::{Database, DbError};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionUsage {
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: Option<f64>,
+}
+
+impl SessionUsage {
+    pub fn total_tokens(&self) -> i64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+pub fn usage_day(at: chrono::DateTime<chrono::Utc>) -> String {
+    at.format("%Y-%m-%d").to_string()
+}
+
+impl Database {
+    /// Stores the cumulative totals the CLI reported for one run. Totals never move backwards,
+    /// so a late or repeated report cannot undercount a run. Whatever the run added since its
+    /// previous report is also credited to today's (UTC) daily total. Reports arrive on the CLI
+    /// event task, so the write goes through the writer.
+    pub fn record_run_usage(
+        &self,
+        session_id: &str,
+        run_id: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+        cost_usd: Option<f64>,
+    ) -> Result<(), DbError> {
+        let (session_id, run_id) = (session_id.to_string(), run_id.to_string());
+        self.writer.execute(move |conn| {
+            let previous = conn
+                .query_row(
+                    "SELECT input_tokens, output_tokens, cost_usd
+                     FROM session_run_usage
+                     WHERE session_id = ?1 AND run_id = ?2",
+                    params![session_id, run_id],
+                    |row| {
+                        Ok(SessionUsage {
+                            input_tokens: row.get(0)?,
+                            output_tokens: row.get(1)?,
+                            cost_usd: row.get(2)?,
+                        })
+                    },
+                )
+                .optional()?
+                .unwrap_or_default();
+
+            let now = chrono::Utc::now();
+            conn.execute(
+                "INSERT INTO session_run_usage (session_id, run_id, input_tokens, output_tokens, cost_usd, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(session_id, run_id) DO UPDATE SET
+                    input_tokens = MAX(input_tokens, excluded.input_tokens),
+                    output_tokens = MAX(output_tokens, excluded.output_tokens),
+                    cost_usd = CASE
+                        WHEN excluded.cost_usd IS NULL THEN cost_usd
+                        WHEN cost_usd IS NULL THEN excluded.cost_usd
+                        ELSE MAX(cost_usd, excluded.cost_usd)
+                    END,
+                    updated_at = excluded.updated_at",
+                params![
+                    session_id,
+                    run_id,
+                    input_tokens as i64,
+                    output_tokens as i64,
+                    cost_usd,
+                    now.to_rfc3339(),
+                ],
+            )?;
+
+            let input_delta = (input_tokens as i64 - previous.input_tokens).max(0);
+            let output_delta = (output_tokens as i64 - previous.output_tokens).max(0);
+            let cost_delta = match (cost_usd, previous.cost_usd) {
+                (Some(current), Some(previous)) => (current - previous).max(0.0),
+                (Some(current), None) => current.max(0.0),
+                (None, _) => 0.0,
+            };
+
+            if input_delta > 0 || output_delta > 0 || cost_delta > 0.0 {
+                conn.execute(
+                    "INSERT INTO daily_usage (day, input_tokens, output_tokens, cost_usd, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT(day) DO UPDATE SET
+                        input_tokens = input_tokens + excluded.input_tokens,
+                        output_tokens = output_tokens + excluded.output_tokens,
+                        cost_usd = cost_usd + excluded.cost_usd,
+                        updated_at = excluded.updated_at",
+                    params![
+                        usage_day(now),
+                        input_delta,
+                        output_delta,
+                        cost_delta,
+                        now.to_rfc3339(),
+                    ],
+                )?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Usage credited to one UTC day (`YYYY-MM-DD`) across every session.
+    pub fn get_daily_usage(&self, day: &str) -> Result<SessionUsage, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let usage = conn
+            .query_row(
+                "SELECT input_tokens, output_tokens, cost_usd FROM daily_usage WHERE day = ?1",
+                params![day],
+                |row| {
+                    Ok(SessionUsage {
+                        input_tokens: row.get(0)?,
+                        output_tokens: row.get(1)?,
+                        cost_usd: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(usage.unwrap_or_default())
+    }
+
+    pub fn get_session_usage(&self, session_id: &str) -> Result<SessionUsage, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let usage = conn.query_row(
+            "SELECT COALESCE(SUM(input_tokens), 0), COALESCE(SUM(output_tokens), 0), SUM(cost_usd)
+             FROM session_run_usage
+             WHERE session_id = ?1",
+            params![session_id],
+            |row| {
+                Ok(SessionUsage {
+                    input_tokens: row.get(0)?,
+                    output_tokens: row.get(1)?,
+                    cost_usd: row.get(2)?,
+                })
+            },
+        )?;
+
+        Ok(usage)
+    }
+}
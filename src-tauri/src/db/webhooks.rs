@@ -0,0 +1,229 @@
+use crate::db::{Database, DbError};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookTarget {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    #[serde(skip_serializing, default)]
+    pub secret: Option<String>,
+    pub events: Vec<String>,
+    pub cost_threshold_usd: Option<f64>,
+    pub stall_after_secs: Option<i64>,
+    pub max_attempts: i64,
+    pub enabled: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub target_id: String,
+    pub session_id: String,
+    pub event: String,
+    pub attempt: i64,
+    pub status_code: Option<i64>,
+    pub success: bool,
+    pub error: Option<String>,
+    pub payload_json: serde_json::Value,
+    pub created_at: String,
+}
+
+fn json_conversion_error(raw: &str, err: serde_json::Error) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(raw.len(), rusqlite::types::Type::Text, Box::new(err))
+}
+
+fn target_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<WebhookTarget> {
+    let events_raw: String = row.get(4)?;
+    let events = serde_json::from_str(&events_raw)
+        .map_err(|err| json_conversion_error(&events_raw, err))?;
+
+    Ok(WebhookTarget {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        url: row.get(2)?,
+        secret: row.get(3)?,
+        events,
+        cost_threshold_usd: row.get(5)?,
+        stall_after_secs: row.get(6)?,
+        max_attempts: row.get(7)?,
+        enabled: row.get::<_, i64>(8)? != 0,
+        created_at: row.get(9)?,
+    })
+}
+
+impl Database {
+    pub fn create_webhook_target(&self, target: &WebhookTarget) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "INSERT INTO webhook_targets (
+                id, name, url, secret, events, cost_threshold_usd, stall_after_secs, max_attempts,
+                enabled, created_at
+             )
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                target.id,
+                target.name,
+                target.url,
+                target.secret,
+                serde_json::Value::from(target.events.clone()).to_string(),
+                target.cost_threshold_usd,
+                target.stall_after_secs,
+                target.max_attempts,
+                target.enabled as i64,
+                target.created_at,
+            ],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn list_webhook_targets(&self) -> Result<Vec<WebhookTarget>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, name, url, secret, events, cost_threshold_usd, stall_after_secs,
+                    max_attempts, enabled, created_at
+             FROM webhook_targets
+             ORDER BY created_at ASC, id ASC",
+        )?;
+        let rows = stmt.query_map([], target_from_row)?;
+
+        let mut targets = Vec::new();
+        for target in rows {
+            targets.push(target?);
+        }
+
+        Ok(targets)
+    }
+
+    pub fn list_webhook_targets_for_event(&self, event: &str) -> Result<Vec<WebhookTarget>, DbError> {
+        Ok(self
+            .list_webhook_targets()?
+            .into_iter()
+            .filter(|target| target.enabled && target.events.iter().any(|e| e == event))
+            .collect())
+    }
+
+    pub fn set_webhook_target_enabled(&self, id: &str, enabled: bool) -> Result<bool, DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        let updated = tx.execute(
+            "UPDATE webhook_targets SET enabled = ?1 WHERE id = ?2",
+            params![enabled as i64, id],
+        )?;
+
+        tx.commit()?;
+        Ok(updated > 0)
+    }
+
+    pub fn delete_webhook_target(&self, id: &str) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute("DELETE FROM webhook_deliveries WHERE target_id = ?1", params![id])?;
+        tx.execute("DELETE FROM webhook_targets WHERE id = ?1", params![id])?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn insert_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        tx.execute(
+            "INSERT INTO webhook_deliveries (
+                id, target_id, session_id, event, attempt, status_code, success, error,
+                payload_json, created_at
+             )
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                delivery.id,
+                delivery.target_id,
+                delivery.session_id,
+                delivery.event,
+                delivery.attempt,
+                delivery.status_code,
+                delivery.success as i64,
+                delivery.error,
+                delivery.payload_json.to_string(),
+                delivery.created_at,
+            ],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn list_webhook_deliveries(
+        &self,
+        target_id: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, target_id, session_id, event, attempt, status_code, success, error,
+                    payload_json, created_at
+             FROM webhook_deliveries
+             WHERE ?1 IS NULL OR target_id = ?1
+             ORDER BY created_at DESC, attempt DESC
+             LIMIT ?2",
+        )?;
+
+        let rows = stmt.query_map(params![target_id, limit], |row| {
+            let payload_raw: String = row.get(8)?;
+            let payload_json = serde_json::from_str(&payload_raw)
+                .map_err(|err| json_conversion_error(&payload_raw, err))?;
+
+            Ok(WebhookDelivery {
+                id: row.get(0)?,
+                target_id: row.get(1)?,
+                session_id: row.get(2)?,
+                event: row.get(3)?,
+                attempt: row.get(4)?,
+                status_code: row.get(5)?,
+                success: row.get::<_, i64>(6)? != 0,
+                error: row.get(7)?,
+                payload_json,
+                created_at: row.get(9)?,
+            })
+        })?;
+
+        let mut deliveries = Vec::new();
+        for delivery in rows {
+            deliveries.push(delivery?);
+        }
+
+        Ok(deliveries)
+    }
+
+    /// Whether `event` was already attempted for this target and session, optionally only
+    /// counting attempts logged at or after `since`.
+    pub fn has_webhook_delivery(
+        &self,
+        target_id: &str,
+        session_id: &str,
+        event: &str,
+        since: Option<&str>,
+    ) -> Result<bool, DbError> {
+        let conn = self.conn.lock().map_err(|_| DbError::Lock)?;
+
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM webhook_deliveries
+             WHERE target_id = ?1 AND session_id = ?2 AND event = ?3
+               AND (?4 IS NULL OR created_at >= ?4)",
+            params![target_id, session_id, event, since],
+            |row| row.get(0),
+        )?;
+
+        Ok(count > 0)
+    }
+}
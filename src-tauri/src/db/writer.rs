@@ -0,0 +1,220 @@
+//! The thread that owns the connection writing session events and messages. Callers hand it
+//! jobs over a channel instead of taking a lock, so streaming sessions never block an async
+//! worker on SQLite. Jobs that arrive within `GROUP_COMMIT_WINDOW` of each other share one
+//! transaction; each runs in its own savepoint, so a failing job does not take its batch down.
+
+use rusqlite::{Connection, TransactionBehavior};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tokio::sync::broadcast;
+
+use crate::db::DbError;
+
+/// How long the writer keeps collecting queued jobs before committing them together.
+pub const GROUP_COMMIT_WINDOW: Duration = Duration::from_millis(5);
+/// Upper bound on the jobs sharing one transaction.
+pub const MAX_BATCH_SIZE: usize = 512;
+
+type Work = Box<dyn FnOnce(&Connection) -> rusqlite::Result<()> + Send>;
+
+/// A fire-and-forget write that did not make it to the database.
+#[derive(Debug, Clone)]
+pub struct WriteFailure {
+    pub session_id: String,
+    pub message: String,
+}
+
+/// Who learns how a job went once its batch has committed.
+enum Outcome {
+    /// A caller blocked in `execute`.
+    Waiter(Sender<Result<(), DbError>>),
+    /// Nobody is waiting; failures of the session's queued write are broadcast instead.
+    Queued(String),
+}
+
+struct Job {
+    work: Work,
+    outcome: Outcome,
+}
+
+pub struct DbWriter {
+    jobs: Option<Sender<Job>>,
+    failures: broadcast::Sender<WriteFailure>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DbWriter {
+    pub fn spawn(conn: Connection) -> Result<Self, DbError> {
+        let (jobs, queue) = mpsc::channel();
+        let (failures, _) = broadcast::channel(64);
+        let thread_failures = failures.clone();
+        let thread = std::thread::Builder::new()
+            .name("lulu-db-writer".to_string())
+            .spawn(move || run_writer(conn, queue, thread_failures))
+            .map_err(|e| DbError::Writer(format!("failed to start the writer thread: {}", e)))?;
+
+        Ok(Self { jobs: Some(jobs), failures, thread: Some(thread) })
+    }
+
+    /// Failures of queued writes from now on.
+    pub fn subscribe_failures(&self) -> broadcast::Receiver<WriteFailure> {
+        self.failures.subscribe()
+    }
+
+    fn send(&self, job: Job) -> Result<(), DbError> {
+        self.jobs
+            .as_ref()
+            .and_then(|jobs| jobs.send(job).ok())
+            .ok_or_else(|| DbError::Writer("the writer thread has stopped".to_string()))
+    }
+
+    /// Queues `work` for `session_id` and returns immediately; it is committed with the next
+    /// batch. Nobody sees its outcome but `subscribe_failures`, so only use this for writes the
+    /// app can live without.
+    pub fn queue<F>(&self, session_id: &str, work: F) -> Result<(), DbError>
+    where
+        F: FnOnce(&Connection) -> rusqlite::Result<()> + Send + 'static,
+    {
+        self.send(Job { work: Box::new(work), outcome: Outcome::Queued(session_id.to_string()) })
+    }
+
+    /// Runs `work` after everything queued before it and waits until it is committed. Called
+    /// from a tokio worker, the wait hands that worker's other tasks over first.
+    pub fn execute<T, F>(&self, work: F) -> Result<T, DbError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let (value_tx, value_rx) = mpsc::channel();
+        let (done_tx, done_rx) = mpsc::channel();
+        self.send(Job {
+            work: Box::new(move |conn| {
+                let _ = value_tx.send(work(conn)?);
+                Ok(())
+            }),
+            outcome: Outcome::Waiter(done_tx),
+        })?;
+
+        let stopped = || DbError::Writer("the writer thread has stopped".to_string());
+        wait_off_runtime(|| done_rx.recv()).map_err(|_| stopped())??;
+        value_rx.recv().map_err(|_| stopped())
+    }
+
+    /// Waits until every job queued so far is committed.
+    pub fn flush(&self) -> Result<(), DbError> {
+        self.execute(|_| Ok(()))
+    }
+}
+
+impl Drop for DbWriter {
+    /// Closes the queue and lets the thread commit what is left in it.
+    fn drop(&mut self) {
+        self.jobs.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Runs a blocking wait. On a multi-threaded runtime's worker `block_in_place` moves the
+/// worker's queued tasks elsewhere first; the current-thread runtime has nowhere to move them.
+fn wait_off_runtime<T>(wait: impl FnOnce() -> T) -> T {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(wait)
+        }
+        _ => wait(),
+    }
+}
+
+fn run_writer(
+    mut conn: Connection,
+    queue: Receiver<Job>,
+    failures: broadcast::Sender<WriteFailure>,
+) {
+    while let Ok(first) = queue.recv() {
+        let deadline = Instant::now() + GROUP_COMMIT_WINDOW;
+        let mut waited_on = matches!(first.outcome, Outcome::Waiter(_));
+        let mut batch = vec![first];
+
+        // Someone blocking on the batch ends the window early; jobs already queued still join.
+        while batch.len() < MAX_BATCH_SIZE {
+            let next = if waited_on {
+                queue.try_recv().ok()
+            } else {
+                queue.recv_timeout(deadline.saturating_duration_since(Instant::now())).ok()
+            };
+            let Some(job) = next else {
+                break;
+            };
+            waited_on |= matches!(job.outcome, Outcome::Waiter(_));
+            batch.push(job);
+        }
+
+        commit_batch(&mut conn, batch, &failures);
+    }
+}
+
+fn commit_batch(
+    conn: &mut Connection,
+    batch: Vec<Job>,
+    failures: &broadcast::Sender<WriteFailure>,
+) {
+    let mut tx = match conn.transaction_with_behavior(TransactionBehavior::Immediate) {
+        Ok(tx) => tx,
+        Err(e) => return fail_batch(batch.into_iter().map(|job| job.outcome), &e, failures),
+    };
+
+    let mut outcomes = Vec::with_capacity(batch.len());
+    for job in batch {
+        let outcome = tx.savepoint().and_then(|savepoint| {
+            (job.work)(&savepoint)?;
+            savepoint.commit()
+        });
+        outcomes.push((job.outcome, outcome));
+    }
+
+    if let Err(e) = tx.commit() {
+        return fail_batch(outcomes.into_iter().map(|(to, _)| to), &e, failures);
+    }
+    for (to, outcome) in outcomes {
+        report(to, outcome.map_err(DbError::from), failures);
+    }
+}
+
+fn fail_batch(
+    outcomes: impl Iterator<Item = Outcome>,
+    error: &rusqlite::Error,
+    failures: &broadcast::Sender<WriteFailure>,
+) {
+    for to in outcomes {
+        let message = format!("batch commit failed: {}", error);
+        report(to, Err(DbError::Writer(message)), failures);
+    }
+}
+
+fn report(to: Outcome, outcome: Result<(), DbError>, failures: &broadcast::Sender<WriteFailure>) {
+    match (to, outcome) {
+        (Outcome::Waiter(done), outcome) => {
+            let _ = done.send(outcome);
+        }
+        (Outcome::Queued(session_id), Err(e)) => {
+            let _ = failures.send(WriteFailure { session_id, message: e.to_string() });
+        }
+        (Outcome::Queued(_), Ok(())) => {}
+    }
+}
+
+/// Opens a further connection to the database at `db_path` with the pragmas every Lulu
+/// connection runs with.
+pub fn open_connection(db_path: &Path, flags: rusqlite::OpenFlags) -> Result<Connection, DbError> {
+    let conn = Connection::open_with_flags(db_path, flags)?;
+    conn.execute_batch(
+        "PRAGMA busy_timeout=5000;
+         PRAGMA foreign_keys=ON;",
+    )?;
+    Ok(conn)
+}
@@ -2,12 +2,19 @@ use std::sync::Arc;
 use tauri::Manager;
 use tokio::sync::Mutex;
 
-use crate::commands::session::reconcile_sessions_on_startup;
+use crate::commands::session::{
+    reconcile_sessions_on_startup, start_terminal_transition_listener, start_webhook_stall_watcher,
+    start_write_failure_listener,
+};
+use crate::commands::storage::start_history_retention;
+use crate::commands::worktree::start_worktree_retention;
 
 pub mod commands;
 pub mod db;
 pub mod session;
 
+use session::webhooks::WebhookDispatcher;
+use session::worktree::AppDataDir;
 use session::SessionManager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -18,10 +25,18 @@ pub fn run() {
             std::fs::create_dir_all(&app_data_dir)?;
             let db_path = app_data_dir.join("lulu.db");
             let database = db::init_database(&db_path)?;
-            reconcile_sessions_on_startup(&database)
+            reconcile_sessions_on_startup(&database, &app_data_dir)
                 .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
             app.manage(database);
-            app.manage(Arc::new(Mutex::new(SessionManager::new())));
+            start_write_failure_listener(app.handle().clone());
+            app.manage(AppDataDir(app_data_dir));
+            app.manage(WebhookDispatcher::new());
+            start_webhook_stall_watcher(app.handle().clone());
+            let manager = SessionManager::new();
+            start_terminal_transition_listener(app.handle().clone(), manager.supervisor.clone());
+            app.manage(Arc::new(Mutex::new(manager)));
+            start_history_retention(app.handle().clone());
+            start_worktree_retention(app.handle().clone());
             Ok(())
         })
         .plugin(tauri_plugin_opener::init())
@@ -32,11 +47,51 @@ pub fn run() {
             commands::get_session,
             commands::rename_session,
             commands::list_session_messages,
+            commands::list_session_messages_page,
+            commands::count_session_messages,
             commands::list_session_history,
+            commands::list_session_history_page,
+            commands::count_session_history,
+            commands::search_sessions,
+            commands::list_session_runs,
             commands::interrupt_session,
+            commands::list_lifecycle_operations,
             commands::resume_session,
+            commands::rewind_session,
             commands::kill_session,
             commands::delete_session,
+            commands::get_session_diff,
+            commands::apply_sandbox_changes,
+            commands::merge_session_changes,
+            commands::sync_session_worktree,
+            commands::list_checkpoints,
+            commands::list_session_integrations,
+            commands::export_session_changes,
+            commands::run_worktree_retention,
+            commands::get_history_retention_policy,
+            commands::save_history_retention_policy,
+            commands::run_history_retention,
+            commands::rehydrate_session_history,
+            commands::get_storage_usage,
+            commands::reconcile_worktrees,
+            commands::list_quarantined_worktrees,
+            commands::purge_quarantined_worktrees,
+            commands::list_repo_conflicts,
+            commands::create_lifecycle_hook,
+            commands::list_lifecycle_hooks,
+            commands::set_lifecycle_hook_enabled,
+            commands::delete_lifecycle_hook,
+            commands::create_webhook_target,
+            commands::list_webhook_targets,
+            commands::set_webhook_target_enabled,
+            commands::delete_webhook_target,
+            commands::list_webhook_deliveries,
+            commands::get_repository_settings,
+            commands::save_repository_settings,
+            commands::set_global_budget,
+            commands::set_session_budget,
+            commands::list_budgets,
+            commands::delete_budget,
         ])
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::CloseRequested { .. } = event {
@@ -48,6 +103,9 @@ pub fn run() {
                         manager.kill_all().await;
                     });
                 }
+                if let Some(db) = app.try_state::<db::Database>() {
+                    let _ = db.flush_writes();
+                }
             }
         })
         .run(tauri::generate_context!())
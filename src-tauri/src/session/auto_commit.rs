@@ -0,0 +1,146 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::db::Database;
+use crate::session::hooks::HookContext;
+
+pub const DEFAULT_AUTO_COMMIT_TRAILER: &str = "Lulu-Session: {session-id}";
+const SUBJECT_MAX_CHARS: usize = 72;
+const BODY_MAX_CHARS: usize = 4000;
+
+fn truncate_chars(value: &str, max_chars: usize) -> String {
+    if value.chars().count() <= max_chars {
+        return value.to_string();
+    }
+
+    let mut truncated: String = value.chars().take(max_chars.saturating_sub(3)).collect();
+    truncated.push_str("...");
+    truncated
+}
+
+/// Subject from the session name, body from the agent's final message, then the trailer.
+pub fn build_auto_commit_message(
+    session_name: &str,
+    final_message: Option<&str>,
+    trailer: &str,
+) -> String {
+    let subject = session_name.lines().next().unwrap_or_default().trim();
+    let subject = if subject.is_empty() { "Lulu session" } else { subject };
+    let mut message = truncate_chars(subject, SUBJECT_MAX_CHARS);
+
+    if let Some(body) = final_message.map(str::trim).filter(|body| !body.is_empty()) {
+        message.push_str("\n\n");
+        message.push_str(&truncate_chars(body, BODY_MAX_CHARS));
+    }
+
+    let trailer = trailer.trim();
+    if !trailer.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(trailer);
+    }
+
+    message.push('\n');
+    message
+}
+
+fn git(worktree_path: &Path, args: &[&str]) -> Result<std::process::Output, String> {
+    Command::new("git")
+        .args(args)
+        .current_dir(worktree_path)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.first().unwrap_or(&""), e))
+}
+
+fn commit_all(worktree_path: &Path, message: &str) -> Result<Option<String>, String> {
+    let add = git(worktree_path, &["add", "-A"])?;
+    if !add.status.success() {
+        return Err(format!("git add failed: {}", String::from_utf8_lossy(&add.stderr).trim()));
+    }
+
+    // Exit code 0 means nothing is staged.
+    if git(worktree_path, &["diff", "--cached", "--quiet"])?.status.success() {
+        return Ok(None);
+    }
+
+    // Commit hooks are skipped: a rejected commit here would leave the work uncommitted again.
+    let mut child = Command::new("git")
+        .args(["commit", "--no-verify", "-F", "-"])
+        .current_dir(worktree_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run git commit: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(message.as_bytes())
+            .map_err(|e| format!("Failed to write commit message: {}", e))?;
+    }
+    let output =
+        child.wait_with_output().map_err(|e| format!("Failed to run git commit: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git commit failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let head = git(worktree_path, &["rev-parse", "HEAD"])?;
+    Ok(Some(String::from_utf8_lossy(&head.stdout).trim().to_string()))
+}
+
+/// Commits everything left in a completed session's worktree when the repository opted in,
+/// and records the commit on the active run. Returns `None` when auto-commit is off, the
+/// session has no worktree, or there was nothing to commit.
+pub fn auto_commit_session(db: &Database, session_id: &str) -> Result<Option<String>, String> {
+    let Some(context) = HookContext::load(db, session_id)? else {
+        return Ok(None);
+    };
+    let (Some(repo_root), Some(worktree_path)) =
+        (context.repo_root.as_deref(), context.worktree_path.as_deref())
+    else {
+        return Ok(None);
+    };
+
+    let settings = db
+        .get_repository_settings(repo_root)
+        .map_err(|e| format!("Failed to load repository settings: {}", e))?
+        .unwrap_or_default();
+    if !settings.auto_commit {
+        return Ok(None);
+    }
+
+    let Some(run_id) = db
+        .get_session_run_metadata(session_id)
+        .map_err(|e| format!("Failed to load run metadata for auto-commit: {}", e))?
+        .and_then(|metadata| metadata.active_run_id)
+    else {
+        return Ok(None);
+    };
+
+    // The run's last messages can still be on the writer's queue when the session finishes.
+    db.flush_writes().map_err(|e| format!("Failed to flush session events: {}", e))?;
+    let final_message = db
+        .list_session_messages(session_id)
+        .map_err(|e| format!("Failed to load session messages: {}", e))?
+        .into_iter()
+        .rev()
+        .find(|message| message.role == "assistant")
+        .map(|message| message.content);
+    let trailer = settings
+        .auto_commit_trailer
+        .as_deref()
+        .unwrap_or(DEFAULT_AUTO_COMMIT_TRAILER)
+        .replace("{session-id}", session_id);
+    let message =
+        build_auto_commit_message(&context.session_name, final_message.as_deref(), &trailer);
+
+    let Some(commit_sha) = commit_all(Path::new(worktree_path), &message)? else {
+        return Ok(None);
+    };
+    db.update_run_commit(session_id, &run_id, &commit_sha)
+        .map_err(|e| format!("Failed to record auto-commit: {}", e))?;
+
+    Ok(Some(commit_sha))
+}
@@ -0,0 +1,209 @@
+use crate::db::usage::usage_day;
+use crate::db::{Budget, Database, SessionUsage};
+use crate::session::hooks::record_lifecycle_event;
+use crate::session::{SessionEvent, SessionEventPayload, SessionSupervisor};
+
+pub const BUDGET_EXCEEDED_REASON: &str = "budget_exceeded";
+pub const BUDGET_SCOPE_GLOBAL: &str = "global";
+pub const BUDGET_SCOPE_SESSION: &str = "session";
+pub const BUDGET_UNITS: [&str; 2] = ["usd", "tokens"];
+pub const GLOBAL_BUDGET_ID: &str = "global";
+
+pub fn session_budget_id(session_id: &str) -> String {
+    format!("session:{}", session_id)
+}
+
+pub fn is_budget_unit(unit: &str) -> bool {
+    BUDGET_UNITS.contains(&unit)
+}
+
+fn spent_in_unit(usage: &SessionUsage, unit: &str) -> f64 {
+    match unit {
+        "tokens" => usage.total_tokens() as f64,
+        _ => usage.cost_usd.unwrap_or(0.0),
+    }
+}
+
+/// Where one budget stands for a session right now.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetReading {
+    pub budget: Budget,
+    pub period: String,
+    pub spent: f64,
+}
+
+impl BudgetReading {
+    pub fn is_exceeded(&self) -> bool {
+        self.spent >= self.budget.hard_limit
+    }
+
+    pub fn is_past_soft_limit(&self) -> bool {
+        self.budget.soft_limit.is_some_and(|soft_limit| self.spent >= soft_limit)
+    }
+}
+
+/// New soft-limit warnings to report, plus the first hard limit the session is over, if any.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BudgetCheck {
+    pub warnings: Vec<BudgetReading>,
+    pub exceeded: Option<BudgetReading>,
+}
+
+/// Reads every budget that applies to the session: the global daily budget against today's
+/// usage, and the session budget against the session's lifetime usage.
+pub fn read_budgets(
+    db: &Database,
+    session_id: &str,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<BudgetReading>, String> {
+    let budgets = db
+        .list_budgets_for_session(session_id)
+        .map_err(|e| format!("Failed to load budgets: {}", e))?;
+
+    let mut readings = Vec::new();
+    for budget in budgets {
+        let (period, usage) = if budget.scope == BUDGET_SCOPE_GLOBAL {
+            let day = usage_day(now);
+            let usage = db
+                .get_daily_usage(&day)
+                .map_err(|e| format!("Failed to load daily usage: {}", e))?;
+            (day, usage)
+        } else {
+            let usage = db
+                .get_session_usage(session_id)
+                .map_err(|e| format!("Failed to load session usage: {}", e))?;
+            (session_id.to_string(), usage)
+        };
+
+        let spent = spent_in_unit(&usage, &budget.unit);
+        readings.push(BudgetReading { budget, period, spent });
+    }
+
+    Ok(readings)
+}
+
+/// Evaluates the session's budgets after new usage was recorded. Each soft-limit warning is
+/// reported once per budget period; the alert record lives in SQLite so restarts don't repeat it.
+pub fn check_budgets(
+    db: &Database,
+    session_id: &str,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<BudgetCheck, String> {
+    let mut check = BudgetCheck::default();
+
+    for reading in read_budgets(db, session_id, now)? {
+        if reading.is_exceeded() {
+            db.record_budget_alert(&reading.budget.id, &reading.period, "hard")
+                .map_err(|e| format!("Failed to record budget alert: {}", e))?;
+            if check.exceeded.is_none() {
+                check.exceeded = Some(reading);
+            }
+            continue;
+        }
+
+        if reading.is_past_soft_limit()
+            && db
+                .record_budget_alert(&reading.budget.id, &reading.period, "soft")
+                .map_err(|e| format!("Failed to record budget alert: {}", e))?
+        {
+            check.warnings.push(reading);
+        }
+    }
+
+    Ok(check)
+}
+
+/// What enforcing the budgets after new usage did.
+#[derive(Debug, Clone, Default)]
+pub struct BudgetEnforcement {
+    /// Recorded `budget_warning` and `budget_exceeded` events, to be emitted.
+    pub events: Vec<SessionEvent>,
+    /// Events that could not be recorded.
+    pub errors: Vec<String>,
+    /// Set when the run went over a hard limit and has to be stopped with
+    /// `SessionSupervisor::stop_session_for_budget`.
+    pub stop: bool,
+}
+
+/// Checks the session's budgets after new usage arrives: records soft-limit warnings and, the
+/// first time the running session is over a hard limit, marks its runtime and asks for a stop.
+pub async fn enforce_budgets(
+    db: &Database,
+    supervisor: &SessionSupervisor,
+    session_id: &str,
+) -> Result<BudgetEnforcement, String> {
+    let check = check_budgets(db, session_id, chrono::Utc::now())?;
+    let mut enforcement = BudgetEnforcement::default();
+    let mut record = |payload: SessionEventPayload| match record_lifecycle_event(
+        db, session_id, payload,
+    ) {
+        Ok(event) => enforcement.events.push(event),
+        Err(message) => enforcement.errors.push(message),
+    };
+
+    for warning in check.warnings {
+        record(SessionEventPayload::BudgetWarning {
+            budget_id: warning.budget.id.clone(),
+            scope: warning.budget.scope.clone(),
+            unit: warning.budget.unit.clone(),
+            spent: warning.spent,
+            soft_limit: warning.budget.soft_limit.unwrap_or(warning.budget.hard_limit),
+            hard_limit: warning.budget.hard_limit,
+        });
+    }
+
+    let Some(exceeded) = check.exceeded else {
+        return Ok(enforcement);
+    };
+    let Some(runtime) = supervisor.get(session_id).await else {
+        return Ok(enforcement);
+    };
+    if !runtime.mark_budget_exceeded() {
+        return Ok(enforcement);
+    }
+
+    record(SessionEventPayload::BudgetExceeded {
+        budget_id: exceeded.budget.id.clone(),
+        scope: exceeded.budget.scope.clone(),
+        unit: exceeded.budget.unit.clone(),
+        spent: exceeded.spent,
+        hard_limit: exceeded.budget.hard_limit,
+    });
+    enforcement.stop = true;
+    Ok(enforcement)
+}
+
+/// Explains why new work cannot start because a budget is already spent. Without a session
+/// only the global budget is considered.
+pub fn budget_block_reason(
+    db: &Database,
+    session_id: Option<&str>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<Option<String>, String> {
+    let readings = match session_id {
+        Some(session_id) => read_budgets(db, session_id, now)?,
+        None => read_budgets(db, "", now)?
+            .into_iter()
+            .filter(|reading| reading.budget.scope == BUDGET_SCOPE_GLOBAL)
+            .collect(),
+    };
+
+    Ok(readings.into_iter().find(BudgetReading::is_exceeded).map(|reading| {
+        let scope =
+            if reading.budget.scope == BUDGET_SCOPE_GLOBAL { "Global daily" } else { "Session" };
+        format!(
+            "{} budget exceeded: {} of {} used",
+            scope,
+            format_amount(reading.spent, &reading.budget.unit),
+            format_amount(reading.budget.hard_limit, &reading.budget.unit)
+        )
+    }))
+}
+
+fn format_amount(value: f64, unit: &str) -> String {
+    if unit == "tokens" {
+        format!("{} tokens", value as i64)
+    } else {
+        format!("${:.2}", value)
+    }
+}
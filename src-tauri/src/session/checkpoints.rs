@@ -0,0 +1,174 @@
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+
+use crate::db::{Database, SessionCheckpoint};
+use crate::session::events::{SessionEvent, SessionEventPayload};
+use crate::session::hooks::record_lifecycle_event;
+use crate::session::worktree::snapshot_worktree;
+
+/// Hidden ref namespace keeping checkpoint commits alive; branch and tag listings never show it.
+pub const CHECKPOINT_REF_PREFIX: &str = "refs/lulu/checkpoints";
+
+/// Tools whose results never change files, so they need no checkpoint.
+pub const READ_ONLY_TOOLS: [&str; 8] =
+    ["Read", "Grep", "Glob", "LS", "NotebookRead", "WebFetch", "WebSearch", "TodoWrite"];
+
+fn git_ok(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args[0], e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Checkpoints the session worktree after the event at `run_id`/`seq`: its full state, untracked
+/// files included, is committed on the side and kept under `CHECKPOINT_REF_PREFIX`. Nothing is
+/// recorded when the session has no worktree or the worktree is unchanged since the last
+/// checkpoint.
+pub fn create_checkpoint(
+    db: &Database,
+    session_id: &str,
+    run_id: &str,
+    seq: u64,
+) -> Result<Option<SessionCheckpoint>, String> {
+    let Some(worktree_path) = db
+        .get_session_worktree_path(session_id)
+        .map_err(|e| format!("Failed to get session worktree path: {}", e))?
+    else {
+        return Ok(None);
+    };
+    let worktree = Path::new(&worktree_path);
+    if !worktree.exists() {
+        return Ok(None);
+    }
+
+    let head_sha = git_ok(worktree, &["rev-parse", "HEAD"])?;
+    let message = format!("Checkpoint of session {} after event {}", session_id, seq);
+    let commit_sha = snapshot_worktree(worktree, &message)?.unwrap_or_else(|| head_sha.clone());
+    let tree_sha = git_ok(worktree, &["rev-parse", &format!("{}^{{tree}}", commit_sha)])?;
+
+    let previous = db
+        .latest_session_checkpoint(session_id)
+        .map_err(|e| format!("Failed to load the previous checkpoint: {}", e))?;
+    if previous.as_ref().is_some_and(|previous| previous.tree_sha == tree_sha) {
+        return Ok(None);
+    }
+    let since = previous.map(|previous| previous.commit_sha).unwrap_or_else(|| head_sha.clone());
+    let changed = git_ok(worktree, &["diff", "--name-only", &since, &commit_sha])?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let ref_name = format!("{}/{}/{}", CHECKPOINT_REF_PREFIX, session_id, id);
+    git_ok(worktree, &["update-ref", &ref_name, &commit_sha])?;
+
+    let checkpoint = SessionCheckpoint {
+        id,
+        session_id: session_id.to_string(),
+        run_id: run_id.to_string(),
+        seq,
+        head_sha,
+        commit_sha,
+        tree_sha,
+        ref_name,
+        changed_paths: changed.lines().map(str::to_string).collect(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    db.insert_session_checkpoint(&checkpoint)
+        .map_err(|e| format!("Failed to record checkpoint: {}", e))?;
+    Ok(Some(checkpoint))
+}
+
+/// Takes a run's checkpoints in the background, one at a time, so snapshotting the worktree
+/// never holds up the event stream. Send the seq of each event to checkpoint after; seqs that
+/// queue up while a checkpoint is being taken are coalesced into one after the newest of them.
+/// The worker stops once the sender is dropped and the queue is drained.
+pub fn spawn_checkpoint_worker<F>(checkpoint: F) -> mpsc::UnboundedSender<u64>
+where
+    F: Fn(u64) + Send + Sync + 'static,
+{
+    let (tx, mut rx) = mpsc::unbounded_channel::<u64>();
+    let checkpoint = Arc::new(checkpoint);
+    tokio::spawn(async move {
+        while let Some(mut seq) = rx.recv().await {
+            while let Ok(newer) = rx.try_recv() {
+                seq = seq.max(newer);
+            }
+            let checkpoint = checkpoint.clone();
+            let _ = tokio::task::spawn_blocking(move || checkpoint(seq)).await;
+        }
+    });
+    tx
+}
+
+/// Puts the worktree back into the checkpointed state: the branch returns to the commit it was
+/// on, and files, including untracked ones, match the checkpoint. Ignored files are left alone.
+fn restore_checkpoint(worktree: &Path, checkpoint: &SessionCheckpoint) -> Result<(), String> {
+    git_ok(worktree, &["reset", "--hard", "--quiet", &checkpoint.head_sha])?;
+    git_ok(worktree, &["clean", "-fd", "--quiet"])?;
+    if checkpoint.commit_sha != checkpoint.head_sha {
+        // Write the snapshot's files, then drop them from the index again so uncommitted work
+        // stays uncommitted.
+        git_ok(worktree, &["read-tree", "-u", "--reset", &checkpoint.commit_sha])?;
+        git_ok(worktree, &["reset", "--quiet"])?;
+    }
+    Ok(())
+}
+
+/// Rewinds a session's worktree to one of its checkpoints. The current state is checkpointed
+/// first, so the rewind itself can be undone; commits made after the checkpoint stay reachable
+/// through their own checkpoints. Returns the checkpoint and the recorded `rewind` event.
+pub fn rewind_to_checkpoint(
+    db: &Database,
+    session_id: &str,
+    checkpoint_id: &str,
+) -> Result<(SessionCheckpoint, Vec<SessionEvent>), String> {
+    let checkpoint = db
+        .get_session_checkpoint(session_id, checkpoint_id)
+        .map_err(|e| format!("Failed to load checkpoint: {}", e))?
+        .ok_or_else(|| {
+            format!("Checkpoint {} not found for session {}", checkpoint_id, session_id)
+        })?;
+    let worktree_path = db
+        .get_session_worktree_path(session_id)
+        .map_err(|e| format!("Failed to get session worktree path: {}", e))?
+        .ok_or_else(|| format!("Session {} has no worktree", session_id))?;
+
+    db.flush_writes().map_err(|e| format!("Failed to flush queued session events: {}", e))?;
+    if let Some((run_id, seq)) = db
+        .latest_session_event_position(session_id)
+        .map_err(|e| format!("Failed to load the latest session event: {}", e))?
+    {
+        create_checkpoint(db, session_id, &run_id, seq)?;
+    }
+    restore_checkpoint(Path::new(&worktree_path), &checkpoint)?;
+
+    let payload = SessionEventPayload::Rewind {
+        checkpoint_id: checkpoint.id.clone(),
+        run_id: checkpoint.run_id.clone(),
+        seq: checkpoint.seq,
+        commit_sha: checkpoint.commit_sha.clone(),
+    };
+    let event = record_lifecycle_event(db, session_id, payload)?;
+    Ok((checkpoint, vec![event]))
+}
+
+/// Deletes the checkpoint refs of a session so git can collect their commits.
+pub fn remove_checkpoint_refs(repo_root: &Path, session_id: &str) -> Result<(), String> {
+    let prefix = format!("{}/{}/", CHECKPOINT_REF_PREFIX, session_id);
+    let refs = git_ok(repo_root, &["for-each-ref", "--format=%(refname)", &prefix])?;
+    for ref_name in refs.lines().filter(|line| !line.is_empty()) {
+        git_ok(repo_root, &["update-ref", "-d", ref_name])?;
+    }
+    Ok(())
+}
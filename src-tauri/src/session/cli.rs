@@ -213,6 +213,7 @@ impl ClaudeCli {
         tokio::spawn(async move {
             let reader = BufReader::new(stdout);
             let mut lines = reader.lines();
+            let mut usage_tracker = RunUsageTracker::default();
             while let Ok(Some(line)) = lines.next_line().await {
                 let event =
                     parse_output_line(&out_session, out_seq.fetch_add(1, Ordering::SeqCst), &line);
@@ -223,6 +224,16 @@ impl ClaudeCli {
                     &out_overflow_reported,
                     event,
                 );
+
+                if let Some(usage) = usage_tracker.observe(&line) {
+                    try_send_with_overflow(
+                        &tx_out,
+                        &out_session,
+                        &out_seq,
+                        &out_overflow_reported,
+                        usage,
+                    );
+                }
             }
         });
 
@@ -401,6 +412,131 @@ pub fn parse_output_line(session_id: &str, seq: u64, line: &str) -> SessionEvent
     build_event(session_id, seq, SessionEventPayload::Message { content: line.to_string() })
 }
 
+/// Usage of one API call, as reported on the assistant lines of its message.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct TurnUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+    cost_usd: Option<f64>,
+}
+
+impl TurnUsage {
+    fn add(self, other: TurnUsage) -> TurnUsage {
+        TurnUsage {
+            input_tokens: self.input_tokens + other.input_tokens,
+            output_tokens: self.output_tokens + other.output_tokens,
+            cost_usd: self.cost_usd.zip(other.cost_usd).map(|(a, b)| a + b),
+        }
+    }
+}
+
+/// Running usage totals of one CLI run, so budgets see the spend while the run is still going.
+/// Assistant lines carry the usage of their own API call, repeated on every content block of
+/// the message, so turns are told apart by message id. Cost is estimated from list prices until
+/// the `result` line reports the run's actual totals.
+#[derive(Debug, Default)]
+pub struct RunUsageTracker {
+    finished: Option<TurnUsage>,
+    current: Option<(Option<String>, TurnUsage)>,
+    reported: Option<TurnUsage>,
+}
+
+impl RunUsageTracker {
+    /// Returns a `Usage` event with the run's cumulative totals when the line changes them.
+    pub fn observe(&mut self, line: &str) -> Option<SessionEventPayload> {
+        let value = serde_json::from_str::<Value>(line).ok()?;
+        match value.get("type").and_then(Value::as_str) {
+            Some("result") => result_usage(&value),
+            Some("assistant") => {
+                let message = value.get("message")?;
+                let usage = message.get("usage")?;
+                let (input_tokens, output_tokens) = token_counts(Some(usage));
+                let model = message.get("model").and_then(Value::as_str).unwrap_or_default();
+                let turn = TurnUsage {
+                    input_tokens,
+                    output_tokens,
+                    cost_usd: estimate_cost_usd(model, usage),
+                };
+                let id = message.get("id").and_then(Value::as_str).map(str::to_string);
+
+                match self.current.take() {
+                    Some((current_id, _)) if id.is_some() && current_id == id => {}
+                    Some((_, previous)) => {
+                        self.finished =
+                            Some(self.finished.map_or(previous, |finished| finished.add(previous)));
+                    }
+                    None => {}
+                }
+                self.current = Some((id, turn));
+
+                let total = self.finished.map_or(turn, |finished| finished.add(turn));
+                if self.reported == Some(total) {
+                    return None;
+                }
+                self.reported = Some(total);
+                Some(SessionEventPayload::Usage {
+                    input_tokens: total.input_tokens,
+                    output_tokens: total.output_tokens,
+                    cost_usd: total.cost_usd,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Input and output tokens of a usage object. Cache reads and writes count as input.
+fn token_counts(usage: Option<&Value>) -> (u64, u64) {
+    let count = |key: &str| usage.and_then(|u| u.get(key)).and_then(Value::as_u64).unwrap_or(0);
+    let input =
+        count("input_tokens") + count("cache_creation_input_tokens") + count("cache_read_input_tokens");
+    (input, count("output_tokens"))
+}
+
+/// List prices in USD per million input and output tokens, by model family.
+fn model_prices(model: &str) -> Option<(f64, f64)> {
+    let model = model.to_ascii_lowercase();
+    if model.contains("opus") {
+        let legacy = ["3-opus", "opus-4-1", "opus-4-2"].iter().any(|id| model.contains(id));
+        Some(if legacy { (15.0, 75.0) } else { (5.0, 25.0) })
+    } else if model.contains("sonnet") {
+        Some((3.0, 15.0))
+    } else if model.contains("3-5-haiku") {
+        Some((0.8, 4.0))
+    } else if model.contains("3-haiku") {
+        Some((0.25, 1.25))
+    } else if model.contains("haiku") {
+        Some((1.0, 5.0))
+    } else {
+        None
+    }
+}
+
+/// What one API call cost at list prices; cache writes bill at 1.25x input, reads at 0.1x.
+fn estimate_cost_usd(model: &str, usage: &Value) -> Option<f64> {
+    let (input_price, output_price) = model_prices(model)?;
+    let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0) as f64;
+    let input = count("input_tokens")
+        + count("cache_creation_input_tokens") * 1.25
+        + count("cache_read_input_tokens") * 0.1;
+    Some((input * input_price + count("output_tokens") * output_price) / 1_000_000.0)
+}
+
+/// Extracts the run's totals from a stream-json `result` line.
+fn result_usage(value: &Value) -> Option<SessionEventPayload> {
+    let usage = value.get("usage");
+    let cost_usd = value
+        .get("total_cost_usd")
+        .or_else(|| value.get("cost_usd"))
+        .and_then(Value::as_f64);
+    if usage.is_none() && cost_usd.is_none() {
+        return None;
+    }
+
+    let (input_tokens, output_tokens) = token_counts(usage);
+    Some(SessionEventPayload::Usage { input_tokens, output_tokens, cost_usd })
+}
+
 fn parse_json_event(session_id: &str, seq: u64, value: Value) -> Option<SessionEvent> {
     let event_type = value.get("type")?.as_str()?;
     let data = value.get("data").cloned().unwrap_or(Value::Null);
@@ -572,7 +708,52 @@ fn canonical_status(status: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{compose_spawn_args, SpawnMode};
+    use super::{compose_spawn_args, RunUsageTracker, SpawnMode};
+    use crate::session::events::SessionEventPayload;
+
+    #[test]
+    fn usage_tracker_reads_result_totals() {
+        let usage = RunUsageTracker::default().observe(
+            r#"{"type":"result","subtype":"success","total_cost_usd":0.25,"usage":{"input_tokens":10,"cache_read_input_tokens":5,"output_tokens":7}}"#,
+        );
+
+        assert_eq!(
+            usage,
+            Some(SessionEventPayload::Usage {
+                input_tokens: 15,
+                output_tokens: 7,
+                cost_usd: Some(0.25),
+            })
+        );
+    }
+
+    #[test]
+    fn usage_tracker_accumulates_assistant_turns_by_message_id() {
+        let mut tracker = RunUsageTracker::default();
+        let line = |id: &str, output: u64| {
+            format!(
+                r#"{{"type":"assistant","message":{{"id":"{}","model":"claude-sonnet-4-5","content":[],"usage":{{"input_tokens":1000,"cache_read_input_tokens":10000,"output_tokens":{}}}}}}}"#,
+                id, output
+            )
+        };
+        let totals = |usage: Option<SessionEventPayload>| match usage {
+            Some(SessionEventPayload::Usage { input_tokens, output_tokens, cost_usd }) => {
+                (input_tokens, output_tokens, cost_usd.expect("sonnet has a known price"))
+            }
+            other => panic!("expected usage, got {:?}", other),
+        };
+
+        let (input, output, cost) = totals(tracker.observe(&line("msg-1", 100)));
+        assert_eq!((input, output), (11_000, 100));
+        assert!((cost - 0.0075).abs() < 1e-9, "{}", cost);
+        assert!(tracker.observe(&line("msg-1", 100)).is_none(), "repeated blocks add nothing");
+
+        let (input, output, _) = totals(tracker.observe(&line("msg-1", 400)));
+        assert_eq!((input, output), (11_000, 400));
+        let (input, output, _) = totals(tracker.observe(&line("msg-2", 50)));
+        assert_eq!((input, output), (22_000, 450));
+        assert!(tracker.observe(r#"{"type":"assistant","message":{"content":[]}}"#).is_none());
+    }
 
     #[test]
     fn compose_spawn_args_sets_deterministic_identity_for_new_runs() {
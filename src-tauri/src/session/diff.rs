@@ -0,0 +1,355 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_DIFF_MAX_FILE_BYTES: usize = 256 * 1024;
+pub const DEFAULT_DIFF_MAX_TOTAL_BYTES: usize = 2 * 1024 * 1024;
+/// Same heuristic git uses: a NUL byte in the first 8000 bytes marks a file as binary.
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffLimits {
+    pub max_file_bytes: usize,
+    pub max_total_bytes: usize,
+}
+
+impl Default for DiffLimits {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: DEFAULT_DIFF_MAX_FILE_BYTES,
+            max_total_bytes: DEFAULT_DIFF_MAX_TOTAL_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileDiff {
+    pub path: String,
+    /// One of `added`, `modified`, `deleted`, `type_changed` or `untracked`.
+    pub status: String,
+    pub additions: i64,
+    pub deletions: i64,
+    pub binary: bool,
+    /// Unified diff against the base commit. `None` for binary files and for patches that were
+    /// dropped because they exceeded the size cap (`truncated` is set in that case).
+    pub patch: Option<String>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorktreeDiff {
+    /// Commit the changes are diffed against; `None` for a sandbox, which has no commit.
+    pub base_sha: Option<String>,
+    /// When a sandbox's snapshot of the original folder was taken.
+    pub snapshot_at: Option<String>,
+    pub files: Vec<FileDiff>,
+    pub additions: i64,
+    pub deletions: i64,
+    pub truncated: bool,
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.first().unwrap_or(&""), e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+/// `git diff --no-index` exits with 1 when the files differ, so only 2+ is a failure.
+fn run_git_no_index(dir: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git diff: {}", e))?;
+
+    if output.status.code().is_some_and(|code| code > 1) {
+        return Err(format!("git diff failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    Ok(output.stdout)
+}
+
+/// An empty file that stands in for the missing side of an added or deleted file, since there
+/// is no null device path that works everywhere. Removed on drop.
+struct EmptyFile(PathBuf);
+
+impl EmptyFile {
+    fn create() -> Result<Self, String> {
+        let path = std::env::temp_dir().join(format!("lulu-empty-{}", uuid::Uuid::new_v4()));
+        std::fs::File::create(&path)
+            .map_err(|e| format!("Failed to create empty file for diff: {}", e))?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for EmptyFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Runs `git diff --no-index` between two files, either of which may be missing, relative to
+/// `dir`.
+fn diff_no_index(
+    dir: &Path,
+    old: Option<&Path>,
+    new: Option<&Path>,
+    options: &[&str],
+) -> Result<Vec<u8>, String> {
+    let empty = if old.is_none() || new.is_none() { Some(EmptyFile::create()?) } else { None };
+    let side = |path: Option<&Path>| {
+        path.or(empty.as_ref().map(|empty| empty.0.as_path()))
+            .map(|path| path.display().to_string())
+            .unwrap_or_default()
+    };
+    let (old, new) = (side(old), side(new));
+
+    let mut args = vec!["diff", "--no-index", "--no-color"];
+    args.extend_from_slice(options);
+    args.extend_from_slice(&["--", &old, &new]);
+    run_git_no_index(dir, &args)
+}
+
+/// Unified diff between two files, either of which may be missing, with headers naming `path`
+/// the way git labels modified, added and deleted files.
+pub(crate) fn diff_files(
+    dir: &Path,
+    old: Option<&Path>,
+    new: Option<&Path>,
+    path: &str,
+) -> Result<String, String> {
+    let patch = diff_no_index(dir, old, new, &[])?;
+    let (added, deleted) = (old.is_none(), new.is_none());
+
+    let mut lines = Vec::new();
+    let mut in_header = true;
+    for line in String::from_utf8_lossy(&patch).lines() {
+        if !in_header || line.starts_with("@@") || line.starts_with("Binary files") {
+            in_header = false;
+            lines.push(line.to_string());
+        } else if line.starts_with("diff --git ") {
+            lines.push(format!("diff --git a/{} b/{}", path, path));
+        } else if let Some(mode) = line.strip_prefix("new mode ").filter(|_| added) {
+            lines.push(format!("new file mode {}", mode));
+        } else if let Some(mode) = line.strip_prefix("old mode ").filter(|_| deleted) {
+            lines.push(format!("deleted file mode {}", mode));
+        } else if (added && line.starts_with("old mode ")) || (deleted && line.starts_with("new mode "))
+        {
+            continue;
+        } else if let Some(index) = line.strip_prefix("index ") {
+            // With matching modes the mode trails the index line; an added or deleted file
+            // carries it on its own header line instead.
+            match index.split_once(' ') {
+                Some((hashes, mode)) if added || deleted => {
+                    let label = if added { "new" } else { "deleted" };
+                    lines.push(format!("{} file mode {}", label, mode));
+                    lines.push(format!("index {}", hashes));
+                }
+                _ => lines.push(line.to_string()),
+            }
+        } else if line.starts_with("--- ") {
+            lines.push(if added { "--- /dev/null".to_string() } else { format!("--- a/{}", path) });
+        } else if line.starts_with("+++ ") {
+            lines.push(if deleted { "+++ /dev/null".to_string() } else { format!("+++ b/{}", path) });
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+
+    if lines.is_empty() {
+        return Ok(String::new());
+    }
+    let mut relabeled = lines.join("\n");
+    relabeled.push('\n');
+    Ok(relabeled)
+}
+
+/// Lines added and removed between two files, either of which may be missing, without building
+/// the patch.
+pub(crate) fn count_file_changes(
+    dir: &Path,
+    old: Option<&Path>,
+    new: Option<&Path>,
+) -> Result<(i64, i64), String> {
+    let numstat = diff_no_index(dir, old, new, &["--numstat"])?;
+    let numstat = String::from_utf8_lossy(&numstat);
+    let mut fields = numstat.split('\t');
+    let additions = fields.next().and_then(|count| count.trim().parse().ok()).unwrap_or(0);
+    let deletions = fields.next().and_then(|count| count.trim().parse().ok()).unwrap_or(0);
+    Ok((additions, deletions))
+}
+
+fn split_nul(output: &[u8]) -> Vec<String> {
+    output
+        .split(|byte| *byte == 0)
+        .filter(|part| !part.is_empty())
+        .map(|part| String::from_utf8_lossy(part).into_owned())
+        .collect()
+}
+
+fn status_label(code: &str) -> &'static str {
+    match code.chars().next() {
+        Some('A') => "added",
+        Some('D') => "deleted",
+        Some('T') => "type_changed",
+        _ => "modified",
+    }
+}
+
+pub(crate) fn is_binary_file(path: &Path) -> bool {
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    let mut buffer = Vec::with_capacity(BINARY_SNIFF_BYTES);
+    if file.take(BINARY_SNIFF_BYTES as u64).read_to_end(&mut buffer).is_err() {
+        return false;
+    }
+    buffer.contains(&0)
+}
+
+fn count_lines(path: &Path) -> i64 {
+    std::fs::read(path)
+        .map(|bytes| {
+            let newlines = bytes.iter().filter(|byte| **byte == b'\n').count() as i64;
+            if bytes.last().is_some_and(|byte| *byte != b'\n') {
+                newlines + 1
+            } else {
+                newlines
+            }
+        })
+        .unwrap_or(0)
+}
+
+/// Paths a worktree changed relative to `base_sha`, committed or not, plus untracked files that
+/// aren't ignored. Cheaper than [`diff_worktree`] when only the file list is needed.
+pub fn changed_paths(worktree_path: &Path, base_sha: &str) -> Result<Vec<String>, String> {
+    let mut paths = split_nul(&run_git(
+        worktree_path,
+        &["diff", "--name-only", "-z", "--no-renames", base_sha],
+    )?);
+    paths.extend(split_nul(&run_git(
+        worktree_path,
+        &["ls-files", "--others", "--exclude-standard", "-z"],
+    )?));
+
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// Diffs a worktree against `base_sha`, covering both commits made on the session branch and
+/// uncommitted edits, plus untracked files that aren't ignored.
+pub fn diff_worktree(
+    worktree_path: &Path,
+    base_sha: &str,
+    limits: DiffLimits,
+) -> Result<WorktreeDiff, String> {
+    let mut files = Vec::new();
+
+    let name_status = split_nul(&run_git(
+        worktree_path,
+        &["diff", "--name-status", "-z", "--no-renames", base_sha],
+    )?);
+    let numstat =
+        split_nul(&run_git(worktree_path, &["diff", "--numstat", "-z", "--no-renames", base_sha])?);
+
+    for pair in name_status.chunks(2) {
+        let [code, path] = pair else {
+            continue;
+        };
+        let stats = numstat.iter().find_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let additions = fields.next()?;
+            let deletions = fields.next()?;
+            (fields.next()? == path.as_str()).then_some((additions, deletions))
+        });
+        let binary = stats.is_some_and(|(additions, _)| additions == "-");
+        let (additions, deletions) = stats
+            .map(|(additions, deletions)| {
+                (additions.parse().unwrap_or(0), deletions.parse().unwrap_or(0))
+            })
+            .unwrap_or((0, 0));
+
+        files.push(FileDiff {
+            path: path.clone(),
+            status: status_label(code).to_string(),
+            additions,
+            deletions,
+            binary,
+            patch: None,
+            truncated: false,
+        });
+    }
+
+    let untracked =
+        split_nul(&run_git(worktree_path, &["ls-files", "--others", "--exclude-standard", "-z"])?);
+    for path in untracked {
+        let full_path = worktree_path.join(&path);
+        let binary = is_binary_file(&full_path);
+        files.push(FileDiff {
+            additions: if binary { 0 } else { count_lines(&full_path) },
+            path,
+            status: "untracked".to_string(),
+            deletions: 0,
+            binary,
+            patch: None,
+            truncated: false,
+        });
+    }
+
+    files.sort_by(|left, right| left.path.cmp(&right.path));
+
+    let mut total_bytes = 0usize;
+    let mut truncated = false;
+    let mut total_spent = false;
+    for file in files.iter_mut().filter(|file| !file.binary) {
+        // Counts already come from `--numstat`, so once a patch overflows the total cap the
+        // remaining ones are not worth building.
+        if total_spent {
+            file.truncated = true;
+            truncated = true;
+            continue;
+        }
+
+        let patch = if file.status == "untracked" {
+            diff_files(worktree_path, None, Some(Path::new(&file.path)), &file.path)?.into_bytes()
+        } else {
+            let pathspec = format!(":(literal){}", file.path);
+            run_git(worktree_path, &["diff", "--no-color", base_sha, "--", &pathspec])?
+        };
+
+        let too_large = patch.len() > limits.max_file_bytes;
+        total_spent = !too_large && total_bytes + patch.len() > limits.max_total_bytes;
+        if too_large || total_spent {
+            file.truncated = true;
+            truncated = true;
+            continue;
+        }
+
+        total_bytes += patch.len();
+        file.patch = Some(String::from_utf8_lossy(&patch).into_owned());
+    }
+
+    Ok(WorktreeDiff {
+        base_sha: Some(base_sha.to_string()),
+        snapshot_at: None,
+        additions: files.iter().map(|file| file.additions).sum(),
+        deletions: files.iter().map(|file| file.deletions).sum(),
+        files,
+        truncated,
+    })
+}
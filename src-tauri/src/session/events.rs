@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct SessionEvent {
@@ -27,4 +28,89 @@ pub enum SessionEventPayload {
     },
     Status { status: String },
     Error { message: String },
+    /// Cumulative token and cost totals the CLI reported for the current run.
+    Usage {
+        input_tokens: u64,
+        output_tokens: u64,
+        cost_usd: Option<f64>,
+    },
+    Hook {
+        hook_id: String,
+        event: String,
+        command: String,
+        exit_code: Option<i32>,
+        timed_out: bool,
+        stdout: String,
+        stderr: String,
+        duration_ms: u64,
+    },
+    /// One line of output from the repository's post-run verification command.
+    VerificationOutput { stream: String, line: String },
+    Verification {
+        status: String,
+        command: String,
+        exit_code: Option<i32>,
+        timed_out: bool,
+        duration_ms: u64,
+    },
+    /// One step of preparing a new session worktree: copying or linking local files, or
+    /// running the repository's setup command.
+    ProvisionStep {
+        step: String,
+        status: String,
+        detail: String,
+        duration_ms: u64,
+    },
+    /// One line of output from the repository's worktree setup command.
+    ProvisionOutput { stream: String, line: String },
+    /// A budget that applies to this session passed its soft limit.
+    BudgetWarning {
+        budget_id: String,
+        scope: String,
+        unit: String,
+        spent: f64,
+        soft_limit: f64,
+        hard_limit: f64,
+    },
+    /// A budget hit its hard limit and the session is being stopped.
+    BudgetExceeded {
+        budget_id: String,
+        scope: String,
+        unit: String,
+        spent: f64,
+        hard_limit: f64,
+    },
+    /// Another active session of the same repository changed a file this session changed too.
+    ConflictWarning {
+        path: String,
+        other_session_id: String,
+        other_session_name: String,
+    },
+    /// The session branch was rebased onto or merged with the latest commit of its base ref.
+    WorktreeSync {
+        strategy: String,
+        base_ref: String,
+        base_sha: String,
+        status: String,
+        conflicts: Vec<String>,
+        summary: String,
+    },
+    /// The session worktree was rewound to the checkpoint taken after event `run_id`/`seq`.
+    Rewind {
+        checkpoint_id: String,
+        run_id: String,
+        seq: u64,
+        commit_sha: String,
+    },
+    /// History retention removed the older events of run `run_id`, keeping its messages unless
+    /// they were moved to `archive_path` with everything else.
+    HistoryCompacted {
+        run_id: String,
+        event_count: u64,
+        event_counts: BTreeMap<String, u64>,
+        tools: Vec<String>,
+        first_at: String,
+        last_at: String,
+        archive_path: Option<String>,
+    },
 }
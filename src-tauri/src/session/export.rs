@@ -0,0 +1,191 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::session::worktree::snapshot_worktree;
+use crate::session::WorktreeService;
+
+pub const EXPORT_MANIFEST_FILE: &str = "manifest.json";
+/// Ref namespace used to carry a session tip inside a bundle.
+pub const EXPORT_BUNDLE_REF_PREFIX: &str = "refs/lulu/export/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// `git format-patch` series written into a directory.
+    PatchSeries,
+    /// The same mailbox, concatenated into one `.patch` file that `git am` accepts.
+    Patch,
+    /// A `git bundle` holding the session commits on top of the base commit.
+    Bundle,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportRequest {
+    pub session_branch: String,
+    pub worktree_path: PathBuf,
+    pub base_sha: String,
+    pub format: ExportFormat,
+    /// Directory for `PatchSeries`, file path otherwise.
+    pub destination: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedCommit {
+    pub sha: String,
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportResult {
+    pub format: ExportFormat,
+    pub files: Vec<String>,
+    pub manifest_path: String,
+    pub tip_sha: String,
+    pub commits: Vec<ExportedCommit>,
+    /// True when uncommitted worktree changes were exported as a final snapshot commit.
+    pub includes_uncommitted: bool,
+    /// Ref to fetch from the bundle; only set for `Bundle`.
+    pub bundle_ref: Option<String>,
+}
+
+fn git_ok(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.first().unwrap_or(&""), e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn manifest_path_for(format: ExportFormat, destination: &Path) -> PathBuf {
+    match format {
+        ExportFormat::PatchSeries => destination.join(EXPORT_MANIFEST_FILE),
+        ExportFormat::Patch | ExportFormat::Bundle => {
+            let mut path = destination.as_os_str().to_owned();
+            path.push(".manifest.json");
+            PathBuf::from(path)
+        }
+    }
+}
+
+/// Writes everything the session produced since its base commit: its commits plus, when the
+/// worktree is dirty, one extra snapshot commit of the uncommitted changes. `manifest` is
+/// written next to the export with the export details merged in under `"export"`.
+pub fn write_session_export(
+    service: &WorktreeService,
+    request: &ExportRequest,
+    mut manifest: serde_json::Value,
+) -> Result<ExportResult, String> {
+    let repo_root = service.repo_root();
+    let snapshot = snapshot_worktree(&request.worktree_path, "Uncommitted session changes")?;
+    let includes_uncommitted = snapshot.is_some();
+    let tip_sha = match snapshot {
+        Some(snapshot) => snapshot,
+        None => service.resolve_commit(&request.session_branch)?,
+    };
+
+    let range = format!("{}..{}", request.base_sha, tip_sha);
+    let log = git_ok(repo_root, &["log", "--reverse", "--format=%H%x09%s", &range])?;
+    let commits: Vec<ExportedCommit> = log
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(sha, subject)| ExportedCommit { sha: sha.to_string(), subject: subject.to_string() })
+        .collect();
+    if commits.is_empty() {
+        return Err("Session has no changes since its base commit to export".to_string());
+    }
+
+    let destination = &request.destination;
+    let parent = match request.format {
+        ExportFormat::PatchSeries => Some(destination.as_path()),
+        ExportFormat::Patch | ExportFormat::Bundle => destination.parent(),
+    };
+    if let Some(parent) = parent.filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create export directory: {}", e))?;
+    }
+    let destination_arg = destination.display().to_string();
+
+    let mut bundle_ref = None;
+    let files = match request.format {
+        ExportFormat::PatchSeries => {
+            let written = git_ok(repo_root, &["format-patch", "-o", &destination_arg, &range])?;
+            written.lines().map(str::to_string).collect()
+        }
+        ExportFormat::Patch => {
+            let output = Command::new("git")
+                .args(["format-patch", "--stdout", &range])
+                .current_dir(repo_root)
+                .output()
+                .map_err(|e| format!("Failed to run git format-patch: {}", e))?;
+            if !output.status.success() {
+                return Err(format!(
+                    "git format-patch failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            std::fs::write(destination, &output.stdout)
+                .map_err(|e| format!("Failed to write patch file: {}", e))?;
+            vec![destination_arg.clone()]
+        }
+        ExportFormat::Bundle => {
+            // A bundle carries refs, so the tip gets a temporary one named after the session
+            // branch; it is removed again once the bundle is written.
+            let reference = format!("{}{}", EXPORT_BUNDLE_REF_PREFIX, request.session_branch);
+            git_ok(repo_root, &["update-ref", &reference, &tip_sha])?;
+            let exclude_base = format!("^{}", request.base_sha);
+            let created = git_ok(
+                repo_root,
+                &["bundle", "create", &destination_arg, &reference, &exclude_base],
+            );
+            let _ = git_ok(repo_root, &["update-ref", "-d", &reference]);
+            created?;
+            bundle_ref = Some(reference);
+            vec![destination_arg.clone()]
+        }
+    };
+
+    let result = ExportResult {
+        format: request.format,
+        files,
+        manifest_path: manifest_path_for(request.format, destination).display().to_string(),
+        tip_sha,
+        commits,
+        includes_uncommitted,
+        bundle_ref,
+    };
+
+    if let Some(object) = manifest.as_object_mut() {
+        object.insert(
+            "export".to_string(),
+            serde_json::json!({
+                "format": result.format,
+                "base_sha": request.base_sha,
+                "tip_sha": result.tip_sha,
+                "branch": request.session_branch,
+                "bundle_ref": result.bundle_ref,
+                "includes_uncommitted": result.includes_uncommitted,
+                "commits": result.commits,
+                "files": result.files,
+                "exported_at": chrono::Utc::now().to_rfc3339(),
+            }),
+        );
+    }
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize export manifest: {}", e))?;
+    std::fs::write(&result.manifest_path, manifest_json)
+        .map_err(|e| format!("Failed to write export manifest: {}", e))?;
+
+    Ok(result)
+}
@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{
+    Database, DatabaseFileUsage, ExpiredRun, HistoryRetentionPolicy, SessionHistoryEvent,
+    SessionStorageUsage,
+};
+use crate::session::SessionEventPayload;
+
+pub const HISTORY_RETENTION_COMPACT: &str = "compact";
+pub const HISTORY_RETENTION_ARCHIVE: &str = "archive";
+pub const HISTORY_RETENTION_MODES: [&str; 2] =
+    [HISTORY_RETENTION_COMPACT, HISTORY_RETENTION_ARCHIVE];
+/// Directory under the app data dir that holds the history archives.
+pub const HISTORY_ARCHIVE_DIR: &str = "archives";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryRetentionAction {
+    pub session_id: String,
+    pub run_id: String,
+    /// Events removed from the database, or that would be on a dry run.
+    pub event_count: u64,
+    pub event_bytes: i64,
+    pub archive_path: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistoryRetentionReport {
+    pub dry_run: bool,
+    pub mode: String,
+    /// Runs whose newest event is older than this were compacted; `None` when retention is off.
+    pub cutoff: Option<String>,
+    pub actions: Vec<HistoryRetentionAction>,
+    pub removed_events: u64,
+    pub removed_bytes: i64,
+    /// How much the database file shrank after vacuuming.
+    pub freed_bytes: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageUsageReport {
+    pub database: DatabaseFileUsage,
+    pub event_bytes: i64,
+    pub message_bytes: i64,
+    pub archived_bytes: i64,
+    pub sessions: Vec<SessionStorageUsage>,
+}
+
+/// Where a session's archived history goes. Each archiving pass appends a gzip member of JSON
+/// lines, one `SessionHistoryEvent` per line.
+pub fn archive_path(archive_dir: &Path, session_id: &str) -> PathBuf {
+    archive_dir.join(format!("{}.jsonl.gz", session_id))
+}
+
+fn append_to_archive(path: &Path, events: &[SessionHistoryEvent]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create archive directory: {}", e))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open history archive: {}", e))?;
+
+    let mut encoder = GzEncoder::new(file, Compression::default());
+    for event in events {
+        let line = serde_json::to_string(event)
+            .map_err(|e| format!("Failed to serialize archived event: {}", e))?;
+        writeln!(encoder, "{}", line)
+            .map_err(|e| format!("Failed to write history archive: {}", e))?;
+    }
+    // The events are deleted right after this, so they have to be on disk first.
+    let file = encoder.finish().map_err(|e| format!("Failed to write history archive: {}", e))?;
+    file.sync_all().map_err(|e| format!("Failed to write history archive: {}", e))
+}
+
+pub fn read_archive(path: &Path) -> Result<Vec<SessionHistoryEvent>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open history archive: {}", e))?;
+
+    let mut events = Vec::new();
+    for line in BufReader::new(MultiGzDecoder::new(file)).lines() {
+        let line = line.map_err(|e| format!("Failed to read history archive: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        events.push(
+            serde_json::from_str(&line)
+                .map_err(|e| format!("Failed to parse archived event: {}", e))?,
+        );
+    }
+
+    Ok(events)
+}
+
+/// The `history_compacted` event left in place of a run: how many events of each type it had
+/// and which tools it used.
+pub fn summarize_run(
+    run: &ExpiredRun,
+    events: &[SessionHistoryEvent],
+    archive_path: Option<String>,
+) -> SessionEventPayload {
+    let mut event_counts = BTreeMap::new();
+    let mut tools = Vec::new();
+    for event in events {
+        *event_counts.entry(event.event_type.clone()).or_insert(0) += 1;
+        if let Some(tool) = event.payload_json["data"]["tool_name"].as_str() {
+            tools.push(tool.to_string());
+        }
+    }
+    tools.sort();
+    tools.dedup();
+
+    SessionEventPayload::HistoryCompacted {
+        run_id: run.run_id.clone(),
+        event_count: events.len() as u64,
+        event_counts,
+        tools,
+        first_at: run.first_at.clone(),
+        last_at: run.last_at.clone(),
+        archive_path,
+    }
+}
+
+fn compact_expired_run(
+    db: &Database,
+    run: &ExpiredRun,
+    mode: &str,
+    archive_dir: &Path,
+) -> Result<Option<String>, String> {
+    let events = db
+        .list_run_events(&run.session_id, &run.run_id)
+        .map_err(|e| format!("Failed to load run events: {}", e))?;
+
+    let archive = if mode == HISTORY_RETENTION_ARCHIVE {
+        let path = archive_path(archive_dir, &run.session_id);
+        append_to_archive(&path, &events)?;
+        Some(path.display().to_string())
+    } else {
+        None
+    };
+
+    let summary = serde_json::to_value(summarize_run(run, &events, archive.clone()))
+        .map_err(|e| format!("Failed to serialize run summary: {}", e))?;
+    db.compact_run(run, mode == HISTORY_RETENTION_COMPACT, &summary)
+        .map_err(|e| format!("Failed to compact run: {}", e))?;
+    Ok(archive)
+}
+
+/// Compacts every run of a finished session whose newest event is older than the policy's
+/// `keep_days`. `compact` keeps the run's messages and drops the rest; `archive` moves all of
+/// its events to the session's archive file first, and its assistant messages stay searchable
+/// through the stored messages. Either way a summary event takes their place, and the freed
+/// space is vacuumed. With `dry_run` nothing changes and the report lists what would.
+pub fn apply_history_retention(
+    db: &Database,
+    archive_dir: &Path,
+    dry_run: bool,
+) -> Result<HistoryRetentionReport, String> {
+    run_history_retention(db, archive_dir, dry_run, true)
+}
+
+/// The retention pass run in the background after startup. Databases that predate incremental
+/// vacuuming are not given the full `VACUUM` that converts them; that waits for a pass the user
+/// starts.
+pub fn apply_startup_history_retention(
+    db: &Database,
+    archive_dir: &Path,
+) -> Result<HistoryRetentionReport, String> {
+    run_history_retention(db, archive_dir, false, false)
+}
+
+fn run_history_retention(
+    db: &Database,
+    archive_dir: &Path,
+    dry_run: bool,
+    full_vacuum: bool,
+) -> Result<HistoryRetentionReport, String> {
+    let policy = db
+        .get_history_retention_policy()
+        .map_err(|e| format!("Failed to load history retention policy: {}", e))?;
+    let mut report =
+        HistoryRetentionReport { dry_run, mode: policy.mode.clone(), ..Default::default() };
+    let Some(keep_days) = policy.keep_days.filter(|days| *days >= 0) else {
+        return Ok(report);
+    };
+
+    let cutoff = (Utc::now() - chrono::Duration::days(keep_days)).to_rfc3339();
+    let runs = db
+        .list_expired_runs(&cutoff, policy.mode == HISTORY_RETENTION_COMPACT)
+        .map_err(|e| format!("Failed to list expired runs: {}", e))?;
+    report.cutoff = Some(cutoff);
+
+    let before =
+        db.database_file_usage().map_err(|e| format!("Failed to measure database: {}", e))?;
+    for run in runs {
+        let mut action = HistoryRetentionAction {
+            session_id: run.session_id.clone(),
+            run_id: run.run_id.clone(),
+            event_count: run.event_count,
+            event_bytes: run.event_bytes,
+            archive_path: None,
+            error: None,
+        };
+        if dry_run {
+            report.removed_events += run.event_count;
+            report.removed_bytes += run.event_bytes;
+        } else {
+            match compact_expired_run(db, &run, &policy.mode, archive_dir) {
+                Ok(archive) => {
+                    action.archive_path = archive;
+                    report.removed_events += run.event_count;
+                    report.removed_bytes += run.event_bytes;
+                }
+                Err(message) => action.error = Some(message),
+            }
+        }
+        report.actions.push(action);
+    }
+
+    if !dry_run && report.removed_events > 0 {
+        db.reclaim_free_space(full_vacuum)
+            .map_err(|e| format!("Failed to vacuum database: {}", e))?;
+        let after =
+            db.database_file_usage().map_err(|e| format!("Failed to measure database: {}", e))?;
+        report.freed_bytes = (before.database_bytes - after.database_bytes).max(0);
+    }
+
+    Ok(report)
+}
+
+/// Puts a session's archived events back into the database and removes its archive. The runs
+/// stay eligible for retention, so the next pass archives them again. Returns the number of
+/// events restored.
+pub fn rehydrate_session_history(
+    db: &Database,
+    archive_dir: &Path,
+    session_id: &str,
+) -> Result<u64, String> {
+    let path = archive_path(archive_dir, session_id);
+    if !path.is_file() {
+        return Ok(0);
+    }
+
+    let events = read_archive(&path)?;
+    let restored = db
+        .restore_archived_events(session_id, events)
+        .map_err(|e| format!("Failed to restore archived events: {}", e))?;
+    std::fs::remove_file(&path).map_err(|e| format!("Failed to remove history archive: {}", e))?;
+    Ok(restored)
+}
+
+/// Stored history per session and overall, including the archive files on disk.
+pub fn storage_usage_report(
+    db: &Database,
+    archive_dir: &Path,
+) -> Result<StorageUsageReport, String> {
+    let database =
+        db.database_file_usage().map_err(|e| format!("Failed to measure database: {}", e))?;
+    let mut sessions = db
+        .list_session_storage_usage()
+        .map_err(|e| format!("Failed to measure session storage: {}", e))?;
+
+    let mut report = StorageUsageReport { database, ..Default::default() };
+    for session in &mut sessions {
+        session.archived_bytes = std::fs::metadata(archive_path(archive_dir, &session.session_id))
+            .map(|metadata| metadata.len() as i64)
+            .unwrap_or(0);
+        report.event_bytes += session.event_bytes;
+        report.message_bytes += session.message_bytes;
+        report.archived_bytes += session.archived_bytes;
+    }
+    report.sessions = sessions;
+
+    Ok(report)
+}
+
+pub fn validate_history_retention_policy(policy: &HistoryRetentionPolicy) -> Result<(), String> {
+    if policy.keep_days.is_some_and(|days| days < 0) {
+        return Err("keep_days must not be negative".to_string());
+    }
+    if !HISTORY_RETENTION_MODES.contains(&policy.mode.as_str()) {
+        return Err(format!(
+            "Unknown history retention mode '{}'; expected one of {}",
+            policy.mode,
+            HISTORY_RETENTION_MODES.join(", ")
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,427 @@
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use crate::db::{Database, LifecycleHook};
+use crate::session::events::{SessionEvent, SessionEventPayload};
+use crate::session::WorktreeService;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+pub const HOOK_EVENTS: [&str; 5] = ["started", "completed", "failed", "interrupted", "killed"];
+pub const DEFAULT_HOOK_TIMEOUT_MS: i64 = 30_000;
+const COMMAND_OUTPUT_LIMIT: usize = 16 * 1024;
+/// How often a running command is checked for exit while its output is quiet.
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long output is still collected after the command exits, from processes it left running
+/// in the background.
+const COMMAND_OUTPUT_DRAIN: Duration = Duration::from_millis(250);
+/// Upper bound on the output lines handed over in one batch.
+const OUTPUT_BATCH_LINES: usize = 256;
+
+pub fn is_hook_event(event: &str) -> bool {
+    HOOK_EVENTS.contains(&event)
+}
+
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub session_id: String,
+    pub session_name: String,
+    pub status: String,
+    pub working_dir: String,
+    pub worktree_path: Option<String>,
+    pub sandbox_path: Option<String>,
+    pub failure_reason: Option<String>,
+    pub repo_root: Option<String>,
+}
+
+impl HookContext {
+    pub fn load(db: &Database, session_id: &str) -> Result<Option<Self>, String> {
+        let Some(session) = db
+            .get_session(session_id)
+            .map_err(|e| format!("Failed to load session for hooks: {}", e))?
+        else {
+            return Ok(None);
+        };
+        let dashboard = db
+            .get_dashboard_session(session_id)
+            .map_err(|e| format!("Failed to load session metadata for hooks: {}", e))?;
+
+        let repo_root = WorktreeService::from_working_dir(&session.working_dir)
+            .ok()
+            .map(|service| service.repo_root().display().to_string());
+
+        Ok(Some(Self {
+            session_id: session.id,
+            session_name: session.name,
+            status: session.status,
+            working_dir: session.working_dir,
+            worktree_path: dashboard.as_ref().and_then(|row| row.worktree_path.clone()),
+            sandbox_path: dashboard.as_ref().and_then(|row| row.sandbox_path.clone()),
+            failure_reason: dashboard.and_then(|row| row.failure_reason),
+            repo_root,
+        }))
+    }
+
+    /// Hooks run inside the session worktree or sandbox when it still exists, else the
+    /// original folder.
+    pub fn execution_dir(&self) -> &str {
+        [self.worktree_path.as_deref(), self.sandbox_path.as_deref()]
+            .into_iter()
+            .flatten()
+            .find(|path| Path::new(path).is_dir())
+            .unwrap_or(&self.working_dir)
+    }
+
+    pub fn env_vars(&self, event: &str) -> Vec<(&'static str, String)> {
+        vec![
+            ("LULU_HOOK_EVENT", event.to_string()),
+            ("LULU_SESSION_ID", self.session_id.clone()),
+            ("LULU_SESSION_NAME", self.session_name.clone()),
+            ("LULU_SESSION_STATUS", self.status.clone()),
+            ("LULU_WORKING_DIR", self.working_dir.clone()),
+            ("LULU_WORKTREE_PATH", self.worktree_path.clone().unwrap_or_default()),
+            ("LULU_SANDBOX_PATH", self.sandbox_path.clone().unwrap_or_default()),
+            ("LULU_FAILURE_REASON", self.failure_reason.clone().unwrap_or_default()),
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShellCommandOutput {
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration_ms: u64,
+}
+
+impl ShellCommandOutput {
+    pub fn succeeded(&self) -> bool {
+        !self.timed_out && self.exit_code == Some(0)
+    }
+}
+
+fn shell_command(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    }
+
+    #[cfg(not(windows))]
+    {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        // Its own process group, so a timeout can take down whatever the command started.
+        cmd.process_group(0);
+        cmd
+    }
+}
+
+/// Kills the command started by `shell_command` along with every process it spawned.
+fn kill_process_tree(pid: u32) {
+    #[cfg(windows)]
+    let mut kill = {
+        let mut kill = std::process::Command::new("taskkill");
+        kill.args(["/T", "/F", "/PID", &pid.to_string()]);
+        kill
+    };
+
+    #[cfg(not(windows))]
+    let mut kill = {
+        let mut kill = std::process::Command::new("kill");
+        kill.args(["-KILL", "--", &format!("-{}", pid)]);
+        kill
+    };
+
+    let _ = kill.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).status();
+}
+
+/// Keeps the tail of long output, where build and test failures usually end up.
+fn truncate_output(raw: &[u8]) -> String {
+    let text = String::from_utf8_lossy(raw);
+    if text.len() <= COMMAND_OUTPUT_LIMIT {
+        return text.into_owned();
+    }
+
+    let mut start = text.len() - COMMAND_OUTPUT_LIMIT;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("[truncated {} bytes]\n{}", start, &text[start..])
+}
+
+pub async fn run_shell_command(
+    command: &str,
+    working_dir: &str,
+    env: &[(&'static str, String)],
+    timeout: Duration,
+) -> ShellCommandOutput {
+    run_shell_command_streaming(command, working_dir, env, timeout, |_| {}).await
+}
+
+fn spawn_line_reader<R>(
+    stream: &'static str,
+    reader: R,
+    lines: mpsc::UnboundedSender<(&'static str, Vec<u8>)>,
+) where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(reader);
+        loop {
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if lines.send((stream, line)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Like [`run_shell_command`], but hands stdout/stderr lines to `on_lines` as they arrive, in
+/// batches of whatever is ready at once. Output captured before a timeout is kept. Output from
+/// background processes the command leaves behind is only collected for a moment after it
+/// exits; on timeout, they are killed along with it.
+pub async fn run_shell_command_streaming<F>(
+    command: &str,
+    working_dir: &str,
+    env: &[(&'static str, String)],
+    timeout: Duration,
+    mut on_lines: F,
+) -> ShellCommandOutput
+where
+    F: FnMut(Vec<(&'static str, String)>),
+{
+    let started = Instant::now();
+    let mut cmd = shell_command(command);
+    cmd.current_dir(working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    for (key, value) in env {
+        cmd.env(key, value);
+    }
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            return ShellCommandOutput {
+                exit_code: None,
+                timed_out: false,
+                stdout: String::new(),
+                stderr: format!("Failed to spawn command in '{}': {}", working_dir, err),
+                duration_ms: started.elapsed().as_millis() as u64,
+            };
+        }
+    };
+    let pid = child.id();
+
+    let (line_tx, mut line_rx) = mpsc::unbounded_channel();
+    if let Some(stdout) = child.stdout.take() {
+        spawn_line_reader("stdout", stdout, line_tx.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_line_reader("stderr", stderr, line_tx.clone());
+    }
+    drop(line_tx);
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let result = tokio::time::timeout(timeout, async {
+        let mut exit_status = None;
+        let mut drain_until = None;
+        loop {
+            let mut ready = Vec::new();
+            match tokio::time::timeout(COMMAND_POLL_INTERVAL, line_rx.recv()).await {
+                Ok(Some(line)) => ready.push(line),
+                Ok(None) => break,
+                Err(_) => {}
+            }
+            while !ready.is_empty() && ready.len() < OUTPUT_BATCH_LINES {
+                match line_rx.try_recv() {
+                    Ok(line) => ready.push(line),
+                    Err(_) => break,
+                }
+            }
+
+            let mut batch = Vec::with_capacity(ready.len());
+            for (stream, line) in ready {
+                let text = String::from_utf8_lossy(&line);
+                batch.push((stream, text.trim_end_matches(['\r', '\n']).to_string()));
+                if stream == "stdout" {
+                    stdout.extend_from_slice(&line);
+                } else {
+                    stderr.extend_from_slice(&line);
+                }
+            }
+            if !batch.is_empty() {
+                on_lines(batch);
+            }
+
+            if exit_status.is_none() {
+                exit_status = child.try_wait()?;
+                if exit_status.is_some() {
+                    drain_until = Some(Instant::now() + COMMAND_OUTPUT_DRAIN);
+                }
+            }
+            if drain_until.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+        }
+        match exit_status {
+            Some(status) => Ok(status),
+            None => child.wait().await,
+        }
+    })
+    .await;
+    if result.is_err() {
+        if let Some(pid) = pid {
+            kill_process_tree(pid);
+        }
+    }
+
+    let duration_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(Ok(status)) => ShellCommandOutput {
+            exit_code: status.code(),
+            timed_out: false,
+            stdout: truncate_output(&stdout),
+            stderr: truncate_output(&stderr),
+            duration_ms,
+        },
+        Ok(Err(err)) => ShellCommandOutput {
+            exit_code: None,
+            timed_out: false,
+            stdout: truncate_output(&stdout),
+            stderr: format!("Failed waiting for command: {}", err),
+            duration_ms,
+        },
+        Err(_) => {
+            let mut stderr = truncate_output(&stderr);
+            if !stderr.is_empty() && !stderr.ends_with('\n') {
+                stderr.push('\n');
+            }
+            stderr.push_str(&format!("Command timed out after {} ms", timeout.as_millis()));
+
+            ShellCommandOutput {
+                exit_code: None,
+                timed_out: true,
+                stdout: truncate_output(&stdout),
+                stderr,
+                duration_ms,
+            }
+        }
+    }
+}
+
+pub async fn run_hook(hook: &LifecycleHook, context: &HookContext, event: &str) -> SessionEventPayload {
+    let timeout = Duration::from_millis(hook.timeout_ms.max(1) as u64);
+    let output = run_shell_command(
+        &hook.command,
+        context.execution_dir(),
+        &context.env_vars(event),
+        timeout,
+    )
+    .await;
+
+    SessionEventPayload::Hook {
+        hook_id: hook.id.clone(),
+        event: event.to_string(),
+        command: hook.command.clone(),
+        exit_code: output.exit_code,
+        timed_out: output.timed_out,
+        stdout: output.stdout,
+        stderr: output.stderr,
+        duration_ms: output.duration_ms,
+    }
+}
+
+/// Appends an event raised by Lulu itself (rather than the CLI stream) to the active run. While
+/// the run streams, its seq comes from the stream's counter, so no CLI event is displaced.
+pub fn record_lifecycle_event(
+    db: &Database,
+    session_id: &str,
+    payload: SessionEventPayload,
+) -> Result<SessionEvent, String> {
+    let mut events = record_lifecycle_events(db, session_id, vec![payload])?;
+    Ok(events.remove(0))
+}
+
+/// Records several lifecycle events at once, in one database round trip; used for streamed
+/// command output.
+pub fn record_lifecycle_events(
+    db: &Database,
+    session_id: &str,
+    payloads: Vec<SessionEventPayload>,
+) -> Result<Vec<SessionEvent>, String> {
+    let run_id = db
+        .get_session_run_metadata(session_id)
+        .map_err(|e| format!("Failed to load run metadata for session {}: {}", session_id, e))?
+        .and_then(|metadata| metadata.active_run_id)
+        .unwrap_or_else(|| "lifecycle".to_string());
+
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    let mut rows = Vec::with_capacity(payloads.len());
+    for payload in &payloads {
+        let payload_json = serde_json::to_value(payload)
+            .map_err(|e| format!("Failed to serialize lifecycle event: {}", e))?;
+        let event_type = payload_json
+            .get("type")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("lifecycle")
+            .to_string();
+        rows.push((event_type, payload_json.to_string(), timestamp.clone()));
+    }
+
+    let seqs = db
+        .append_session_events(session_id, &run_id, rows)
+        .map_err(|e| format!("Failed to persist lifecycle event for session {}: {}", session_id, e))?;
+
+    Ok(payloads
+        .into_iter()
+        .zip(seqs)
+        .map(|(payload, seq)| SessionEvent {
+            session_id: session_id.to_string(),
+            seq,
+            timestamp: timestamp.clone(),
+            payload,
+        })
+        .collect())
+}
+
+/// Runs every hook registered for `event` (global first, then repository-scoped) and stores
+/// each result in the session history. Returns the recorded events for the caller to emit.
+pub async fn run_lifecycle_hooks(
+    db: &Database,
+    session_id: &str,
+    event: &str,
+) -> Result<Vec<SessionEvent>, String> {
+    if !is_hook_event(event) {
+        return Ok(Vec::new());
+    }
+
+    let Some(context) = HookContext::load(db, session_id)? else {
+        return Ok(Vec::new());
+    };
+
+    let hooks = db
+        .list_hooks_for_event(event, context.repo_root.as_deref())
+        .map_err(|e| format!("Failed to load lifecycle hooks: {}", e))?;
+
+    let mut recorded = Vec::new();
+    for hook in hooks {
+        let payload = run_hook(&hook, &context, event).await;
+        recorded.push(record_lifecycle_event(db, session_id, payload)?);
+    }
+
+    Ok(recorded)
+}
@@ -0,0 +1,311 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use serde::{Deserialize, Serialize};
+
+use crate::session::worktree::snapshot_worktree;
+use crate::session::WorktreeService;
+
+pub const INTEGRATION_STATUS_MERGED: &str = "merged";
+pub const INTEGRATION_STATUS_CONFLICTED: &str = "conflicted";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrationStrategy {
+    /// `git merge --no-ff` of the session branch.
+    Merge,
+    /// All session commits collapsed into one commit on the target.
+    Squash,
+    /// The session's commits replayed one by one on the target.
+    CherryPick,
+    /// The session worktree's uncommitted changes applied as a single commit.
+    Patch,
+}
+
+impl IntegrationStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Merge => "merge",
+            Self::Squash => "squash",
+            Self::CherryPick => "cherry_pick",
+            Self::Patch => "patch",
+        }
+    }
+
+    fn verb(self) -> &'static str {
+        match self {
+            Self::Merge => "merged into",
+            Self::Squash => "squash-merged into",
+            Self::CherryPick => "cherry-picked onto",
+            Self::Patch => "applied onto",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IntegrationRequest {
+    pub session_name: String,
+    pub session_branch: String,
+    pub worktree_path: PathBuf,
+    /// Commit the session started from; commits after it are the session's own.
+    pub base_sha: String,
+    pub target_branch: String,
+    pub strategy: IntegrationStrategy,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntegrationOutcome {
+    pub status: String,
+    pub result_sha: Option<String>,
+    pub conflicts: Vec<String>,
+    pub summary: String,
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<Output, String> {
+    Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.first().unwrap_or(&""), e))
+}
+
+fn git_ok(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = git(dir, args)?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn short_sha(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+/// Path of the worktree that has `branch` checked out, if any.
+fn checkout_of_branch(repo_root: &Path, branch: &str) -> Result<Option<PathBuf>, String> {
+    let listing = git_ok(repo_root, &["worktree", "list", "--porcelain"])?;
+    let wanted = format!("branch refs/heads/{}", branch);
+
+    let mut current_path = None;
+    for line in listing.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            current_path = Some(PathBuf::from(path));
+        } else if line == wanted {
+            return Ok(current_path);
+        }
+    }
+
+    Ok(None)
+}
+
+fn unmerged_paths(dir: &Path) -> Result<Vec<String>, String> {
+    let output = git_ok(dir, &["diff", "--name-only", "--diff-filter=U"])?;
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+/// Files named in `error: patch failed: <path>:<line>` / `error: <path>: ...` lines.
+fn rejected_patch_paths(stderr: &str) -> Vec<String> {
+    let mut paths: Vec<String> = stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix("error: "))
+        .filter_map(|rest| {
+            let rest = rest.strip_prefix("patch failed: ").unwrap_or(rest);
+            rest.split(':').next().map(str::trim).map(str::to_string)
+        })
+        .filter(|path| !path.is_empty() && !path.contains(' '))
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Uncommitted changes of the session worktree, untracked files included, as a binary patch.
+fn uncommitted_patch(worktree_path: &Path) -> Result<Vec<u8>, String> {
+    let Some(snapshot) = snapshot_worktree(worktree_path, "Uncommitted session changes")? else {
+        return Ok(Vec::new());
+    };
+
+    let output = git(worktree_path, &["diff", "--binary", "--full-index", "HEAD", &snapshot])?;
+    if !output.status.success() {
+        return Err(format!(
+            "git diff failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Result of running a strategy inside the scratch worktree: the new tip, or the files that
+/// conflicted.
+enum Attempt {
+    Clean(String),
+    Conflicted(Vec<String>),
+}
+
+fn attempt_in_scratch(request: &IntegrationRequest, scratch: &Path) -> Result<Attempt, String> {
+    let message = format!(
+        "{} session '{}' ({})",
+        match request.strategy {
+            IntegrationStrategy::Merge => "Merge",
+            IntegrationStrategy::Squash => "Squash",
+            IntegrationStrategy::CherryPick => "Cherry-pick",
+            IntegrationStrategy::Patch => "Apply uncommitted changes from",
+        },
+        request.session_name,
+        request.session_branch
+    );
+
+    let output = match request.strategy {
+        IntegrationStrategy::Merge => git(
+            scratch,
+            &["merge", "--no-ff", "--no-edit", "-m", &message, &request.session_branch],
+        )?,
+        IntegrationStrategy::Squash => {
+            let output = git(scratch, &["merge", "--squash", &request.session_branch])?;
+            if output.status.success() {
+                git(scratch, &["commit", "-m", &message])?
+            } else {
+                output
+            }
+        }
+        IntegrationStrategy::CherryPick => {
+            let range = format!("{}..{}", request.base_sha, request.session_branch);
+            git(scratch, &["cherry-pick", &range])?
+        }
+        IntegrationStrategy::Patch => {
+            let patch = uncommitted_patch(&request.worktree_path)?;
+            if patch.is_empty() {
+                return Err("Session worktree has no uncommitted changes to apply".to_string());
+            }
+            let patch_path =
+                std::env::temp_dir().join(format!("lulu-session-{}.patch", uuid::Uuid::new_v4()));
+            std::fs::write(&patch_path, &patch)
+                .map_err(|e| format!("Failed to write session patch: {}", e))?;
+            let patch_arg = patch_path.display().to_string();
+            let output = git(scratch, &["apply", "--3way", "--index", &patch_arg]);
+            let _ = std::fs::remove_file(&patch_path);
+            let output = output?;
+            if !output.status.success() {
+                let mut conflicts = unmerged_paths(scratch)?;
+                if conflicts.is_empty() {
+                    conflicts = rejected_patch_paths(&String::from_utf8_lossy(&output.stderr));
+                }
+                if conflicts.is_empty() {
+                    return Err(format!(
+                        "git apply failed: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+                return Ok(Attempt::Conflicted(conflicts));
+            }
+            git(scratch, &["commit", "-m", &message])?
+        }
+    };
+
+    if !output.status.success() {
+        let conflicts = unmerged_paths(scratch)?;
+        if conflicts.is_empty() {
+            return Err(format!(
+                "git {} failed: {}",
+                request.strategy.as_str(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        return Ok(Attempt::Conflicted(conflicts));
+    }
+
+    Ok(Attempt::Clean(git_ok(scratch, &["rev-parse", "HEAD"])?))
+}
+
+/// Moves `target_branch` from `old_tip` to `new_tip`. When the branch is checked out somewhere
+/// (usually the main checkout) that checkout is fast-forwarded so its files follow the branch.
+fn advance_target(
+    repo_root: &Path,
+    target_branch: &str,
+    old_tip: &str,
+    new_tip: &str,
+) -> Result<(), String> {
+    match checkout_of_branch(repo_root, target_branch)? {
+        Some(checkout) => {
+            let status = git_ok(&checkout, &["status", "--porcelain", "--untracked-files=no"])?;
+            if !status.is_empty() {
+                return Err(format!(
+                    "'{}' is checked out in {} with uncommitted changes; commit or stash them first",
+                    target_branch,
+                    checkout.display()
+                ));
+            }
+            git_ok(&checkout, &["merge", "--ff-only", new_tip]).map(|_| ())
+        }
+        None => {
+            let reference = format!("refs/heads/{}", target_branch);
+            git_ok(repo_root, &["update-ref", &reference, new_tip, old_tip]).map(|_| ())
+        }
+    }
+}
+
+/// Lands a session's work on `target_branch`. The strategy runs in a scratch worktree detached
+/// at the target tip, so a conflict never touches the main checkout: the scratch worktree is
+/// thrown away and the conflicting files are reported. Only a clean result moves the branch.
+pub fn integrate_session_changes(
+    service: &WorktreeService,
+    request: &IntegrationRequest,
+) -> Result<IntegrationOutcome, String> {
+    let repo_root = service.repo_root();
+    if !service.branch_exists(&request.target_branch) {
+        return Err(format!("Target branch '{}' does not exist", request.target_branch));
+    }
+    if request.strategy != IntegrationStrategy::Patch {
+        let range = format!("{}..{}", request.base_sha, request.session_branch);
+        let count = git_ok(repo_root, &["rev-list", "--count", &range])?;
+        if count == "0" {
+            return Err(format!(
+                "Session branch '{}' has no commits to integrate",
+                request.session_branch
+            ));
+        }
+    }
+
+    let target_tip = service.resolve_commit(&request.target_branch)?;
+    let scratch = std::env::temp_dir().join(format!("lulu-integrate-{}", uuid::Uuid::new_v4()));
+    let scratch_arg = scratch.display().to_string();
+    git_ok(repo_root, &["worktree", "add", "--detach", &scratch_arg, &target_tip])?;
+
+    let attempt = attempt_in_scratch(request, &scratch);
+    let _ = service.remove_worktree_at_path(&scratch, true);
+    let _ = service.prune_worktrees();
+
+    match attempt? {
+        Attempt::Conflicted(conflicts) => Ok(IntegrationOutcome {
+            status: INTEGRATION_STATUS_CONFLICTED.to_string(),
+            result_sha: None,
+            summary: format!(
+                "{} conflicted with {}: {}",
+                request.strategy.as_str(),
+                request.target_branch,
+                conflicts.join(", ")
+            ),
+            conflicts,
+        }),
+        Attempt::Clean(new_tip) => {
+            advance_target(repo_root, &request.target_branch, &target_tip, &new_tip)?;
+            Ok(IntegrationOutcome {
+                status: INTEGRATION_STATUS_MERGED.to_string(),
+                summary: format!(
+                    "{} {} at {}",
+                    request.strategy.verb(),
+                    request.target_branch,
+                    short_sha(&new_tip)
+                ),
+                result_sha: Some(new_tip),
+                conflicts: Vec::new(),
+            })
+        }
+    }
+}
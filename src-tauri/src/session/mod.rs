@@ -1,12 +1,31 @@
+pub mod auto_commit;
+pub mod budgets;
+pub mod checkpoints;
 pub mod cli;
+pub mod diff;
 pub mod events;
+pub mod export;
+pub mod history_retention;
+pub mod hooks;
+pub mod integration;
 pub mod manager;
+pub mod overlap;
 pub mod projection;
+pub mod provisioning;
+pub mod quarantine;
+pub mod retention;
+pub mod sandbox;
 pub mod supervisor;
+pub mod sync;
+pub mod verification;
+pub mod webhooks;
 pub mod worktree;
 
 pub use cli::ClaudeCli;
 pub use events::{SessionEvent, SessionEventPayload};
 pub use manager::SessionManager;
-pub use supervisor::{SessionRuntime, SessionSupervisor};
+pub use supervisor::{
+    LifecycleAdmission, LifecycleOperationKind, LifecycleOperationSnapshot, SessionRuntime,
+    SessionSupervisor, TerminalTransitionNotice,
+};
 pub use worktree::WorktreeService;
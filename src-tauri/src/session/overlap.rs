@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::db::Database;
+use crate::session::diff::changed_paths;
+use crate::session::events::{SessionEvent, SessionEventPayload};
+use crate::session::hooks::{record_lifecycle_event, HookContext};
+use crate::session::WorktreeService;
+
+/// Sessions whose edits can still collide with another session's.
+pub const ACTIVE_SESSION_STATUSES: [&str; 4] = ["starting", "running", "interrupting", "resuming"];
+/// CLI tools that write the file named in their arguments.
+const EDIT_TOOLS: [&str; 4] = ["Edit", "Write", "MultiEdit", "NotebookEdit"];
+
+/// The file an edit tool call writes, as given in its arguments.
+pub fn edited_file_path(tool_name: &str, args: &Value) -> Option<String> {
+    if !EDIT_TOOLS.contains(&tool_name) {
+        return None;
+    }
+
+    ["file_path", "notebook_path", "path"]
+        .iter()
+        .find_map(|key| args.get(key).and_then(Value::as_str))
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+}
+
+/// Resolves `.` and `..` without touching the filesystem, since the file may not exist yet.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+fn strip_root(path: &Path, root: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).map(Path::to_path_buf).ok().or_else(|| {
+        let canonical_root = std::fs::canonicalize(root).ok()?;
+        path.strip_prefix(canonical_root).map(Path::to_path_buf).ok()
+    })?;
+
+    let parts: Vec<String> =
+        relative.components().map(|part| part.as_os_str().to_string_lossy().into_owned()).collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Maps a path a session wrote to its repository-relative form. Relative paths are taken from
+/// the session's execution directory; paths outside the worktree and the repository are
+/// ignored.
+fn repo_relative_path(context: &HookContext, repo_root: &str, raw: &str) -> Option<String> {
+    let raw = Path::new(raw);
+    let absolute = if raw.is_absolute() {
+        normalize(raw)
+    } else {
+        normalize(&Path::new(context.execution_dir()).join(raw))
+    };
+
+    context
+        .worktree_path
+        .as_deref()
+        .and_then(|worktree| strip_root(&absolute, Path::new(worktree)))
+        .or_else(|| strip_root(&absolute, Path::new(repo_root)))
+        .filter(|path| path != ".lulu" && !path.starts_with(".lulu/"))
+}
+
+/// Records a `conflict_warning` in both sessions' histories for every file this session shares
+/// with another active session, once per pair and file. Returns the recorded events.
+fn warn_on_overlaps(db: &Database, session_id: &str) -> Result<Vec<SessionEvent>, String> {
+    let overlaps = db
+        .list_session_overlaps(session_id, &ACTIVE_SESSION_STATUSES)
+        .map_err(|e| format!("Failed to check overlapping edits: {}", e))?;
+
+    let mut events = Vec::new();
+    for (path, other_session_id) in overlaps {
+        let first_warning = db
+            .record_overlap_warning(session_id, &other_session_id, &path)
+            .map_err(|e| format!("Failed to record overlapping edit: {}", e))?;
+        if !first_warning {
+            continue;
+        }
+
+        for (target, other) in
+            [(session_id, other_session_id.as_str()), (&other_session_id, session_id)]
+        {
+            let other_session_name = db
+                .get_session(other)
+                .map_err(|e| format!("Failed to load session: {}", e))?
+                .map(|session| session.name)
+                .unwrap_or_default();
+            let payload = SessionEventPayload::ConflictWarning {
+                path: path.clone(),
+                other_session_id: other.to_string(),
+                other_session_name,
+            };
+            events.push(record_lifecycle_event(db, target, payload)?);
+        }
+    }
+
+    Ok(events)
+}
+
+/// Tracks the file an edit tool call writes and warns about new overlaps. Calls to other tools
+/// are ignored.
+pub fn track_tool_call(
+    db: &Database,
+    session_id: &str,
+    tool_name: &str,
+    args: &Value,
+) -> Result<Vec<SessionEvent>, String> {
+    let Some(raw_path) = edited_file_path(tool_name, args) else {
+        return Ok(Vec::new());
+    };
+    let Some(context) = HookContext::load(db, session_id)? else {
+        return Ok(Vec::new());
+    };
+    let Some(repo_root) = context.repo_root.clone() else {
+        return Ok(Vec::new());
+    };
+    let Some(path) = repo_relative_path(&context, &repo_root, &raw_path) else {
+        return Ok(Vec::new());
+    };
+
+    db.record_tool_touched_file(session_id, &repo_root, &path)
+        .map_err(|e| format!("Failed to record edited file: {}", e))?;
+    warn_on_overlaps(db, session_id)
+}
+
+/// Re-reads the files the session's worktree changed against its base commit, which also
+/// catches edits made through shell commands, and warns about new overlaps. Sessions without a
+/// worktree only have their tool edits tracked.
+pub fn refresh_diff_paths(db: &Database, session_id: &str) -> Result<Vec<SessionEvent>, String> {
+    let Some(context) = HookContext::load(db, session_id)? else {
+        return Ok(Vec::new());
+    };
+    let (Some(repo_root), Some(worktree_path)) =
+        (context.repo_root.clone(), context.worktree_path.clone())
+    else {
+        return Ok(Vec::new());
+    };
+    if !Path::new(&worktree_path).is_dir() {
+        return Ok(Vec::new());
+    }
+    let Some(base) = db
+        .get_session_base(session_id)
+        .map_err(|e| format!("Failed to get session base commit: {}", e))?
+    else {
+        return Ok(Vec::new());
+    };
+
+    let paths = changed_paths(Path::new(&worktree_path), &base.base_sha)?;
+    db.replace_diff_touched_files(session_id, &repo_root, &paths)
+        .map_err(|e| format!("Failed to record changed files: {}", e))?;
+    warn_on_overlaps(db, session_id)
+}
+
+/// Refreshes the diff-derived files of every active session of the repository.
+pub fn refresh_repo_diff_paths(
+    db: &Database,
+    repo_root: &str,
+) -> Result<Vec<SessionEvent>, String> {
+    let sessions = db.list_sessions().map_err(|e| format!("Failed to list sessions: {}", e))?;
+
+    let mut events = Vec::new();
+    for session in sessions {
+        if !ACTIVE_SESSION_STATUSES.contains(&session.status.as_str()) {
+            continue;
+        }
+        let same_repo = WorktreeService::from_working_dir(&session.working_dir)
+            .is_ok_and(|service| service.repo_root().display().to_string() == repo_root);
+        if same_repo {
+            events.extend(refresh_diff_paths(db, &session.id)?);
+        }
+    }
+
+    Ok(events)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConflictingSession {
+    pub session_id: String,
+    pub session_name: String,
+    pub status: String,
+    /// Written through an edit tool call.
+    pub from_tool: bool,
+    /// Changed in the session worktree relative to its base commit.
+    pub from_diff: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepoConflict {
+    pub path: String,
+    pub sessions: Vec<ConflictingSession>,
+}
+
+/// Files of the repository that more than one active session changed, with those sessions.
+pub fn list_repo_conflicts(db: &Database, repo_root: &str) -> Result<Vec<RepoConflict>, String> {
+    let files = db
+        .list_repo_touched_files(repo_root, &ACTIVE_SESSION_STATUSES)
+        .map_err(|e| format!("Failed to list changed files: {}", e))?;
+
+    let mut by_path: BTreeMap<String, Vec<ConflictingSession>> = BTreeMap::new();
+    for file in files {
+        let Some(session) = db
+            .get_session(&file.session_id)
+            .map_err(|e| format!("Failed to load session: {}", e))?
+        else {
+            continue;
+        };
+        by_path.entry(file.path).or_default().push(ConflictingSession {
+            session_id: session.id,
+            session_name: session.name,
+            status: session.status,
+            from_tool: file.from_tool,
+            from_diff: file.from_diff,
+        });
+    }
+
+    Ok(by_path
+        .into_iter()
+        .filter(|(_, sessions)| sessions.len() > 1)
+        .map(|(path, sessions)| RepoConflict { path, sessions })
+        .collect())
+}
@@ -18,6 +18,18 @@ pub struct DashboardSessionProjection {
     pub restored: bool,
     pub restored_at: Option<String>,
     pub recovery_hint: bool,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: Option<f64>,
+    pub verification_status: Option<String>,
+    pub branch_name: Option<String>,
+    pub base_ref: Option<String>,
+    pub base_sha: Option<String>,
+    pub integration_summary: Option<String>,
+    pub commit_sha: Option<String>,
+    pub worktree_bytes: Option<i64>,
+    pub worktree_pruned_at: Option<String>,
+    pub sandbox_path: Option<String>,
 }
 
 pub fn normalize_dashboard_status(status: &str) -> &'static str {
@@ -78,6 +90,18 @@ pub fn project_dashboard_row(row: SessionDashboardRow) -> DashboardSessionProjec
         restored: row.restored,
         restored_at: row.restored_at,
         recovery_hint: row.recovery_hint,
+        input_tokens: row.input_tokens,
+        output_tokens: row.output_tokens,
+        cost_usd: row.cost_usd,
+        verification_status: row.verification_status,
+        branch_name: row.branch_name,
+        base_ref: row.base_ref,
+        base_sha: row.base_sha,
+        integration_summary: row.integration_summary,
+        commit_sha: row.commit_sha,
+        worktree_bytes: row.worktree_bytes,
+        worktree_pruned_at: row.worktree_pruned_at,
+        sandbox_path: row.sandbox_path,
     }
 }
 
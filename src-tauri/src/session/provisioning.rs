@@ -0,0 +1,538 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::db::{Database, RepositorySettings};
+use crate::session::events::{SessionEvent, SessionEventPayload};
+use crate::session::hooks::{
+    record_lifecycle_event, record_lifecycle_events, run_shell_command_streaming, HookContext,
+};
+
+pub const PROVISION_STEP_COPY: &str = "copy";
+pub const PROVISION_STEP_SYMLINK: &str = "symlink";
+pub const PROVISION_STEP_SETUP: &str = "setup";
+pub const PROVISION_STEP_SUBMODULES: &str = "submodules";
+pub const PROVISION_STEP_LFS: &str = "lfs";
+pub const PROVISION_STATUS_OK: &str = "ok";
+pub const PROVISION_STATUS_FAILED: &str = "failed";
+pub const PROVISION_STATUS_SKIPPED: &str = "skipped";
+pub const DEFAULT_SETUP_TIMEOUT_MS: u64 = 15 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProvisionMode {
+    Copy,
+    Symlink,
+}
+
+/// Matches a repository-relative path against a glob. `*` and `?` stay within one path
+/// component, `**` spans any number of components.
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.trim_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match_components(&pattern, &path)
+}
+
+fn match_components(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_components(rest, &path[skip..])),
+        Some((first, rest)) => match path.split_first() {
+            Some((component, remaining)) => {
+                match_component(first.as_bytes(), component.as_bytes())
+                    && match_components(rest, remaining)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_component(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| match_component(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && match_component(rest, &name[1..]),
+        Some((byte, rest)) => name.first() == Some(byte) && match_component(rest, &name[1..]),
+    }
+}
+
+/// Leading components of a pattern that contain no wildcard, e.g. `node_modules/.cache` for
+/// `node_modules/.cache/**`.
+fn literal_prefix(pattern: &str) -> String {
+    pattern
+        .trim_matches('/')
+        .split('/')
+        .take_while(|component| !component.contains(['*', '?']))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn git_paths(repo_root: &Path, args: &[&str]) -> Result<Vec<String>, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_root)
+        .output()
+        .map_err(|e| format!("Failed to run git ls-files: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "git ls-files failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(output
+        .stdout
+        .split(|byte| *byte == 0)
+        .filter(|part| !part.is_empty())
+        .map(|part| String::from_utf8_lossy(part).into_owned())
+        .collect())
+}
+
+fn collect_matches_in_dir(
+    repo_root: &Path,
+    relative: &str,
+    pattern: &str,
+    matches: &mut Vec<String>,
+) {
+    let Ok(entries) = std::fs::read_dir(repo_root.join(relative)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let child = format!("{}/{}", relative, entry.file_name().to_string_lossy());
+        if glob_matches(pattern, &child) {
+            matches.push(child);
+        } else if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            collect_matches_in_dir(repo_root, &child, pattern, matches);
+        }
+    }
+}
+
+/// Untracked and ignored paths of the main checkout that match any of `patterns`. Git reports
+/// a wholly untracked or ignored directory as one entry, so `node_modules` matches the whole
+/// directory; a pattern only looks inside such a directory when its literal prefix names it
+/// (`node_modules/.cache/**`), so `**/.env` never walks `node_modules`.
+pub fn matching_local_paths(repo_root: &Path, patterns: &[String]) -> Result<Vec<String>, String> {
+    let patterns: Vec<&str> =
+        patterns.iter().map(|pattern| pattern.trim()).filter(|p| !p.is_empty()).collect();
+    if patterns.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut candidates = git_paths(
+        repo_root,
+        &["ls-files", "--others", "--ignored", "--exclude-standard", "--directory", "-z"],
+    )?;
+    candidates.extend(git_paths(
+        repo_root,
+        &["ls-files", "--others", "--exclude-standard", "--directory", "-z"],
+    )?);
+
+    let mut matches = Vec::new();
+    for candidate in &candidates {
+        let path = candidate.trim_end_matches('/');
+        if path == ".lulu" || path.starts_with(".lulu/") {
+            continue;
+        }
+        for pattern in &patterns {
+            if glob_matches(pattern, path) {
+                matches.push(path.to_string());
+                break;
+            }
+            let prefix = literal_prefix(pattern);
+            if candidate.ends_with('/') && prefix.starts_with(&format!("{}/", path)) {
+                collect_matches_in_dir(repo_root, path, pattern, &mut matches);
+            }
+        }
+    }
+
+    matches.sort();
+    matches.dedup();
+    Ok(matches)
+}
+
+pub(crate) fn copy_recursive(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let metadata = std::fs::symlink_metadata(source)?;
+    if metadata.file_type().is_symlink() {
+        let target = std::fs::read_link(source)?;
+        return create_symlink(&target, destination, source.is_dir());
+    }
+    if metadata.is_dir() {
+        std::fs::create_dir_all(destination)?;
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &destination.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+
+    std::fs::copy(source, destination).map(|_| ())
+}
+
+pub(crate) fn create_symlink(target: &Path, link: &Path, is_dir: bool) -> std::io::Result<()> {
+    #[cfg(windows)]
+    {
+        if is_dir {
+            std::os::windows::fs::symlink_dir(target, link)
+        } else {
+            std::os::windows::fs::symlink_file(target, link)
+        }
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = is_dir;
+        std::os::unix::fs::symlink(target, link)
+    }
+}
+
+/// Copies or links `paths` from the main checkout into the worktree. Paths that already exist
+/// in the worktree are left alone. Returns the paths that were provisioned.
+pub fn provision_paths(
+    repo_root: &Path,
+    worktree_path: &Path,
+    paths: &[String],
+    mode: ProvisionMode,
+) -> Result<Vec<String>, String> {
+    let mut provisioned = Vec::new();
+    for path in paths {
+        let source = repo_root.join(path);
+        let destination: PathBuf = worktree_path.join(path);
+        if std::fs::symlink_metadata(&destination).is_ok() {
+            continue;
+        }
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+
+        let result = match mode {
+            ProvisionMode::Copy => copy_recursive(&source, &destination),
+            ProvisionMode::Symlink => create_symlink(&source, &destination, source.is_dir()),
+        };
+        result.map_err(|e| format!("Failed to provision {}: {}", path, e))?;
+        provisioned.push(path.clone());
+    }
+
+    Ok(provisioned)
+}
+
+fn run_git(dir: &Path, configs: &[String], args: &[&str]) -> Result<String, String> {
+    let mut command = Command::new("git");
+    for config in configs {
+        command.arg("-c").arg(config);
+    }
+    let output = command
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// A submodule checked out in a working tree, with the URL its clone came from.
+struct CheckedOutSubmodule {
+    name: String,
+    path: String,
+    url: String,
+}
+
+fn checked_out_submodules(dir: &Path) -> Result<Vec<CheckedOutSubmodule>, String> {
+    let listing = run_git(
+        dir,
+        &[],
+        &[
+            "submodule",
+            "foreach",
+            "--quiet",
+            "--recursive",
+            r#"printf '%s\t%s\t%s\n' "$name" "$displaypath" "$(git config --get remote.origin.url)""#,
+        ],
+    )?;
+
+    Ok(listing
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            Some(CheckedOutSubmodule {
+                name: fields.next()?.to_string(),
+                path: fields.next()?.to_string(),
+                url: fields.next()?.to_string(),
+            })
+        })
+        .collect())
+}
+
+pub fn has_submodules(worktree_path: &Path) -> bool {
+    worktree_path.join(".gitmodules").is_file()
+}
+
+/// Whether any tracked `.gitattributes` routes files through the Git LFS filter.
+pub fn uses_lfs(worktree_path: &Path) -> bool {
+    Command::new("git")
+        .args(["grep", "--quiet", "--fixed-strings", "filter=lfs", "--", "*.gitattributes"])
+        .current_dir(worktree_path)
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Initialises the worktree's submodules recursively. Submodules the main checkout has checked
+/// out are cloned from there, sharing its objects instead of fetching them again, and their
+/// `origin` is pointed back at the real remote afterwards; any others are cloned from their
+/// configured URL. Returns the paths of the worktree's submodules.
+pub fn init_worktree_submodules(
+    repo_root: &Path,
+    worktree_path: &Path,
+) -> Result<Vec<String>, String> {
+    let local = checked_out_submodules(repo_root)?;
+    // `-c` settings reach the git commands run inside nested submodules, and command-line URLs
+    // win over the repository config without being written to it.
+    let mut configs = vec!["protocol.file.allow=always".to_string()];
+    configs.extend(local.iter().map(|submodule| {
+        format!("submodule.{}.url={}", submodule.name, repo_root.join(&submodule.path).display())
+    }));
+    run_git(worktree_path, &configs, &["submodule", "update", "--init", "--recursive"])?;
+
+    for submodule in local.iter().filter(|submodule| !submodule.url.is_empty()) {
+        let checkout = worktree_path.join(&submodule.path);
+        if checkout.join(".git").exists() {
+            run_git(&checkout, &[], &["remote", "set-url", "origin", &submodule.url])?;
+        }
+    }
+
+    Ok(checked_out_submodules(worktree_path)?.into_iter().map(|submodule| submodule.path).collect())
+}
+
+/// Replaces the worktree's LFS pointer files with their content from the repository's local
+/// LFS cache; nothing is downloaded. `Ok(None)` means git-lfs is not installed.
+pub fn checkout_lfs_files(worktree_path: &Path) -> Result<Option<String>, String> {
+    if run_git(worktree_path, &[], &["lfs", "version"]).is_err() {
+        return Ok(None);
+    }
+    run_git(worktree_path, &[], &["lfs", "checkout"])?;
+
+    // `git lfs ls-files` marks files whose content is checked out with `*` and pointers with `-`.
+    let listing = run_git(worktree_path, &[], &["lfs", "ls-files"])?;
+    let total = listing.lines().count();
+    let pointers =
+        listing.lines().filter(|line| line.split_whitespace().nth(1) == Some("-")).count();
+    Ok(Some(format!("{} of {} files checked out from the local cache", total - pointers, total)))
+}
+
+fn record_step<F>(
+    db: &Database,
+    session_id: &str,
+    on_event: &mut F,
+    step: &str,
+    status: &str,
+    detail: String,
+    duration_ms: u64,
+) where
+    F: FnMut(&SessionEvent) + Send,
+{
+    let payload = SessionEventPayload::ProvisionStep {
+        step: step.to_string(),
+        status: status.to_string(),
+        detail,
+        duration_ms,
+    };
+    if let Ok(event) = record_lifecycle_event(db, session_id, payload) {
+        on_event(&event);
+    }
+}
+
+/// Runs a provisioning step that shells out to git or copies files on the blocking pool, so a
+/// large checkout does not stall the async runtime.
+async fn run_blocking<T, F>(step: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(step)
+        .await
+        .map_err(|e| format!("Provisioning step did not finish: {}", e))?
+}
+
+/// Records the outcome of a provisioning step that started at `started`; a failure becomes the
+/// error that fails the spawn.
+fn finish_step<F>(
+    db: &Database,
+    session_id: &str,
+    on_event: &mut F,
+    step: &str,
+    started: Instant,
+    result: Result<String, String>,
+) -> Result<(), String>
+where
+    F: FnMut(&SessionEvent) + Send,
+{
+    let duration_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(detail) => {
+            record_step(db, session_id, on_event, step, PROVISION_STATUS_OK, detail, duration_ms);
+            Ok(())
+        }
+        Err(message) => {
+            record_step(
+                db,
+                session_id,
+                on_event,
+                step,
+                PROVISION_STATUS_FAILED,
+                message.clone(),
+                duration_ms,
+            );
+            Err(format!("Worktree provisioning failed: {}", message))
+        }
+    }
+}
+
+/// Prepares a fresh session worktree before the CLI starts: initialises submodules and checks
+/// out LFS files when the repository uses them, copies and links the repository's configured
+/// local files, then runs its setup command in the worktree. Every step and every
+/// setup output line is appended to the session history and handed to `on_event`. An `Err`
+/// means the worktree is not usable and the spawn should fail with that message.
+pub async fn provision_session_worktree<F>(
+    db: &Database,
+    session_id: &str,
+    mut on_event: F,
+) -> Result<(), String>
+where
+    F: FnMut(&SessionEvent) + Send,
+{
+    let Some(context) = HookContext::load(db, session_id)? else {
+        return Ok(());
+    };
+    let (Some(repo_root), Some(worktree_path)) =
+        (context.repo_root.clone(), context.worktree_path.clone())
+    else {
+        return Ok(());
+    };
+    let settings: RepositorySettings = db
+        .get_repository_settings(&repo_root)
+        .map_err(|e| format!("Failed to load repository settings: {}", e))?
+        .unwrap_or_default();
+
+    let repo_path = PathBuf::from(&repo_root);
+    let worktree = PathBuf::from(&worktree_path);
+
+    if settings.init_submodules && has_submodules(&worktree) {
+        let started = Instant::now();
+        let (repo_path, worktree) = (repo_path.clone(), worktree.clone());
+        let result = run_blocking(move || init_worktree_submodules(&repo_path, &worktree))
+            .await
+            .map(|paths| paths.join(", "));
+        finish_step(db, session_id, &mut on_event, PROVISION_STEP_SUBMODULES, started, result)?;
+    }
+
+    if settings.lfs_checkout && uses_lfs(&worktree) {
+        let started = Instant::now();
+        let lfs_worktree = worktree.clone();
+        match run_blocking(move || checkout_lfs_files(&lfs_worktree)).await {
+            Ok(None) => record_step(
+                db,
+                session_id,
+                &mut on_event,
+                PROVISION_STEP_LFS,
+                PROVISION_STATUS_SKIPPED,
+                "git-lfs is not installed; LFS files stay pointers".to_string(),
+                started.elapsed().as_millis() as u64,
+            ),
+            result => {
+                let result = result.map(Option::unwrap_or_default);
+                finish_step(db, session_id, &mut on_event, PROVISION_STEP_LFS, started, result)?;
+            }
+        }
+    }
+
+    for (step, patterns, mode) in [
+        (PROVISION_STEP_COPY, &settings.provision_copy, ProvisionMode::Copy),
+        (PROVISION_STEP_SYMLINK, &settings.provision_symlink, ProvisionMode::Symlink),
+    ] {
+        if patterns.is_empty() {
+            continue;
+        }
+
+        let started = Instant::now();
+        let (repo_path, worktree) = (repo_path.clone(), worktree.clone());
+        let patterns = patterns.clone();
+        let result = run_blocking(move || {
+            matching_local_paths(&repo_path, &patterns)
+                .and_then(|paths| provision_paths(&repo_path, &worktree, &paths, mode))
+        })
+        .await
+        .map(|paths| {
+            if paths.is_empty() {
+                "no matching files".to_string()
+            } else {
+                paths.join(", ")
+            }
+        });
+        finish_step(db, session_id, &mut on_event, step, started, result)?;
+    }
+
+    let Some(command) =
+        settings.setup_command.as_deref().map(str::trim).filter(|command| !command.is_empty())
+    else {
+        return Ok(());
+    };
+
+    let output = run_shell_command_streaming(
+        command,
+        &worktree_path,
+        &context.env_vars("setup"),
+        Duration::from_millis(DEFAULT_SETUP_TIMEOUT_MS),
+        |lines| {
+            let payloads = lines
+                .into_iter()
+                .map(|(stream, line)| SessionEventPayload::ProvisionOutput {
+                    stream: stream.to_string(),
+                    line,
+                })
+                .collect();
+            if let Ok(events) = record_lifecycle_events(db, session_id, payloads) {
+                events.iter().for_each(&mut on_event);
+            }
+        },
+    )
+    .await;
+
+    if output.succeeded() {
+        record_step(
+            db,
+            session_id,
+            &mut on_event,
+            PROVISION_STEP_SETUP,
+            PROVISION_STATUS_OK,
+            command.to_string(),
+            output.duration_ms,
+        );
+        return Ok(());
+    }
+
+    let reason = if output.timed_out {
+        format!("Setup command timed out: {}", command)
+    } else {
+        match output.exit_code {
+            Some(code) => format!("Setup command exited with {}: {}", code, command),
+            None => format!("Setup command did not run: {}", output.stderr.trim()),
+        }
+    };
+    record_step(
+        db,
+        session_id,
+        &mut on_event,
+        PROVISION_STEP_SETUP,
+        PROVISION_STATUS_FAILED,
+        reason.clone(),
+        output.duration_ms,
+    );
+    Err(reason)
+}
@@ -0,0 +1,277 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::session::WorktreeService;
+
+/// Directory inside a repository's worktrees root that holds worktrees no session owns.
+pub const QUARANTINE_DIR: &str = ".quarantine";
+const MANIFEST_EXTENSION: &str = "json";
+
+/// A worktree reconciliation moved aside instead of deleting, with what it held at the time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuarantinedWorktree {
+    /// Directory name inside the quarantine, also the manifest's file stem.
+    pub name: String,
+    /// The worktree directory name, which is the id of the session that created it.
+    pub session_id: String,
+    pub original_path: String,
+    pub path: String,
+    pub branch: Option<String>,
+    pub last_commit: Option<String>,
+    pub last_commit_summary: Option<String>,
+    /// Uncommitted changes, from `git status --porcelain`.
+    pub dirty_files: Vec<String>,
+    /// HEAD is not contained in the main checkout's current branch.
+    pub unmerged: bool,
+    pub quarantined_at: String,
+}
+
+impl QuarantinedWorktree {
+    /// Deleting this worktree could lose work nobody has looked at yet.
+    pub fn needs_confirmation(&self) -> bool {
+        self.unmerged || !self.dirty_files.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    pub repo_root: String,
+    pub dry_run: bool,
+    /// Worktrees moved to the quarantine, or that would be with `dry_run`.
+    pub quarantined: Vec<QuarantinedWorktree>,
+    /// Worktrees that could not be moved; they stay where they are.
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PurgeReport {
+    pub repo_root: String,
+    pub purged: Vec<String>,
+    /// Dirty or unmerged entries left alone because deletion wasn't confirmed.
+    pub needs_confirmation: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).current_dir(dir).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Paths with uncommitted changes. Unlike other git output the status lines can't be trimmed,
+/// since their leading space is part of the status code.
+fn dirty_files(path: &Path) -> Option<Vec<String>> {
+    let output = Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.get(3..))
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+pub fn quarantine_root(service: &WorktreeService) -> PathBuf {
+    service.worktrees_root().join(QUARANTINE_DIR)
+}
+
+/// Records what a worktree holds: its branch, last commit, uncommitted files and whether its
+/// commits made it into the main checkout's branch.
+fn inspect_worktree(service: &WorktreeService, path: &Path) -> QuarantinedWorktree {
+    let session_id =
+        path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let last_commit = git_output(path, &["rev-parse", "HEAD"]);
+    let dirty_files = dirty_files(path).unwrap_or_else(|| vec!["(git status failed)".to_string()]);
+    let unmerged = match last_commit.as_deref() {
+        Some(commit) => !Command::new("git")
+            .args(["merge-base", "--is-ancestor", commit, "HEAD"])
+            .current_dir(service.repo_root())
+            .status()
+            .is_ok_and(|status| status.success()),
+        None => true,
+    };
+
+    QuarantinedWorktree {
+        name: String::new(),
+        session_id,
+        original_path: path.display().to_string(),
+        path: String::new(),
+        branch: git_output(path, &["symbolic-ref", "--short", "-q", "HEAD"]),
+        last_commit_summary: git_output(path, &["log", "-1", "--format=%s", "HEAD"]),
+        last_commit,
+        dirty_files,
+        unmerged,
+        quarantined_at: String::new(),
+    }
+}
+
+/// Moves a worktree into the quarantine, keeping it a working git worktree.
+fn move_into_quarantine(
+    service: &WorktreeService,
+    path: &Path,
+    destination: &Path,
+) -> Result<(), String> {
+    let moved = Command::new("git")
+        .arg("worktree")
+        .arg("move")
+        .arg(path)
+        .arg(destination)
+        .current_dir(service.repo_root())
+        .output()
+        .map_err(|e| format!("Failed to run git worktree move: {}", e))?;
+    if moved.status.success() {
+        return Ok(());
+    }
+
+    // Locked worktrees and ones with submodules can't be moved by git; move the directory and
+    // let git repair its link to the repository.
+    std::fs::rename(path, destination).map_err(|e| {
+        format!(
+            "Failed to quarantine {}: {} ({})",
+            path.display(),
+            e,
+            String::from_utf8_lossy(&moved.stderr).trim()
+        )
+    })?;
+    let _ = Command::new("git")
+        .arg("worktree")
+        .arg("repair")
+        .arg(destination)
+        .current_dir(service.repo_root())
+        .output();
+    Ok(())
+}
+
+fn manifest_path(root: &Path, name: &str) -> PathBuf {
+    root.join(format!("{}.{}", name, MANIFEST_EXTENSION))
+}
+
+/// Moves every managed worktree that no session expects into the quarantine, with a manifest
+/// of what it held, and prunes registrations whose directory is gone. Nothing is deleted. With
+/// `dry_run` the report lists what would move and nothing changes.
+pub fn reconcile_managed_worktrees(
+    service: &WorktreeService,
+    expected_paths: &[PathBuf],
+    dry_run: bool,
+) -> Result<ReconcileReport, String> {
+    let expected: HashSet<&PathBuf> = expected_paths.iter().collect();
+    let root = quarantine_root(service);
+    let mut report = ReconcileReport {
+        repo_root: service.repo_root().display().to_string(),
+        dry_run,
+        ..Default::default()
+    };
+
+    for entry in service.list_worktrees()? {
+        if !entry.path.starts_with(service.worktrees_root()) || entry.path.starts_with(&root) {
+            continue;
+        }
+        if expected.contains(&entry.path) || entry.prunable || !entry.path.exists() {
+            continue;
+        }
+
+        let mut quarantined = inspect_worktree(service, &entry.path);
+        let stamp = chrono::Utc::now();
+        quarantined.name = format!("{}-{}", quarantined.session_id, stamp.format("%Y%m%d%H%M%S"));
+        quarantined.quarantined_at = stamp.to_rfc3339();
+        let destination = root.join(&quarantined.name);
+        quarantined.path = destination.display().to_string();
+
+        if !dry_run {
+            let moved = std::fs::create_dir_all(&root)
+                .map_err(|e| format!("Failed to create {}: {}", root.display(), e))
+                .and_then(|_| move_into_quarantine(service, &entry.path, &destination))
+                .and_then(|_| {
+                    let contents = serde_json::to_string_pretty(&quarantined)
+                        .map_err(|e| format!("Failed to serialize quarantine manifest: {}", e))?;
+                    std::fs::write(manifest_path(&root, &quarantined.name), contents)
+                        .map_err(|e| format!("Failed to write quarantine manifest: {}", e))
+                });
+            if let Err(message) = moved {
+                report.errors.push(message);
+                continue;
+            }
+        }
+        report.quarantined.push(quarantined);
+    }
+
+    if !dry_run {
+        service.prune_worktrees()?;
+    }
+    Ok(report)
+}
+
+/// Quarantined worktrees of the repository, oldest first.
+pub fn list_quarantined_worktrees(
+    service: &WorktreeService,
+) -> Result<Vec<QuarantinedWorktree>, String> {
+    let root = quarantine_root(service);
+    let Ok(entries) = std::fs::read_dir(&root) else {
+        return Ok(Vec::new());
+    };
+
+    let mut quarantined = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(MANIFEST_EXTENSION) {
+            continue;
+        }
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let manifest: QuarantinedWorktree = serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        quarantined.push(manifest);
+    }
+
+    quarantined.sort_by(|left, right| left.quarantined_at.cmp(&right.quarantined_at));
+    Ok(quarantined)
+}
+
+/// Deletes quarantined worktrees, all of them or those named in `names`. Dirty or unmerged
+/// ones are only deleted with `confirmed`; their branches are always kept.
+pub fn purge_quarantined_worktrees(
+    service: &WorktreeService,
+    names: Option<&[String]>,
+    confirmed: bool,
+) -> Result<PurgeReport, String> {
+    let root = quarantine_root(service);
+    let mut report =
+        PurgeReport { repo_root: service.repo_root().display().to_string(), ..Default::default() };
+
+    for quarantined in list_quarantined_worktrees(service)? {
+        if names.is_some_and(|names| !names.contains(&quarantined.name)) {
+            continue;
+        }
+        if quarantined.needs_confirmation() && !confirmed {
+            report.needs_confirmation.push(quarantined.name);
+            continue;
+        }
+
+        let path = root.join(&quarantined.name);
+        let removed = service.remove_worktree_at_path(&path, true).or_else(|_| {
+            if path.exists() {
+                std::fs::remove_dir_all(&path)
+                    .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))
+            } else {
+                Ok(())
+            }
+        });
+        match removed.and_then(|_| {
+            std::fs::remove_file(manifest_path(&root, &quarantined.name))
+                .map_err(|e| format!("Failed to delete manifest of {}: {}", quarantined.name, e))
+        }) {
+            Ok(()) => report.purged.push(quarantined.name),
+            Err(message) => report.errors.push(message),
+        }
+    }
+
+    service.prune_worktrees()?;
+    Ok(report)
+}
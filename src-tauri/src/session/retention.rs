@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::{Database, RepositorySettings};
+use crate::session::integration::INTEGRATION_STATUS_MERGED;
+use crate::session::WorktreeService;
+
+pub const RETENTION_REASON_AGE: &str = "age";
+pub const RETENTION_REASON_SIZE: &str = "size";
+/// Statuses after which a session's worktree is only kept for inspection.
+const FINISHED_STATUSES: [&str; 3] = ["completed", "failed", "killed"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_age_days: Option<i64>,
+    pub max_bytes: Option<i64>,
+    pub keep_unmerged: bool,
+}
+
+impl RetentionPolicy {
+    pub fn from_settings(settings: &RepositorySettings) -> Self {
+        Self {
+            max_age_days: settings.retention_max_age_days.filter(|days| *days >= 0),
+            max_bytes: settings.retention_max_bytes.filter(|bytes| *bytes >= 0),
+            keep_unmerged: settings.retention_keep_unmerged,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_days.is_some() || self.max_bytes.is_some()
+    }
+}
+
+/// One session worktree as the policy sees it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionCandidate {
+    pub session_id: String,
+    pub bytes: i64,
+    /// When the session finished; `None` while it can still run.
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Commits not on the base branch, or uncommitted changes.
+    pub unmerged: bool,
+}
+
+/// Picks the worktrees to remove, with the reason for each. Worktrees of sessions that can
+/// still run are never removed but count towards the size cap; when the cap is exceeded the
+/// oldest finished worktrees go first.
+pub fn plan_retention(
+    policy: &RetentionPolicy,
+    candidates: &[RetentionCandidate],
+    now: DateTime<Utc>,
+) -> Vec<(String, &'static str)> {
+    let mut removable: Vec<&RetentionCandidate> = candidates
+        .iter()
+        .filter(|candidate| candidate.finished_at.is_some())
+        .filter(|candidate| !(policy.keep_unmerged && candidate.unmerged))
+        .collect();
+    removable.sort_by_key(|candidate| candidate.finished_at);
+
+    let mut planned = Vec::new();
+    if let Some(days) = policy.max_age_days {
+        let cutoff = now - chrono::Duration::days(days);
+        removable.retain(|candidate| {
+            let expired = candidate.finished_at.is_some_and(|finished| finished < cutoff);
+            if expired {
+                planned.push((candidate.session_id.clone(), RETENTION_REASON_AGE));
+            }
+            !expired
+        });
+    }
+
+    if let Some(max_bytes) = policy.max_bytes {
+        let mut total: i64 = candidates
+            .iter()
+            .filter(|candidate| !planned.iter().any(|(id, _)| *id == candidate.session_id))
+            .map(|candidate| candidate.bytes)
+            .sum();
+        for candidate in removable {
+            if total <= max_bytes {
+                break;
+            }
+            total -= candidate.bytes;
+            planned.push((candidate.session_id.clone(), RETENTION_REASON_SIZE));
+        }
+    }
+
+    planned
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetentionAction {
+    pub session_id: String,
+    pub repo_root: String,
+    pub worktree_path: String,
+    pub bytes: i64,
+    /// `age` or `size`.
+    pub reason: String,
+    pub removed: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    pub actions: Vec<RetentionAction>,
+    /// Finished sessions whose worktree was kept because it holds unmerged work.
+    pub kept_unmerged: Vec<String>,
+    pub freed_bytes: i64,
+}
+
+/// Apparent size of everything under `path`. Symlinks are not followed, so linked
+/// `node_modules` directories don't count against the worktree.
+pub fn measure_dir_bytes(path: &Path) -> i64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return if metadata.file_type().is_symlink() { 0 } else { metadata.len() as i64 };
+    }
+
+    std::fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| measure_dir_bytes(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Measures a session's worktree and stores the result for the dashboard. Returns the size, or
+/// `None` when the session has no worktree on disk.
+pub fn record_worktree_usage(db: &Database, session_id: &str) -> Result<Option<i64>, String> {
+    let Some(worktree_path) = db
+        .get_session_worktree_path(session_id)
+        .map_err(|e| format!("Failed to get session worktree path: {}", e))?
+        .filter(|path| Path::new(path).is_dir())
+    else {
+        return Ok(None);
+    };
+
+    let bytes = measure_dir_bytes(Path::new(&worktree_path));
+    db.update_worktree_bytes(session_id, bytes)
+        .map_err(|e| format!("Failed to record worktree usage: {}", e))?;
+    Ok(Some(bytes))
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).current_dir(dir).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value).map(|parsed| parsed.with_timezone(&Utc)).ok()
+}
+
+/// True when the worktree has uncommitted changes or commits its base branch doesn't have.
+/// After a successful merge, only commits made since then count, since squash and
+/// cherry-pick merges leave the session commits unreachable from the target. Anything git
+/// can't answer counts as unmerged.
+fn has_unmerged_work(
+    db: &Database,
+    session_id: &str,
+    worktree_path: &Path,
+    base_ref: Option<&str>,
+    base_sha: Option<&str>,
+) -> bool {
+    match git_output(worktree_path, &["status", "--porcelain"]) {
+        Some(status) if status.is_empty() => {}
+        _ => return true,
+    }
+
+    let last_merge = db
+        .list_session_integrations(session_id)
+        .ok()
+        .and_then(|integrations| integrations.into_iter().next())
+        .filter(|integration| integration.status == INTEGRATION_STATUS_MERGED);
+    if let Some(integration) = last_merge {
+        let head_time = git_output(worktree_path, &["log", "-1", "--format=%cI", "HEAD"])
+            .as_deref()
+            .and_then(parse_timestamp);
+        return match (head_time, parse_timestamp(&integration.created_at)) {
+            (Some(head_time), Some(merged_at)) => head_time > merged_at,
+            _ => true,
+        };
+    }
+
+    let ahead = |upstream: &str| {
+        let range = format!("{}..HEAD", upstream);
+        git_output(worktree_path, &["rev-list", "--count", &range])
+            .and_then(|count| count.parse::<i64>().ok())
+    };
+    let ahead = base_ref.and_then(ahead).or_else(|| base_sha.and_then(ahead));
+    ahead != Some(0)
+}
+
+/// When a finished session last did anything; `None` for sessions that can still run.
+fn finished_at(
+    status: &str,
+    last_activity_at: Option<&str>,
+    created_at: &str,
+) -> Option<DateTime<Utc>> {
+    if !FINISHED_STATUSES.contains(&status) {
+        return None;
+    }
+
+    parse_timestamp(last_activity_at.unwrap_or(created_at))
+}
+
+/// Applies every repository's retention policy. Each worktree is measured (and the size stored
+/// for the dashboard) whether or not its repository has a policy. With `dry_run` nothing is
+/// removed and the report lists what would be.
+pub fn apply_worktree_retention(db: &Database, dry_run: bool) -> Result<RetentionReport, String> {
+    run_worktree_retention(db, dry_run, true)
+}
+
+/// The retention pass run in the background after startup. Walking a worktree is expensive, so
+/// only worktrees a policy applies to are measured, plus those the dashboard has no size for yet.
+pub fn apply_startup_worktree_retention(db: &Database) -> Result<RetentionReport, String> {
+    run_worktree_retention(db, false, false)
+}
+
+fn run_worktree_retention(
+    db: &Database,
+    dry_run: bool,
+    measure_all: bool,
+) -> Result<RetentionReport, String> {
+    let sessions = db.list_sessions().map_err(|e| format!("Failed to list sessions: {}", e))?;
+    let dashboard: HashMap<String, _> = db
+        .list_dashboard_sessions()
+        .map_err(|e| format!("Failed to list sessions: {}", e))?
+        .into_iter()
+        .map(|row| (row.id.clone(), row))
+        .collect();
+
+    let mut policies: HashMap<String, RetentionPolicy> = HashMap::new();
+    let mut by_repo: HashMap<String, Vec<(RetentionCandidate, String)>> = HashMap::new();
+    for session in sessions {
+        let Some(row) = dashboard.get(&session.id) else {
+            continue;
+        };
+        let Some(worktree_path) = row.worktree_path.clone() else {
+            continue;
+        };
+        let path = Path::new(&worktree_path);
+        if !path.is_dir() {
+            continue;
+        }
+        let Ok(service) = WorktreeService::from_working_dir(&session.working_dir) else {
+            continue;
+        };
+        let repo_root = service.repo_root().display().to_string();
+        if !policies.contains_key(&repo_root) {
+            let settings = db
+                .get_repository_settings(&repo_root)
+                .map_err(|e| format!("Failed to load repository settings: {}", e))?
+                .unwrap_or_default();
+            policies.insert(repo_root.clone(), RetentionPolicy::from_settings(&settings));
+        }
+        let policy_enabled = policies.get(&repo_root).is_some_and(RetentionPolicy::is_enabled);
+
+        let bytes = if measure_all || policy_enabled || row.worktree_bytes.is_none() {
+            let bytes = measure_dir_bytes(path);
+            let _ = db.update_worktree_bytes(&session.id, bytes);
+            bytes
+        } else {
+            row.worktree_bytes.unwrap_or_default()
+        };
+        if !policy_enabled {
+            continue;
+        }
+
+        let finished_at =
+            finished_at(&row.status, row.last_activity_at.as_deref(), &row.created_at);
+        let unmerged = finished_at.is_some()
+            && has_unmerged_work(
+                db,
+                &session.id,
+                path,
+                row.base_ref.as_deref(),
+                row.base_sha.as_deref(),
+            );
+
+        by_repo.entry(repo_root).or_default().push((
+            RetentionCandidate { session_id: session.id, bytes, finished_at, unmerged },
+            worktree_path,
+        ));
+    }
+
+    let mut report = RetentionReport { dry_run, ..Default::default() };
+    let now = Utc::now();
+    for (repo_root, entries) in by_repo {
+        let Some(policy) = policies.remove(&repo_root) else {
+            continue;
+        };
+
+        let candidates: Vec<RetentionCandidate> =
+            entries.iter().map(|(candidate, _)| candidate.clone()).collect();
+        if policy.keep_unmerged {
+            report.kept_unmerged.extend(
+                candidates
+                    .iter()
+                    .filter(|candidate| candidate.finished_at.is_some() && candidate.unmerged)
+                    .map(|candidate| candidate.session_id.clone()),
+            );
+        }
+
+        let service = WorktreeService::new(&repo_root);
+        for (session_id, reason) in plan_retention(&policy, &candidates, now) {
+            let Some((candidate, worktree_path)) =
+                entries.iter().find(|(candidate, _)| candidate.session_id == session_id)
+            else {
+                continue;
+            };
+
+            let mut action = RetentionAction {
+                session_id,
+                repo_root: repo_root.clone(),
+                worktree_path: worktree_path.clone(),
+                bytes: candidate.bytes,
+                reason: reason.to_string(),
+                removed: false,
+                error: None,
+            };
+            if !dry_run {
+                let removed = service
+                    .remove_worktree_at_path(Path::new(worktree_path), true)
+                    .and_then(|_| {
+                        db.mark_worktree_pruned(&action.session_id)
+                            .map_err(|e| format!("Failed to record removed worktree: {}", e))
+                    });
+                match removed {
+                    Ok(()) => {
+                        action.removed = true;
+                        report.freed_bytes += candidate.bytes;
+                    }
+                    Err(message) => action.error = Some(message),
+                }
+            }
+            report.actions.push(action);
+        }
+        let _ = service.prune_worktrees();
+    }
+
+    Ok(report)
+}
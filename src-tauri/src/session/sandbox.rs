@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::session::diff::{
+    count_file_changes, diff_files, is_binary_file, DiffLimits, FileDiff, WorktreeDiff,
+};
+use crate::session::provisioning::{copy_recursive, create_symlink};
+
+/// Directory under the app data dir that holds one sandbox per session.
+pub const SANDBOXES_DIR: &str = "sandboxes";
+/// Folders with more files than this run in place instead of being copied.
+pub const MAX_SANDBOX_FILES: usize = 100_000;
+/// Folders holding more than this run in place too: where extents can't be cloned, the copy
+/// takes as much disk space as the original.
+pub const MAX_SANDBOX_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+const TREE_DIR: &str = "tree";
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Where a session's sandbox copy lives; its manifest sits next to it.
+pub fn sandbox_tree_for(app_data_dir: &Path, session_id: &str) -> PathBuf {
+    app_data_dir.join(SANDBOXES_DIR).join(session_id).join(TREE_DIR)
+}
+
+/// How large a folder may be and still be copied into a sandbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandboxLimits {
+    pub max_files: usize,
+    pub max_bytes: u64,
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self { max_files: MAX_SANDBOX_FILES, max_bytes: MAX_SANDBOX_BYTES }
+    }
+}
+
+/// Enough of a file's metadata to tell whether it changed since it was recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub len: u64,
+    pub modified_ns: i64,
+    pub symlink: bool,
+}
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    if metadata.is_dir() {
+        return None;
+    }
+
+    let modified_ns = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_nanos() as i64)
+        .unwrap_or(0);
+    Some(FileStamp { len: metadata.len(), modified_ns, symlink: metadata.file_type().is_symlink() })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    /// The original file when the sandbox was created or the file was last applied back.
+    pub source: FileStamp,
+    /// The sandbox copy at that same moment.
+    pub sandbox: FileStamp,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxManifest {
+    pub source_dir: String,
+    pub created_at: String,
+    pub files: BTreeMap<String, SnapshotEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl SandboxChangeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Modified => "modified",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SandboxApplyReport {
+    /// Files written back to the original folder.
+    pub applied: Vec<String>,
+    /// Files removed from the original folder.
+    pub deleted: Vec<String>,
+    /// Files changed in both places since the snapshot; left alone unless forced.
+    pub conflicts: Vec<String>,
+}
+
+/// Files and symlinks under `root` by `/`-separated relative path. Symlinks are not followed.
+fn list_files(root: &Path, limit: usize) -> Result<BTreeMap<String, FileStamp>, String> {
+    fn walk(
+        root: &Path,
+        relative: &str,
+        limit: usize,
+        files: &mut BTreeMap<String, FileStamp>,
+    ) -> Result<(), String> {
+        let dir = root.join(relative);
+        let entries = std::fs::read_dir(&dir)
+            .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let child = if relative.is_empty() { name } else { format!("{}/{}", relative, name) };
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                walk(root, &child, limit, files)?;
+            } else if let Some(stamp) = file_stamp(&entry.path()) {
+                files.insert(child, stamp);
+                if files.len() > limit {
+                    return Err(format!("{} has more than {} files", root.display(), limit));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = BTreeMap::new();
+    walk(root, "", limit, &mut files)?;
+    Ok(files)
+}
+
+/// Copies `source`'s contents into `destination` with the platform `cp`, which clones file
+/// extents (reflinks on Btrfs and XFS, clonefile on APFS) so the copy costs no extra space
+/// until files diverge. Falls back to a plain copy where cloning isn't available.
+fn clone_tree(source: &Path, destination: &Path) -> Result<(), String> {
+    let source_contents = source.join(".");
+    let cloned = if cfg!(target_os = "linux") {
+        Command::new("cp")
+            .arg("-a")
+            .arg("--reflink=auto")
+            .arg(&source_contents)
+            .arg(destination)
+            .status()
+            .is_ok_and(|status| status.success())
+    } else if cfg!(target_os = "macos") {
+        Command::new("cp")
+            .args(["-c", "-R", "-p"])
+            .arg(&source_contents)
+            .arg(destination)
+            .status()
+            .is_ok_and(|status| status.success())
+    } else {
+        false
+    };
+    if cloned {
+        return Ok(());
+    }
+
+    let _ = std::fs::remove_dir_all(destination);
+    copy_recursive(source, destination)
+        .map_err(|e| format!("Failed to copy {} into the sandbox: {}", source.display(), e))
+}
+
+/// Canonicalizes the deepest existing ancestor of `path` and appends the rest, so paths that
+/// don't exist yet can be compared with canonical ones.
+fn resolve_existing_prefix(path: &Path) -> PathBuf {
+    for ancestor in path.ancestors() {
+        if let Ok(resolved) = std::fs::canonicalize(ancestor) {
+            let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            return resolved.join(rest);
+        }
+    }
+    path.to_path_buf()
+}
+
+fn contents_differ(left: &Path, right: &Path) -> bool {
+    let left_link = std::fs::read_link(left).ok();
+    let right_link = std::fs::read_link(right).ok();
+    if left_link.is_some() || right_link.is_some() {
+        return left_link != right_link;
+    }
+
+    match (std::fs::read(left), std::fs::read(right)) {
+        (Ok(left), Ok(right)) => left != right,
+        _ => true,
+    }
+}
+
+fn count_patch_lines(patch: &str) -> (i64, i64) {
+    let mut additions = 0;
+    let mut deletions = 0;
+    let mut in_hunk = false;
+    for line in patch.lines() {
+        if line.starts_with("@@") {
+            in_hunk = true;
+        } else if in_hunk && line.starts_with('+') {
+            additions += 1;
+        } else if in_hunk && line.starts_with('-') {
+            deletions += 1;
+        }
+    }
+    (additions, deletions)
+}
+
+/// A copy of a non-git working directory a session runs in, so its edits stay away from the
+/// original until they are applied back.
+#[derive(Debug, Clone)]
+pub struct SessionSandbox {
+    tree: PathBuf,
+}
+
+impl SessionSandbox {
+    pub fn open(tree: &Path) -> Self {
+        Self { tree: tree.to_path_buf() }
+    }
+
+    /// Copies `source_dir` to `tree` and records a manifest of both sides. Fails when the
+    /// folder is too large to copy or would contain the sandbox itself.
+    pub fn create(source_dir: &Path, tree: &Path) -> Result<Self, String> {
+        Self::create_with_limits(source_dir, tree, SandboxLimits::default())
+    }
+
+    pub fn create_with_limits(
+        source_dir: &Path,
+        tree: &Path,
+        limits: SandboxLimits,
+    ) -> Result<Self, String> {
+        let source_dir = std::fs::canonicalize(source_dir)
+            .map_err(|e| format!("Failed to resolve {}: {}", source_dir.display(), e))?;
+        let sandbox = Self::open(tree);
+        let root = sandbox.root();
+        if resolve_existing_prefix(&root).starts_with(&source_dir) {
+            return Err(format!("The sandbox location is inside {}", source_dir.display()));
+        }
+        std::fs::create_dir_all(&root)
+            .map_err(|e| format!("Failed to create {}: {}", root.display(), e))?;
+
+        if let Err(message) = sandbox.snapshot(&source_dir, limits) {
+            let _ = std::fs::remove_dir_all(&root);
+            return Err(message);
+        }
+
+        Ok(sandbox)
+    }
+
+    fn snapshot(&self, source_dir: &Path, limits: SandboxLimits) -> Result<(), String> {
+        let source_files = list_files(source_dir, limits.max_files)?;
+        let source_bytes: u64 =
+            source_files.values().filter(|stamp| !stamp.symlink).map(|stamp| stamp.len).sum();
+        if source_bytes > limits.max_bytes {
+            return Err(format!(
+                "{} holds more than {} bytes",
+                source_dir.display(),
+                limits.max_bytes
+            ));
+        }
+        clone_tree(source_dir, &self.tree)?;
+        let sandbox_files = list_files(&self.tree, usize::MAX)?;
+
+        let files = source_files
+            .into_iter()
+            .filter_map(|(path, source)| {
+                let sandbox = *sandbox_files.get(&path)?;
+                Some((path, SnapshotEntry { source, sandbox }))
+            })
+            .collect();
+        self.write_manifest(&SandboxManifest {
+            source_dir: source_dir.display().to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            files,
+        })
+    }
+
+    pub fn tree(&self) -> &Path {
+        &self.tree
+    }
+
+    fn root(&self) -> PathBuf {
+        self.tree.parent().map(Path::to_path_buf).unwrap_or_else(|| self.tree.clone())
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.root().join(MANIFEST_FILE)
+    }
+
+    pub fn manifest(&self) -> Result<SandboxManifest, String> {
+        let contents = std::fs::read_to_string(self.manifest_path())
+            .map_err(|e| format!("Failed to read the sandbox manifest: {}", e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse the sandbox manifest: {}", e))
+    }
+
+    fn write_manifest(&self, manifest: &SandboxManifest) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(manifest)
+            .map_err(|e| format!("Failed to serialize the sandbox manifest: {}", e))?;
+        std::fs::write(self.manifest_path(), contents)
+            .map_err(|e| format!("Failed to write the sandbox manifest: {}", e))
+    }
+
+    /// Files the session changed in the sandbox since the snapshot, sorted by path. A file
+    /// whose metadata changed but whose content still matches the original doesn't count.
+    pub fn changes(&self) -> Result<Vec<(String, SandboxChangeKind)>, String> {
+        let manifest = self.manifest()?;
+        let source_dir = Path::new(&manifest.source_dir);
+        let current = list_files(&self.tree, usize::MAX)?;
+
+        let mut changes = Vec::new();
+        for (path, stamp) in &current {
+            match manifest.files.get(path) {
+                None => changes.push((path.clone(), SandboxChangeKind::Added)),
+                Some(entry)
+                    if entry.sandbox != *stamp
+                        && contents_differ(&source_dir.join(path), &self.tree.join(path)) =>
+                {
+                    changes.push((path.clone(), SandboxChangeKind::Modified))
+                }
+                Some(_) => {}
+            }
+        }
+        for path in manifest.files.keys().filter(|path| !current.contains_key(*path)) {
+            changes.push((path.clone(), SandboxChangeKind::Deleted));
+        }
+
+        changes.sort_by(|left, right| left.0.cmp(&right.0));
+        Ok(changes)
+    }
+
+    /// Diffs the session's changes against the original folder. There is no commit to name,
+    /// so the diff carries the snapshot time instead of a base SHA.
+    pub fn diff(&self, limits: DiffLimits) -> Result<WorktreeDiff, String> {
+        let manifest = self.manifest()?;
+        let source_dir = Path::new(&manifest.source_dir);
+
+        let mut files = Vec::new();
+        let mut total_bytes = 0usize;
+        let mut truncated = false;
+        let mut total_spent = false;
+        for (path, kind) in self.changes()? {
+            let original = source_dir.join(&path);
+            let copy = self.tree.join(&path);
+            let binary = is_binary_file(&original) || is_binary_file(&copy);
+            let mut file = FileDiff {
+                path: path.clone(),
+                status: kind.as_str().to_string(),
+                additions: 0,
+                deletions: 0,
+                binary,
+                patch: None,
+                truncated: false,
+            };
+
+            if !binary {
+                let old = original.exists().then_some(original.as_path());
+                let new = (kind != SandboxChangeKind::Deleted).then_some(Path::new(&path));
+                if total_spent {
+                    // Past the total cap only the counts are needed, so skip building the patch.
+                    (file.additions, file.deletions) = count_file_changes(&self.tree, old, new)?;
+                    file.truncated = true;
+                } else {
+                    let patch = diff_files(&self.tree, old, new, &file.path)?;
+                    (file.additions, file.deletions) = count_patch_lines(&patch);
+
+                    let too_large = patch.len() > limits.max_file_bytes;
+                    total_spent =
+                        !too_large && total_bytes + patch.len() > limits.max_total_bytes;
+                    if too_large || total_spent {
+                        file.truncated = true;
+                        truncated = true;
+                    } else {
+                        total_bytes += patch.len();
+                        file.patch = Some(patch);
+                    }
+                }
+            }
+            files.push(file);
+        }
+
+        Ok(WorktreeDiff {
+            base_sha: None,
+            snapshot_at: Some(manifest.created_at),
+            additions: files.iter().map(|file| file.additions).sum(),
+            deletions: files.iter().map(|file| file.deletions).sum(),
+            files,
+            truncated,
+        })
+    }
+
+    /// Writes the session's changes back to the original folder. Files that were also changed
+    /// in the original since the snapshot are reported as conflicts and left alone unless
+    /// `force` is set. Applied files become the new baseline, so applying twice is a no-op.
+    pub fn apply(&self, force: bool) -> Result<SandboxApplyReport, String> {
+        let mut manifest = self.manifest()?;
+        let source_dir = PathBuf::from(&manifest.source_dir);
+
+        let mut report = SandboxApplyReport::default();
+        for (path, kind) in self.changes()? {
+            let original = source_dir.join(&path);
+            let copy = self.tree.join(&path);
+            let expected = manifest.files.get(&path).map(|entry| entry.source);
+            let already_applied = match kind {
+                SandboxChangeKind::Deleted => file_stamp(&original).is_none(),
+                _ => file_stamp(&original).is_some() && !contents_differ(&original, &copy),
+            };
+
+            if !already_applied {
+                if file_stamp(&original) != expected && !force {
+                    report.conflicts.push(path);
+                    continue;
+                }
+
+                if std::fs::symlink_metadata(&original).is_ok() {
+                    std::fs::remove_file(&original)
+                        .map_err(|e| format!("Failed to replace {}: {}", path, e))?;
+                }
+                if kind != SandboxChangeKind::Deleted {
+                    if let Some(parent) = original.parent() {
+                        std::fs::create_dir_all(parent)
+                            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+                    }
+                    let written = match std::fs::read_link(&copy) {
+                        Ok(target) => create_symlink(&target, &original, copy.is_dir()),
+                        Err(_) => std::fs::copy(&copy, &original).map(|_| ()),
+                    };
+                    written.map_err(|e| format!("Failed to apply {}: {}", path, e))?;
+                }
+            }
+
+            match (file_stamp(&original), file_stamp(&copy)) {
+                (Some(source), Some(sandbox)) => {
+                    manifest.files.insert(path.clone(), SnapshotEntry { source, sandbox });
+                }
+                _ => {
+                    manifest.files.remove(&path);
+                }
+            }
+            if kind == SandboxChangeKind::Deleted {
+                report.deleted.push(path);
+            } else {
+                report.applied.push(path);
+            }
+        }
+
+        self.write_manifest(&manifest)?;
+        Ok(report)
+    }
+
+    /// Deletes the sandbox copy and its manifest.
+    pub fn remove(&self) -> Result<(), String> {
+        let root = self.root();
+        if !root.exists() {
+            return Ok(());
+        }
+        std::fs::remove_dir_all(&root)
+            .map_err(|e| format!("Failed to remove sandbox {}: {}", root.display(), e))
+    }
+}
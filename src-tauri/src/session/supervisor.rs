@@ -1,17 +1,29 @@
-use std::collections::{HashMap, HashSet};
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use crate::db::Database;
+use crate::session::budgets::BUDGET_EXCEEDED_REASON;
 use crate::session::projection::normalize_failure_reason;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tauri::{AppHandle, Emitter};
 use tokio::process::Child;
 use tokio::time::sleep;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::{broadcast, watch, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use tokio_util::sync::CancellationToken;
 
+/// How long an interrupt waits for the CLI to exit before giving up, or killing it.
+pub const INTERRUPT_DEADLINE: Duration = Duration::from_secs(10);
+/// Headroom after the interrupt deadline for killing the CLI and finalizing the session.
+const KILL_GRACE: Duration = Duration::from_secs(5);
+/// How long a lifecycle request waits behind each operation holding the session: longer than
+/// the slowest one, an interrupt that falls back to kill, so a queued request only gives up
+/// when the operation ahead of it is stuck.
+const LIFECYCLE_WAIT_TIMEOUT: Duration =
+    Duration::from_secs(INTERRUPT_DEADLINE.as_secs() + 2 * KILL_GRACE.as_secs());
+
 fn is_terminal_status(status: &str) -> bool {
     matches!(status, "completed" | "failed" | "killed" | "interrupted")
 }
@@ -29,11 +41,19 @@ pub struct TerminalTransitionResult {
     pub failure_message: Option<String>,
 }
 
+#[derive(Debug, Clone)]
+pub struct TerminalTransitionNotice {
+    pub session_id: String,
+    pub final_status: String,
+    pub failure_message: Option<String>,
+}
+
 pub struct SessionRuntime {
     pub id: String,
     pub name: String,
     pub child: Mutex<Child>,
     killed: AtomicBool,
+    budget_exceeded: AtomicBool,
     interrupt_requested: AtomicBool,
     interrupt_requests: AtomicUsize,
     terminal_transitioned: AtomicBool,
@@ -47,6 +67,7 @@ impl SessionRuntime {
             name,
             child: Mutex::new(child),
             killed: AtomicBool::new(false),
+            budget_exceeded: AtomicBool::new(false),
             interrupt_requested: AtomicBool::new(false),
             interrupt_requests: AtomicUsize::new(0),
             terminal_transitioned: AtomicBool::new(false),
@@ -63,6 +84,15 @@ impl SessionRuntime {
         self.killed.load(Ordering::SeqCst)
     }
 
+    /// Flags the run as stopped for exceeding a budget. Returns false if it was already flagged.
+    pub fn mark_budget_exceeded(&self) -> bool {
+        !self.budget_exceeded.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn was_budget_exceeded(&self) -> bool {
+        self.budget_exceeded.load(Ordering::SeqCst)
+    }
+
     pub fn mark_interrupt_requested(&self) {
         self.interrupt_requested.store(true, Ordering::SeqCst);
     }
@@ -88,54 +118,278 @@ impl SessionRuntime {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleOperationKind {
+    Interrupt,
+    Resume,
+    Kill,
+    /// Rebasing or merging a stopped session's worktree onto its base ref.
+    Sync,
+    /// Landing a stopped session's changes on a target branch.
+    Merge,
+    /// Restoring a stopped session's worktree to one of its checkpoints.
+    Rewind,
+}
+
+impl LifecycleOperationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleOperationKind::Interrupt => "interrupt",
+            LifecycleOperationKind::Resume => "resume",
+            LifecycleOperationKind::Kill => "kill",
+            LifecycleOperationKind::Sync => "sync",
+            LifecycleOperationKind::Merge => "merge",
+            LifecycleOperationKind::Rewind => "rewind",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LifecycleConflict {
+    Preempt,
+    Join,
+    Wait,
+}
+
+/// Precedence when a lifecycle request arrives while another one is still in flight:
+/// kill pre-empts interrupt, identical requests join the running one, everything else waits.
+/// A sync, merge or rewind has an outcome of its own to report, so a second one waits instead
+/// of joining.
+fn resolve_lifecycle_conflict(
+    active: LifecycleOperationKind,
+    requested: LifecycleOperationKind,
+) -> LifecycleConflict {
+    match (active, requested) {
+        (LifecycleOperationKind::Interrupt, LifecycleOperationKind::Kill) => {
+            LifecycleConflict::Preempt
+        }
+        (LifecycleOperationKind::Sync, LifecycleOperationKind::Sync)
+        | (LifecycleOperationKind::Merge, LifecycleOperationKind::Merge)
+        | (LifecycleOperationKind::Rewind, LifecycleOperationKind::Rewind) => {
+            LifecycleConflict::Wait
+        }
+        (active, requested) if active == requested => LifecycleConflict::Join,
+        _ => LifecycleConflict::Wait,
+    }
+}
+
+type LifecycleOutcome = Option<Result<(), String>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LifecycleOperationSnapshot {
+    pub session_id: String,
+    pub operation: LifecycleOperationKind,
+    pub started_at: String,
+    pub elapsed_ms: u64,
+}
+
+struct ActiveLifecycleOperation {
+    token: u64,
+    operation: LifecycleOperationKind,
+    started_at: String,
+    started: Instant,
+    preempted: CancellationToken,
+    outcome: watch::Sender<LifecycleOutcome>,
+}
+
 pub struct SessionSupervisor {
     runtimes: RwLock<HashMap<String, Arc<SessionRuntime>>>,
-    lifecycle_ops: std::sync::Mutex<HashSet<String>>,
+    lifecycle_ops: std::sync::Mutex<HashMap<String, ActiveLifecycleOperation>>,
+    next_lifecycle_token: AtomicU64,
+    terminal_transitions: broadcast::Sender<TerminalTransitionNotice>,
+    /// Shared by spawns while their worktree exists without a session row pointing at it,
+    /// exclusive for worktree reconciliation; see `lock_worktree_creation`.
+    worktree_sweep: RwLock<()>,
+}
+
+pub enum LifecycleAdmission<'a> {
+    Acquired(LifecycleOperationGuard<'a>),
+    Joined(Result<(), String>),
 }
 
 pub struct LifecycleOperationGuard<'a> {
     supervisor: &'a SessionSupervisor,
     session_id: String,
+    token: u64,
+    operation: LifecycleOperationKind,
+    preempted: CancellationToken,
+}
+
+impl LifecycleOperationGuard<'_> {
+    pub fn operation(&self) -> LifecycleOperationKind {
+        self.operation
+    }
+
+    pub fn is_preempted(&self) -> bool {
+        self.preempted.is_cancelled()
+    }
+
+    /// Publishes the result to any requests that joined this operation.
+    pub fn complete(&self, result: &Result<(), String>) {
+        if let Ok(ops) = self.supervisor.lifecycle_ops.lock() {
+            if let Some(active) = ops.get(&self.session_id) {
+                if active.token == self.token {
+                    active.outcome.send_replace(Some(result.clone()));
+                }
+            }
+        }
+    }
 }
 
 impl Drop for LifecycleOperationGuard<'_> {
     fn drop(&mut self) {
         if let Ok(mut ops) = self.supervisor.lifecycle_ops.lock() {
-            ops.remove(&self.session_id);
+            if ops.get(&self.session_id).is_some_and(|active| active.token == self.token) {
+                ops.remove(&self.session_id);
+            }
         }
     }
 }
 
+enum LifecycleWait {
+    Join(watch::Receiver<LifecycleOutcome>),
+    Wait(watch::Receiver<LifecycleOutcome>),
+}
+
 impl SessionSupervisor {
     pub fn new() -> Self {
         Self {
             runtimes: RwLock::new(HashMap::new()),
-            lifecycle_ops: std::sync::Mutex::new(HashSet::new()),
+            lifecycle_ops: std::sync::Mutex::new(HashMap::new()),
+            next_lifecycle_token: AtomicU64::new(1),
+            terminal_transitions: broadcast::channel(64).0,
+            worktree_sweep: RwLock::new(()),
+        }
+    }
+
+    /// Held by a spawn from creating its worktree until the session row records it, so a
+    /// reconciliation never takes the new worktree for one no session owns.
+    pub async fn lock_worktree_creation(&self) -> RwLockReadGuard<'_, ()> {
+        self.worktree_sweep.read().await
+    }
+
+    /// Held while reconciling worktrees; waits for spawns that are still creating one.
+    pub async fn lock_worktree_sweep(&self) -> RwLockWriteGuard<'_, ()> {
+        self.worktree_sweep.write().await
+    }
+
+    /// Every applied terminal transition is published here, whichever path finalized it.
+    pub fn subscribe_terminal_transitions(&self) -> broadcast::Receiver<TerminalTransitionNotice> {
+        self.terminal_transitions.subscribe()
+    }
+
+    pub async fn acquire_lifecycle_operation(
+        &self,
+        session_id: &str,
+        operation: LifecycleOperationKind,
+    ) -> Result<LifecycleAdmission<'_>, String> {
+        loop {
+            // Each operation ahead of this one gets the full wait; they are bounded themselves.
+            let deadline = tokio::time::Instant::now() + LIFECYCLE_WAIT_TIMEOUT;
+            let pending = match self.try_acquire_lifecycle_operation(session_id, operation)? {
+                Ok(guard) => return Ok(LifecycleAdmission::Acquired(guard)),
+                Err(pending) => pending,
+            };
+
+            let waited = match pending {
+                LifecycleWait::Join(mut rx) => {
+                    match tokio::time::timeout_at(deadline, rx.wait_for(Option::is_some)).await {
+                        Ok(Ok(outcome)) => {
+                            let result = outcome.clone().unwrap_or(Ok(()));
+                            return Ok(LifecycleAdmission::Joined(result));
+                        }
+                        Ok(Err(_)) => Ok(()),
+                        Err(elapsed) => Err(elapsed),
+                    }
+                }
+                LifecycleWait::Wait(mut rx) => {
+                    tokio::time::timeout_at(deadline, async {
+                        while rx.changed().await.is_ok() {}
+                    })
+                    .await
+                }
+            };
+
+            if waited.is_err() {
+                return Err(format!(
+                    "Timed out waiting for in-progress lifecycle operation on session {} before {}",
+                    session_id,
+                    operation.as_str()
+                ));
+            }
         }
     }
 
-    pub fn acquire_lifecycle_operation(
+    fn try_acquire_lifecycle_operation(
         &self,
         session_id: &str,
-        operation: &str,
-    ) -> Result<LifecycleOperationGuard<'_>, String> {
+        operation: LifecycleOperationKind,
+    ) -> Result<Result<LifecycleOperationGuard<'_>, LifecycleWait>, String> {
         let mut ops = self
             .lifecycle_ops
             .lock()
             .map_err(|_| "Lifecycle operation lock poisoned".to_string())?;
-        if ops.contains(session_id) {
-            return Err(format!(
-                "Session {} already has an in-progress lifecycle operation",
-                session_id
-            ));
+
+        if let Some(active) = ops.get(session_id) {
+            match resolve_lifecycle_conflict(active.operation, operation) {
+                LifecycleConflict::Join => {
+                    return Ok(Err(LifecycleWait::Join(active.outcome.subscribe())));
+                }
+                LifecycleConflict::Wait => {
+                    return Ok(Err(LifecycleWait::Wait(active.outcome.subscribe())));
+                }
+                LifecycleConflict::Preempt => {
+                    active.preempted.cancel();
+                    active.outcome.send_replace(Some(Err(format!(
+                        "{} pre-empted by {}",
+                        active.operation.as_str(),
+                        operation.as_str()
+                    ))));
+                }
+            }
         }
 
-        let _ = operation;
-        ops.insert(session_id.to_string());
-        Ok(LifecycleOperationGuard {
+        let token = self.next_lifecycle_token.fetch_add(1, Ordering::SeqCst);
+        let preempted = CancellationToken::new();
+        let (outcome, _) = watch::channel(None);
+        ops.insert(
+            session_id.to_string(),
+            ActiveLifecycleOperation {
+                token,
+                operation,
+                started_at: chrono::Utc::now().to_rfc3339(),
+                started: Instant::now(),
+                preempted: preempted.clone(),
+                outcome,
+            },
+        );
+
+        Ok(Ok(LifecycleOperationGuard {
             supervisor: self,
             session_id: session_id.to_string(),
-        })
+            token,
+            operation,
+            preempted,
+        }))
+    }
+
+    pub fn lifecycle_operation(&self, session_id: &str) -> Option<LifecycleOperationSnapshot> {
+        let ops = self.lifecycle_ops.lock().ok()?;
+        ops.get(session_id).map(|active| snapshot_lifecycle_operation(session_id, active))
+    }
+
+    pub fn list_lifecycle_operations(&self) -> Vec<LifecycleOperationSnapshot> {
+        let Ok(ops) = self.lifecycle_ops.lock() else {
+            return Vec::new();
+        };
+
+        let mut snapshots: Vec<LifecycleOperationSnapshot> = ops
+            .iter()
+            .map(|(session_id, active)| snapshot_lifecycle_operation(session_id, active))
+            .collect();
+        snapshots.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+        snapshots
     }
 
     pub async fn register(&self, session_id: String, name: String, child: Child) -> Arc<SessionRuntime> {
@@ -251,9 +505,18 @@ impl SessionSupervisor {
                 .map_err(|err| format!("Failed failure update for session {}: {}", session_id, err))?;
         }
 
+        let failure_message = normalized_failure.or(failure_message);
+        if is_terminal_status(final_status) {
+            let _ = self.terminal_transitions.send(TerminalTransitionNotice {
+                session_id: session_id.to_string(),
+                final_status: final_status.to_string(),
+                failure_message: failure_message.clone(),
+            });
+        }
+
         Ok(Some(TerminalTransitionResult {
             final_status: final_status.to_string(),
-            failure_message: normalized_failure.or(failure_message),
+            failure_message,
         }))
     }
 
@@ -265,6 +528,20 @@ impl SessionSupervisor {
     }
 
     pub async fn kill_session(&self, session_id: &str) -> Result<bool, String> {
+        let op = match self
+            .acquire_lifecycle_operation(session_id, LifecycleOperationKind::Kill)
+            .await?
+        {
+            LifecycleAdmission::Acquired(guard) => guard,
+            LifecycleAdmission::Joined(result) => return result.map(|_| true),
+        };
+
+        let result = self.kill_runtime(session_id).await;
+        op.complete(&result.clone().map(|_| ()));
+        result
+    }
+
+    async fn kill_runtime(&self, session_id: &str) -> Result<bool, String> {
         let Some(runtime) = self.get(session_id).await else {
             return Ok(false);
         };
@@ -285,8 +562,69 @@ impl SessionSupervisor {
         session_id: &str,
         total_deadline: Duration,
     ) -> Result<(), String> {
-        let _op = self.acquire_lifecycle_operation(session_id, "interrupt")?;
+        let op = match self
+            .acquire_lifecycle_operation(session_id, LifecycleOperationKind::Interrupt)
+            .await?
+        {
+            LifecycleAdmission::Acquired(guard) => guard,
+            LifecycleAdmission::Joined(result) => return result,
+        };
+
+        let result = self
+            .run_interrupt(db, session_id, total_deadline, &op, "interrupted", None)
+            .await;
+        op.complete(&result);
+        result
+    }
+
+    /// Stops a run that hit a hard budget. The CLI is interrupted first so it can wind down; if
+    /// it does not exit before the deadline it is killed. Either way the session finalizes as
+    /// failed with the `budget_exceeded` reason.
+    pub async fn stop_session_for_budget(
+        &self,
+        db: &Database,
+        session_id: &str,
+        total_deadline: Duration,
+    ) -> Result<(), String> {
+        if let Some(runtime) = self.get(session_id).await {
+            runtime.mark_budget_exceeded();
+        }
+
+        let op = match self
+            .acquire_lifecycle_operation(session_id, LifecycleOperationKind::Interrupt)
+            .await?
+        {
+            LifecycleAdmission::Acquired(guard) => guard,
+            LifecycleAdmission::Joined(result) => return result,
+        };
+
+        let result = match self
+            .run_interrupt(
+                db,
+                session_id,
+                total_deadline,
+                &op,
+                "failed",
+                Some(BUDGET_EXCEEDED_REASON.to_string()),
+            )
+            .await
+        {
+            Err(_) if !op.is_preempted() => self.kill_runtime(session_id).await.map(|_| ()),
+            result => result,
+        };
+        op.complete(&result);
+        result
+    }
 
+    async fn run_interrupt(
+        &self,
+        db: &Database,
+        session_id: &str,
+        total_deadline: Duration,
+        op: &LifecycleOperationGuard<'_>,
+        final_status: &str,
+        failure_message: Option<String>,
+    ) -> Result<(), String> {
         let transitioned = db
             .transition_session_to_interrupting(session_id)
             .map_err(|err| format!("Failed to mark session interrupting: {}", err))?;
@@ -300,29 +638,37 @@ impl SessionSupervisor {
 
         let _ = self.request_interrupt_once(session_id).await;
         if self
-            .wait_for_runtime_exit(session_id, retry_deadline.min(deadline))
+            .wait_for_runtime_exit(session_id, retry_deadline.min(deadline), op)
             .await?
         {
             let _ = self
-                .finalize_terminal_transition(db, session_id, "interrupted", None)
+                .finalize_terminal_transition(db, session_id, final_status, failure_message.clone())
                 .await;
             let _ = self.remove(session_id).await;
             return Ok(());
         }
 
+        if op.is_preempted() {
+            return Err(interrupt_preempted_message());
+        }
+
         let _ = self.request_interrupt_once(session_id).await;
-        if self.wait_for_runtime_exit(session_id, deadline).await? {
+        if self.wait_for_runtime_exit(session_id, deadline, op).await? {
             let _ = self
-                .finalize_terminal_transition(db, session_id, "interrupted", None)
+                .finalize_terminal_transition(db, session_id, final_status, failure_message.clone())
                 .await;
             let _ = self.remove(session_id).await;
             return Ok(());
         }
 
+        if op.is_preempted() {
+            return Err(interrupt_preempted_message());
+        }
+
         db.update_session_status(session_id, "running")
             .map_err(|err| format!("Failed to restore session status after interrupt timeout: {}", err))?;
 
-        Err("Interrupt did not complete within 10 seconds".to_string())
+        Err(format!("Interrupt did not complete within {} seconds", total_deadline.as_secs()))
     }
 
     async fn request_interrupt_once(&self, session_id: &str) -> Result<(), String> {
@@ -346,9 +692,14 @@ impl SessionSupervisor {
             .map_err(|err| format!("Failed to interrupt session process: {}", err))
     }
 
-    async fn wait_for_runtime_exit(&self, session_id: &str, deadline: Instant) -> Result<bool, String> {
+    async fn wait_for_runtime_exit(
+        &self,
+        session_id: &str,
+        deadline: Instant,
+        op: &LifecycleOperationGuard<'_>,
+    ) -> Result<bool, String> {
         loop {
-            if Instant::now() >= deadline {
+            if Instant::now() >= deadline || op.is_preempted() {
                 return Ok(false);
             }
 
@@ -390,6 +741,22 @@ impl SessionSupervisor {
     }
 }
 
+fn snapshot_lifecycle_operation(
+    session_id: &str,
+    active: &ActiveLifecycleOperation,
+) -> LifecycleOperationSnapshot {
+    LifecycleOperationSnapshot {
+        session_id: session_id.to_string(),
+        operation: active.operation,
+        started_at: active.started_at.clone(),
+        elapsed_ms: active.started.elapsed().as_millis() as u64,
+    }
+}
+
+fn interrupt_preempted_message() -> String {
+    "Interrupt was pre-empted by kill".to_string()
+}
+
 impl Default for SessionSupervisor {
     fn default() -> Self {
         Self::new()
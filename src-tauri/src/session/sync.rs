@@ -0,0 +1,311 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::{Database, RepositorySettings};
+use crate::session::events::{SessionEvent, SessionEventPayload};
+use crate::session::hooks::record_lifecycle_event;
+
+pub const SYNC_STATUS_UP_TO_DATE: &str = "up_to_date";
+pub const SYNC_STATUS_SYNCED: &str = "synced";
+pub const SYNC_STATUS_CONFLICTED: &str = "conflicted";
+pub const SYNC_STATUS_ABORTED: &str = "aborted";
+pub const SYNC_STRATEGIES: [&str; 2] = ["rebase", "merge"];
+pub const SYNC_CONFLICT_POLICIES: [&str; 2] = ["abort", "leave"];
+
+/// How a session branch catches up with its base ref.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStrategy {
+    /// Replays the session's commits on top of the latest base.
+    #[default]
+    Rebase,
+    /// Merges the latest base into the session branch.
+    Merge,
+}
+
+impl SyncStrategy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "rebase" => Some(Self::Rebase),
+            "merge" => Some(Self::Merge),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Rebase => "rebase",
+            Self::Merge => "merge",
+        }
+    }
+}
+
+/// What a sync does when the session's changes conflict with the new base.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncConflictPolicy {
+    /// Aborts the rebase or merge, leaving the worktree as it was.
+    #[default]
+    Abort,
+    /// Leaves the conflict in the worktree for the next agent run to resolve.
+    Leave,
+}
+
+impl SyncConflictPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "abort" => Some(Self::Abort),
+            "leave" => Some(Self::Leave),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Abort => "abort",
+            Self::Leave => "leave",
+        }
+    }
+}
+
+/// The repository's configured sync strategy and conflict policy; unset or unknown values fall
+/// back to rebasing and aborting.
+pub fn configured_sync(settings: &RepositorySettings) -> (SyncStrategy, SyncConflictPolicy) {
+    (
+        settings.sync_strategy.as_deref().and_then(SyncStrategy::parse).unwrap_or_default(),
+        settings
+            .sync_on_conflict
+            .as_deref()
+            .and_then(SyncConflictPolicy::parse)
+            .unwrap_or_default(),
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncOutcome {
+    pub status: String,
+    pub strategy: SyncStrategy,
+    pub base_ref: String,
+    /// The session's base commit before the sync.
+    pub previous_base_sha: String,
+    /// The session's base commit after the sync. Unchanged when it was aborted, and while
+    /// conflicts left in the worktree are unresolved: the next sync records the new base once
+    /// the rebase or merge has been finished.
+    pub base_sha: String,
+    pub conflicts: Vec<String>,
+    /// For conflicts left in the worktree: what the next agent run has to do first.
+    pub resume_prompt: Option<String>,
+    pub summary: String,
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<Output, String> {
+    Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args.first().unwrap_or(&""), e))
+}
+
+fn git_ok(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = git(dir, args)?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn short_sha(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+/// Whether the worktree is in the middle of a rebase or merge.
+fn operation_in_progress(worktree_path: &Path) -> Result<bool, String> {
+    for marker in ["rebase-merge", "rebase-apply", "MERGE_HEAD"] {
+        let path = git_ok(worktree_path, &["rev-parse", "--git-path", marker])?;
+        if worktree_path.join(path).exists() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn unmerged_paths(worktree_path: &Path) -> Result<Vec<String>, String> {
+    let output = git_ok(worktree_path, &["diff", "--name-only", "--diff-filter=U"])?;
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+fn resume_prompt(
+    strategy: SyncStrategy,
+    base_ref: &str,
+    base_sha: &str,
+    conflicts: &[String],
+) -> String {
+    let files: Vec<String> = conflicts.iter().map(|path| format!("- {}", path)).collect();
+    let finish = match strategy {
+        SyncStrategy::Rebase => {
+            "stage them with `git add` and run `git rebase --continue`; later commits may \
+             conflict too, so repeat until the rebase finishes"
+        }
+        SyncStrategy::Merge => "stage them with `git add` and run `git commit --no-edit`",
+    };
+    format!(
+        "This worktree is in the middle of a {} onto {} ({}) that stopped with conflicts in:\n\
+         {}\n\nBefore anything else, resolve the conflict markers in these files, keeping both \
+         sides' intent, then {}. Afterwards carry on with the request below.",
+        strategy.as_str(),
+        base_ref,
+        short_sha(base_sha),
+        files.join("\n"),
+        finish
+    )
+}
+
+/// Brings the branch checked out in `worktree_path` up to date with the latest commit of
+/// `base_ref`. Uncommitted changes are stashed for the duration and put back afterwards. On
+/// conflict the rebase or merge is either aborted, restoring the worktree, or left in place
+/// with a resume prompt listing the conflicted files.
+pub fn sync_worktree(
+    worktree_path: &Path,
+    base_ref: &str,
+    previous_base_sha: &str,
+    strategy: SyncStrategy,
+    on_conflict: SyncConflictPolicy,
+) -> Result<SyncOutcome, String> {
+    if operation_in_progress(worktree_path)? {
+        return Err(
+            "The worktree has an unfinished rebase or merge; resolve it before syncing again"
+                .to_string(),
+        );
+    }
+
+    let target = format!("{}^{{commit}}", base_ref);
+    let base_sha = git_ok(worktree_path, &["rev-parse", "--verify", "--quiet", &target])
+        .map_err(|_| format!("Base ref '{}' no longer resolves to a commit", base_ref))?;
+    let mut outcome = SyncOutcome {
+        status: SYNC_STATUS_UP_TO_DATE.to_string(),
+        strategy,
+        base_ref: base_ref.to_string(),
+        previous_base_sha: previous_base_sha.to_string(),
+        base_sha: base_sha.clone(),
+        conflicts: Vec::new(),
+        resume_prompt: None,
+        summary: format!("already contains {} at {}", base_ref, short_sha(&base_sha)),
+    };
+
+    let contained = git(worktree_path, &["merge-base", "--is-ancestor", &base_sha, "HEAD"])?;
+    if contained.status.success() {
+        return Ok(outcome);
+    }
+
+    let message = format!("Sync with {}", base_ref);
+    let output = match strategy {
+        SyncStrategy::Rebase => git(worktree_path, &["rebase", "--autostash", &base_sha])?,
+        SyncStrategy::Merge => {
+            git(worktree_path, &["merge", "--autostash", "--no-edit", "-m", &message, &base_sha])?
+        }
+    };
+    if output.status.success() {
+        outcome.status = SYNC_STATUS_SYNCED.to_string();
+        outcome.summary = format!(
+            "{} onto {} at {}",
+            match strategy {
+                SyncStrategy::Rebase => "rebased",
+                SyncStrategy::Merge => "merged",
+            },
+            base_ref,
+            short_sha(&base_sha)
+        );
+        return Ok(outcome);
+    }
+
+    let conflicts = unmerged_paths(worktree_path)?;
+    if conflicts.is_empty() || on_conflict == SyncConflictPolicy::Abort {
+        let aborted = git(worktree_path, &[strategy.as_str(), "--abort"])?;
+        if conflicts.is_empty() {
+            return Err(format!(
+                "git {} failed: {}",
+                strategy.as_str(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        if !aborted.status.success() {
+            return Err(format!(
+                "git {} --abort failed: {}",
+                strategy.as_str(),
+                String::from_utf8_lossy(&aborted.stderr).trim()
+            ));
+        }
+
+        outcome.status = SYNC_STATUS_ABORTED.to_string();
+        outcome.base_sha = previous_base_sha.to_string();
+        outcome.summary = format!(
+            "{} onto {} aborted, conflicts in: {}",
+            strategy.as_str(),
+            base_ref,
+            conflicts.join(", ")
+        );
+        outcome.conflicts = conflicts;
+        return Ok(outcome);
+    }
+
+    outcome.status = SYNC_STATUS_CONFLICTED.to_string();
+    outcome.base_sha = previous_base_sha.to_string();
+    outcome.summary = format!(
+        "{} onto {} at {} left with conflicts in: {}",
+        strategy.as_str(),
+        base_ref,
+        short_sha(&base_sha),
+        conflicts.join(", ")
+    );
+    outcome.resume_prompt = Some(resume_prompt(strategy, base_ref, &base_sha, &conflicts));
+    outcome.conflicts = conflicts;
+    Ok(outcome)
+}
+
+/// Syncs a session's worktree onto its base ref, moves the session's recorded base along and
+/// appends the outcome to the session history.
+pub fn sync_session_worktree(
+    db: &Database,
+    session_id: &str,
+    strategy: SyncStrategy,
+    on_conflict: SyncConflictPolicy,
+) -> Result<(SyncOutcome, Vec<SessionEvent>), String> {
+    let worktree_path = db
+        .get_session_worktree_path(session_id)
+        .map_err(|e| format!("Failed to get session worktree path: {}", e))?
+        .ok_or_else(|| format!("Session {} has no worktree", session_id))?;
+    let base = db
+        .get_session_base(session_id)
+        .map_err(|e| format!("Failed to get session base commit: {}", e))?
+        .ok_or_else(|| format!("Session {} has no recorded base commit", session_id))?;
+
+    let outcome = sync_worktree(
+        Path::new(&worktree_path),
+        &base.base_ref,
+        &base.base_sha,
+        strategy,
+        on_conflict,
+    )?;
+    if outcome.base_sha != base.base_sha {
+        db.update_session_base(session_id, Some(&base.base_ref), Some(&outcome.base_sha))
+            .map_err(|e| format!("Failed to record the new session base: {}", e))?;
+    }
+
+    let payload = SessionEventPayload::WorktreeSync {
+        strategy: strategy.as_str().to_string(),
+        base_ref: outcome.base_ref.clone(),
+        base_sha: outcome.base_sha.clone(),
+        status: outcome.status.clone(),
+        conflicts: outcome.conflicts.clone(),
+        summary: outcome.summary.clone(),
+    };
+    let event = record_lifecycle_event(db, session_id, payload)?;
+    Ok((outcome, vec![event]))
+}
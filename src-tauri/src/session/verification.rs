@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use crate::db::Database;
+use crate::session::events::{SessionEvent, SessionEventPayload};
+use crate::session::hooks::{
+    record_lifecycle_event, record_lifecycle_events, run_shell_command_streaming, HookContext,
+};
+
+pub const VERIFICATION_RUNNING: &str = "running";
+pub const VERIFICATION_PASSED: &str = "passed";
+pub const VERIFICATION_FAILED: &str = "failed";
+pub const VERIFICATION_SKIPPED: &str = "skipped";
+pub const DEFAULT_VERIFICATION_TIMEOUT_MS: i64 = 10 * 60 * 1000;
+
+/// Runs the repository's verification command for the session's active run and records the
+/// outcome on that run. Output lines and the final result are appended to the session history
+/// and handed to `on_event` as they are recorded. Returns the stored status, or `None` when the
+/// session has no run to attach a result to.
+pub async fn run_session_verification<F>(
+    db: &Database,
+    session_id: &str,
+    mut on_event: F,
+) -> Result<Option<String>, String>
+where
+    F: FnMut(&SessionEvent) + Send,
+{
+    // Output is sequenced after the run's last streamed events, which may still be queued.
+    db.flush_writes().map_err(|e| format!("Failed to flush session events: {}", e))?;
+    let Some(context) = HookContext::load(db, session_id)? else {
+        return Ok(None);
+    };
+    let Some(run_id) = db
+        .get_session_run_metadata(session_id)
+        .map_err(|e| format!("Failed to load run metadata for verification: {}", e))?
+        .and_then(|metadata| metadata.active_run_id)
+    else {
+        return Ok(None);
+    };
+
+    let settings = match context.repo_root.as_deref() {
+        Some(repo_root) => db
+            .get_repository_settings(repo_root)
+            .map_err(|e| format!("Failed to load repository settings: {}", e))?,
+        None => None,
+    };
+    let command = settings
+        .as_ref()
+        .and_then(|settings| settings.verification_command.as_deref())
+        .map(str::trim)
+        .filter(|command| !command.is_empty());
+
+    let Some(command) = command else {
+        db.update_run_verification(session_id, &run_id, VERIFICATION_SKIPPED, None, None)
+            .map_err(|e| format!("Failed to record verification status: {}", e))?;
+        return Ok(Some(VERIFICATION_SKIPPED.to_string()));
+    };
+
+    db.update_run_verification(session_id, &run_id, VERIFICATION_RUNNING, Some(command), None)
+        .map_err(|e| format!("Failed to record verification status: {}", e))?;
+
+    let timeout_ms = settings
+        .as_ref()
+        .and_then(|settings| settings.verification_timeout_ms)
+        .unwrap_or(DEFAULT_VERIFICATION_TIMEOUT_MS)
+        .max(1);
+    let output = run_shell_command_streaming(
+        command,
+        context.execution_dir(),
+        &context.env_vars("verification"),
+        Duration::from_millis(timeout_ms as u64),
+        |lines| {
+            let payloads = lines
+                .into_iter()
+                .map(|(stream, line)| SessionEventPayload::VerificationOutput {
+                    stream: stream.to_string(),
+                    line,
+                })
+                .collect();
+            if let Ok(events) = record_lifecycle_events(db, session_id, payloads) {
+                events.iter().for_each(&mut on_event);
+            }
+        },
+    )
+    .await;
+
+    let status = if output.succeeded() { VERIFICATION_PASSED } else { VERIFICATION_FAILED };
+    db.update_run_verification(session_id, &run_id, status, Some(command), output.exit_code)
+        .map_err(|e| format!("Failed to record verification status: {}", e))?;
+
+    let event = record_lifecycle_event(
+        db,
+        session_id,
+        SessionEventPayload::Verification {
+            status: status.to_string(),
+            command: command.to_string(),
+            exit_code: output.exit_code,
+            timed_out: output.timed_out,
+            duration_ms: output.duration_ms,
+        },
+    )?;
+    on_event(&event);
+
+    Ok(Some(status.to_string()))
+}
@@ -0,0 +1,298 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::db::{Database, WebhookDelivery, WebhookTarget};
+use crate::session::projection::project_dashboard_row;
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use uuid::Uuid;
+
+pub const WEBHOOK_EVENTS: [&str; 4] = ["completed", "failed", "stalled", "cost_threshold"];
+pub const DEFAULT_WEBHOOK_MAX_ATTEMPTS: i64 = 4;
+pub const DEFAULT_WEBHOOK_RETRY_BASE: Duration = Duration::from_secs(2);
+pub const WEBHOOK_STALL_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Lulu-Signature-256";
+pub const WEBHOOK_EVENT_HEADER: &str = "X-Lulu-Event";
+pub const WEBHOOK_DELIVERY_HEADER: &str = "X-Lulu-Delivery";
+const WEBHOOK_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn is_webhook_event(event: &str) -> bool {
+    WEBHOOK_EVENTS.contains(&event)
+}
+
+/// Maps a terminal session status onto the webhook event it reports, if any.
+pub fn webhook_event_for_status(status: &str) -> Option<&'static str> {
+    match status {
+        "completed" => Some("completed"),
+        "failed" | "killed" => Some("failed"),
+        _ => None,
+    }
+}
+
+/// GitHub-style `sha256=<hex>` HMAC of the exact request body.
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+pub fn build_webhook_payload(
+    db: &Database,
+    session_id: &str,
+    event: &str,
+    details: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let row = db
+        .get_dashboard_session(session_id)
+        .map_err(|e| format!("Failed to load session for webhook: {}", e))?
+        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+
+    Ok(json!({
+        "event": event,
+        "session_id": session_id,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "session": project_dashboard_row(row),
+        "details": details,
+    }))
+}
+
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    client: reqwest::Client,
+    retry_base: Duration,
+    /// `(target, session)` pairs whose `cost_threshold` delivery has started. Checks run once
+    /// per usage report, and the delivery log only shows a delivery after its first attempt.
+    cost_threshold_claims: Arc<Mutex<HashSet<(String, String)>>>,
+}
+
+impl Default for WebhookDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookDispatcher {
+    pub fn new() -> Self {
+        Self::with_retry_base(DEFAULT_WEBHOOK_RETRY_BASE)
+    }
+
+    pub fn with_retry_base(retry_base: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+
+        Self { client, retry_base, cost_threshold_claims: Arc::default() }
+    }
+
+    /// Posts `payload` to one target, retrying with exponential backoff until a 2xx response
+    /// or `max_attempts` is reached. Every attempt is written to the delivery log.
+    pub async fn deliver(
+        &self,
+        db: &Database,
+        target: &WebhookTarget,
+        session_id: &str,
+        event: &str,
+        payload: &serde_json::Value,
+    ) -> Result<bool, String> {
+        let body = serde_json::to_vec(payload)
+            .map_err(|e| format!("Failed to serialize webhook payload: {}", e))?;
+        let signature = target
+            .secret
+            .as_deref()
+            .map(|secret| sign_payload(secret, &body));
+        let delivery_id = Uuid::new_v4().to_string();
+        let max_attempts = target.max_attempts.max(1);
+
+        for attempt in 1..=max_attempts {
+            let mut request = self
+                .client
+                .post(&target.url)
+                .header("Content-Type", "application/json")
+                .header(WEBHOOK_EVENT_HEADER, event)
+                .header(WEBHOOK_DELIVERY_HEADER, &delivery_id)
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header(WEBHOOK_SIGNATURE_HEADER, signature);
+            }
+
+            let (status_code, success, error) = match request.send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    let error = (!status.is_success()).then(|| format!("HTTP {}", status));
+                    (Some(status.as_u16() as i64), status.is_success(), error)
+                }
+                Err(err) => (None, false, Some(err.to_string())),
+            };
+
+            db.insert_webhook_delivery(&WebhookDelivery {
+                id: Uuid::new_v4().to_string(),
+                target_id: target.id.clone(),
+                session_id: session_id.to_string(),
+                event: event.to_string(),
+                attempt,
+                status_code,
+                success,
+                error,
+                payload_json: payload.clone(),
+                created_at: chrono::Utc::now().to_rfc3339(),
+            })
+            .map_err(|e| format!("Failed to record webhook delivery: {}", e))?;
+
+            if success {
+                return Ok(true);
+            }
+
+            if attempt < max_attempts {
+                tokio::time::sleep(self.retry_base * 2u32.pow((attempt - 1) as u32)).await;
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Sends `event` for a session to every enabled target subscribed to it. Returns how many
+    /// targets accepted the delivery.
+    pub async fn notify(
+        &self,
+        db: &Database,
+        session_id: &str,
+        event: &str,
+        details: serde_json::Value,
+    ) -> Result<usize, String> {
+        let targets = db
+            .list_webhook_targets_for_event(event)
+            .map_err(|e| format!("Failed to load webhook targets: {}", e))?;
+        if targets.is_empty() {
+            return Ok(0);
+        }
+
+        let payload = build_webhook_payload(db, session_id, event, details)?;
+        let mut delivered = 0;
+        for target in &targets {
+            if self.deliver(db, target, session_id, event, &payload).await? {
+                delivered += 1;
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// Fires `cost_threshold` once per target and session, the first time the session's
+    /// total cost reaches the target's threshold.
+    pub async fn notify_cost_threshold(&self, db: &Database, session_id: &str) -> Result<usize, String> {
+        let targets = db
+            .list_webhook_targets_for_event("cost_threshold")
+            .map_err(|e| format!("Failed to load webhook targets: {}", e))?;
+        if targets.is_empty() {
+            return Ok(0);
+        }
+
+        let usage = db
+            .get_session_usage(session_id)
+            .map_err(|e| format!("Failed to load session usage: {}", e))?;
+        let Some(cost_usd) = usage.cost_usd else {
+            return Ok(0);
+        };
+
+        let mut delivered = 0;
+        for target in &targets {
+            let Some(threshold) = target.cost_threshold_usd else {
+                continue;
+            };
+            if cost_usd < threshold
+                || db
+                    .has_webhook_delivery(&target.id, session_id, "cost_threshold", None)
+                    .map_err(|e| format!("Failed to read webhook deliveries: {}", e))?
+                || !self.claim_cost_threshold(&target.id, session_id)
+            {
+                continue;
+            }
+
+            let payload = build_webhook_payload(
+                db,
+                session_id,
+                "cost_threshold",
+                json!({ "threshold_usd": threshold, "cost_usd": cost_usd }),
+            )?;
+            if self
+                .deliver(db, target, session_id, "cost_threshold", &payload)
+                .await?
+            {
+                delivered += 1;
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// Returns true for the first caller only, so concurrent checks deliver once.
+    fn claim_cost_threshold(&self, target_id: &str, session_id: &str) -> bool {
+        self.cost_threshold_claims
+            .lock()
+            .map(|mut claims| claims.insert((target_id.to_string(), session_id.to_string())))
+            .unwrap_or(false)
+    }
+
+    /// Fires `stalled` for running sessions with no activity for longer than a target's
+    /// `stall_after_secs`. Each quiet stretch is reported once per target.
+    pub async fn notify_stalled(
+        &self,
+        db: &Database,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<usize, String> {
+        let targets = db
+            .list_webhook_targets_for_event("stalled")
+            .map_err(|e| format!("Failed to load webhook targets: {}", e))?;
+        if targets.is_empty() {
+            return Ok(0);
+        }
+
+        let sessions = db
+            .list_dashboard_sessions()
+            .map_err(|e| format!("Failed to load sessions: {}", e))?;
+
+        let mut delivered = 0;
+        for session in sessions.into_iter().filter(|row| row.status == "running") {
+            let last_activity = session
+                .last_activity_at
+                .clone()
+                .unwrap_or_else(|| session.created_at.clone());
+            let Ok(last_activity_at) = chrono::DateTime::parse_from_rfc3339(&last_activity) else {
+                continue;
+            };
+            let idle_secs = (now - last_activity_at.with_timezone(&chrono::Utc)).num_seconds();
+
+            for target in &targets {
+                let Some(stall_after_secs) = target.stall_after_secs else {
+                    continue;
+                };
+                if idle_secs < stall_after_secs
+                    || db
+                        .has_webhook_delivery(&target.id, &session.id, "stalled", Some(&last_activity))
+                        .map_err(|e| format!("Failed to read webhook deliveries: {}", e))?
+                {
+                    continue;
+                }
+
+                let payload = build_webhook_payload(
+                    db,
+                    &session.id,
+                    "stalled",
+                    json!({ "idle_secs": idle_secs, "last_activity_at": last_activity }),
+                )?;
+                if self
+                    .deliver(db, target, &session.id, "stalled", &payload)
+                    .await?
+                {
+                    delivered += 1;
+                }
+            }
+        }
+
+        Ok(delivered)
+    }
+}
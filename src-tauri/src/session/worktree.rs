@@ -1,6 +1,165 @@
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::{collections::HashSet, fs};
+use std::fs;
+
+use sha2::{Digest, Sha256};
+
+use crate::session::diff::{diff_worktree, DiffLimits, WorktreeDiff};
+
+pub const DEFAULT_BRANCH_TEMPLATE: &str = "lulu/{session-name-slug}-{short-id}";
+const BRANCH_SLUG_MAX_LEN: usize = 40;
+pub const WORKTREE_LOCATIONS: [&str; 3] = ["in_repo", "sibling", "app_data"];
+
+/// Where a repository keeps its session worktrees.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WorktreeLocation {
+    /// `<repo>/.lulu/worktrees`, hidden from git through `.git/info/exclude`.
+    #[default]
+    InRepo,
+    /// `<repo>.lulu-worktrees` next to the repository.
+    Sibling,
+    /// `<app data>/worktrees/<repo name>-<hash of repo root>`.
+    AppData,
+}
+
+impl WorktreeLocation {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "in_repo" => Some(Self::InRepo),
+            "sibling" => Some(Self::Sibling),
+            "app_data" => Some(Self::AppData),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InRepo => "in_repo",
+            Self::Sibling => "sibling",
+            Self::AppData => "app_data",
+        }
+    }
+}
+
+/// App data directory, managed as app state so commands can place `AppData` worktrees.
+#[derive(Debug, Clone)]
+pub struct AppDataDir(pub PathBuf);
+
+fn repository_name(repo_root: &Path) -> String {
+    repo_root
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "repository".to_string())
+}
+
+/// Directory that holds a repository's session worktrees for `location`.
+pub fn worktrees_root_for(
+    repo_root: &Path,
+    location: WorktreeLocation,
+    app_data_dir: &Path,
+) -> PathBuf {
+    match location {
+        WorktreeLocation::InRepo => repo_root.join(".lulu").join("worktrees"),
+        WorktreeLocation::Sibling => {
+            let parent = repo_root.parent().unwrap_or(repo_root);
+            parent.join(format!("{}.lulu-worktrees", repository_name(repo_root)))
+        }
+        WorktreeLocation::AppData => {
+            // The hash keeps two checkouts with the same directory name apart.
+            let digest = Sha256::digest(repo_root.display().to_string().as_bytes());
+            let key = format!("{}-{}", repository_name(repo_root), &hex::encode(digest)[..16]);
+            app_data_dir.join("worktrees").join(key)
+        }
+    }
+}
+
+/// Lowercase, dash-separated form of a session name that is safe inside a branch name.
+pub fn slugify_session_name(name: &str) -> String {
+    let mut slug = String::new();
+    for ch in name.chars().flat_map(char::to_lowercase) {
+        if ch.is_ascii_alphanumeric() {
+            slug.push(ch);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let mut slug: String = slug.chars().take(BRANCH_SLUG_MAX_LEN).collect();
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+
+    if slug.is_empty() {
+        "session".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Expands a branch template. Supported placeholders: `{session-name-slug}`, `{short-id}`
+/// (first 8 characters of the session id), `{session-id}` and `{date}` (UTC, `YYYYMMDD`).
+pub fn render_branch_name(template: &str, session_name: &str, session_id: &str) -> String {
+    let short_id: String = session_id.chars().take(8).collect();
+    template
+        .replace("{session-name-slug}", &slugify_session_name(session_name))
+        .replace("{short-id}", &short_id)
+        .replace("{session-id}", session_id)
+        .replace("{date}", &chrono::Utc::now().format("%Y%m%d").to_string())
+}
+
+/// Records the full state of a worktree (tracked edits and untracked, non-ignored files) as a
+/// commit on top of its HEAD without touching the branch, the index or any file. Returns `None`
+/// when the worktree has nothing beyond HEAD. The commit stays unreferenced unless the caller
+/// points a ref at it.
+pub fn snapshot_worktree(worktree_path: &Path, message: &str) -> Result<Option<String>, String> {
+    let index_path =
+        std::env::temp_dir().join(format!("lulu-snapshot-index-{}", uuid::Uuid::new_v4()));
+    let git = |args: &[&str]| -> Result<String, String> {
+        let output = Command::new("git")
+            .args(args)
+            .env("GIT_INDEX_FILE", &index_path)
+            .current_dir(worktree_path)
+            .output()
+            .map_err(|e| format!("Failed to run git {}: {}", args[0], e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "git {} failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    let result = (|| {
+        git(&["read-tree", "HEAD"])?;
+        git(&["add", "-A"])?;
+        let tree = git(&["write-tree"])?;
+        if tree == git(&["rev-parse", "HEAD^{tree}"])? {
+            return Ok(None);
+        }
+        git(&["commit-tree", &tree, "-p", "HEAD", "-m", message]).map(Some)
+    })();
+
+    let _ = fs::remove_file(&index_path);
+    result
+}
+
+/// Checks a rendered branch name with `git check-ref-format --branch`.
+pub fn validate_branch_name(branch: &str) -> Result<(), String> {
+    let output = Command::new("git")
+        .arg("check-ref-format")
+        .arg("--branch")
+        .arg(branch)
+        .output()
+        .map_err(|e| format!("Failed to run git check-ref-format: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!("Invalid branch name '{}'", branch));
+    }
+
+    Ok(())
+}
 
 #[derive(Debug, Clone)]
 pub struct WorktreeEntry {
@@ -12,6 +171,7 @@ pub struct WorktreeEntry {
 pub struct WorktreeService {
     repo_root: PathBuf,
     worktrees_root: PathBuf,
+    skip_lfs_smudge: bool,
 }
 
 impl WorktreeService {
@@ -37,12 +197,127 @@ impl WorktreeService {
     pub fn new(repo_root: impl AsRef<Path>) -> Self {
         let repo_root = repo_root.as_ref().to_path_buf();
         let worktrees_root = repo_root.join(".lulu").join("worktrees");
-        Self { repo_root, worktrees_root }
+        Self { repo_root, worktrees_root, skip_lfs_smudge: false }
+    }
+
+    /// Service whose session worktrees live in `location` instead of the in-repo default.
+    pub fn with_location(
+        repo_root: impl AsRef<Path>,
+        location: WorktreeLocation,
+        app_data_dir: &Path,
+    ) -> Self {
+        let repo_root = repo_root.as_ref().to_path_buf();
+        let worktrees_root = worktrees_root_for(&repo_root, location, app_data_dir);
+        Self { repo_root, worktrees_root, skip_lfs_smudge: false }
+    }
+
+    /// Leaves Git LFS files as pointers when creating worktrees instead of downloading them;
+    /// provisioning checks them out from the local LFS cache.
+    pub fn with_lfs_smudge_skipped(mut self, skip: bool) -> Self {
+        self.skip_lfs_smudge = skip;
+        self
+    }
+
+    /// Adds the top-level directory of an in-repo worktrees root (`/.lulu/`) to
+    /// `.git/info/exclude`, so session worktrees never show up as untracked files. Roots outside
+    /// the repository need no entry.
+    pub fn ensure_worktrees_root_excluded(&self) -> Result<(), String> {
+        let Ok(relative) = self.worktrees_root.strip_prefix(&self.repo_root) else {
+            return Ok(());
+        };
+        let Some(top) = relative.components().next() else {
+            return Ok(());
+        };
+        let top = top.as_os_str().to_string_lossy();
+        let entry = format!("/{}/", top);
+
+        let output = Command::new("git")
+            .arg("rev-parse")
+            .arg("--git-path")
+            .arg("info/exclude")
+            .current_dir(&self.repo_root)
+            .output()
+            .map_err(|e| format!("Failed to run git rev-parse: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "git rev-parse --git-path failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let exclude_path = self.repo_root.join(String::from_utf8_lossy(&output.stdout).trim());
+
+        let existing = fs::read_to_string(&exclude_path).unwrap_or_default();
+        let already_excluded = existing.lines().map(str::trim).any(|line| {
+            line == entry || line == entry.trim_end_matches('/') || line == &entry[1..]
+        });
+        if already_excluded {
+            return Ok(());
+        }
+
+        if let Some(parent) = exclude_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create git info directory: {}", e))?;
+        }
+        let mut contents = existing;
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push_str(&entry);
+        contents.push('\n');
+        fs::write(&exclude_path, contents)
+            .map_err(|e| format!("Failed to update {}: {}", exclude_path.display(), e))
     }
 
-    pub fn create_worktree(&self, session_id: &str) -> Result<PathBuf, String> {
+    /// Moves an existing session worktree into this service's worktrees root with
+    /// `git worktree move`, returning its new path. A worktree already in place is left alone.
+    pub fn relocate_worktree(&self, current: &Path, session_id: &str) -> Result<PathBuf, String> {
+        let destination = self.worktrees_root.join(session_id);
+        if current == destination {
+            return Ok(destination);
+        }
+        if destination.exists() {
+            return Err(format!("Destination already exists: {}", destination.display()));
+        }
+
+        fs::create_dir_all(&self.worktrees_root)
+            .map_err(|e| format!("Failed to create worktrees root: {}", e))?;
+        self.ensure_worktrees_root_excluded()?;
+
+        let output = Command::new("git")
+            .arg("worktree")
+            .arg("move")
+            .arg(current)
+            .arg(&destination)
+            .current_dir(&self.repo_root)
+            .output()
+            .map_err(|e| format!("Failed to run git worktree move: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "git worktree move failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(destination)
+    }
+
+    /// Creates the session worktree on a new branch `branch` starting at the repository HEAD.
+    pub fn create_worktree(&self, session_id: &str, branch: &str) -> Result<PathBuf, String> {
+        let head = self.resolve_commit("HEAD")?;
+        self.create_worktree_at(session_id, branch, &head)
+    }
+
+    /// Creates the session worktree on a new branch `branch` starting at `base_commit`.
+    pub fn create_worktree_at(
+        &self,
+        session_id: &str,
+        branch: &str,
+        base_commit: &str,
+    ) -> Result<PathBuf, String> {
         std::fs::create_dir_all(&self.worktrees_root)
             .map_err(|e| format!("Failed to create worktrees root: {}", e))?;
+        self.ensure_worktrees_root_excluded()?;
 
         let worktree_path = self.worktrees_root.join(session_id);
 
@@ -50,14 +325,20 @@ impl WorktreeService {
             self.remove_worktree_at_path(&worktree_path, true)?;
         }
 
-        let output = Command::new("git")
+        let mut command = Command::new("git");
+        command
             .arg("worktree")
             .arg("add")
-            .arg("--detach")
+            .arg("-b")
+            .arg(branch)
             .arg(&worktree_path)
-            .current_dir(&self.repo_root)
-            .output()
-            .map_err(|e| format!("Failed to run git worktree add: {}", e))?;
+            .arg(base_commit)
+            .current_dir(&self.repo_root);
+        if self.skip_lfs_smudge {
+            command.env("GIT_LFS_SKIP_SMUDGE", "1");
+        }
+        let output =
+            command.output().map_err(|e| format!("Failed to run git worktree add: {}", e))?;
 
         if !output.status.success() {
             return Err(format!(
@@ -69,6 +350,95 @@ impl WorktreeService {
         Ok(worktree_path)
     }
 
+    /// Resolves a branch, tag or SHA to the full commit id it points at.
+    pub fn resolve_commit(&self, git_ref: &str) -> Result<String, String> {
+        if git_ref.is_empty() || git_ref.starts_with('-') {
+            return Err(format!("Invalid base ref '{}'", git_ref));
+        }
+
+        let output = Command::new("git")
+            .arg("rev-parse")
+            .arg("--verify")
+            .arg("--quiet")
+            .arg(format!("{}^{{commit}}", git_ref))
+            .current_dir(&self.repo_root)
+            .output()
+            .map_err(|e| format!("Failed to run git rev-parse: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!("Base ref '{}' does not resolve to a commit", git_ref));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Name of the branch checked out in the main worktree, or `HEAD` when detached.
+    pub fn current_ref(&self) -> Result<String, String> {
+        let output = Command::new("git")
+            .arg("rev-parse")
+            .arg("--abbrev-ref")
+            .arg("HEAD")
+            .current_dir(&self.repo_root)
+            .output()
+            .map_err(|e| format!("Failed to run git rev-parse: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "git rev-parse --abbrev-ref HEAD failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    pub fn branch_exists(&self, branch: &str) -> bool {
+        Command::new("git")
+            .arg("rev-parse")
+            .arg("--verify")
+            .arg("--quiet")
+            .arg(format!("refs/heads/{}", branch))
+            .current_dir(&self.repo_root)
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    /// Picks `branch`, or `branch-2`, `branch-3`, ... if that name is already taken.
+    pub fn unique_branch_name(&self, branch: &str) -> String {
+        if !self.branch_exists(branch) {
+            return branch.to_string();
+        }
+
+        (2..)
+            .map(|suffix| format!("{}-{}", branch, suffix))
+            .find(|candidate| !self.branch_exists(candidate))
+            .unwrap_or_else(|| branch.to_string())
+    }
+
+    pub fn delete_branch(&self, branch: &str) -> Result<(), String> {
+        if !self.branch_exists(branch) {
+            return Ok(());
+        }
+
+        let output = Command::new("git")
+            .arg("branch")
+            .arg("-D")
+            .arg(branch)
+            .current_dir(&self.repo_root)
+            .output()
+            .map_err(|e| format!("Failed to run git branch -D: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "git branch -D failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(())
+    }
+
     pub fn remove_worktree_for_session(&self, session_id: &str) -> Result<(), String> {
         let path = self.worktrees_root.join(session_id);
         self.remove_worktree_at_path(&path, true)
@@ -164,30 +534,22 @@ impl WorktreeService {
         Ok(entries)
     }
 
-    pub fn reconcile_managed_worktrees(&self, expected_paths: &[PathBuf]) -> Result<(), String> {
-        let expected: HashSet<PathBuf> = expected_paths.iter().cloned().collect();
-        let worktrees = self.list_worktrees()?;
-
-        for entry in worktrees {
-            if !entry.path.starts_with(&self.worktrees_root) {
-                continue;
-            }
-
-            let is_expected = expected.contains(&entry.path);
-            if is_expected && !entry.prunable && entry.path.exists() {
-                continue;
-            }
+    pub fn worktrees_root(&self) -> &Path {
+        &self.worktrees_root
+    }
 
-            if entry.path.exists() {
-                let _ = fs::remove_dir_all(&entry.path);
-            }
+    /// Changes in a session worktree relative to the commit the session started from.
+    pub fn diff_against_base(
+        &self,
+        worktree_path: &Path,
+        base_sha: &str,
+        limits: DiffLimits,
+    ) -> Result<WorktreeDiff, String> {
+        if !worktree_path.exists() {
+            return Err(format!("Worktree does not exist: {}", worktree_path.display()));
         }
 
-        self.prune_worktrees()
-    }
-
-    pub fn worktrees_root(&self) -> &Path {
-        &self.worktrees_root
+        diff_worktree(worktree_path, base_sha, limits)
     }
 
     pub fn repo_root(&self) -> &Path {
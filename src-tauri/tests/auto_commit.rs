@@ -0,0 +1,94 @@
+use tauri_app_lib::db::{init_database, Database, RepositorySettings, Session};
+use tauri_app_lib::session::auto_commit::{auto_commit_session, build_auto_commit_message};
+use tauri_app_lib::session::WorktreeService;
+
+mod common;
+
+use common::{git, init_repo};
+
+fn completed_session_with_worktree(db: &Database, repo_root: &str, id: &str) -> std::path::PathBuf {
+    let service = WorktreeService::new(repo_root);
+    let worktree =
+        service.create_worktree(id, &format!("lulu/{}", id)).expect("worktree should create");
+
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: "Add greeting".to_string(),
+        status: "running".to_string(),
+        working_dir: repo_root.to_string(),
+        created_at: now.clone(),
+        updated_at: now.clone(),
+    })
+    .expect("session should persist");
+    db.update_worktree_path(id, Some(&worktree.display().to_string()))
+        .expect("worktree path should persist");
+    db.begin_run_attempt(id, "run-1").expect("run should begin");
+    let later = (chrono::Utc::now() + chrono::Duration::seconds(1)).to_rfc3339();
+    db.insert_session_message(id, "assistant", "Working on it", &now)
+        .expect("message should persist");
+    db.insert_session_message(id, "assistant", "Added hello.txt with a greeting.", &later)
+        .expect("message should persist");
+    db.update_session_status(id, "completed").expect("status should update");
+
+    worktree
+}
+
+#[test]
+fn auto_commit_message_uses_name_final_message_and_trailer() {
+    assert_eq!(
+        build_auto_commit_message("Fix login\nextra", Some("  Done.  "), "Lulu-Session: abc"),
+        "Fix login\n\nDone.\n\nLulu-Session: abc\n"
+    );
+    assert_eq!(build_auto_commit_message("  ", None, ""), "Lulu session\n");
+}
+
+#[test]
+fn completed_session_changes_are_committed_when_repository_opts_in() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    let worktree = completed_session_with_worktree(&db, &repo_root, "commit-session");
+    std::fs::write(worktree.join("hello.txt"), "hello\n").expect("file should write");
+
+    assert_eq!(
+        auto_commit_session(&db, "commit-session").expect("auto-commit should run"),
+        None,
+        "auto-commit is opt-in"
+    );
+    assert_eq!(git(&worktree, &["status", "--porcelain"]), "?? hello.txt");
+
+    db.save_repository_settings(&RepositorySettings {
+        repo_root: repo_root.clone(),
+        auto_commit: true,
+        auto_commit_trailer: Some("Agent-Session: {session-id}".to_string()),
+        ..Default::default()
+    })
+    .expect("settings should save");
+
+    let commit_sha = auto_commit_session(&db, "commit-session")
+        .expect("auto-commit should run")
+        .expect("changes should be committed");
+    assert_eq!(git(&worktree, &["rev-parse", "HEAD"]), commit_sha);
+    assert_eq!(git(&worktree, &["status", "--porcelain"]), "");
+    assert_eq!(
+        git(&worktree, &["log", "-1", "--format=%B"]),
+        "Add greeting\n\nAdded hello.txt with a greeting.\n\nAgent-Session: commit-session"
+    );
+
+    let run = db
+        .get_session_run("commit-session", "run-1")
+        .expect("run read should succeed")
+        .expect("run should exist");
+    assert_eq!(run.commit_sha.as_deref(), Some(commit_sha.as_str()));
+    let row = db
+        .get_dashboard_session("commit-session")
+        .expect("dashboard read should succeed")
+        .expect("row should exist");
+    assert_eq!(row.commit_sha.as_deref(), Some(commit_sha.as_str()));
+
+    assert_eq!(
+        auto_commit_session(&db, "commit-session").expect("auto-commit should run"),
+        None,
+        "a clean worktree has nothing to commit"
+    );
+}
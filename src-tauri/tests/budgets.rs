@@ -0,0 +1,247 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tempfile::tempdir;
+use tokio::time::{timeout, Duration};
+
+use tauri_app_lib::db::usage::usage_day;
+use tauri_app_lib::db::{init_database, Budget, Database, Session};
+use tauri_app_lib::session::budgets::{
+    budget_block_reason, check_budgets, enforce_budgets, session_budget_id, BUDGET_EXCEEDED_REASON,
+    GLOBAL_BUDGET_ID,
+};
+use tauri_app_lib::session::{ClaudeCli, SessionEventPayload, SessionSupervisor};
+
+fn create_running_session(db: &Database, id: &str) {
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: format!("{} name", id),
+        status: "running".to_string(),
+        working_dir: std::env::temp_dir().display().to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+    db.begin_run_attempt(id, "run-1").expect("run should begin");
+}
+
+fn budget(
+    id: String,
+    scope: &str,
+    session_id: Option<&str>,
+    unit: &str,
+    soft: Option<f64>,
+    hard: f64,
+) -> Budget {
+    Budget {
+        id,
+        scope: scope.to_string(),
+        session_id: session_id.map(str::to_string),
+        unit: unit.to_string(),
+        soft_limit: soft,
+        hard_limit: hard,
+        updated_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+#[test]
+fn daily_usage_counts_only_new_usage_from_each_report() {
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&db_dir.path().join("lulu.db")).expect("database should initialize");
+
+    create_running_session(&db, "first");
+    create_running_session(&db, "second");
+
+    db.record_run_usage("first", "run-1", 100, 10, Some(0.10)).expect("usage should persist");
+    db.record_run_usage("first", "run-1", 150, 20, Some(0.25)).expect("usage should persist");
+    db.record_run_usage("first", "run-1", 150, 20, Some(0.25)).expect("repeat should persist");
+    db.record_run_usage("second", "run-1", 50, 5, Some(0.05)).expect("usage should persist");
+
+    let today =
+        db.get_daily_usage(&usage_day(chrono::Utc::now())).expect("daily usage should load");
+    assert_eq!(today.input_tokens, 200);
+    assert_eq!(today.output_tokens, 25);
+    assert!((today.cost_usd.expect("cost should be tracked") - 0.30).abs() < 1e-9);
+
+    let yesterday = db
+        .get_daily_usage(&usage_day(chrono::Utc::now() - chrono::Duration::days(1)))
+        .expect("daily usage should load");
+    assert_eq!(yesterday.total_tokens(), 0);
+}
+
+#[test]
+fn soft_warning_is_raised_once_and_survives_restart() {
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db_path = db_dir.path().join("lulu.db");
+    let db = init_database(&db_path).expect("database should initialize");
+
+    create_running_session(&db, "capped");
+    db.save_budget(&budget(
+        session_budget_id("capped"),
+        "session",
+        Some("capped"),
+        "usd",
+        Some(0.5),
+        1.0,
+    ))
+    .expect("budget should persist");
+
+    db.record_run_usage("capped", "run-1", 10, 10, Some(0.6)).expect("usage should persist");
+    let now = chrono::Utc::now();
+    let check = check_budgets(&db, "capped", now).expect("check should succeed");
+    assert_eq!(check.warnings.len(), 1);
+    assert!(check.exceeded.is_none());
+    assert!(check_budgets(&db, "capped", now).expect("check should succeed").warnings.is_empty());
+
+    drop(db);
+    let db = init_database(&db_path).expect("database should reopen");
+    assert!(
+        check_budgets(&db, "capped", now).expect("check should succeed").warnings.is_empty(),
+        "the warning should not repeat after a restart"
+    );
+
+    db.record_run_usage("capped", "run-1", 10, 10, Some(1.2)).expect("usage should persist");
+    let check = check_budgets(&db, "capped", now).expect("check should succeed");
+    let exceeded = check.exceeded.expect("hard limit should be reported");
+    assert_eq!(exceeded.budget.id, session_budget_id("capped"));
+    assert!((exceeded.spent - 1.2).abs() < 1e-9);
+    assert!(budget_block_reason(&db, Some("capped"), now)
+        .expect("block check should succeed")
+        .is_some_and(|reason| reason.starts_with("Session budget exceeded")));
+
+    db.save_budget(&budget(
+        session_budget_id("capped"),
+        "session",
+        Some("capped"),
+        "usd",
+        Some(1.5),
+        5.0,
+    ))
+    .expect("raised budget should persist");
+    assert!(check_budgets(&db, "capped", now).expect("check should succeed").exceeded.is_none());
+    assert!(budget_block_reason(&db, Some("capped"), now)
+        .expect("block check should succeed")
+        .is_none());
+}
+
+#[test]
+fn global_token_budget_blocks_new_work_for_the_day() {
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&db_dir.path().join("lulu.db")).expect("database should initialize");
+
+    create_running_session(&db, "a");
+    create_running_session(&db, "b");
+    db.save_budget(&budget(GLOBAL_BUDGET_ID.to_string(), "global", None, "tokens", None, 1_000.0))
+        .expect("budget should persist");
+
+    let now = chrono::Utc::now();
+    db.record_run_usage("a", "run-1", 400, 100, None).expect("usage should persist");
+    assert!(budget_block_reason(&db, None, now).expect("block check should succeed").is_none());
+
+    db.record_run_usage("b", "run-1", 400, 100, None).expect("usage should persist");
+    let check = check_budgets(&db, "a", now).expect("check should succeed");
+    assert_eq!(
+        check.exceeded.map(|reading| reading.budget.id),
+        Some(GLOBAL_BUDGET_ID.to_string()),
+        "every session sharing the day's budget should see it exceeded"
+    );
+    assert_eq!(
+        budget_block_reason(&db, None, now).expect("block check should succeed").as_deref(),
+        Some("Global daily budget exceeded: 1000 tokens of 1000 tokens used")
+    );
+
+    let tomorrow = now + chrono::Duration::days(1);
+    assert!(budget_block_reason(&db, None, tomorrow)
+        .expect("block check should succeed")
+        .is_none());
+}
+
+#[tokio::test]
+async fn assistant_usage_stops_run_over_budget_as_failed_budget_exceeded() {
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = Arc::new(
+        init_database(&db_dir.path().join("lulu.db")).expect("database should initialize"),
+    );
+    let supervisor = Arc::new(SessionSupervisor::new());
+    create_running_session(&db, "spender");
+    db.save_budget(&budget(
+        session_budget_id("spender"),
+        "session",
+        Some("spender"),
+        "tokens",
+        Some(2_000.0),
+        4_000.0,
+    ))
+    .expect("budget should persist");
+
+    // Each turn reports 1,500 tokens; the run would take well over ten seconds to finish.
+    let cli = ClaudeCli::find_with_override(Some(PathBuf::from(env!("CARGO_BIN_EXE_lulu_test_cli"))))
+        .expect("fixture cli should resolve");
+    let (event_tx, mut event_rx) = tokio::sync::mpsc::channel(128);
+    let spawned = cli
+        .spawn_with_events(
+            "turns=100 turn-delay-ms=150",
+            &db_dir.path().display().to_string(),
+            "spender",
+            event_tx,
+        )
+        .await
+        .expect("fixture cli should spawn");
+    let runtime =
+        supervisor.register("spender".to_string(), "spender".to_string(), spawned.child).await;
+    db.register_run_sequence("spender", "run-1", spawned.seq.clone());
+    let mut transitions = supervisor.subscribe_terminal_transitions();
+
+    let mut last_input_tokens = 0;
+    let mut stop = None;
+    while let Ok(Some(event)) = timeout(Duration::from_secs(10), event_rx.recv()).await {
+        let SessionEventPayload::Usage { input_tokens, output_tokens, cost_usd } = event.payload
+        else {
+            continue;
+        };
+        assert!(cost_usd.is_some(), "turns of a known model should carry a cost estimate");
+        last_input_tokens = input_tokens;
+        db.record_run_usage("spender", "run-1", input_tokens, output_tokens, cost_usd)
+            .expect("usage should persist");
+
+        let enforcement =
+            enforce_budgets(&db, &supervisor, "spender").await.expect("enforcement should run");
+        assert!(enforcement.errors.is_empty(), "{:?}", enforcement.errors);
+        if enforcement.stop {
+            let (db, supervisor) = (db.clone(), supervisor.clone());
+            stop = Some(tokio::spawn(async move {
+                supervisor.stop_session_for_budget(&db, "spender", Duration::from_secs(5)).await
+            }));
+        }
+    }
+
+    stop.expect("the hard limit should stop the run")
+        .await
+        .expect("stop task should join")
+        .expect("budget stop should succeed");
+    assert!(runtime.was_budget_exceeded());
+    assert!(last_input_tokens < 10_000, "the run should stop mid-stream, not at its result");
+
+    let session = db.get_session("spender").expect("session should load").expect("session exists");
+    assert_eq!(session.status, "failed");
+    let row = db
+        .get_dashboard_session("spender")
+        .expect("dashboard should load")
+        .expect("dashboard row exists");
+    assert_eq!(row.failure_reason.as_deref(), Some(BUDGET_EXCEEDED_REASON));
+
+    let notice = transitions.try_recv().expect("terminal notice should be published");
+    assert_eq!(notice.final_status, "failed");
+    assert_eq!(notice.failure_message.as_deref(), Some(BUDGET_EXCEEDED_REASON));
+
+    db.flush_writes().expect("queued events should flush");
+    let budget_events: Vec<String> = db
+        .list_session_history("spender")
+        .expect("history should load")
+        .into_iter()
+        .map(|event| event.event_type)
+        .filter(|event_type| event_type.starts_with("budget_"))
+        .collect();
+    assert_eq!(budget_events, ["budget_warning", "budget_exceeded"]);
+}
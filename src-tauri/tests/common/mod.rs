@@ -0,0 +1,49 @@
+//! Git fixtures shared by the integration tests that need a real repository.
+#![allow(dead_code)]
+
+use std::path::Path;
+
+use tempfile::{tempdir, TempDir};
+
+/// Runs `git` in `repo_path`, panicking with its stderr on failure. Returns trimmed stdout.
+pub fn git(repo_path: &Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(repo_path)
+        .output()
+        .expect("git command should execute");
+
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Repository on `main` with an initial commit of `README.md` and a `.gitignore` for `*.db`.
+pub fn init_repo() -> (TempDir, String) {
+    init_repo_with(|dir| {
+        std::fs::write(dir.join(".gitignore"), "*.db\n").expect("gitignore should write");
+        std::fs::write(dir.join("README.md"), "# test\n").expect("seed file should write");
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", "initial"]);
+    })
+}
+
+/// Empty repository on `main` with a committer configured; `seed` fills it in. Returns the
+/// directory and its canonical path.
+pub fn init_repo_with(seed: impl FnOnce(&Path)) -> (TempDir, String) {
+    let dir = tempdir().expect("tempdir should be created");
+    git(dir.path(), &["init", "--initial-branch=main"]);
+    git(dir.path(), &["config", "user.name", "Lulu Test"]);
+    git(dir.path(), &["config", "user.email", "lulu@example.com"]);
+    seed(dir.path());
+
+    let repo_root = std::fs::canonicalize(dir.path())
+        .expect("repo path should canonicalize")
+        .display()
+        .to_string();
+    (dir, repo_root)
+}
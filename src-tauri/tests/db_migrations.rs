@@ -0,0 +1,180 @@
+use std::path::Path;
+
+use rusqlite::Connection;
+use tempfile::tempdir;
+
+use tauri_app_lib::db::migrations::{
+    apply_migrations, schema_version, Migration, LATEST_SCHEMA_VERSION, MIGRATIONS,
+};
+use tauri_app_lib::db::{init_database, DbError};
+
+fn backups(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .expect("dir should list")
+        .map(|entry| entry.expect("entry should read").file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".bak"))
+        .collect();
+    names.sort();
+    names
+}
+
+fn columns(conn: &Connection, table: &str) -> Vec<String> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table)).expect("pragma");
+    let names = stmt.query_map([], |row| row.get(1)).expect("columns should load");
+    names.map(|name| name.expect("column name")).collect()
+}
+
+fn index_exists(conn: &Connection, name: &str) -> bool {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = ?1",
+        [name],
+        |row| row.get::<_, i64>(0),
+    )
+    .expect("sqlite_master should query")
+        > 0
+}
+
+#[test]
+fn fresh_database_is_created_at_the_latest_version_without_a_backup() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db_path = dir.path().join("lulu.db");
+
+    let db = init_database(&db_path).expect("database should initialize");
+    let conn = db.conn.lock().expect("connection lock");
+    assert_eq!(schema_version(&conn).expect("version should read"), LATEST_SCHEMA_VERSION);
+    assert!(columns(&conn, "sessions").contains(&"recovery_hint".to_string()));
+    drop(conn);
+    drop(db);
+    assert!(backups(dir.path()).is_empty());
+
+    init_database(&db_path).expect("reopening should be a no-op");
+    assert!(backups(dir.path()).is_empty(), "an up-to-date database is not backed up");
+}
+
+#[test]
+fn legacy_database_is_backed_up_and_brought_up_to_date() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db_path = dir.path().join("lulu.db");
+    {
+        // The schema of the first release, before any column was bolted on.
+        let conn = Connection::open(&db_path).expect("legacy database should open");
+        conn.execute_batch(
+            "CREATE TABLE sessions (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'created',
+                working_dir TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE session_events (
+                id TEXT PRIMARY KEY,
+                session_id TEXT NOT NULL,
+                run_id TEXT NOT NULL,
+                seq INTEGER NOT NULL,
+                event_type TEXT NOT NULL,
+                payload_json TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                UNIQUE(session_id, run_id, seq)
+            );
+            CREATE INDEX idx_session_events_session_id_run_id_seq
+                ON session_events(session_id, run_id, seq);
+            INSERT INTO sessions (id, name, status, working_dir, created_at, updated_at)
+                VALUES ('legacy', 'Legacy', 'completed', '/tmp', '2024-01-01', '2024-01-01');",
+        )
+        .expect("legacy schema should apply");
+    }
+
+    let db = init_database(&db_path).expect("legacy database should migrate");
+    let session = db.get_session("legacy").expect("session should load").expect("session");
+    assert_eq!(session.name, "Legacy");
+    let conn = db.conn.lock().expect("connection lock");
+    assert_eq!(schema_version(&conn).expect("version should read"), LATEST_SCHEMA_VERSION);
+    let session_columns = columns(&conn, "sessions");
+    for column in ["worktree_path", "resume_count", "recovery_hint", "base_sha"] {
+        assert!(session_columns.contains(&column.to_string()), "missing {}", column);
+    }
+    assert!(!index_exists(&conn, "idx_session_events_session_id_run_id_seq"));
+    drop(conn);
+
+    let backups = backups(dir.path());
+    assert_eq!(backups.len(), 1, "{:?}", backups);
+    assert!(backups[0].starts_with("lulu.db.v0-"), "{:?}", backups);
+    let backup = Connection::open(dir.path().join(&backups[0])).expect("backup should open");
+    assert_eq!(schema_version(&backup).expect("version should read"), 0);
+    assert_eq!(columns(&backup, "sessions").len(), 6, "the backup keeps the old schema");
+}
+
+#[test]
+fn each_migration_applies_in_order_on_top_of_the_previous_one() {
+    for (index, migration) in MIGRATIONS.iter().enumerate() {
+        assert_eq!(migration.version as usize, index + 1, "{}", migration.description);
+    }
+
+    let mut conn = Connection::open_in_memory().expect("database should open");
+    for migration in MIGRATIONS {
+        let version = apply_migrations(&mut conn, std::slice::from_ref(migration))
+            .unwrap_or_else(|e| panic!("migration {} failed: {}", migration.version, e));
+        assert_eq!(version, migration.version);
+        assert_eq!(schema_version(&conn).expect("version should read"), migration.version);
+
+        // Re-running a step that is already applied is skipped.
+        assert_eq!(
+            apply_migrations(&mut conn, std::slice::from_ref(migration)).expect("no-op"),
+            migration.version
+        );
+        if migration.version == 1 {
+            assert!(index_exists(&conn, "idx_session_events_session_id_run_id_seq"));
+        }
+        if migration.version == 2 {
+            assert!(!index_exists(&conn, "idx_session_events_session_id_run_id_seq"));
+        }
+    }
+}
+
+#[test]
+fn database_from_a_newer_version_is_refused_untouched() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db_path = dir.path().join("lulu.db");
+    {
+        let conn = Connection::open(&db_path).expect("database should open");
+        conn.execute_batch("CREATE TABLE future (id TEXT);").expect("table should create");
+        conn.pragma_update(None, "user_version", LATEST_SCHEMA_VERSION + 1)
+            .expect("version should set");
+    }
+
+    let error = init_database(&db_path).err().expect("a newer schema must be refused");
+    assert!(
+        matches!(error, DbError::NewerSchema { found, supported }
+            if found == LATEST_SCHEMA_VERSION + 1 && supported == LATEST_SCHEMA_VERSION),
+        "unexpected error: {}",
+        error
+    );
+    assert!(error.to_string().contains("update Lulu"), "{}", error);
+
+    let conn = Connection::open(&db_path).expect("database should reopen");
+    assert_eq!(schema_version(&conn).expect("version should read"), LATEST_SCHEMA_VERSION + 1);
+    assert!(columns(&conn, "sessions").is_empty(), "no tables are created");
+    assert!(backups(dir.path()).is_empty());
+}
+
+fn create_then_fail(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("CREATE TABLE half_done (id TEXT);")?;
+    conn.execute_batch("INSERT INTO missing_table VALUES (1);")
+}
+
+#[test]
+fn failing_migration_rolls_back_and_keeps_the_previous_version() {
+    let mut conn = Connection::open_in_memory().expect("database should open");
+    apply_migrations(&mut conn, MIGRATIONS).expect("real migrations should apply");
+
+    let broken = [Migration {
+        version: LATEST_SCHEMA_VERSION + 1,
+        description: "fails halfway",
+        apply: create_then_fail,
+    }];
+    apply_migrations(&mut conn, &broken).expect_err("the broken migration should fail");
+
+    assert_eq!(schema_version(&conn).expect("version should read"), LATEST_SCHEMA_VERSION);
+    assert!(columns(&conn, "half_done").is_empty(), "the partial step is rolled back");
+}
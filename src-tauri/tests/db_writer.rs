@@ -0,0 +1,143 @@
+use tempfile::tempdir;
+
+use tauri_app_lib::db::{init_database, Database, QueuedSessionEvent, Session};
+
+fn create_session(db: &Database, id: &str) {
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: id.to_string(),
+        status: "running".to_string(),
+        working_dir: "/tmp".to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+}
+
+fn streamed(session_id: &str, seq: u64, content: Option<&str>) -> QueuedSessionEvent {
+    let event_type = if content.is_some() { "message" } else { "tool_call" };
+    QueuedSessionEvent {
+        session_id: session_id.to_string(),
+        run_id: "run-1".to_string(),
+        seq,
+        event_type: event_type.to_string(),
+        payload_json: serde_json::json!({ "type": event_type, "data": { "seq": seq } }).to_string(),
+        timestamp: format!("2025-01-01T00:00:{:02}Z", seq),
+        activity: content.is_some(),
+        assistant_message: content.map(str::to_string),
+    }
+}
+
+#[test]
+fn queued_events_land_with_their_bookkeeping_after_a_flush() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    create_session(&db, "queued");
+    db.mark_sessions_restored(&["queued".to_string()], "2025-01-01T00:00:00Z")
+        .expect("restored flag should set");
+
+    for seq in 1..=50 {
+        let content = (seq % 10 == 0).then(|| format!("output {}", seq));
+        db.queue_session_event(streamed("queued", seq, content.as_deref()))
+            .expect("event should queue");
+    }
+    db.flush_writes().expect("flush should succeed");
+
+    let history = db.list_session_history("queued").expect("history should load");
+    assert_eq!(history.len(), 50);
+    assert!(history.iter().enumerate().all(|(index, event)| event.seq == index as i64 + 1));
+    let messages = db.list_session_messages("queued").expect("messages should load");
+    let contents: Vec<&str> = messages.iter().map(|message| message.content.as_str()).collect();
+    assert_eq!(contents, ["output 10", "output 20", "output 30", "output 40", "output 50"]);
+    let row = db.get_dashboard_session("queued").expect("row should load").expect("row");
+    assert_eq!(row.last_activity_at.as_deref(), Some("2025-01-01T00:00:50Z"));
+    assert!(!row.restored, "streamed events clear the restored marker");
+}
+
+#[test]
+fn appended_events_are_sequenced_after_queued_ones() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    create_session(&db, "append");
+
+    for seq in 1..=3 {
+        db.queue_session_event(streamed("append", seq, None)).expect("event should queue");
+    }
+    let appended = db
+        .append_session_event(
+            "append",
+            "run-1",
+            "hook_result",
+            &serde_json::json!({ "type": "hook_result" }),
+            "2025-01-01T00:01:00Z",
+        )
+        .expect("append should succeed");
+
+    assert_eq!(appended, 4, "queued events count towards the next seq");
+    let history = db.list_session_history("append").expect("history should load");
+    let types: Vec<&str> = history.iter().map(|event| event.event_type.as_str()).collect();
+    assert_eq!(types, ["tool_call", "tool_call", "tool_call", "hook_result"]);
+}
+
+#[test]
+fn a_failing_write_does_not_take_its_batch_down() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    create_session(&db, "kept");
+    let mut failures = db.subscribe_write_failures();
+
+    db.queue_session_event(streamed("kept", 1, None)).expect("event should queue");
+    db.queue_session_event(streamed("deleted-session", 1, None)).expect("event should queue");
+    db.queue_session_event(streamed("kept", 2, None)).expect("event should queue");
+    let error = db
+        .insert_session_message("deleted-session", "user", "hello", "2025-01-01T00:00:00Z")
+        .expect_err("a message for a missing session violates its foreign key");
+    assert!(error.to_string().contains("FOREIGN KEY"), "unexpected error: {}", error);
+
+    let history = db.list_session_history("kept").expect("history should load");
+    assert_eq!(history.len(), 2);
+    assert!(db.list_session_history("deleted-session").expect("history").is_empty());
+    let failure = failures.try_recv().expect("the failed queued write should be reported");
+    assert_eq!(failure.session_id, "deleted-session");
+    assert!(failure.message.contains("FOREIGN KEY"), "unexpected failure: {:?}", failure);
+    assert!(failures.try_recv().is_err(), "only the failed write is reported");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn waiting_writes_can_run_on_async_workers() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = std::sync::Arc::new(
+        init_database(&dir.path().join("lulu.db")).expect("database should initialize"),
+    );
+    create_session(&db, "async");
+
+    let worker_db = db.clone();
+    tokio::spawn(async move {
+        for seq in 1..=10 {
+            let event = streamed("async", seq, None);
+            worker_db.queue_session_event(event).expect("event should queue");
+        }
+        worker_db.flush_writes().expect("flush should succeed");
+    })
+    .await
+    .expect("task should finish");
+
+    assert_eq!(db.list_session_history("async").expect("history should load").len(), 10);
+}
+
+#[test]
+fn dropping_the_database_commits_what_is_still_queued() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db_path = dir.path().join("lulu.db");
+    {
+        let db = init_database(&db_path).expect("database should initialize");
+        create_session(&db, "shutdown");
+        for seq in 1..=20 {
+            db.queue_session_event(streamed("shutdown", seq, None)).expect("event should queue");
+        }
+    }
+
+    let db = init_database(&db_path).expect("database should reopen");
+    assert_eq!(db.list_session_history("shutdown").expect("history should load").len(), 20);
+}
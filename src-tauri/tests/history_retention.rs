@@ -0,0 +1,233 @@
+use serde_json::json;
+use tempfile::tempdir;
+
+use tauri_app_lib::db::{
+    init_database, Database, HistoryRetentionPolicy, Session, SessionSearchFilters,
+};
+use tauri_app_lib::session::history_retention::{
+    apply_history_retention, apply_startup_history_retention, archive_path,
+    rehydrate_session_history, storage_usage_report,
+};
+
+const OLD: &str = "2024-01-01T00:00:00Z";
+
+fn create_session(db: &Database, id: &str, status: &str) {
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: id.to_string(),
+        status: status.to_string(),
+        working_dir: "/tmp".to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+}
+
+/// A message, a tool call and its result in one run, all at `at`.
+fn record_run(db: &Database, session_id: &str, run_id: &str, at: &str) {
+    let events = [
+        json!({ "type": "message", "data": { "content": "Fixing the login form." } }),
+        json!({ "type": "tool_call", "data": {
+            "tool_name": "Edit", "args": { "file_path": "src/login.ts" }
+        }}),
+        json!({ "type": "tool_result", "data": { "tool_name": "Edit", "content": "ok" } }),
+    ];
+    for (index, payload) in events.iter().enumerate() {
+        let event_type = payload["type"].as_str().expect("payload type");
+        db.insert_session_event(session_id, run_id, index as u64 + 1, event_type, payload, at)
+            .expect("event should persist");
+    }
+}
+
+fn set_policy(db: &Database, keep_days: Option<i64>, mode: &str) {
+    db.save_history_retention_policy(&HistoryRetentionPolicy { keep_days, mode: mode.to_string() })
+        .expect("policy should save");
+}
+
+fn event_types(db: &Database, session_id: &str) -> Vec<String> {
+    db.list_session_history(session_id)
+        .expect("history should load")
+        .into_iter()
+        .map(|event| event.event_type)
+        .collect()
+}
+
+#[test]
+fn compaction_keeps_messages_and_summarizes_expired_runs_of_finished_sessions() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    create_session(&db, "done", "completed");
+    create_session(&db, "busy", "running");
+    record_run(&db, "done", "run-1", OLD);
+    record_run(&db, "done", "run-2", &chrono::Utc::now().to_rfc3339());
+    record_run(&db, "busy", "run-1", OLD);
+    set_policy(&db, Some(30), "compact");
+
+    let report =
+        apply_history_retention(&db, &dir.path().join("archives"), false).expect("retention");
+    assert_eq!(report.actions.len(), 1, "{:?}", report);
+    assert_eq!(report.actions[0].run_id, "run-1");
+    assert_eq!(report.removed_events, 2);
+    assert!(report.actions[0].error.is_none());
+
+    let history = db.list_session_history("done").expect("history should load");
+    let run_1: Vec<_> = history.iter().filter(|event| event.run_id == "run-1").collect();
+    assert_eq!(
+        run_1.iter().map(|event| event.event_type.as_str()).collect::<Vec<_>>(),
+        ["message", "history_compacted"]
+    );
+    let summary = &run_1[1].payload_json["data"];
+    assert_eq!(summary["event_count"], 3);
+    assert_eq!(summary["event_counts"]["tool_call"], 1);
+    assert_eq!(summary["tools"], json!(["Edit"]));
+    assert!(summary["archive_path"].is_null());
+    assert_eq!(history.iter().filter(|event| event.run_id == "run-2").count(), 3);
+    assert_eq!(event_types(&db, "busy").len(), 3, "running sessions are left alone");
+
+    let again =
+        apply_history_retention(&db, &dir.path().join("archives"), false).expect("retention");
+    assert!(again.actions.is_empty(), "compacted runs are not compacted twice: {:?}", again);
+}
+
+#[test]
+fn archived_runs_rehydrate_with_their_original_events() {
+    let dir = tempdir().expect("tempdir should be created");
+    let archive_dir = dir.path().join("archives");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    create_session(&db, "done", "failed");
+    record_run(&db, "done", "run-1", OLD);
+    let original = db.list_session_history("done").expect("history should load");
+    set_policy(&db, Some(7), "archive");
+
+    let report = apply_history_retention(&db, &archive_dir, false).expect("retention");
+    let archive = archive_path(&archive_dir, "done");
+    assert_eq!(report.removed_events, 3);
+    assert_eq!(report.actions[0].archive_path.as_deref(), Some(archive.to_str().expect("utf-8")));
+    assert!(archive.is_file());
+    assert_eq!(event_types(&db, "done"), ["history_compacted"]);
+
+    let usage = storage_usage_report(&db, &archive_dir).expect("report");
+    let session = usage.sessions.iter().find(|s| s.session_id == "done").expect("session row");
+    assert!(session.archived_bytes > 0);
+    assert_eq!(session.compacted_runs, 1);
+    assert_eq!(usage.archived_bytes, session.archived_bytes);
+
+    assert_eq!(rehydrate_session_history(&db, &archive_dir, "done").expect("rehydrate"), 3);
+    assert!(!archive.exists(), "the archive is removed once its events are back");
+    let restored = db.list_session_history("done").expect("history should load");
+    let ids = |events: &[tauri_app_lib::db::SessionHistoryEvent]| -> Vec<String> {
+        events.iter().map(|event| event.id.clone()).collect()
+    };
+    assert_eq!(ids(&restored), ids(&original));
+    assert_eq!(restored[1].payload_json, original[1].payload_json);
+}
+
+#[test]
+fn dry_runs_and_disabled_policies_change_nothing() {
+    let dir = tempdir().expect("tempdir should be created");
+    let archive_dir = dir.path().join("archives");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    create_session(&db, "done", "completed");
+    record_run(&db, "done", "run-1", OLD);
+
+    let disabled = apply_history_retention(&db, &archive_dir, false).expect("retention");
+    assert!(disabled.cutoff.is_none());
+    assert!(disabled.actions.is_empty());
+
+    set_policy(&db, Some(0), "archive");
+    let dry_run = apply_history_retention(&db, &archive_dir, true).expect("retention");
+    assert_eq!(dry_run.actions.len(), 1);
+    assert_eq!(dry_run.removed_events, 3);
+    assert!(dry_run.removed_bytes > 0);
+    assert_eq!(event_types(&db, "done").len(), 3);
+    assert!(!archive_dir.exists());
+}
+
+#[test]
+fn compaction_vacuums_the_space_it_frees() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    create_session(&db, "big", "completed");
+    let output = "x".repeat(4096);
+    for seq in 1..=500u64 {
+        db.insert_session_event(
+            "big",
+            "run-1",
+            seq,
+            "tool_result",
+            &json!({ "type": "tool_result", "data": { "content": format!("{}{}", seq, output) } }),
+            OLD,
+        )
+        .expect("event should persist");
+    }
+    let before = storage_usage_report(&db, &dir.path().join("archives")).expect("report");
+    assert_eq!(before.sessions[0].event_count, 500);
+    assert!(before.event_bytes > 500 * 4096);
+    set_policy(&db, Some(1), "compact");
+
+    let report =
+        apply_history_retention(&db, &dir.path().join("archives"), false).expect("retention");
+    assert_eq!(report.removed_events, 500);
+    assert!(report.freed_bytes > 0, "{:?}", report);
+
+    let after = db.database_file_usage().expect("usage");
+    assert!(after.database_bytes < before.database.database_bytes);
+    assert_eq!(after.free_bytes, 0);
+}
+
+#[test]
+fn archived_assistant_messages_stay_searchable_without_duplicates_after_rehydrating() {
+    let dir = tempdir().expect("tempdir should be created");
+    let archive_dir = dir.path().join("archives");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    create_session(&db, "done", "completed");
+    record_run(&db, "done", "run-1", OLD);
+    db.insert_session_message("done", "assistant", "Fixing the login form.", OLD)
+        .expect("message should persist");
+    let hits = |db: &Database| {
+        db.search_sessions("login form", &SessionSearchFilters::default(), 50)
+            .expect("search should succeed")
+    };
+    assert_eq!(hits(&db).len(), 1);
+    set_policy(&db, Some(7), "archive");
+
+    apply_history_retention(&db, &archive_dir, false).expect("retention");
+    let archived = hits(&db);
+    assert_eq!(archived.len(), 1, "{:?}", archived);
+    assert_eq!(archived[0].run_id, None, "the hit now points at the stored message");
+
+    rehydrate_session_history(&db, &archive_dir, "done").expect("rehydrate");
+    let restored = hits(&db);
+    assert_eq!(restored.len(), 1, "{:?}", restored);
+    assert_eq!(restored[0].run_id.as_deref(), Some("run-1"));
+}
+
+#[test]
+fn startup_retention_leaves_the_full_vacuum_to_a_manual_pass() {
+    let dir = tempdir().expect("tempdir should be created");
+    let archive_dir = dir.path().join("archives");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    let auto_vacuum = |db: &Database| -> i64 {
+        let conn = db.conn.lock().expect("connection");
+        conn.query_row("PRAGMA auto_vacuum", [], |row| row.get(0)).expect("pragma")
+    };
+    db.conn
+        .lock()
+        .expect("connection")
+        .execute_batch("PRAGMA auto_vacuum=NONE; VACUUM;")
+        .expect("database should convert back");
+    assert_eq!(auto_vacuum(&db), 0);
+    create_session(&db, "done", "completed");
+    record_run(&db, "done", "run-1", OLD);
+    record_run(&db, "done", "run-2", OLD);
+    set_policy(&db, Some(7), "compact");
+
+    let startup = apply_startup_history_retention(&db, &archive_dir).expect("retention");
+    assert_eq!(startup.removed_events, 4);
+    assert_eq!(auto_vacuum(&db), 0, "startup does not rewrite the whole database");
+
+    record_run(&db, "done", "run-3", OLD);
+    apply_history_retention(&db, &archive_dir, false).expect("retention");
+    assert_eq!(auto_vacuum(&db), 2, "a manual pass converts to incremental vacuuming");
+}
@@ -0,0 +1,301 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tempfile::tempdir;
+use tokio::time::{timeout, Duration};
+
+use tauri_app_lib::db::{init_database, Database, LifecycleHook, QueuedSessionEvent, Session};
+use tauri_app_lib::session::hooks::{
+    record_lifecycle_event, run_lifecycle_hooks, run_shell_command, run_shell_command_streaming,
+};
+use tauri_app_lib::session::{SessionEventPayload, SessionSupervisor};
+
+mod common;
+
+use common::{git, init_repo_with};
+
+fn init_repo() -> tempfile::TempDir {
+    init_repo_with(|dir| {
+        std::fs::write(dir.join("README.md"), "# test\n").expect("seed file should write");
+        git(dir, &["add", "README.md"]);
+        git(dir, &["commit", "-m", "initial"]);
+    })
+    .0
+}
+
+fn create_session(db: &Database, id: &str, status: &str, working_dir: &str) {
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: format!("{} name", id),
+        status: status.to_string(),
+        working_dir: working_dir.to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+}
+
+fn hook(event: &str, command: &str, repo_root: Option<String>, timeout_ms: i64) -> LifecycleHook {
+    LifecycleHook {
+        id: uuid::Uuid::new_v4().to_string(),
+        repo_root,
+        event: event.to_string(),
+        command: command.to_string(),
+        timeout_ms,
+        enabled: true,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+#[tokio::test]
+async fn hooks_run_with_session_env_and_persist_results_as_events() {
+    let repo = init_repo();
+    let repo_root = std::fs::canonicalize(repo.path())
+        .expect("repo path should canonicalize")
+        .display()
+        .to_string();
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&db_dir.path().join("lulu.db")).expect("database should initialize");
+
+    create_session(&db, "hook-session", "failed", &repo_root);
+    db.begin_run_attempt("hook-session", "run-1").expect("run should begin");
+    db.update_session_status("hook-session", "failed").expect("status should update");
+    db.update_failure_reason("hook-session", Some("cli exited 1"))
+        .expect("failure reason should persist");
+
+    db.create_lifecycle_hook(&hook(
+        "failed",
+        "echo \"$LULU_SESSION_ID|$LULU_SESSION_NAME|$LULU_SESSION_STATUS|$LULU_FAILURE_REASON\"",
+        None,
+        5_000,
+    ))
+    .expect("global hook should persist");
+    db.create_lifecycle_hook(&hook("failed", "echo repo-scoped; exit 3", Some(repo_root.clone()), 5_000))
+        .expect("repo hook should persist");
+    db.create_lifecycle_hook(&hook("failed", "echo other-repo", Some("/not/this/repo".to_string()), 5_000))
+        .expect("foreign repo hook should persist");
+    db.create_lifecycle_hook(&hook("completed", "echo wrong-event", None, 5_000))
+        .expect("other event hook should persist");
+
+    let events = run_lifecycle_hooks(&db, "hook-session", "failed")
+        .await
+        .expect("hooks should run");
+    assert_eq!(events.len(), 2, "only global and same-repo hooks should run");
+
+    match &events[0].payload {
+        SessionEventPayload::Hook { stdout, exit_code, timed_out, event, .. } => {
+            assert_eq!(event, "failed");
+            assert_eq!(*exit_code, Some(0));
+            assert!(!timed_out);
+            assert_eq!(stdout.trim(), "hook-session|hook-session name|failed|cli exited 1");
+        }
+        other => panic!("expected hook payload, got {:?}", other),
+    }
+    match &events[1].payload {
+        SessionEventPayload::Hook { stdout, exit_code, .. } => {
+            assert_eq!(stdout.trim(), "repo-scoped");
+            assert_eq!(*exit_code, Some(3));
+        }
+        other => panic!("expected hook payload, got {:?}", other),
+    }
+
+    let history = db.list_session_history("hook-session").expect("history should load");
+    let hook_events: Vec<_> = history.iter().filter(|event| event.event_type == "hook").collect();
+    assert_eq!(hook_events.len(), 2);
+    assert!(hook_events.iter().all(|event| event.run_id == "run-1"));
+    assert_eq!(hook_events[0].payload_json["data"]["exit_code"], 0);
+}
+
+#[tokio::test]
+async fn hook_that_exceeds_timeout_is_killed_and_recorded() {
+    let temp = tempdir().expect("tempdir should be created");
+    let db = init_database(&temp.path().join("lulu.db")).expect("database should initialize");
+    let working_dir = temp.path().display().to_string();
+    create_session(&db, "slow-hook", "completed", &working_dir);
+
+    db.create_lifecycle_hook(&hook("completed", "sleep 5", None, 200))
+        .expect("hook should persist");
+
+    let events = timeout(Duration::from_secs(3), run_lifecycle_hooks(&db, "slow-hook", "completed"))
+        .await
+        .expect("timed out hook should not block")
+        .expect("hooks should run");
+
+    assert_eq!(events.len(), 1);
+    match &events[0].payload {
+        SessionEventPayload::Hook { timed_out, exit_code, .. } => {
+            assert!(*timed_out);
+            assert!(exit_code.is_none());
+        }
+        other => panic!("expected hook payload, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn disabled_hooks_and_non_hook_events_are_skipped() {
+    let temp = tempdir().expect("tempdir should be created");
+    let db = init_database(&temp.path().join("lulu.db")).expect("database should initialize");
+    let working_dir = temp.path().display().to_string();
+    create_session(&db, "quiet-session", "completed", &working_dir);
+
+    let disabled = hook("completed", "echo should-not-run", None, 1_000);
+    db.create_lifecycle_hook(&disabled).expect("hook should persist");
+    assert!(db
+        .set_lifecycle_hook_enabled(&disabled.id, false)
+        .expect("hook should update"));
+
+    let events = run_lifecycle_hooks(&db, "quiet-session", "completed")
+        .await
+        .expect("hooks should run");
+    assert!(events.is_empty());
+
+    let events = run_lifecycle_hooks(&db, "quiet-session", "resuming")
+        .await
+        .expect("unknown events should be ignored");
+    assert!(events.is_empty());
+}
+
+#[tokio::test]
+async fn supervisor_publishes_each_terminal_transition_once() {
+    let temp = tempdir().expect("tempdir should be created");
+    let db = Arc::new(init_database(&temp.path().join("lulu.db")).expect("database should initialize"));
+    let supervisor = SessionSupervisor::new();
+    let working_dir = temp.path().display().to_string();
+    create_session(&db, "notify-session", "running", &working_dir);
+
+    let child = tokio::process::Command::new("true")
+        .spawn()
+        .expect("placeholder process should spawn");
+    supervisor
+        .register("notify-session".to_string(), "notify-session".to_string(), child)
+        .await;
+
+    let mut transitions = supervisor.subscribe_terminal_transitions();
+    for _ in 0..2 {
+        let _ = supervisor
+            .finalize_terminal_transition(db.as_ref(), "notify-session", "completed", None)
+            .await
+            .expect("finalization should succeed");
+    }
+
+    let notice = timeout(Duration::from_secs(1), transitions.recv())
+        .await
+        .expect("notice should publish")
+        .expect("channel should stay open");
+    assert_eq!(notice.session_id, "notify-session");
+    assert_eq!(notice.final_status, "completed");
+    assert!(transitions.try_recv().is_err(), "terminal notice must publish once");
+}
+
+#[tokio::test]
+async fn lifecycle_events_recorded_mid_stream_never_displace_cli_events() {
+    let temp = tempdir().expect("tempdir should be created");
+    let db = init_database(&temp.path().join("lulu.db")).expect("database should initialize");
+    let working_dir = temp.path().display().to_string();
+    create_session(&db, "live-session", "running", &working_dir);
+    db.begin_run_attempt("live-session", "run-1").expect("run should begin");
+
+    // The CLI reader takes seqs from this counter as lines arrive, ahead of the writer.
+    let sequence = Arc::new(AtomicU64::new(1));
+    db.register_run_sequence("live-session", "run-1", sequence.clone());
+    let stream_event = |seq: u64| QueuedSessionEvent {
+        session_id: "live-session".to_string(),
+        run_id: "run-1".to_string(),
+        seq,
+        event_type: "tool_call".to_string(),
+        payload_json: format!("{{\"type\":\"tool_call\",\"data\":{{\"n\":{}}}}}", seq),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        activity: false,
+        assistant_message: None,
+    };
+
+    let mut taken = Vec::new();
+    for _ in 0..3 {
+        let seq = sequence.fetch_add(1, Ordering::SeqCst);
+        db.queue_session_event(stream_event(seq)).expect("event should queue");
+        taken.push(seq);
+    }
+    // Taken by the reader but not yet handed to the writer when the warning is recorded.
+    let in_flight = sequence.fetch_add(1, Ordering::SeqCst);
+    let warning = record_lifecycle_event(
+        &db,
+        "live-session",
+        SessionEventPayload::Status { status: "running".to_string() },
+    )
+    .expect("lifecycle event should record");
+    db.queue_session_event(stream_event(in_flight)).expect("event should queue");
+    taken.push(in_flight);
+    let next = sequence.fetch_add(1, Ordering::SeqCst);
+    db.queue_session_event(stream_event(next)).expect("event should queue");
+    taken.push(next);
+    db.flush_writes().expect("writes should flush");
+
+    let history = db.list_session_history("live-session").expect("history should load");
+    let mut stream_seqs: Vec<u64> = history
+        .iter()
+        .filter(|event| event.event_type == "tool_call")
+        .map(|event| event.seq as u64)
+        .collect();
+    stream_seqs.sort();
+    assert_eq!(stream_seqs, taken, "every CLI event is stored");
+    assert!(!taken.contains(&warning.seq));
+    assert_eq!(history.len(), taken.len() + 1);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn commands_finish_without_waiting_on_background_children() {
+    let temp = tempdir().expect("tempdir should be created");
+    let working_dir = temp.path().display().to_string();
+
+    let mut batches = Vec::new();
+    let started = std::time::Instant::now();
+    let output = run_shell_command_streaming(
+        "(sleep 30; echo late) & for n in 1 2 3; do echo line-$n; done",
+        &working_dir,
+        &[],
+        Duration::from_secs(20),
+        |lines| batches.push(lines),
+    )
+    .await;
+
+    assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+    assert!(output.succeeded(), "{:?}", output);
+    let lines: Vec<String> = batches.into_iter().flatten().map(|(_, line)| line).collect();
+    assert_eq!(lines, ["line-1", "line-2", "line-3"]);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn timed_out_commands_take_their_background_children_down() {
+    let temp = tempdir().expect("tempdir should be created");
+    let working_dir = temp.path().display().to_string();
+    let pid_file = temp.path().join("child.pid");
+
+    let output = run_shell_command(
+        &format!("sleep 30 & echo $! > '{}'; wait", pid_file.display()),
+        &working_dir,
+        &[],
+        Duration::from_millis(500),
+    )
+    .await;
+    assert!(output.timed_out);
+
+    let pid = std::fs::read_to_string(&pid_file).expect("pid file should exist");
+    let mut alive = true;
+    for _ in 0..20 {
+        // Exited but not yet reaped by init still counts as gone.
+        let state = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", pid.trim()])
+            .output()
+            .expect("ps should run");
+        let state = String::from_utf8_lossy(&state.stdout).trim().to_string();
+        alive = !state.is_empty() && !state.starts_with('Z');
+        if !alive {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(!alive, "the background sleep should be killed with the command");
+}
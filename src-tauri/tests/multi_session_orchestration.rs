@@ -10,7 +10,9 @@ use tokio::sync::Mutex;
 use tokio::time::{sleep, timeout, Duration};
 
 use tauri_app_lib::db::{init_database, Database, Session};
-use tauri_app_lib::session::{ClaudeCli, SessionEventPayload, SessionSupervisor};
+use tauri_app_lib::session::{
+    ClaudeCli, LifecycleAdmission, LifecycleOperationKind, SessionEventPayload, SessionSupervisor,
+};
 
 #[derive(Clone)]
 struct SessionSpec {
@@ -458,6 +460,111 @@ async fn interrupt_retries_once_and_times_out_after_total_deadline() {
     let _ = supervisor.remove("interrupt-timeout").await;
 }
 
+#[tokio::test]
+async fn kill_preempts_in_progress_interrupt_and_duplicate_interrupt_joins() {
+    let temp = tempdir().expect("tempdir should be created");
+    let db_path = temp.path().join("lulu.db");
+    let db = Arc::new(init_database(&db_path).expect("database should initialize"));
+    let supervisor = Arc::new(SessionSupervisor::new());
+    let cli = ClaudeCli::find_with_override(Some(PathBuf::from(env!("CARGO_BIN_EXE_lulu_test_cli"))))
+        .expect("fixture cli should resolve");
+
+    let work_dir = temp.path().display().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: "kill-preempt".to_string(),
+        name: "kill-preempt".to_string(),
+        status: "running".to_string(),
+        working_dir: work_dir.clone(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+
+    let (event_tx, _event_rx) = tokio::sync::mpsc::channel(128);
+    let spawned = cli
+        .spawn_with_events("delay-ms=20000", &work_dir, "kill-preempt", event_tx)
+        .await
+        .expect("process should spawn");
+    let runtime = supervisor
+        .register("kill-preempt".to_string(), "kill-preempt".to_string(), spawned.child)
+        .await;
+
+    let lock_runtime = runtime.clone();
+    let lock_holder = tokio::spawn(async move {
+        let _hold = lock_runtime.child.lock().await;
+        sleep(Duration::from_millis(1500)).await;
+    });
+    sleep(Duration::from_millis(100)).await;
+
+    let first_supervisor = supervisor.clone();
+    let first_db = db.clone();
+    let first_interrupt = tokio::spawn(async move {
+        first_supervisor
+            .interrupt_session_with_deadline(first_db.as_ref(), "kill-preempt", Duration::from_secs(10))
+            .await
+    });
+    sleep(Duration::from_millis(100)).await;
+
+    let duplicate_supervisor = supervisor.clone();
+    let duplicate_db = db.clone();
+    let duplicate_interrupt = tokio::spawn(async move {
+        duplicate_supervisor
+            .interrupt_session_with_deadline(
+                duplicate_db.as_ref(),
+                "kill-preempt",
+                Duration::from_secs(10),
+            )
+            .await
+    });
+    sleep(Duration::from_millis(100)).await;
+
+    let active = supervisor
+        .lifecycle_operation("kill-preempt")
+        .expect("interrupt should be in progress");
+    assert_eq!(active.operation, LifecycleOperationKind::Interrupt);
+    assert_eq!(supervisor.list_lifecycle_operations().len(), 1);
+
+    let started = Instant::now();
+    let killed = supervisor
+        .kill_session("kill-preempt")
+        .await
+        .expect("kill should pre-empt the interrupt");
+    assert!(killed);
+    assert!(started.elapsed() < Duration::from_secs(5), "kill must not wait out the interrupt deadline");
+
+    let first_err = timeout(Duration::from_secs(2), first_interrupt)
+        .await
+        .expect("pre-empted interrupt should return promptly")
+        .expect("interrupt task should join")
+        .expect_err("pre-empted interrupt should report pre-emption");
+    assert!(first_err.contains("pre-empted"));
+
+    let duplicate_err = timeout(Duration::from_secs(2), duplicate_interrupt)
+        .await
+        .expect("joined interrupt should return with the original")
+        .expect("duplicate task should join")
+        .expect_err("joined interrupt should share the original outcome");
+    assert!(duplicate_err.contains("pre-empted"));
+    assert_eq!(runtime.interrupt_attempts(), 1, "duplicate interrupt must not signal again");
+    assert!(runtime.was_killed());
+
+    lock_holder.await.expect("lock holder should join");
+    wait_for_runtime_exit(runtime).await;
+    let _ = supervisor
+        .finalize_terminal_transition(db.as_ref(), "kill-preempt", "killed", None)
+        .await
+        .expect("kill finalization should succeed");
+    let _ = supervisor.remove("kill-preempt").await;
+
+    let stored = db
+        .get_session("kill-preempt")
+        .expect("session query should succeed")
+        .expect("session should exist");
+    assert_eq!(stored.status, "killed");
+    assert!(supervisor.lifecycle_operation("kill-preempt").is_none());
+}
+
 #[tokio::test]
 async fn resume_reuses_same_row_updates_metadata_and_keeps_terminal_idempotent() {
     let temp = tempdir().expect("tempdir should be created");
@@ -480,16 +587,40 @@ async fn resume_reuses_same_row_updates_metadata_and_keeps_terminal_idempotent()
     })
     .expect("session should persist");
 
-    let existing_gate = supervisor
-        .acquire_lifecycle_operation("resume-session", "interrupt")
-        .expect("initial operation gate should lock");
-    let gate_err = match supervisor.acquire_lifecycle_operation("resume-session", "resume") {
-        Ok(_) => panic!("second operation should be blocked"),
-        Err(err) => err,
+    let existing_gate = match supervisor
+        .acquire_lifecycle_operation("resume-session", LifecycleOperationKind::Interrupt)
+        .await
+        .expect("initial operation gate should lock")
+    {
+        LifecycleAdmission::Acquired(guard) => guard,
+        LifecycleAdmission::Joined(_) => panic!("first operation should acquire the gate"),
     };
-    assert!(gate_err.contains("in-progress"));
+
+    let supervisor_for_resume = supervisor.clone();
+    let waiting_resume = tokio::spawn(async move {
+        matches!(
+            supervisor_for_resume
+                .acquire_lifecycle_operation("resume-session", LifecycleOperationKind::Resume)
+                .await,
+            Ok(LifecycleAdmission::Acquired(_))
+        )
+    });
+
+    sleep(Duration::from_millis(100)).await;
+    assert!(!waiting_resume.is_finished(), "resume should wait for the in-progress interrupt");
+    let active = supervisor
+        .lifecycle_operation("resume-session")
+        .expect("in-progress operation should be introspectable");
+    assert_eq!(active.operation, LifecycleOperationKind::Interrupt);
     drop(existing_gate);
 
+    let acquired = timeout(Duration::from_secs(2), waiting_resume)
+        .await
+        .expect("resume should proceed once the interrupt releases")
+        .expect("resume waiter should join");
+    assert!(acquired, "resume should acquire the gate after waiting");
+    assert!(supervisor.lifecycle_operation("resume-session").is_none());
+
     let resumed_at = chrono::Utc::now().to_rfc3339();
     let run_id = uuid::Uuid::new_v4().to_string();
     let resumed = db
@@ -720,3 +851,71 @@ async fn cli_spawn_failure_is_actionable_and_retry_path_recovers() {
         .expect("retry process should finish cleanly");
     assert!(exit.success(), "retry process should exit successfully");
 }
+
+#[tokio::test]
+async fn worktree_operations_hold_the_lifecycle_gate_and_a_second_one_waits() {
+    for held in [
+        LifecycleOperationKind::Sync,
+        LifecycleOperationKind::Merge,
+        LifecycleOperationKind::Rewind,
+    ] {
+        let supervisor = Arc::new(SessionSupervisor::new());
+        let gate = match supervisor
+            .acquire_lifecycle_operation("worktree-session", held)
+            .await
+            .expect("operation should lock the gate")
+        {
+            LifecycleAdmission::Acquired(guard) => guard,
+            LifecycleAdmission::Joined(_) => panic!("first operation should acquire the gate"),
+        };
+
+        let mut waiters = Vec::new();
+        for operation in [LifecycleOperationKind::Resume, LifecycleOperationKind::Sync, held] {
+            let supervisor = supervisor.clone();
+            waiters.push(tokio::spawn(async move {
+                match supervisor.acquire_lifecycle_operation("worktree-session", operation).await {
+                    Ok(LifecycleAdmission::Acquired(_)) => "acquired",
+                    Ok(LifecycleAdmission::Joined(_)) => "joined",
+                    Err(_) => "failed",
+                }
+            }));
+        }
+
+        sleep(Duration::from_millis(100)).await;
+        assert!(
+            waiters.iter().all(|waiter| !waiter.is_finished()),
+            "everything should wait for the {}",
+            held.as_str()
+        );
+        drop(gate);
+
+        for waiter in waiters {
+            let admission = timeout(Duration::from_secs(2), waiter)
+                .await
+                .expect("waiters should proceed once the operation releases")
+                .expect("waiter should join");
+            assert_eq!(admission, "acquired");
+        }
+    }
+}
+
+#[tokio::test]
+async fn worktree_sweeps_wait_for_spawns_that_are_still_recording_their_worktree() {
+    let supervisor = Arc::new(SessionSupervisor::new());
+    let creation = supervisor.lock_worktree_creation().await;
+    let second_creation = supervisor.lock_worktree_creation().await;
+
+    let sweeper = supervisor.clone();
+    let sweep = tokio::spawn(async move {
+        let _sweep = sweeper.lock_worktree_sweep().await;
+    });
+    sleep(Duration::from_millis(100)).await;
+    assert!(!sweep.is_finished(), "the sweep should wait for both spawns");
+
+    drop(creation);
+    drop(second_creation);
+    timeout(Duration::from_secs(2), sweep)
+        .await
+        .expect("the sweep should run once the spawns are recorded")
+        .expect("sweep task should finish");
+}
@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tauri_app_lib::db::{init_database, Database, Session};
+use tauri_app_lib::session::checkpoints::{
+    create_checkpoint, remove_checkpoint_refs, rewind_to_checkpoint, spawn_checkpoint_worker,
+    CHECKPOINT_REF_PREFIX,
+};
+use tauri_app_lib::session::WorktreeService;
+
+mod common;
+
+use common::{git, init_repo};
+
+fn session_with_worktree(db: &Database, repo_root: &str, id: &str) -> PathBuf {
+    let worktree = WorktreeService::new(repo_root)
+        .create_worktree(id, &format!("lulu/{}", id))
+        .expect("worktree should create");
+
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: id.to_string(),
+        status: "completed".to_string(),
+        working_dir: repo_root.to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+    db.update_worktree_path(id, Some(&worktree.display().to_string()))
+        .expect("worktree path should persist");
+
+    worktree
+}
+
+/// Records a tool result at `seq` and checkpoints after it, as the event loop does.
+fn tool_result_checkpoint(
+    db: &Database,
+    session_id: &str,
+    seq: u64,
+) -> Option<tauri_app_lib::db::SessionCheckpoint> {
+    db.insert_session_event(
+        session_id,
+        "run-1",
+        seq,
+        "tool_result",
+        &serde_json::json!({ "type": "tool_result", "data": {} }),
+        &chrono::Utc::now().to_rfc3339(),
+    )
+    .expect("event should persist");
+    create_checkpoint(db, session_id, "run-1", seq).expect("checkpoint should succeed")
+}
+
+#[test]
+fn checkpoints_follow_worktree_changes_and_rewind_restores_them() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    let worktree = session_with_worktree(&db, &repo_root, "rewind");
+
+    std::fs::write(worktree.join("README.md"), "# edited\n").expect("edit should write");
+    let first = tool_result_checkpoint(&db, "rewind", 1).expect("an edit should checkpoint");
+    assert_eq!(first.changed_paths, vec!["README.md".to_string()]);
+    assert!(tool_result_checkpoint(&db, "rewind", 2).is_none(), "nothing changed since");
+
+    std::fs::write(worktree.join("feature.txt"), "feature\n").expect("feature should write");
+    git(&worktree, &["add", "feature.txt"]);
+    git(&worktree, &["commit", "-m", "feature"]);
+    std::fs::write(worktree.join("notes.txt"), "notes\n").expect("notes should write");
+    let second = tool_result_checkpoint(&db, "rewind", 3).expect("a commit should checkpoint");
+    assert_eq!(second.changed_paths, vec!["feature.txt".to_string(), "notes.txt".to_string()]);
+    assert_ne!(second.head_sha, first.head_sha);
+
+    std::fs::remove_file(worktree.join("README.md")).expect("delete should work");
+    std::fs::write(worktree.join("junk.txt"), "off the rails\n").expect("junk should write");
+
+    let (rewound, events) =
+        rewind_to_checkpoint(&db, "rewind", &first.id).expect("rewind should succeed");
+    assert_eq!(rewound.id, first.id);
+    assert_eq!(git(&worktree, &["rev-parse", "HEAD"]), first.head_sha);
+    assert_eq!(
+        std::fs::read_to_string(worktree.join("README.md")).expect("readme should be back"),
+        "# edited\n"
+    );
+    assert!(!worktree.join("feature.txt").exists());
+    assert!(!worktree.join("notes.txt").exists());
+    assert!(!worktree.join("junk.txt").exists());
+    assert_eq!(git(&worktree, &["status", "--porcelain"]), "M README.md");
+    assert_eq!(events.len(), 1);
+    let history = db.list_session_history("rewind").expect("history should load");
+    let rewind = history.last().expect("rewind event should be recorded");
+    assert_eq!(rewind.event_type, "rewind");
+    assert_eq!(rewind.payload_json["data"]["checkpoint_id"], first.id);
+    assert_eq!(rewind.payload_json["data"]["seq"], 1);
+
+    let checkpoints = db.list_session_checkpoints("rewind").expect("checkpoints should list");
+    assert_eq!(checkpoints.len(), 3, "the state before the rewind is checkpointed too");
+    let before_rewind = checkpoints.last().expect("pre-rewind checkpoint");
+    rewind_to_checkpoint(&db, "rewind", &before_rewind.id).expect("undo should succeed");
+    assert_eq!(git(&worktree, &["rev-parse", "HEAD"]), second.head_sha);
+    assert!(!worktree.join("README.md").exists());
+    assert_eq!(
+        std::fs::read_to_string(worktree.join("junk.txt")).expect("junk should be back"),
+        "off the rails\n"
+    );
+    assert!(worktree.join("notes.txt").exists());
+}
+
+#[test]
+fn checkpoint_refs_stay_hidden_and_are_removed_with_the_session() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    let worktree = session_with_worktree(&db, &repo_root, "refs");
+
+    std::fs::write(worktree.join("README.md"), "# edited\n").expect("edit should write");
+    let checkpoint = tool_result_checkpoint(&db, "refs", 1).expect("an edit should checkpoint");
+    assert!(checkpoint.ref_name.starts_with(CHECKPOINT_REF_PREFIX));
+    assert_eq!(git(repo.path(), &["rev-parse", &checkpoint.ref_name]), checkpoint.commit_sha);
+    assert!(!git(repo.path(), &["branch", "--all"]).contains("checkpoint"));
+
+    remove_checkpoint_refs(repo.path(), "refs").expect("refs should be removed");
+    let prefix = format!("{}/refs/", CHECKPOINT_REF_PREFIX);
+    assert!(git(repo.path(), &["for-each-ref", &prefix]).is_empty());
+}
+
+#[tokio::test]
+async fn checkpoint_worker_coalesces_requests_queued_behind_a_running_checkpoint() {
+    let taken = Arc::new(Mutex::new(Vec::new()));
+    let taken_by_worker = taken.clone();
+    let (done_tx, mut done_rx) = tokio::sync::mpsc::unbounded_channel();
+    let checkpoints = spawn_checkpoint_worker(move |seq| {
+        std::thread::sleep(Duration::from_millis(100));
+        taken_by_worker.lock().expect("lock").push(seq);
+        let _ = done_tx.send(());
+    });
+
+    checkpoints.send(3).expect("worker should accept");
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    for seq in [5, 8, 13] {
+        checkpoints.send(seq).expect("worker should accept");
+    }
+    drop(checkpoints);
+
+    while done_rx.recv().await.is_some() {}
+    assert_eq!(*taken.lock().expect("lock"), vec![3, 13]);
+}
@@ -0,0 +1,117 @@
+use tauri_app_lib::session::export::{write_session_export, ExportFormat, ExportRequest};
+use tauri_app_lib::session::WorktreeService;
+use tempfile::tempdir;
+
+mod common;
+
+use common::{git, init_repo_with};
+
+fn init_repo() -> tempfile::TempDir {
+    init_repo_with(|dir| {
+        std::fs::write(dir.join("README.md"), "# test\n").expect("seed file should write");
+        git(dir, &["add", "README.md"]);
+        git(dir, &["commit", "-m", "initial"]);
+    })
+    .0
+}
+
+/// Session with one commit and one uncommitted new file.
+fn session_with_work(repo: &std::path::Path) -> (WorktreeService, std::path::PathBuf, String) {
+    let service = WorktreeService::new(repo);
+    let base_sha = service.resolve_commit("HEAD").expect("HEAD should resolve");
+    let worktree = service
+        .create_worktree_at("export-session", "lulu/export-session", &base_sha)
+        .expect("worktree should create");
+    std::fs::write(worktree.join("committed.txt"), "committed\n").expect("file should write");
+    git(&worktree, &["add", "committed.txt"]);
+    git(&worktree, &["commit", "-m", "add committed file"]);
+    std::fs::write(worktree.join("draft.txt"), "draft\n").expect("file should write");
+
+    (service, worktree, base_sha)
+}
+
+fn request(
+    worktree: &std::path::Path,
+    base_sha: &str,
+    format: ExportFormat,
+    destination: std::path::PathBuf,
+) -> ExportRequest {
+    ExportRequest {
+        session_branch: "lulu/export-session".to_string(),
+        worktree_path: worktree.to_path_buf(),
+        base_sha: base_sha.to_string(),
+        format,
+        destination,
+    }
+}
+
+#[test]
+fn patch_series_includes_commits_uncommitted_snapshot_and_manifest() {
+    let repo = init_repo();
+    let (service, worktree, base_sha) = session_with_work(repo.path());
+    let out = tempdir().expect("tempdir should be created");
+
+    let result = write_session_export(
+        &service,
+        &request(&worktree, &base_sha, ExportFormat::PatchSeries, out.path().join("series")),
+        serde_json::json!({ "session": { "name": "Export me", "prompt": "do it" } }),
+    )
+    .expect("export should succeed");
+
+    assert!(result.includes_uncommitted);
+    assert_eq!(result.files.len(), 2);
+    assert!(result.files.iter().all(|file| std::path::Path::new(file).exists()));
+    assert_eq!(result.commits[0].subject, "add committed file");
+    assert_eq!(git(&worktree, &["status", "--porcelain"]), "?? draft.txt");
+
+    let manifest: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(&result.manifest_path).expect("manifest should read"),
+    )
+    .expect("manifest should be JSON");
+    assert_eq!(manifest["session"]["prompt"], "do it");
+    assert_eq!(manifest["export"]["base_sha"], base_sha);
+    assert_eq!(manifest["export"]["commits"].as_array().map(Vec::len), Some(2));
+
+    let target = init_repo();
+    for file in &result.files {
+        git(target.path(), &["am", file]);
+    }
+    assert!(target.path().join("committed.txt").exists());
+    assert!(target.path().join("draft.txt").exists());
+}
+
+#[test]
+fn combined_patch_and_bundle_can_be_applied_elsewhere() {
+    let repo = init_repo();
+    let (service, worktree, base_sha) = session_with_work(repo.path());
+    let out = tempdir().expect("tempdir should be created");
+
+    let patch = write_session_export(
+        &service,
+        &request(&worktree, &base_sha, ExportFormat::Patch, out.path().join("session.patch")),
+        serde_json::json!({}),
+    )
+    .expect("patch export should succeed");
+    assert!(patch.manifest_path.ends_with("session.patch.manifest.json"));
+    let target = init_repo();
+    git(target.path(), &["am", &patch.files[0]]);
+    assert_eq!(git(target.path(), &["rev-list", "--count", "HEAD"]), "3");
+
+    let bundle = write_session_export(
+        &service,
+        &request(&worktree, &base_sha, ExportFormat::Bundle, out.path().join("session.bundle")),
+        serde_json::json!({}),
+    )
+    .expect("bundle export should succeed");
+    let bundle_ref = bundle.bundle_ref.clone().expect("bundle should name its ref");
+    assert!(
+        git(repo.path(), &["for-each-ref", "refs/lulu/export"]).is_empty(),
+        "temporary export ref should be removed"
+    );
+
+    let clone = tempdir().expect("tempdir should be created");
+    git(clone.path(), &["clone", "-q", &repo.path().display().to_string(), "."]);
+    git(clone.path(), &["fetch", "-q", &bundle.files[0], &format!("{}:imported", bundle_ref)]);
+    assert_eq!(git(clone.path(), &["rev-parse", "imported"]), bundle.tip_sha);
+    assert_eq!(git(clone.path(), &["show", "imported:draft.txt"]), "draft");
+}
@@ -0,0 +1,181 @@
+use serde_json::json;
+use tempfile::tempdir;
+
+use tauri_app_lib::db::{
+    init_database, Database, HistoryCursor, HistoryPageQuery, MessageCursor, MessagePageQuery,
+    Session,
+};
+
+fn database_with_history(dir: &std::path::Path) -> Database {
+    let db = init_database(&dir.join("lulu.db")).expect("database should initialize");
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: "paged".to_string(),
+        name: "paged".to_string(),
+        status: "completed".to_string(),
+        working_dir: "/tmp".to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+
+    // Two runs of 30 events; every third one is a tool result, and pairs share a timestamp so
+    // the cursor has to break ties on seq.
+    for (run, offset) in [("run-1", 0), ("run-2", 30)] {
+        for seq in 1..=30u64 {
+            let event_type = if seq % 3 == 0 { "tool_result" } else { "message" };
+            let second = (offset + seq).div_ceil(2);
+            let at = format!("2025-01-01T00:{:02}:{:02}Z", second / 60, second % 60);
+            db.insert_session_event(
+                "paged",
+                run,
+                seq,
+                event_type,
+                &json!({ "type": event_type, "data": { "n": offset + seq } }),
+                &at,
+            )
+            .expect("event should persist");
+        }
+    }
+    for n in 0..25 {
+        let at = format!("2025-01-01T01:00:{:02}Z", n / 2);
+        db.insert_session_message("paged", "assistant", &format!("message {}", n), &at)
+            .expect("message should persist");
+    }
+    db
+}
+
+fn numbers(page: &tauri_app_lib::db::SessionHistoryPage) -> Vec<u64> {
+    page.events.iter().map(|event| event.payload_json["data"]["n"].as_u64().expect("n")).collect()
+}
+
+#[test]
+fn history_pages_walk_forwards_and_backwards_without_gaps() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = database_with_history(dir.path());
+    let all: Vec<u64> = (1..=60).collect();
+
+    let mut forwards = Vec::new();
+    let mut query = HistoryPageQuery { limit: Some(7), ..Default::default() };
+    loop {
+        let page = db.list_session_history_page("paged", &query).expect("page should load");
+        forwards.extend(numbers(&page));
+        if !page.has_more {
+            break;
+        }
+        query.after = page.events.last().map(HistoryCursor::from);
+    }
+    assert_eq!(forwards, all);
+
+    let mut backwards = Vec::new();
+    let mut query = HistoryPageQuery { limit: Some(7), tail: true, ..Default::default() };
+    loop {
+        let page = db.list_session_history_page("paged", &query).expect("page should load");
+        let mut chunk = numbers(&page);
+        chunk.extend(backwards);
+        backwards = chunk;
+        if !page.has_more {
+            break;
+        }
+        query.before = page.events.first().map(HistoryCursor::from);
+    }
+    assert_eq!(backwards, all);
+
+    let newest = db
+        .list_session_history_page(
+            "paged",
+            &HistoryPageQuery { limit: Some(3), tail: true, ..Default::default() },
+        )
+        .expect("page should load");
+    assert_eq!(numbers(&newest), [58, 59, 60], "pages are oldest first even from the end");
+}
+
+#[test]
+fn history_pages_filter_by_run_and_event_type_and_count_matches() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = database_with_history(dir.path());
+
+    let query = HistoryPageQuery {
+        run_id: Some("run-2".to_string()),
+        event_types: vec!["tool_result".to_string()],
+        limit: Some(4),
+        ..Default::default()
+    };
+    let first = db.list_session_history_page("paged", &query).expect("page should load");
+    assert_eq!(numbers(&first), [33, 36, 39, 42]);
+    assert!(first.has_more);
+    let second = db
+        .list_session_history_page(
+            "paged",
+            &HistoryPageQuery { after: first.events.last().map(HistoryCursor::from), ..query },
+        )
+        .expect("page should load");
+    assert_eq!(numbers(&second), [45, 48, 51, 54]);
+
+    assert_eq!(db.count_session_history("paged", None, &[]).expect("count"), 60);
+    assert_eq!(db.count_session_history("paged", Some("run-1"), &[]).expect("count"), 30);
+    assert_eq!(
+        db.count_session_history("paged", Some("run-2"), &["tool_result".to_string()])
+            .expect("count"),
+        10
+    );
+    assert_eq!(db.count_session_history("missing", None, &[]).expect("count"), 0);
+}
+
+#[test]
+fn message_pages_follow_the_same_cursor_rules() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = database_with_history(dir.path());
+    let content = |page: &tauri_app_lib::db::SessionMessagePage| -> Vec<String> {
+        page.messages.iter().map(|message| message.content.clone()).collect()
+    };
+
+    let all = db.list_session_messages("paged").expect("messages should load");
+    let mut walked = Vec::new();
+    let mut query = MessagePageQuery { limit: Some(10), ..Default::default() };
+    loop {
+        let page = db.list_session_messages_page("paged", &query).expect("page should load");
+        walked.extend(page.messages.iter().map(|message| message.id.clone()));
+        if !page.has_more {
+            break;
+        }
+        query.after = page.messages.last().map(MessageCursor::from);
+    }
+    let mut expected: Vec<(String, String)> =
+        all.iter().map(|message| (message.timestamp.clone(), message.id.clone())).collect();
+    expected.sort();
+    assert_eq!(walked, expected.into_iter().map(|(_, id)| id).collect::<Vec<_>>());
+
+    let tail = db
+        .list_session_messages_page(
+            "paged",
+            &MessagePageQuery { limit: Some(1), tail: true, ..Default::default() },
+        )
+        .expect("page should load");
+    assert_eq!(content(&tail), ["message 24"]);
+    assert_eq!(db.count_session_messages("paged").expect("count"), 25);
+}
+
+#[test]
+fn history_pages_are_index_range_scans() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = database_with_history(dir.path());
+    let conn = db.conn.lock().expect("connection lock");
+
+    let plan: Vec<String> = conn
+        .prepare(
+            "EXPLAIN QUERY PLAN
+             SELECT id FROM session_events
+             WHERE session_id = ?1 AND (timestamp, seq, id) > (?2, ?3, ?4)
+             ORDER BY timestamp ASC, seq ASC, id ASC
+             LIMIT 10",
+        )
+        .expect("plan should prepare")
+        .query_map(["paged", "2025-01-01T00:00:10Z", "1", "x"], |row| row.get(3))
+        .expect("plan should run")
+        .map(|detail| detail.expect("plan detail"))
+        .collect();
+    let plan = plan.join("\n");
+    assert!(plan.contains("idx_session_events_session_id_timestamp"), "{}", plan);
+    assert!(!plan.contains("TEMP B-TREE"), "pages must not sort in memory: {}", plan);
+}
@@ -0,0 +1,130 @@
+use serde_json::json;
+
+use tauri_app_lib::db::{init_database, Database, Session};
+use tauri_app_lib::session::overlap::{
+    edited_file_path, list_repo_conflicts, refresh_repo_diff_paths, track_tool_call,
+};
+use tauri_app_lib::session::WorktreeService;
+
+mod common;
+
+use common::{git, init_repo_with};
+
+fn init_repo() -> (tempfile::TempDir, String) {
+    init_repo_with(|dir| {
+        std::fs::write(dir.join(".gitignore"), "*.db\n").expect("gitignore should write");
+        std::fs::create_dir_all(dir.join("src")).expect("src should be created");
+        std::fs::write(dir.join("src/lib.rs"), "pub fn answer() {}\n")
+            .expect("seed file should write");
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", "initial"]);
+    })
+}
+
+fn session_with_worktree(
+    db: &Database,
+    repo_root: &str,
+    id: &str,
+    status: &str,
+) -> std::path::PathBuf {
+    let service = WorktreeService::new(repo_root);
+    let head = service.resolve_commit("HEAD").expect("HEAD should resolve");
+    let worktree =
+        service.create_worktree(id, &format!("lulu/{}", id)).expect("worktree should create");
+
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: format!("Session {}", id),
+        status: status.to_string(),
+        working_dir: repo_root.to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+    db.update_worktree_path(id, Some(&worktree.display().to_string()))
+        .expect("worktree path should persist");
+    db.update_session_base(id, Some("main"), Some(&head)).expect("base should persist");
+
+    worktree
+}
+
+#[test]
+fn edited_file_path_only_reads_edit_tools() {
+    assert_eq!(
+        edited_file_path("Edit", &json!({ "file_path": "/repo/src/lib.rs" })).as_deref(),
+        Some("/repo/src/lib.rs")
+    );
+    assert_eq!(
+        edited_file_path("NotebookEdit", &json!({ "notebook_path": "analysis.ipynb" })).as_deref(),
+        Some("analysis.ipynb")
+    );
+    assert_eq!(edited_file_path("Read", &json!({ "file_path": "/repo/src/lib.rs" })), None);
+    assert_eq!(edited_file_path("Write", &json!({ "content": "no path" })), None);
+}
+
+#[test]
+fn edits_to_the_same_file_warn_both_active_sessions_once() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    let first = session_with_worktree(&db, &repo_root, "first", "running");
+    let second = session_with_worktree(&db, &repo_root, "second", "running");
+
+    let edit = |worktree: &std::path::Path| {
+        json!({ "file_path": worktree.join("src/lib.rs").display().to_string() })
+    };
+    let warnings =
+        track_tool_call(&db, "first", "Edit", &edit(&first)).expect("edit should be tracked");
+    assert!(warnings.is_empty());
+
+    let warnings =
+        track_tool_call(&db, "second", "Write", &edit(&second)).expect("edit should be tracked");
+    assert_eq!(warnings.len(), 2, "both sessions get a warning");
+
+    let history = db.list_session_history("first").expect("history should load");
+    let warning = history.last().expect("warning should be recorded");
+    assert_eq!(warning.event_type, "conflict_warning");
+    assert_eq!(warning.payload_json["data"]["path"], "src/lib.rs");
+    assert_eq!(warning.payload_json["data"]["other_session_id"], "second");
+    assert_eq!(warning.payload_json["data"]["other_session_name"], "Session second");
+
+    let repeated =
+        track_tool_call(&db, "first", "Edit", &edit(&first)).expect("edit should be tracked");
+    assert!(repeated.is_empty(), "an overlap is only reported once");
+
+    let conflicts = list_repo_conflicts(&db, &repo_root).expect("conflicts should list");
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].path, "src/lib.rs");
+    let ids: Vec<&str> =
+        conflicts[0].sessions.iter().map(|session| session.session_id.as_str()).collect();
+    assert_eq!(ids, vec!["first", "second"]);
+    assert!(conflicts[0].sessions.iter().all(|session| session.from_tool && !session.from_diff));
+}
+
+#[test]
+fn worktree_diffs_reveal_overlaps_between_active_sessions_only() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    let first = session_with_worktree(&db, &repo_root, "first", "running");
+    let second = session_with_worktree(&db, &repo_root, "second", "running");
+    let finished = session_with_worktree(&db, &repo_root, "finished", "completed");
+
+    for worktree in [&first, &second, &finished] {
+        std::fs::write(worktree.join("src/lib.rs"), "pub fn answer() -> u8 { 42 }\n")
+            .expect("edit should write");
+    }
+    std::fs::write(first.join("notes.md"), "only here\n").expect("untracked file should write");
+
+    let warnings = refresh_repo_diff_paths(&db, &repo_root).expect("diffs should refresh");
+    assert_eq!(warnings.len(), 2);
+
+    let conflicts = list_repo_conflicts(&db, &repo_root).expect("conflicts should list");
+    assert_eq!(conflicts.len(), 1, "untracked files of one session alone don't conflict");
+    assert_eq!(conflicts[0].path, "src/lib.rs");
+    assert_eq!(conflicts[0].sessions.len(), 2, "finished sessions are left out");
+    assert!(conflicts[0].sessions.iter().all(|session| session.from_diff));
+
+    std::fs::write(second.join("src/lib.rs"), "pub fn answer() {}\n").expect("revert should write");
+    refresh_repo_diff_paths(&db, &repo_root).expect("diffs should refresh");
+    assert!(list_repo_conflicts(&db, &repo_root).expect("conflicts should list").is_empty());
+}
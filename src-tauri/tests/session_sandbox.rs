@@ -0,0 +1,133 @@
+use tempfile::tempdir;
+
+use tauri_app_lib::session::diff::DiffLimits;
+use tauri_app_lib::session::sandbox::{sandbox_tree_for, SandboxLimits, SessionSandbox};
+
+fn seed_folder() -> tempfile::TempDir {
+    let dir = tempdir().expect("tempdir should be created");
+    std::fs::create_dir_all(dir.path().join("docs")).expect("docs should be created");
+    std::fs::write(dir.path().join("docs/plan.md"), "step one\n").expect("plan should write");
+    std::fs::write(dir.path().join("todo.txt"), "buy milk\n").expect("todo should write");
+    std::fs::write(dir.path().join("scratch.txt"), "temporary\n").expect("scratch should write");
+    dir
+}
+
+#[test]
+fn sandbox_isolates_edits_and_diffs_them_against_the_original() {
+    let source = seed_folder();
+    let app_data = tempdir().expect("app data tempdir should be created");
+    let sandbox = SessionSandbox::create(source.path(), &sandbox_tree_for(app_data.path(), "s1"))
+        .expect("sandbox should be created");
+
+    std::fs::write(sandbox.tree().join("docs/plan.md"), "step one\nstep two\n")
+        .expect("edit should write");
+    std::fs::write(sandbox.tree().join("new.txt"), "fresh\n").expect("new file should write");
+    std::fs::remove_file(sandbox.tree().join("scratch.txt")).expect("delete should work");
+    std::fs::write(sandbox.tree().join("todo.txt"), "buy milk\n").expect("rewrite should work");
+
+    assert_eq!(
+        std::fs::read_to_string(source.path().join("docs/plan.md")).expect("original should read"),
+        "step one\n",
+        "the original folder must not change"
+    );
+
+    let diff = sandbox.diff(DiffLimits::default()).expect("diff should succeed");
+    let summary: Vec<(&str, &str)> =
+        diff.files.iter().map(|file| (file.path.as_str(), file.status.as_str())).collect();
+    assert_eq!(
+        summary,
+        vec![("docs/plan.md", "modified"), ("new.txt", "added"), ("scratch.txt", "deleted")],
+        "rewriting a file with the same content is not a change"
+    );
+    assert_eq!((diff.additions, diff.deletions), (2, 1));
+    assert!(diff.base_sha.is_none(), "a sandbox has no base commit");
+    assert!(diff.snapshot_at.is_some());
+    let patch = diff.files[0].patch.as_deref().expect("patch should be included");
+    assert!(patch.starts_with("diff --git a/docs/plan.md b/docs/plan.md\n"), "{}", patch);
+    assert!(patch.contains("--- a/docs/plan.md\n+++ b/docs/plan.md\n"));
+    assert!(patch.contains("+step two"));
+    let added = diff.files[1].patch.as_deref().expect("added files carry a patch");
+    assert!(added.starts_with("diff --git a/new.txt b/new.txt\nnew file mode "), "{}", added);
+    assert!(added.contains("--- /dev/null\n+++ b/new.txt\n"), "{}", added);
+    let deleted = diff.files[2].patch.as_deref().expect("deleted files carry a patch");
+    assert!(deleted.contains("deleted file mode "), "{}", deleted);
+    assert!(deleted.contains("--- a/scratch.txt\n+++ /dev/null\n"), "{}", deleted);
+
+    let capped = sandbox
+        .diff(DiffLimits { max_file_bytes: 4096, max_total_bytes: 1 })
+        .expect("capped diff should succeed");
+    assert!(capped.truncated);
+    assert!(capped.files.iter().all(|file| file.patch.is_none() && file.truncated));
+    assert_eq!((capped.additions, capped.deletions), (2, 1), "counts survive the cap");
+}
+
+#[test]
+fn applying_changes_back_skips_conflicts_unless_forced() {
+    let source = seed_folder();
+    let app_data = tempdir().expect("app data tempdir should be created");
+    let sandbox = SessionSandbox::create(source.path(), &sandbox_tree_for(app_data.path(), "s2"))
+        .expect("sandbox should be created");
+
+    std::fs::write(sandbox.tree().join("docs/plan.md"), "sandbox plan\n").expect("edit");
+    std::fs::write(sandbox.tree().join("todo.txt"), "sandbox todo\n").expect("edit");
+    std::fs::write(sandbox.tree().join("new.txt"), "fresh\n").expect("new file");
+    std::fs::remove_file(sandbox.tree().join("scratch.txt")).expect("delete");
+    std::fs::write(source.path().join("todo.txt"), "edited by hand\n").expect("user edit");
+
+    let report = sandbox.apply(false).expect("apply should succeed");
+    assert_eq!(report.applied, vec!["docs/plan.md".to_string(), "new.txt".to_string()]);
+    assert_eq!(report.deleted, vec!["scratch.txt".to_string()]);
+    assert_eq!(report.conflicts, vec!["todo.txt".to_string()]);
+    assert_eq!(
+        std::fs::read_to_string(source.path().join("docs/plan.md")).expect("plan should read"),
+        "sandbox plan\n"
+    );
+    assert!(source.path().join("new.txt").exists());
+    assert!(!source.path().join("scratch.txt").exists());
+    assert_eq!(
+        std::fs::read_to_string(source.path().join("todo.txt")).expect("todo should read"),
+        "edited by hand\n"
+    );
+
+    let again = sandbox.apply(false).expect("second apply should succeed");
+    assert!(again.applied.is_empty() && again.deleted.is_empty());
+    assert_eq!(again.conflicts, vec!["todo.txt".to_string()]);
+
+    let forced = sandbox.apply(true).expect("forced apply should succeed");
+    assert_eq!(forced.applied, vec!["todo.txt".to_string()]);
+    assert_eq!(
+        std::fs::read_to_string(source.path().join("todo.txt")).expect("todo should read"),
+        "sandbox todo\n"
+    );
+    assert!(sandbox.diff(DiffLimits::default()).expect("diff should succeed").files.is_empty());
+
+    sandbox.remove().expect("sandbox should be removed");
+    assert!(!sandbox.tree().exists());
+}
+
+#[test]
+fn sandbox_refuses_a_location_inside_the_source_folder() {
+    let source = seed_folder();
+    let error = SessionSandbox::create(source.path(), &sandbox_tree_for(source.path(), "nested"))
+        .expect_err("copying a folder into itself must fail");
+    assert!(error.contains("inside"), "unexpected error: {}", error);
+    assert!(!source.path().join("sandboxes").exists());
+}
+
+#[test]
+fn sandbox_refuses_folders_over_the_size_caps() {
+    let source = seed_folder();
+    let app_data = tempdir().expect("app data tempdir should be created");
+    let tree = sandbox_tree_for(app_data.path(), "big");
+
+    let limits = SandboxLimits { max_bytes: 16, ..SandboxLimits::default() };
+    let error = SessionSandbox::create_with_limits(source.path(), &tree, limits)
+        .expect_err("a folder over the byte cap must not be copied");
+    assert!(error.contains("bytes"), "unexpected error: {}", error);
+    assert!(!tree.exists(), "nothing is left behind");
+
+    let limits = SandboxLimits { max_files: 2, ..SandboxLimits::default() };
+    let error = SessionSandbox::create_with_limits(source.path(), &tree, limits)
+        .expect_err("a folder over the file cap must not be copied");
+    assert!(error.contains("files"), "unexpected error: {}", error);
+}
@@ -0,0 +1,187 @@
+use rusqlite::Connection;
+use serde_json::json;
+use tempfile::tempdir;
+
+use tauri_app_lib::db::migrations::{apply_migrations, MIGRATIONS};
+use tauri_app_lib::db::search::{SNIPPET_MATCH_END, SNIPPET_MATCH_START};
+use tauri_app_lib::db::{init_database, Database, DbError, Session, SessionSearchFilters};
+
+fn create_session(db: &Database, id: &str, working_dir: &str, status: &str) {
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: format!("{} session", id),
+        status: status.to_string(),
+        working_dir: working_dir.to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+}
+
+fn record(db: &Database, session_id: &str, seq: u64, payload: serde_json::Value, at: &str) {
+    let event_type = payload["type"].as_str().expect("payload type").to_string();
+    db.insert_session_event(session_id, "run-1", seq, &event_type, &payload, at)
+        .expect("event should persist");
+}
+
+fn seed(db: &Database) {
+    create_session(db, "auth", "/repos/api", "completed");
+    create_session(db, "docs", "/repos/site", "running");
+    record(
+        db,
+        "auth",
+        1,
+        json!({ "type": "tool_call", "data": {
+            "tool_name": "Edit",
+            "args": { "file_path": "src/middleware/auth.rs", "old_string": "verify_token(req)" }
+        }}),
+        "2025-03-03T10:00:00Z",
+    );
+    record(
+        db,
+        "auth",
+        2,
+        json!({ "type": "tool_result", "data": {
+            "tool_name": "Bash", "content": "test auth::middleware::rejects_expired ... ok"
+        }}),
+        "2025-03-03T10:01:00Z",
+    );
+    record(
+        db,
+        "docs",
+        1,
+        json!({ "type": "message", "data": {
+            "content": "Documented how the auth middleware refreshes tokens."
+        }}),
+        "2025-03-10T09:00:00Z",
+    );
+}
+
+#[test]
+fn search_finds_tool_calls_results_and_messages_with_highlighted_snippets() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    seed(&db);
+
+    let hits = db
+        .search_sessions("auth middleware", &SessionSearchFilters::default(), 50)
+        .expect("search should succeed");
+    let mut found: Vec<(String, Option<i64>)> =
+        hits.iter().map(|hit| (hit.session_id.clone(), hit.seq)).collect();
+    found.sort();
+    assert_eq!(
+        found,
+        [
+            ("auth".to_string(), Some(1)),
+            ("auth".to_string(), Some(2)),
+            ("docs".to_string(), Some(1))
+        ]
+    );
+
+    let edit = hits.iter().find(|hit| hit.event_type == "tool_call").expect("tool call hit");
+    assert_eq!(edit.session_name, "auth session");
+    assert_eq!(edit.run_id.as_deref(), Some("run-1"));
+    let highlighted = format!("{}auth{}", SNIPPET_MATCH_START, SNIPPET_MATCH_END);
+    assert!(edit.snippet.contains(&highlighted), "{:?}", edit.snippet);
+
+    let tokens = db
+        .search_sessions("verify_token OR refresh*", &SessionSearchFilters::default(), 50)
+        .expect("search should succeed");
+    assert_eq!(tokens.len(), 2, "{:?}", tokens);
+    let phrase = db
+        .search_sessions("\"rejects expired\"", &SessionSearchFilters::default(), 50)
+        .expect("search should succeed");
+    assert_eq!(phrase.len(), 1);
+    assert_eq!(phrase[0].event_type, "tool_result");
+}
+
+#[test]
+fn search_filters_narrow_by_repo_status_date_and_event_type() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    seed(&db);
+    let count = |filters: SessionSearchFilters| {
+        db.search_sessions("auth", &filters, 50).expect("search should succeed").len()
+    };
+
+    assert_eq!(count(SessionSearchFilters::default()), 3);
+    assert_eq!(
+        count(SessionSearchFilters { repo: Some("/repos/site".to_string()), ..Default::default() }),
+        1
+    );
+    assert_eq!(
+        count(SessionSearchFilters {
+            statuses: vec!["completed".to_string()],
+            ..Default::default()
+        }),
+        2
+    );
+    assert_eq!(
+        count(SessionSearchFilters {
+            from: Some("2025-03-03T10:00:30Z".to_string()),
+            to: Some("2025-03-04T00:00:00Z".to_string()),
+            ..Default::default()
+        }),
+        1
+    );
+    assert_eq!(
+        count(SessionSearchFilters {
+            event_types: vec!["tool_call".to_string(), "message".to_string()],
+            ..Default::default()
+        }),
+        2
+    );
+    assert_eq!(
+        db.search_sessions("auth", &SessionSearchFilters::default(), 1).expect("ok").len(),
+        1
+    );
+}
+
+#[test]
+fn index_follows_deletes_and_covers_rows_written_before_it_existed() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db_path = dir.path().join("lulu.db");
+    {
+        // A database from before the index, with history already in it.
+        let mut conn = Connection::open(&db_path).expect("database should open");
+        apply_migrations(&mut conn, &MIGRATIONS[..2]).expect("older schema should apply");
+        conn.execute_batch(
+            "INSERT INTO sessions (id, name, status, working_dir, created_at, updated_at)
+                VALUES ('old', 'Old', 'completed', '/repos/api', '2024-01-01', '2024-01-01');
+             INSERT INTO session_events
+                (id, session_id, run_id, seq, event_type, payload_json, timestamp)
+                VALUES ('e1', 'old', 'run-1', 1, 'tool_call',
+                        '{\"type\":\"tool_call\",\"data\":{\"tool_name\":\"Grep\",\"args\":{\"pattern\":\"ratelimiter\"}}}',
+                        '2024-01-01T00:00:00Z');
+             INSERT INTO messages (id, session_id, role, content, timestamp)
+                VALUES ('m1', 'old', 'user', 'please look at the ratelimiter', '2024-01-01T00:00:00Z');",
+        )
+        .expect("history should insert");
+    }
+
+    let db = init_database(&db_path).expect("database should migrate");
+    let hits = db
+        .search_sessions("ratelimiter", &SessionSearchFilters::default(), 50)
+        .expect("search should succeed");
+    assert_eq!(hits.len(), 2, "{:?}", hits);
+    assert!(hits.iter().any(|hit| hit.seq.is_none() && hit.event_type == "message"));
+
+    db.delete_session("old").expect("session should delete");
+    let hits = db
+        .search_sessions("ratelimiter", &SessionSearchFilters::default(), 50)
+        .expect("search should succeed");
+    assert!(hits.is_empty(), "deleted sessions drop out of the index: {:?}", hits);
+}
+
+#[test]
+fn malformed_queries_are_reported_as_errors() {
+    let dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&dir.path().join("lulu.db")).expect("database should initialize");
+    seed(&db);
+
+    let error = db
+        .search_sessions("\"unterminated", &SessionSearchFilters::default(), 50)
+        .expect_err("an unbalanced quote is a syntax error");
+    assert!(matches!(error, DbError::InvalidSearchQuery(_)), "unexpected error: {}", error);
+}
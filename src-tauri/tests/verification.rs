@@ -0,0 +1,156 @@
+use tempfile::tempdir;
+
+use tauri_app_lib::db::{init_database, Database, RepositorySettings, Session};
+use tauri_app_lib::session::projection::project_dashboard_row;
+use tauri_app_lib::session::verification::run_session_verification;
+use tauri_app_lib::session::SessionEventPayload;
+
+mod common;
+
+use common::{git, init_repo_with};
+
+fn init_repo() -> (tempfile::TempDir, String) {
+    init_repo_with(|dir| {
+        std::fs::write(dir.join("README.md"), "# test\n").expect("seed file should write");
+        git(dir, &["add", "README.md"]);
+        git(dir, &["commit", "-m", "initial"]);
+    })
+}
+
+fn create_completed_session(db: &Database, id: &str, working_dir: &str) {
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: format!("{} name", id),
+        status: "running".to_string(),
+        working_dir: working_dir.to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+    db.begin_run_attempt(id, "run-1").expect("run should begin");
+    db.update_session_status(id, "completed").expect("status should update");
+}
+
+fn verification_command(db: &Database, repo_root: &str, command: &str) {
+    db.save_repository_settings(&RepositorySettings {
+        repo_root: repo_root.to_string(),
+        verification_command: Some(command.to_string()),
+        ..Default::default()
+    })
+    .expect("settings should persist");
+}
+
+#[tokio::test]
+async fn passing_verification_streams_output_and_marks_run_passed() {
+    let (_repo, repo_root) = init_repo();
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&db_dir.path().join("lulu.db")).expect("database should initialize");
+
+    create_completed_session(&db, "verify-pass", &repo_root);
+    verification_command(&db, &repo_root, "echo checking; echo warn >&2; echo done");
+
+    let mut emitted = Vec::new();
+    let status = run_session_verification(&db, "verify-pass", |event| emitted.push(event.clone()))
+        .await
+        .expect("verification should run");
+    assert_eq!(status.as_deref(), Some("passed"));
+
+    let mut stdout_lines = Vec::new();
+    let mut stderr_lines = Vec::new();
+    for event in &emitted[..emitted.len() - 1] {
+        match &event.payload {
+            SessionEventPayload::VerificationOutput { stream, line } if stream == "stdout" => {
+                stdout_lines.push(line.clone())
+            }
+            SessionEventPayload::VerificationOutput { line, .. } => stderr_lines.push(line.clone()),
+            other => panic!("unexpected event before result: {:?}", other),
+        }
+    }
+    assert_eq!(stdout_lines, vec!["checking", "done"]);
+    assert_eq!(stderr_lines, vec!["warn"]);
+    assert!(matches!(
+        &emitted.last().expect("result event").payload,
+        SessionEventPayload::Verification { status, exit_code: Some(0), timed_out: false, .. }
+            if status == "passed"
+    ));
+
+    let history = db.list_session_history("verify-pass").expect("history should load");
+    assert_eq!(history.len(), emitted.len(), "every emitted event should be persisted");
+    assert!(history.iter().all(|event| event.run_id == "run-1"));
+    assert_eq!(history.last().expect("history").event_type, "verification");
+
+    let run = db
+        .get_session_run("verify-pass", "run-1")
+        .expect("run should load")
+        .expect("run should exist");
+    assert_eq!(run.verification_status.as_deref(), Some("passed"));
+    assert_eq!(run.verification_exit_code, Some(0));
+
+    let row = db
+        .get_dashboard_session("verify-pass")
+        .expect("dashboard should load")
+        .expect("dashboard row should exist");
+    assert_eq!(project_dashboard_row(row).verification_status.as_deref(), Some("passed"));
+}
+
+#[tokio::test]
+async fn failing_verification_records_exit_code_and_new_run_clears_projection() {
+    let (_repo, repo_root) = init_repo();
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&db_dir.path().join("lulu.db")).expect("database should initialize");
+
+    create_completed_session(&db, "verify-fail", &repo_root);
+    verification_command(&db, &repo_root, "echo broken; exit 3");
+
+    let status = run_session_verification(&db, "verify-fail", |_| {})
+        .await
+        .expect("verification should run");
+    assert_eq!(status.as_deref(), Some("failed"));
+
+    let run = db
+        .get_session_run("verify-fail", "run-1")
+        .expect("run should load")
+        .expect("run should exist");
+    assert_eq!(run.verification_status.as_deref(), Some("failed"));
+    assert_eq!(run.verification_exit_code, Some(3));
+    assert_eq!(run.verification_command.as_deref(), Some("echo broken; exit 3"));
+
+    assert!(db
+        .begin_resume_attempt("verify-fail", "run-2", &chrono::Utc::now().to_rfc3339())
+        .expect("resume should begin"));
+    let row = db
+        .get_dashboard_session("verify-fail")
+        .expect("dashboard should load")
+        .expect("dashboard row should exist");
+    assert_eq!(row.verification_status, None, "a new run has not been verified yet");
+    assert_eq!(db.list_session_runs("verify-fail").expect("runs should list").len(), 1);
+}
+
+#[tokio::test]
+async fn verification_is_skipped_without_a_repository_command() {
+    let (_repo, repo_root) = init_repo();
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&db_dir.path().join("lulu.db")).expect("database should initialize");
+
+    create_completed_session(&db, "verify-skip", &repo_root);
+
+    let mut emitted = 0;
+    let status = run_session_verification(&db, "verify-skip", |_| emitted += 1)
+        .await
+        .expect("verification should resolve");
+    assert_eq!(status.as_deref(), Some("skipped"));
+    assert_eq!(emitted, 0);
+    assert!(db.list_session_history("verify-skip").expect("history should load").is_empty());
+
+    let row = db
+        .get_dashboard_session("verify-skip")
+        .expect("dashboard should load")
+        .expect("dashboard row should exist");
+    assert_eq!(row.verification_status.as_deref(), Some("skipped"));
+
+    let missing = run_session_verification(&db, "missing-session", |_| {})
+        .await
+        .expect("missing session should not error");
+    assert_eq!(missing, None);
+}
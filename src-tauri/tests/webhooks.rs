@@ -0,0 +1,335 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+use tempfile::tempdir;
+use tokio::time::Duration;
+
+use tauri_app_lib::db::{init_database, Database, Session, WebhookTarget};
+use tauri_app_lib::session::webhooks::{
+    sign_payload, webhook_event_for_status, WebhookDispatcher, WEBHOOK_SIGNATURE_HEADER,
+};
+
+#[derive(Debug, Clone)]
+struct ReceivedRequest {
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl ReceivedRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Minimal HTTP endpoint that answers each request with the next status in `statuses`
+/// (repeating the last one) and records what it received.
+fn start_receiver(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<ReceivedRequest>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("listener should bind");
+    let url = format!("http://{}/hook", listener.local_addr().expect("addr should resolve"));
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_for_thread = received.clone();
+
+    std::thread::spawn(move || {
+        for (index, stream) in listener.incoming().enumerate() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let mut reader = BufReader::new(stream.try_clone().expect("stream should clone"));
+
+            let mut headers = Vec::new();
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).expect("request line should read");
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).expect("header should read");
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((key, value)) = line.split_once(':') {
+                    headers.push((key.trim().to_string(), value.trim().to_string()));
+                }
+            }
+
+            let content_length = headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.parse::<usize>().ok())
+                .unwrap_or(0);
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).expect("body should read");
+
+            received_for_thread
+                .lock()
+                .expect("received lock")
+                .push(ReceivedRequest { headers, body });
+
+            let status = statuses
+                .get(index)
+                .or_else(|| statuses.last())
+                .copied()
+                .unwrap_or(200);
+            let response = format!(
+                "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    (url, received)
+}
+
+fn create_session(db: &Database, id: &str, status: &str) {
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: format!("{} name", id),
+        status: status.to_string(),
+        working_dir: std::env::temp_dir().display().to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+}
+
+fn target(url: &str, events: &[&str]) -> WebhookTarget {
+    WebhookTarget {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: "ci".to_string(),
+        url: url.to_string(),
+        secret: Some("s3cret".to_string()),
+        events: events.iter().map(|event| event.to_string()).collect(),
+        cost_threshold_usd: None,
+        stall_after_secs: None,
+        max_attempts: 3,
+        enabled: true,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+fn dispatcher() -> WebhookDispatcher {
+    WebhookDispatcher::with_retry_base(Duration::from_millis(10))
+}
+
+#[tokio::test]
+async fn completed_webhook_is_signed_retried_and_logged() {
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&db_dir.path().join("lulu.db")).expect("database should initialize");
+    let (url, received) = start_receiver(vec![500, 200]);
+
+    create_session(&db, "webhook-session", "completed");
+    db.begin_run_attempt("webhook-session", "run-1").expect("run should begin");
+    db.update_session_status("webhook-session", "completed")
+        .expect("status should update");
+    db.record_run_usage("webhook-session", "run-1", 120, 48, Some(0.25))
+        .expect("usage should persist");
+    let primary = target(&url, &["completed", "failed"]);
+    db.create_webhook_target(&primary).expect("target should persist");
+    db.create_webhook_target(&WebhookTarget {
+        enabled: false,
+        ..target(&url, &["completed"])
+    })
+    .expect("disabled target should persist");
+
+    assert_eq!(webhook_event_for_status("completed"), Some("completed"));
+    assert_eq!(webhook_event_for_status("killed"), Some("failed"));
+    assert_eq!(webhook_event_for_status("interrupted"), None);
+
+    let delivered = dispatcher()
+        .notify(&db, "webhook-session", "completed", serde_json::json!({}))
+        .await
+        .expect("notify should succeed");
+    assert_eq!(delivered, 1);
+
+    let requests = received.lock().expect("received lock").clone();
+    assert_eq!(requests.len(), 2, "500 should be retried once before success");
+    for request in &requests {
+        assert_eq!(
+            request.header(WEBHOOK_SIGNATURE_HEADER),
+            Some(sign_payload("s3cret", &request.body).as_str())
+        );
+        assert_eq!(request.header("X-Lulu-Event"), Some("completed"));
+    }
+    assert_eq!(
+        requests[0].header("X-Lulu-Delivery"),
+        requests[1].header("X-Lulu-Delivery"),
+        "retries should reuse the delivery id"
+    );
+
+    let payload: serde_json::Value =
+        serde_json::from_slice(&requests[1].body).expect("payload should be JSON");
+    assert_eq!(payload["event"], "completed");
+    assert_eq!(payload["session"]["id"], "webhook-session");
+    assert_eq!(payload["session"]["status"], "Completed");
+    assert_eq!(payload["session"]["input_tokens"], 120);
+    assert_eq!(payload["session"]["cost_usd"], 0.25);
+
+    let mut deliveries = db
+        .list_webhook_deliveries(Some(&primary.id), 10)
+        .expect("deliveries should list");
+    deliveries.sort_by_key(|delivery| delivery.attempt);
+    assert_eq!(deliveries.len(), 2);
+    assert_eq!(deliveries[0].status_code, Some(500));
+    assert!(!deliveries[0].success);
+    assert_eq!(deliveries[1].status_code, Some(200));
+    assert!(deliveries[1].success);
+    assert_eq!(deliveries[1].payload_json, payload);
+}
+
+#[tokio::test]
+async fn failing_target_stops_after_max_attempts() {
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&db_dir.path().join("lulu.db")).expect("database should initialize");
+    let (url, received) = start_receiver(vec![503]);
+
+    create_session(&db, "failing-session", "failed");
+    let target = target(&url, &["failed"]);
+    db.create_webhook_target(&target).expect("target should persist");
+
+    let delivered = dispatcher()
+        .notify(&db, "failing-session", "failed", serde_json::json!({}))
+        .await
+        .expect("notify should finish");
+    assert_eq!(delivered, 0);
+    assert_eq!(received.lock().expect("received lock").len(), 3);
+
+    let deliveries = db
+        .list_webhook_deliveries(None, 10)
+        .expect("deliveries should list");
+    assert_eq!(deliveries.len(), 3);
+    assert!(deliveries.iter().all(|delivery| !delivery.success));
+    assert!(deliveries
+        .iter()
+        .all(|delivery| delivery.error.as_deref() == Some("HTTP 503 Service Unavailable")));
+}
+
+#[tokio::test]
+async fn cost_threshold_fires_once_per_session() {
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&db_dir.path().join("lulu.db")).expect("database should initialize");
+    let (url, received) = start_receiver(vec![200]);
+
+    create_session(&db, "costly-session", "running");
+    db.begin_run_attempt("costly-session", "run-1").expect("run should begin");
+    db.create_webhook_target(&WebhookTarget {
+        cost_threshold_usd: Some(1.0),
+        ..target(&url, &["cost_threshold"])
+    })
+    .expect("target should persist");
+
+    db.record_run_usage("costly-session", "run-1", 10, 10, Some(0.5))
+        .expect("usage should persist");
+    assert_eq!(
+        dispatcher()
+            .notify_cost_threshold(&db, "costly-session")
+            .await
+            .expect("check should succeed"),
+        0
+    );
+
+    db.record_run_usage("costly-session", "run-1", 20, 20, Some(1.5))
+        .expect("usage should persist");
+    assert_eq!(
+        dispatcher()
+            .notify_cost_threshold(&db, "costly-session")
+            .await
+            .expect("check should succeed"),
+        1
+    );
+    assert_eq!(
+        dispatcher()
+            .notify_cost_threshold(&db, "costly-session")
+            .await
+            .expect("check should succeed"),
+        0
+    );
+
+    let requests = received.lock().expect("received lock").clone();
+    assert_eq!(requests.len(), 1);
+    let payload: serde_json::Value =
+        serde_json::from_slice(&requests[0].body).expect("payload should be JSON");
+    assert_eq!(payload["details"]["threshold_usd"], 1.0);
+    assert_eq!(payload["details"]["cost_usd"], 1.5);
+}
+
+#[tokio::test]
+async fn concurrent_cost_threshold_checks_deliver_once() {
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&db_dir.path().join("lulu.db")).expect("database should initialize");
+    let (url, received) = start_receiver(vec![200]);
+
+    create_session(&db, "costly-session", "running");
+    db.begin_run_attempt("costly-session", "run-1").expect("run should begin");
+    db.create_webhook_target(&WebhookTarget {
+        cost_threshold_usd: Some(1.0),
+        ..target(&url, &["cost_threshold"])
+    })
+    .expect("target should persist");
+    db.record_run_usage("costly-session", "run-1", 20, 20, Some(1.5))
+        .expect("usage should persist");
+
+    let dispatcher = dispatcher();
+    let check = || dispatcher.notify_cost_threshold(&db, "costly-session");
+    let results = tokio::join!(check(), check(), check());
+    let delivered = [results.0, results.1, results.2]
+        .into_iter()
+        .map(|delivered| delivered.expect("check should succeed"))
+        .sum::<usize>();
+
+    assert_eq!(delivered, 1);
+    assert_eq!(received.lock().expect("received lock").len(), 1);
+}
+
+#[tokio::test]
+async fn stalled_webhook_fires_once_per_quiet_stretch() {
+    let db_dir = tempdir().expect("tempdir should be created");
+    let db = init_database(&db_dir.path().join("lulu.db")).expect("database should initialize");
+    let (url, received) = start_receiver(vec![200]);
+
+    create_session(&db, "quiet-session", "running");
+    create_session(&db, "done-session", "completed");
+    let quiet_since = (chrono::Utc::now() - chrono::Duration::minutes(10)).to_rfc3339();
+    db.update_last_activity("quiet-session", &quiet_since)
+        .expect("activity should update");
+    db.update_last_activity("done-session", &quiet_since)
+        .expect("activity should update");
+    db.create_webhook_target(&WebhookTarget {
+        stall_after_secs: Some(60),
+        ..target(&url, &["stalled"])
+    })
+    .expect("target should persist");
+
+    let now = chrono::Utc::now();
+    assert_eq!(
+        dispatcher().notify_stalled(&db, now).await.expect("check should succeed"),
+        1
+    );
+    assert_eq!(
+        dispatcher().notify_stalled(&db, now).await.expect("check should succeed"),
+        0,
+        "the same quiet stretch should only be reported once"
+    );
+
+    db.update_last_activity("quiet-session", &chrono::Utc::now().to_rfc3339())
+        .expect("activity should update");
+    assert_eq!(
+        dispatcher()
+            .notify_stalled(&db, chrono::Utc::now())
+            .await
+            .expect("check should succeed"),
+        0,
+        "recent activity should not count as stalled"
+    );
+
+    let requests = received.lock().expect("received lock").clone();
+    assert_eq!(requests.len(), 1);
+    let payload: serde_json::Value =
+        serde_json::from_slice(&requests[0].body).expect("payload should be JSON");
+    assert_eq!(payload["session_id"], "quiet-session");
+    assert!(payload["details"]["idle_secs"].as_i64().unwrap_or_default() >= 600);
+}
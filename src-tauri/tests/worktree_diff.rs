@@ -0,0 +1,145 @@
+use tauri_app_lib::session::diff::DiffLimits;
+use tauri_app_lib::session::WorktreeService;
+
+mod common;
+
+use common::{git, init_repo_with};
+
+fn init_repo() -> tempfile::TempDir {
+    init_repo_with(|dir| {
+        std::fs::write(dir.join("README.md"), "# test\n").expect("seed file should write");
+        std::fs::write(dir.join("notes.txt"), "one\ntwo\n").expect("seed file should write");
+        std::fs::write(dir.join(".gitignore"), "target/\n").expect("seed file should write");
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", "initial"]);
+    })
+    .0
+}
+
+#[test]
+fn session_diff_covers_committed_uncommitted_and_untracked_changes() {
+    let repo = init_repo();
+    let service = WorktreeService::new(repo.path());
+    let base_sha = service.resolve_commit("HEAD").expect("HEAD should resolve");
+    let worktree = service
+        .create_worktree_at("diff-session", "lulu/diff-session", &base_sha)
+        .expect("worktree should create");
+
+    std::fs::write(worktree.join("src.rs"), "fn main() {}\n").expect("file should write");
+    git(&worktree, &["add", "src.rs"]);
+    git(&worktree, &["commit", "-m", "add src"]);
+
+    std::fs::write(worktree.join("README.md"), "# test\nmore\n").expect("file should write");
+    std::fs::remove_file(worktree.join("notes.txt")).expect("file should delete");
+    std::fs::write(worktree.join("scratch.txt"), "a\nb\nc").expect("file should write");
+    std::fs::write(worktree.join("image.bin"), [0u8, 159, 146, 150]).expect("file should write");
+    std::fs::create_dir_all(worktree.join("target")).expect("dir should create");
+    std::fs::write(worktree.join("target/out.txt"), "ignored\n").expect("file should write");
+
+    let diff = service
+        .diff_against_base(&worktree, &base_sha, DiffLimits::default())
+        .expect("diff should succeed");
+    assert_eq!(diff.base_sha.as_deref(), Some(base_sha.as_str()));
+    assert!(diff.snapshot_at.is_none());
+    assert!(!diff.truncated);
+
+    let summary: Vec<(&str, &str, i64, i64)> = diff
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file.status.as_str(), file.additions, file.deletions))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("README.md", "modified", 1, 0),
+            ("image.bin", "untracked", 0, 0),
+            ("notes.txt", "deleted", 0, 2),
+            ("scratch.txt", "untracked", 3, 0),
+            ("src.rs", "added", 1, 0),
+        ]
+    );
+    assert_eq!(diff.additions, 5);
+    assert_eq!(diff.deletions, 2);
+
+    let binary = diff.files.iter().find(|file| file.path == "image.bin").expect("binary entry");
+    assert!(binary.binary);
+    assert!(binary.patch.is_none());
+
+    let readme = diff.files.iter().find(|file| file.path == "README.md").expect("readme entry");
+    let patch = readme.patch.as_deref().expect("text files carry a patch");
+    assert!(patch.contains("+more"));
+    let scratch = diff.files.iter().find(|file| file.path == "scratch.txt").expect("scratch entry");
+    let patch = scratch.patch.as_deref().expect("untracked text files carry a patch");
+    assert!(patch.starts_with("diff --git a/scratch.txt b/scratch.txt\nnew file mode 100644\n"));
+    assert!(patch.contains("--- /dev/null\n+++ b/scratch.txt\n"), "{}", patch);
+    assert!(patch.contains("+a"));
+
+    service.remove_worktree_for_session("diff-session").expect("worktree should remove");
+}
+
+#[test]
+fn session_diff_drops_patches_over_the_size_cap() {
+    let repo = init_repo();
+    let service = WorktreeService::new(repo.path());
+    let base_sha = service.resolve_commit("HEAD").expect("HEAD should resolve");
+    let worktree = service
+        .create_worktree_at("big-session", "lulu/big-session", &base_sha)
+        .expect("worktree should create");
+
+    std::fs::write(worktree.join("big.txt"), "line\n".repeat(500)).expect("file should write");
+    std::fs::write(worktree.join("README.md"), "# test\nsmall\n").expect("file should write");
+
+    let diff = service
+        .diff_against_base(
+            &worktree,
+            &base_sha,
+            DiffLimits { max_file_bytes: 1024, max_total_bytes: 4096 },
+        )
+        .expect("diff should succeed");
+
+    assert!(diff.truncated);
+    let big = diff.files.iter().find(|file| file.path == "big.txt").expect("big entry");
+    assert!(big.truncated);
+    assert!(big.patch.is_none());
+    assert_eq!(big.additions, 500, "stats are reported even when the patch is dropped");
+    let readme = diff.files.iter().find(|file| file.path == "README.md").expect("readme entry");
+    assert!(!readme.truncated);
+    assert!(readme.patch.is_some());
+
+    service.remove_worktree_for_session("big-session").expect("worktree should remove");
+}
+
+#[test]
+fn session_diff_stops_building_patches_once_the_total_cap_is_spent() {
+    let repo = init_repo();
+    let service = WorktreeService::new(repo.path());
+    let base_sha = service.resolve_commit("HEAD").expect("HEAD should resolve");
+    let worktree = service
+        .create_worktree_at("capped-session", "lulu/capped-session", &base_sha)
+        .expect("worktree should create");
+
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        std::fs::write(worktree.join(name), "line\n".repeat(100)).expect("file should write");
+    }
+
+    let diff = service
+        .diff_against_base(
+            &worktree,
+            &base_sha,
+            DiffLimits { max_file_bytes: 4096, max_total_bytes: 1024 },
+        )
+        .expect("diff should succeed");
+
+    assert!(diff.truncated);
+    let summary: Vec<(&str, bool, bool, i64)> = diff
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file.patch.is_some(), file.truncated, file.additions))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![("a.txt", true, false, 100), ("b.txt", false, true, 100), ("c.txt", false, true, 100)]
+    );
+
+    service.remove_worktree_for_session("capped-session").expect("worktree should remove");
+}
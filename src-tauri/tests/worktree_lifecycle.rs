@@ -1,9 +1,14 @@
-use tauri_app_lib::commands::session::reconcile_sessions_on_startup;
-use tauri_app_lib::db::{init_database, Session, SessionDashboardRow};
+use tauri_app_lib::commands::session::{
+    reconcile_session_worktrees, reconcile_sessions_on_startup,
+};
+use tauri_app_lib::db::{init_database, RepositorySettings, Session, SessionDashboardRow};
 use tauri_app_lib::session::projection::{
     normalize_dashboard_status, project_dashboard_row, DASHBOARD_STATUS_COMPLETED,
     DASHBOARD_STATUS_FAILED, DASHBOARD_STATUS_INTERRUPTED, DASHBOARD_STATUS_RUNNING,
 };
+use tauri_app_lib::session::worktree::{
+    render_branch_name, slugify_session_name, worktrees_root_for, WorktreeLocation,
+};
 use tauri_app_lib::session::WorktreeService;
 use tempfile::tempdir;
 
@@ -22,16 +27,19 @@ fn run_git(repo_path: &std::path::Path, args: &[&str]) {
     );
 }
 
-fn init_repo() -> tempfile::TempDir {
-    let dir = tempdir().expect("tempdir should be created");
-    run_git(dir.path(), &["init", "--initial-branch=main"]);
-    run_git(dir.path(), &["config", "user.name", "Lulu Test"]);
-    run_git(dir.path(), &["config", "user.email", "lulu@example.com"]);
+fn init_repo_at(path: &std::path::Path) {
+    run_git(path, &["init", "--initial-branch=main"]);
+    run_git(path, &["config", "user.name", "Lulu Test"]);
+    run_git(path, &["config", "user.email", "lulu@example.com"]);
 
-    std::fs::write(dir.path().join("README.md"), "# test\n").expect("seed file should write");
-    run_git(dir.path(), &["add", "README.md"]);
-    run_git(dir.path(), &["commit", "-m", "initial"]);
+    std::fs::write(path.join("README.md"), "# test\n").expect("seed file should write");
+    run_git(path, &["add", "README.md"]);
+    run_git(path, &["commit", "-m", "initial"]);
+}
 
+fn init_repo() -> tempfile::TempDir {
+    let dir = tempdir().expect("tempdir should be created");
+    init_repo_at(dir.path());
     dir
 }
 
@@ -59,6 +67,7 @@ fn projection_normalizes_dashboard_rows_to_locked_statuses() {
         restored: false,
         restored_at: None,
         recovery_hint: false,
+        ..Default::default()
     };
     let failed_projection = project_dashboard_row(failed);
     assert_eq!(failed_projection.status, DASHBOARD_STATUS_FAILED);
@@ -75,6 +84,7 @@ fn projection_normalizes_dashboard_rows_to_locked_statuses() {
         restored: true,
         restored_at: Some(chrono::Utc::now().to_rfc3339()),
         recovery_hint: true,
+        ..Default::default()
     };
     let completed_projection = project_dashboard_row(completed);
     assert_eq!(completed_projection.status, DASHBOARD_STATUS_COMPLETED);
@@ -90,10 +100,10 @@ fn spawn_uses_session_specific_worktree_path() {
     let service = WorktreeService::new(repo.path());
 
     let first = service
-        .create_worktree("session-a")
+        .create_worktree("session-a", "lulu/session-a")
         .expect("first worktree should create");
     let second = service
-        .create_worktree("session-b")
+        .create_worktree("session-b", "lulu/session-b")
         .expect("second worktree should create");
 
     assert_ne!(first, second, "session worktree paths must be unique");
@@ -111,6 +121,45 @@ fn spawn_uses_session_specific_worktree_path() {
     service.prune_worktrees().expect("worktree prune should succeed");
 }
 
+#[test]
+fn session_branches_follow_template_and_survive_worktree_removal() {
+    let repo = init_repo();
+    let service = WorktreeService::new(repo.path());
+
+    assert_eq!(slugify_session_name("  Fix: Login  Bug!! "), "fix-login-bug");
+    assert_eq!(slugify_session_name("???"), "session");
+    let branch = render_branch_name(
+        "lulu/{session-name-slug}-{short-id}",
+        "Fix login bug",
+        "1234abcd-5678",
+    );
+    assert_eq!(branch, "lulu/fix-login-bug-1234abcd");
+
+    service
+        .create_worktree("session-a", &branch)
+        .expect("first worktree should create");
+    assert!(service.branch_exists(&branch));
+
+    let second_branch = service.unique_branch_name(&branch);
+    assert_eq!(second_branch, "lulu/fix-login-bug-1234abcd-2");
+    service
+        .create_worktree("session-b", &second_branch)
+        .expect("second worktree should create");
+
+    service
+        .remove_worktree_for_session("session-a")
+        .expect("first worktree should remove");
+    assert!(service.branch_exists(&branch), "removing the worktree keeps the branch");
+
+    service
+        .remove_worktree_for_session("session-b")
+        .expect("second worktree should remove");
+    service.delete_branch(&second_branch).expect("branch should delete");
+    assert!(!service.branch_exists(&second_branch));
+    service.delete_branch(&second_branch).expect("deleting a missing branch is a no-op");
+    service.prune_worktrees().expect("worktree prune should succeed");
+}
+
 #[test]
 fn startup_reconcile_marks_stale_running_as_restored_without_forcing_failed() {
     let repo = init_repo();
@@ -130,12 +179,18 @@ fn startup_reconcile_marks_stale_running_as_restored_without_forcing_failed() {
 
     let service = WorktreeService::new(repo.path());
     let worktree_path = service
-        .create_worktree("stale-session")
+        .create_worktree("stale-session", "lulu/stale-session")
         .expect("worktree should create");
     db.update_worktree_path("stale-session", Some(&worktree_path.display().to_string()))
         .expect("worktree path should persist");
+    db.update_session_branch("stale-session", Some("lulu/stale-session"))
+        .expect("branch should persist");
+    let head = service.resolve_commit("HEAD").expect("HEAD should resolve");
+    db.update_session_base("stale-session", Some("main"), Some(&head))
+        .expect("base should persist");
 
-    reconcile_sessions_on_startup(&db).expect("startup reconciliation should succeed");
+    let app_data = tempdir().expect("app data dir should be created");
+    reconcile_sessions_on_startup(&db, app_data.path()).expect("startup reconciliation should succeed");
 
     let stored = db
         .get_session("stale-session")
@@ -159,9 +214,175 @@ fn startup_reconcile_marks_stale_running_as_restored_without_forcing_failed() {
         stale_row.recovery_hint,
         "stale row should include recovery hint metadata"
     );
+    assert_eq!(stale_row.branch_name.as_deref(), Some("lulu/stale-session"));
+    assert_eq!(stale_row.base_sha.as_deref(), Some(head.as_str()));
+    let base = db
+        .get_session_base("stale-session")
+        .expect("base read should succeed")
+        .expect("base should be recorded");
+    assert_eq!(base.base_ref, "main");
+    assert_eq!(base.base_sha, head);
+    assert_eq!(
+        db.get_session_branch("stale-session").expect("branch read should succeed").as_deref(),
+        Some("lulu/stale-session")
+    );
 
     service
         .remove_worktree_for_session("stale-session")
         .expect("worktree should remove");
     service.prune_worktrees().expect("prune should succeed");
 }
+
+#[test]
+fn in_repo_worktrees_are_excluded_from_git_status() {
+    let repo = init_repo();
+    let service = WorktreeService::new(repo.path());
+
+    service.create_worktree("excluded-1", "lulu/excluded-1").expect("worktree should create");
+    service.create_worktree("excluded-2", "lulu/excluded-2").expect("worktree should create");
+
+    let exclude = std::fs::read_to_string(repo.path().join(".git/info/exclude"))
+        .expect("exclude file should exist");
+    assert_eq!(exclude.lines().filter(|line| *line == "/.lulu/").count(), 1);
+
+    let status = std::process::Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(repo.path())
+        .output()
+        .expect("git status should run");
+    assert!(String::from_utf8_lossy(&status.stdout).trim().is_empty());
+
+    service.remove_worktree_for_session("excluded-1").expect("worktree should remove");
+    service.remove_worktree_for_session("excluded-2").expect("worktree should remove");
+    service.prune_worktrees().expect("prune should succeed");
+}
+
+#[test]
+fn worktree_roots_follow_the_configured_location() {
+    let repo_root = std::path::Path::new("/work/projects/app");
+    let app_data = std::path::Path::new("/data/lulu");
+
+    assert_eq!(
+        worktrees_root_for(repo_root, WorktreeLocation::InRepo, app_data),
+        repo_root.join(".lulu/worktrees")
+    );
+    assert_eq!(
+        worktrees_root_for(repo_root, WorktreeLocation::Sibling, app_data),
+        std::path::Path::new("/work/projects/app.lulu-worktrees")
+    );
+
+    let keyed = worktrees_root_for(repo_root, WorktreeLocation::AppData, app_data);
+    assert!(keyed.starts_with(app_data.join("worktrees")));
+    let key = keyed.file_name().and_then(|name| name.to_str()).expect("key should be utf-8");
+    assert!(key.starts_with("app-") && key.len() == "app-".len() + 16);
+    assert_ne!(
+        keyed,
+        worktrees_root_for(
+            std::path::Path::new("/elsewhere/app"),
+            WorktreeLocation::AppData,
+            app_data
+        ),
+        "repositories with the same name must not share a root"
+    );
+}
+
+#[test]
+fn startup_reconcile_moves_existing_worktrees_to_the_configured_location() {
+    let workspace = tempdir().expect("workspace should be created");
+    let repo_path = workspace.path().join("project");
+    std::fs::create_dir(&repo_path).expect("repo dir should be created");
+    init_repo_at(&repo_path);
+    let db = init_database(&workspace.path().join("lulu.db")).expect("database should initialize");
+
+    let created_at = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: "moved-session".to_string(),
+        name: "moved".to_string(),
+        status: "completed".to_string(),
+        working_dir: repo_path.display().to_string(),
+        created_at: created_at.clone(),
+        updated_at: created_at,
+    })
+    .expect("session should persist");
+
+    let in_repo = WorktreeService::new(&repo_path);
+    let old_path = in_repo
+        .create_worktree("moved-session", "lulu/moved-session")
+        .expect("worktree should create");
+    std::fs::write(old_path.join("notes.txt"), "work in progress\n").expect("file should write");
+    db.update_worktree_path("moved-session", Some(&old_path.display().to_string()))
+        .expect("worktree path should persist");
+
+    db.save_repository_settings(&RepositorySettings {
+        repo_root: repo_path.display().to_string(),
+        worktree_location: Some("sibling".to_string()),
+        ..Default::default()
+    })
+    .expect("settings should save");
+
+    let app_data = tempdir().expect("app data dir should be created");
+    reconcile_sessions_on_startup(&db, app_data.path()).expect("reconciliation should succeed");
+
+    let new_path = workspace.path().join("project.lulu-worktrees").join("moved-session");
+    assert_eq!(
+        db.get_session_worktree_path("moved-session").expect("path read should succeed"),
+        Some(new_path.display().to_string())
+    );
+    assert!(!old_path.exists());
+    assert_eq!(
+        std::fs::read_to_string(new_path.join("notes.txt")).expect("uncommitted file should move"),
+        "work in progress\n"
+    );
+
+    let sibling =
+        WorktreeService::with_location(&repo_path, WorktreeLocation::Sibling, app_data.path());
+    let listed = sibling.list_worktrees().expect("worktrees should list");
+    assert!(listed.iter().any(|entry| entry.path == new_path && !entry.prunable));
+
+    sibling.remove_worktree_for_session("moved-session").expect("worktree should remove");
+    sibling.prune_worktrees().expect("prune should succeed");
+}
+
+#[test]
+fn reconcile_leaves_the_worktree_of_a_running_session_where_it_is() {
+    let workspace = tempdir().expect("workspace should be created");
+    let repo_path = workspace.path().join("project");
+    std::fs::create_dir(&repo_path).expect("repo dir should be created");
+    init_repo_at(&repo_path);
+    let db = init_database(&workspace.path().join("lulu.db")).expect("database should initialize");
+
+    let created_at = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: "busy-session".to_string(),
+        name: "busy".to_string(),
+        status: "running".to_string(),
+        working_dir: repo_path.display().to_string(),
+        created_at: created_at.clone(),
+        updated_at: created_at,
+    })
+    .expect("session should persist");
+    let in_repo = WorktreeService::new(&repo_path);
+    let old_path = in_repo
+        .create_worktree("busy-session", "lulu/busy-session")
+        .expect("worktree should create");
+    db.update_worktree_path("busy-session", Some(&old_path.display().to_string()))
+        .expect("worktree path should persist");
+    db.save_repository_settings(&RepositorySettings {
+        repo_root: repo_path.display().to_string(),
+        worktree_location: Some("sibling".to_string()),
+        ..Default::default()
+    })
+    .expect("settings should save");
+
+    let app_data = tempdir().expect("app data dir should be created");
+    reconcile_session_worktrees(&db, app_data.path(), false).expect("reconcile should succeed");
+
+    assert_eq!(
+        db.get_session_worktree_path("busy-session").expect("path read should succeed"),
+        Some(old_path.display().to_string())
+    );
+    assert!(old_path.exists(), "the running session's CLI works in there");
+
+    in_repo.remove_worktree_for_session("busy-session").expect("worktree should remove");
+    in_repo.prune_worktrees().expect("prune should succeed");
+}
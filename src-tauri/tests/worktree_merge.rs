@@ -0,0 +1,197 @@
+use tauri_app_lib::db::{init_database, Session, SessionIntegration};
+use tauri_app_lib::session::integration::{
+    integrate_session_changes, IntegrationRequest, IntegrationStrategy,
+    INTEGRATION_STATUS_CONFLICTED, INTEGRATION_STATUS_MERGED,
+};
+use tauri_app_lib::session::WorktreeService;
+
+mod common;
+
+use common::{git, init_repo_with};
+
+fn init_repo() -> tempfile::TempDir {
+    init_repo_with(|dir| {
+        std::fs::write(dir.join(".gitignore"), ".lulu/\n").expect("seed file should write");
+        std::fs::write(dir.join("README.md"), "# test\n").expect("seed file should write");
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", "initial"]);
+    })
+    .0
+}
+
+struct SessionFixture {
+    service: WorktreeService,
+    worktree: std::path::PathBuf,
+    base_sha: String,
+}
+
+fn start_session(repo: &std::path::Path, session_id: &str) -> SessionFixture {
+    let service = WorktreeService::new(repo);
+    let base_sha = service.resolve_commit("HEAD").expect("HEAD should resolve");
+    let worktree = service
+        .create_worktree_at(session_id, &format!("lulu/{}", session_id), &base_sha)
+        .expect("worktree should create");
+    SessionFixture { service, worktree, base_sha }
+}
+
+fn request(
+    fixture: &SessionFixture,
+    session_id: &str,
+    target_branch: &str,
+    strategy: IntegrationStrategy,
+) -> IntegrationRequest {
+    IntegrationRequest {
+        session_name: "Feature work".to_string(),
+        session_branch: format!("lulu/{}", session_id),
+        worktree_path: fixture.worktree.clone(),
+        base_sha: fixture.base_sha.clone(),
+        target_branch: target_branch.to_string(),
+        strategy,
+    }
+}
+
+#[test]
+fn merge_lands_session_commits_and_updates_main_checkout() {
+    let repo = init_repo();
+    let fixture = start_session(repo.path(), "merge-session");
+    std::fs::write(fixture.worktree.join("feature.txt"), "feature\n").expect("file should write");
+    git(&fixture.worktree, &["add", "feature.txt"]);
+    git(&fixture.worktree, &["commit", "-m", "add feature"]);
+
+    let outcome = integrate_session_changes(
+        &fixture.service,
+        &request(&fixture, "merge-session", "main", IntegrationStrategy::Merge),
+    )
+    .expect("merge should run");
+
+    assert_eq!(outcome.status, INTEGRATION_STATUS_MERGED);
+    let result_sha = outcome.result_sha.clone().expect("merge should produce a commit");
+    assert_eq!(git(repo.path(), &["rev-parse", "main"]), result_sha);
+    assert!(outcome.summary.starts_with("merged into main at "));
+    assert!(repo.path().join("feature.txt").exists(), "main checkout should follow the branch");
+    assert_eq!(git(repo.path(), &["rev-list", "--count", "--merges", "main"]), "1");
+
+    let db = init_database(&repo.path().join(".lulu/lulu.db")).expect("database should initialize");
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: "merge-session".to_string(),
+        name: "Feature work".to_string(),
+        status: "completed".to_string(),
+        working_dir: repo.path().display().to_string(),
+        created_at: now.clone(),
+        updated_at: now.clone(),
+    })
+    .expect("session should persist");
+    db.insert_session_integration(&SessionIntegration {
+        id: "integration-1".to_string(),
+        session_id: "merge-session".to_string(),
+        strategy: "merge".to_string(),
+        target_branch: "main".to_string(),
+        status: outcome.status.clone(),
+        result_sha: outcome.result_sha.clone(),
+        conflicts: Vec::new(),
+        summary: outcome.summary.clone(),
+        created_at: now,
+    })
+    .expect("integration should persist");
+    let row = db
+        .get_dashboard_session("merge-session")
+        .expect("dashboard read should succeed")
+        .expect("row should exist");
+    assert_eq!(row.integration_summary.as_deref(), Some(outcome.summary.as_str()));
+    assert_eq!(
+        db.list_session_integrations("merge-session").expect("integrations should list")[0]
+            .result_sha,
+        outcome.result_sha
+    );
+}
+
+#[test]
+fn conflicting_cherry_pick_reports_files_and_leaves_main_untouched() {
+    let repo = init_repo();
+    let fixture = start_session(repo.path(), "conflict-session");
+    std::fs::write(fixture.worktree.join("README.md"), "# session\n").expect("file should write");
+    git(&fixture.worktree, &["commit", "-am", "session readme"]);
+
+    std::fs::write(repo.path().join("README.md"), "# main\n").expect("file should write");
+    git(repo.path(), &["commit", "-am", "main readme"]);
+    let main_tip = git(repo.path(), &["rev-parse", "main"]);
+
+    let outcome = integrate_session_changes(
+        &fixture.service,
+        &request(&fixture, "conflict-session", "main", IntegrationStrategy::CherryPick),
+    )
+    .expect("cherry-pick should run");
+
+    assert_eq!(outcome.status, INTEGRATION_STATUS_CONFLICTED);
+    assert_eq!(outcome.conflicts, vec!["README.md".to_string()]);
+    assert!(outcome.result_sha.is_none());
+    assert_eq!(git(repo.path(), &["rev-parse", "main"]), main_tip);
+    assert_eq!(git(repo.path(), &["status", "--porcelain", "--untracked-files=no"]), "");
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("README.md")).expect("file should read"),
+        "# main\n"
+    );
+    assert!(
+        !git(repo.path(), &["worktree", "list"]).contains("lulu-integrate"),
+        "scratch worktree should be cleaned up"
+    );
+}
+
+#[test]
+fn squash_collapses_session_commits_into_one() {
+    let repo = init_repo();
+    let fixture = start_session(repo.path(), "squash-session");
+    for (file, message) in [("a.txt", "add a"), ("b.txt", "add b")] {
+        std::fs::write(fixture.worktree.join(file), "content\n").expect("file should write");
+        git(&fixture.worktree, &["add", file]);
+        git(&fixture.worktree, &["commit", "-m", message]);
+    }
+
+    let outcome = integrate_session_changes(
+        &fixture.service,
+        &request(&fixture, "squash-session", "main", IntegrationStrategy::Squash),
+    )
+    .expect("squash should run");
+
+    assert_eq!(outcome.status, INTEGRATION_STATUS_MERGED);
+    assert_eq!(
+        git(repo.path(), &["rev-list", "--count", &format!("{}..main", fixture.base_sha)]),
+        "1"
+    );
+    assert!(repo.path().join("a.txt").exists());
+    assert!(repo.path().join("b.txt").exists());
+}
+
+#[test]
+fn patch_applies_uncommitted_changes_to_a_branch_that_is_not_checked_out() {
+    let repo = init_repo();
+    git(repo.path(), &["branch", "release"]);
+    let fixture = start_session(repo.path(), "patch-session");
+    std::fs::write(fixture.worktree.join("README.md"), "# test\npatched\n")
+        .expect("file should write");
+    std::fs::write(fixture.worktree.join("new.txt"), "new\n").expect("file should write");
+
+    let no_commits = integrate_session_changes(
+        &fixture.service,
+        &request(&fixture, "patch-session", "release", IntegrationStrategy::Merge),
+    );
+    assert!(no_commits.is_err(), "merging a branch without commits should be refused");
+
+    let outcome = integrate_session_changes(
+        &fixture.service,
+        &request(&fixture, "patch-session", "release", IntegrationStrategy::Patch),
+    )
+    .expect("patch should apply");
+
+    assert_eq!(outcome.status, INTEGRATION_STATUS_MERGED);
+    assert!(outcome.summary.starts_with("applied onto release at "));
+    assert_eq!(git(repo.path(), &["show", "release:new.txt"]), "new");
+    assert_eq!(git(repo.path(), &["show", "release:README.md"]), "# test\npatched");
+    assert_eq!(
+        git(&fixture.worktree, &["status", "--porcelain"]),
+        "M README.md\n?? new.txt",
+        "the session's own index must be left alone"
+    );
+    assert!(!repo.path().join("new.txt").exists(), "main checkout is on another branch");
+}
@@ -0,0 +1,230 @@
+use tempfile::tempdir;
+
+use tauri_app_lib::db::{init_database, Database, RepositorySettings, Session};
+use tauri_app_lib::session::provisioning::{
+    glob_matches, matching_local_paths, provision_session_worktree,
+};
+use tauri_app_lib::session::WorktreeService;
+
+mod common;
+
+use common::{git, init_repo_with};
+
+/// Repository with an ignored `.env`, an ignored `node_modules` and an untracked `notes.txt`.
+fn init_repo() -> (tempfile::TempDir, String) {
+    init_repo_with(|dir| {
+        std::fs::write(dir.join(".gitignore"), ".env\nnode_modules/\n*.db\n")
+            .expect("gitignore should write");
+        git(dir, &["add", ".gitignore"]);
+        git(dir, &["commit", "-m", "initial"]);
+
+        std::fs::write(dir.join(".env"), "API_KEY=local\n").expect(".env should write");
+        std::fs::create_dir_all(dir.join("node_modules/left-pad"))
+            .expect("node_modules should be created");
+        std::fs::write(dir.join("node_modules/left-pad/index.js"), "module.exports = 1;\n")
+            .expect("module should write");
+        std::fs::write(dir.join("notes.txt"), "scratch\n").expect("notes should write");
+    })
+}
+
+fn session_with_worktree(db: &Database, repo_root: &str, id: &str) -> std::path::PathBuf {
+    let worktree = WorktreeService::new(repo_root)
+        .create_worktree(id, &format!("lulu/{}", id))
+        .expect("worktree should create");
+
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: "Provisioned".to_string(),
+        status: "starting".to_string(),
+        working_dir: repo_root.to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+    db.update_worktree_path(id, Some(&worktree.display().to_string()))
+        .expect("worktree path should persist");
+
+    worktree
+}
+
+#[test]
+fn provisioning_globs_match_by_path_component() {
+    assert!(glob_matches(".env", ".env"));
+    assert!(!glob_matches(".env", "app/.env"));
+    assert!(glob_matches("**/.env", "app/.env"));
+    assert!(glob_matches("**/.env", ".env"));
+    assert!(glob_matches("*.local", "config.local"));
+    assert!(!glob_matches("*.local", "app/config.local"));
+    assert!(glob_matches("node_modules/.cache/**", "node_modules/.cache/babel"));
+    assert!(glob_matches("config/?.json", "config/a.json"));
+    assert!(!glob_matches("config/?.json", "config/ab.json"));
+}
+
+#[test]
+fn matching_local_paths_covers_ignored_and_untracked_files() {
+    let (_repo, repo_root) = init_repo();
+    let root = std::path::Path::new(&repo_root);
+
+    let matched = matching_local_paths(
+        root,
+        &["**/.env".to_string(), "node_modules".to_string(), "notes.txt".to_string()],
+    )
+    .expect("patterns should match");
+    assert_eq!(matched, vec![".env", "node_modules", "notes.txt"]);
+
+    let nested = matching_local_paths(root, &["node_modules/left-pad/*.js".to_string()])
+        .expect("nested pattern should match");
+    assert_eq!(nested, vec!["node_modules/left-pad/index.js"]);
+}
+
+#[tokio::test]
+async fn provisioning_copies_links_and_runs_setup_before_launch() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    db.save_repository_settings(&RepositorySettings {
+        repo_root: repo_root.clone(),
+        provision_copy: vec![".env".to_string()],
+        provision_symlink: vec!["node_modules".to_string()],
+        setup_command: Some("echo installing && touch .setup-done".to_string()),
+        ..Default::default()
+    })
+    .expect("settings should save");
+    let worktree = session_with_worktree(&db, &repo_root, "provision-ok");
+
+    let mut seen = Vec::new();
+    provision_session_worktree(&db, "provision-ok", |event| seen.push(event.seq))
+        .await
+        .expect("provisioning should succeed");
+
+    assert_eq!(
+        std::fs::read_to_string(worktree.join(".env")).expect(".env should be copied"),
+        "API_KEY=local\n"
+    );
+    assert!(std::fs::symlink_metadata(worktree.join("node_modules"))
+        .expect("node_modules should exist")
+        .file_type()
+        .is_symlink());
+    assert!(worktree.join("node_modules/left-pad/index.js").exists());
+    assert!(worktree.join(".setup-done").exists());
+
+    let history = db.list_session_history("provision-ok").expect("history should load");
+    let types: Vec<&str> = history.iter().map(|event| event.event_type.as_str()).collect();
+    assert_eq!(
+        types,
+        vec!["provision_step", "provision_step", "provision_output", "provision_step"]
+    );
+    assert_eq!(history[2].payload_json["data"]["line"], "installing");
+    assert_eq!(history[3].payload_json["data"]["step"], "setup");
+    assert_eq!(history[3].payload_json["data"]["status"], "ok");
+    assert_eq!(seen.len(), history.len());
+}
+
+#[tokio::test]
+async fn failing_setup_command_fails_provisioning() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    db.save_repository_settings(&RepositorySettings {
+        repo_root: repo_root.clone(),
+        setup_command: Some("echo missing lockfile >&2; exit 3".to_string()),
+        ..Default::default()
+    })
+    .expect("settings should save");
+    session_with_worktree(&db, &repo_root, "provision-fail");
+
+    let error = provision_session_worktree(&db, "provision-fail", |_| {})
+        .await
+        .expect_err("a failing setup command must fail provisioning");
+    assert!(error.contains("exited with 3"), "unexpected error: {}", error);
+
+    let history = db.list_session_history("provision-fail").expect("history should load");
+    let last = history.last().expect("history should record the failure");
+    assert_eq!(last.payload_json["data"]["status"], "failed");
+    assert!(history.iter().any(|event| event.payload_json["data"]["line"] == "missing lockfile"
+        && event.payload_json["data"]["stream"] == "stderr"));
+}
+
+/// Adds a submodule cloned from a throwaway upstream at `vendor/lib` and marks `*.bin` as an
+/// LFS file type; the upstream is gone afterwards, so only the main checkout has its objects.
+fn add_submodule_and_lfs_attributes(repo_root: &str) -> String {
+    let upstream = tempdir().expect("upstream tempdir should be created");
+    git(upstream.path(), &["init", "--initial-branch=main"]);
+    git(upstream.path(), &["config", "user.name", "Lulu Test"]);
+    git(upstream.path(), &["config", "user.email", "lulu@example.com"]);
+    std::fs::write(upstream.path().join("lib.txt"), "vendored\n").expect("lib should write");
+    git(upstream.path(), &["add", "lib.txt"]);
+    git(upstream.path(), &["commit", "-m", "lib"]);
+    let upstream_url = upstream.path().display().to_string();
+
+    let root = std::path::Path::new(repo_root);
+    git(
+        root,
+        &["-c", "protocol.file.allow=always", "submodule", "add", &upstream_url, "vendor/lib"],
+    );
+    std::fs::write(root.join(".gitattributes"), "*.bin filter=lfs diff=lfs merge=lfs -text\n")
+        .expect("attributes should write");
+    git(root, &["add", ".gitattributes"]);
+    git(root, &["commit", "-m", "vendor lib"]);
+
+    upstream_url
+}
+
+#[tokio::test]
+async fn provisioning_initialises_submodules_from_the_main_checkout() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    let upstream_url = add_submodule_and_lfs_attributes(&repo_root);
+    let worktree = session_with_worktree(&db, &repo_root, "provision-submodules");
+    assert!(!worktree.join("vendor/lib/lib.txt").exists());
+
+    provision_session_worktree(&db, "provision-submodules", |_| {})
+        .await
+        .expect("provisioning should succeed");
+
+    assert_eq!(
+        std::fs::read_to_string(worktree.join("vendor/lib/lib.txt"))
+            .expect("submodule should be checked out"),
+        "vendored\n"
+    );
+    let origin = std::process::Command::new("git")
+        .args(["remote", "get-url", "origin"])
+        .current_dir(worktree.join("vendor/lib"))
+        .output()
+        .expect("git remote should run");
+    assert_eq!(String::from_utf8_lossy(&origin.stdout).trim(), upstream_url);
+
+    let history = db.list_session_history("provision-submodules").expect("history should load");
+    let steps: Vec<(&str, &str)> = history
+        .iter()
+        .map(|event| {
+            let data = &event.payload_json["data"];
+            (data["step"].as_str().unwrap_or(""), data["status"].as_str().unwrap_or(""))
+        })
+        .collect();
+    assert_eq!(steps[0], ("submodules", "ok"));
+    assert_eq!(history[0].payload_json["data"]["detail"], "vendor/lib");
+    assert_eq!(steps[1].0, "lfs");
+    assert!(matches!(steps[1].1, "ok" | "skipped"), "unexpected lfs status: {:?}", steps[1]);
+}
+
+#[tokio::test]
+async fn submodule_and_lfs_provisioning_can_be_turned_off() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    add_submodule_and_lfs_attributes(&repo_root);
+    db.save_repository_settings(&RepositorySettings {
+        repo_root: repo_root.clone(),
+        init_submodules: false,
+        lfs_checkout: false,
+        ..Default::default()
+    })
+    .expect("settings should save");
+    let worktree = session_with_worktree(&db, &repo_root, "provision-plain");
+
+    provision_session_worktree(&db, "provision-plain", |_| {})
+        .await
+        .expect("provisioning should succeed");
+
+    assert!(!worktree.join("vendor/lib/lib.txt").exists());
+    assert!(db.list_session_history("provision-plain").expect("history should load").is_empty());
+}
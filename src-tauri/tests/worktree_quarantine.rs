@@ -0,0 +1,97 @@
+use tempfile::tempdir;
+
+use tauri_app_lib::commands::session::{
+    reconcile_session_worktrees, reconcile_sessions_on_startup,
+};
+use tauri_app_lib::db::{init_database, Database, Session};
+use tauri_app_lib::session::quarantine::{
+    list_quarantined_worktrees, purge_quarantined_worktrees, quarantine_root,
+};
+use tauri_app_lib::session::WorktreeService;
+
+mod common;
+
+use common::{git, init_repo};
+
+fn owned_session(db: &Database, repo_root: &str, id: &str) -> std::path::PathBuf {
+    let worktree = WorktreeService::new(repo_root)
+        .create_worktree(id, &format!("lulu/{}", id))
+        .expect("worktree should create");
+
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: id.to_string(),
+        status: "completed".to_string(),
+        working_dir: repo_root.to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+    db.update_worktree_path(id, Some(&worktree.display().to_string()))
+        .expect("worktree path should persist");
+
+    worktree
+}
+
+#[test]
+fn reconciliation_quarantines_unknown_worktrees_and_purge_needs_confirmation() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    let app_data = tempdir().expect("app data dir should be created");
+    let service = WorktreeService::new(&repo_root);
+
+    let owned = owned_session(&db, &repo_root, "owned");
+    let dirty = service.create_worktree("lost-dirty", "lulu/lost-dirty").expect("worktree");
+    std::fs::write(dirty.join("feature.txt"), "work in progress\n").expect("file should write");
+    git(&dirty, &["add", "feature.txt"]);
+    git(&dirty, &["commit", "-m", "unmerged work"]);
+    std::fs::write(dirty.join("README.md"), "# uncommitted\n").expect("edit should write");
+    let clean = service.create_worktree("lost-clean", "lulu/lost-clean").expect("worktree");
+
+    let preview =
+        reconcile_session_worktrees(&db, app_data.path(), true).expect("dry run should succeed");
+    assert_eq!(preview.len(), 1);
+    assert!(preview[0].dry_run);
+    let mut previewed: Vec<&str> =
+        preview[0].quarantined.iter().map(|entry| entry.session_id.as_str()).collect();
+    previewed.sort();
+    assert_eq!(previewed, vec!["lost-clean", "lost-dirty"]);
+    assert!(dirty.exists() && clean.exists(), "a dry run must not move anything");
+
+    reconcile_sessions_on_startup(&db, app_data.path()).expect("reconciliation should succeed");
+    assert!(owned.exists());
+    assert!(!dirty.exists() && !clean.exists());
+
+    let quarantined = list_quarantined_worktrees(&service).expect("quarantine should list");
+    assert_eq!(quarantined.len(), 2);
+    let lost_dirty = quarantined
+        .iter()
+        .find(|entry| entry.session_id == "lost-dirty")
+        .expect("dirty worktree should be quarantined");
+    assert!(lost_dirty.unmerged);
+    assert_eq!(lost_dirty.dirty_files, vec!["README.md".to_string()]);
+    assert_eq!(lost_dirty.branch.as_deref(), Some("lulu/lost-dirty"));
+    assert_eq!(lost_dirty.last_commit_summary.as_deref(), Some("unmerged work"));
+    let moved = std::path::Path::new(&lost_dirty.path);
+    assert!(moved.starts_with(quarantine_root(&service)));
+    assert_eq!(
+        std::fs::read_to_string(moved.join("README.md")).expect("work should survive"),
+        "# uncommitted\n"
+    );
+    git(moved, &["status"]);
+
+    let report = purge_quarantined_worktrees(&service, None, false).expect("purge should succeed");
+    assert_eq!(report.purged.len(), 1);
+    assert!(report.purged[0].starts_with("lost-clean-"));
+    assert_eq!(report.needs_confirmation, vec![lost_dirty.name.clone()]);
+    assert!(moved.exists(), "dirty work is kept without confirmation");
+
+    let confirmed =
+        purge_quarantined_worktrees(&service, Some(std::slice::from_ref(&lost_dirty.name)), true)
+            .expect("confirmed purge should succeed");
+    assert_eq!(confirmed.purged, vec![lost_dirty.name.clone()]);
+    assert!(!moved.exists());
+    assert!(list_quarantined_worktrees(&service).expect("quarantine should list").is_empty());
+    assert!(service.branch_exists("lulu/lost-dirty"), "branches outlive a purge");
+}
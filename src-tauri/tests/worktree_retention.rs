@@ -0,0 +1,154 @@
+use chrono::{Duration, Utc};
+
+use tauri_app_lib::db::{init_database, Database, RepositorySettings, Session};
+use tauri_app_lib::session::retention::{
+    apply_startup_worktree_retention, apply_worktree_retention, plan_retention, RetentionCandidate, RetentionPolicy,
+    RETENTION_REASON_AGE, RETENTION_REASON_SIZE,
+};
+use tauri_app_lib::session::WorktreeService;
+
+mod common;
+
+use common::{git, init_repo};
+
+fn finished_session(db: &Database, repo_root: &str, id: &str, days_ago: i64) -> std::path::PathBuf {
+    let service = WorktreeService::new(repo_root);
+    let head = service.resolve_commit("HEAD").expect("HEAD should resolve");
+    let worktree =
+        service.create_worktree(id, &format!("lulu/{}", id)).expect("worktree should create");
+
+    let created_at = (Utc::now() - Duration::days(days_ago)).to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: id.to_string(),
+        status: "completed".to_string(),
+        working_dir: repo_root.to_string(),
+        created_at: created_at.clone(),
+        updated_at: created_at.clone(),
+    })
+    .expect("session should persist");
+    db.update_worktree_path(id, Some(&worktree.display().to_string()))
+        .expect("worktree path should persist");
+    db.update_session_base(id, Some("main"), Some(&head)).expect("base should persist");
+    db.update_last_activity(id, &created_at).expect("activity should persist");
+
+    worktree
+}
+
+fn candidate(id: &str, bytes: i64, days_ago: Option<i64>, unmerged: bool) -> RetentionCandidate {
+    RetentionCandidate {
+        session_id: id.to_string(),
+        bytes,
+        finished_at: days_ago.map(|days| Utc::now() - Duration::days(days)),
+        unmerged,
+    }
+}
+
+#[test]
+fn retention_plan_applies_age_then_size_and_keeps_unmerged_work() {
+    let candidates = vec![
+        candidate("old", 100, Some(30), false),
+        candidate("old-unmerged", 100, Some(40), true),
+        candidate("recent", 300, Some(2), false),
+        candidate("newest", 300, Some(1), false),
+        candidate("running", 500, None, false),
+    ];
+
+    let by_age = RetentionPolicy { max_age_days: Some(7), max_bytes: None, keep_unmerged: true };
+    assert_eq!(
+        plan_retention(&by_age, &candidates, Utc::now()),
+        vec![("old".to_string(), RETENTION_REASON_AGE)]
+    );
+
+    let capped =
+        RetentionPolicy { max_age_days: Some(7), max_bytes: Some(1000), keep_unmerged: true };
+    assert_eq!(
+        plan_retention(&capped, &candidates, Utc::now()),
+        vec![
+            ("old".to_string(), RETENTION_REASON_AGE),
+            ("recent".to_string(), RETENTION_REASON_SIZE),
+        ],
+        "the cap removes the oldest finished worktrees first and never the running one"
+    );
+
+    let everything =
+        RetentionPolicy { max_age_days: Some(7), max_bytes: None, keep_unmerged: false };
+    assert_eq!(plan_retention(&everything, &candidates, Utc::now()).len(), 2);
+}
+
+#[test]
+fn retention_dry_run_reports_and_real_run_removes_merged_worktrees() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    db.save_repository_settings(&RepositorySettings {
+        repo_root: repo_root.clone(),
+        retention_max_age_days: Some(7),
+        ..Default::default()
+    })
+    .expect("settings should save");
+
+    let merged = finished_session(&db, &repo_root, "old-merged", 30);
+    let unmerged = finished_session(&db, &repo_root, "old-unmerged", 30);
+    std::fs::write(unmerged.join("feature.txt"), "not merged yet\n").expect("file should write");
+    git(&unmerged, &["add", "feature.txt"]);
+    git(&unmerged, &["commit", "-m", "unmerged work"]);
+    let recent = finished_session(&db, &repo_root, "recent", 1);
+
+    let preview = apply_worktree_retention(&db, true).expect("dry run should succeed");
+    assert!(preview.dry_run);
+    assert_eq!(preview.actions.len(), 1);
+    assert_eq!(preview.actions[0].session_id, "old-merged");
+    assert!(!preview.actions[0].removed);
+    assert_eq!(preview.kept_unmerged, vec!["old-unmerged".to_string()]);
+    assert!(merged.exists(), "a dry run must not remove anything");
+
+    let row = db
+        .get_dashboard_session("recent")
+        .expect("dashboard read should succeed")
+        .expect("row should exist");
+    assert!(row.worktree_bytes.is_some_and(|bytes| bytes > 0));
+
+    let report = apply_worktree_retention(&db, false).expect("retention should succeed");
+    assert!(report.actions[0].removed);
+    assert_eq!(report.freed_bytes, report.actions[0].bytes);
+    assert!(!merged.exists());
+    assert!(unmerged.exists());
+    assert!(recent.exists());
+
+    let pruned = db
+        .get_dashboard_session("old-merged")
+        .expect("dashboard read should succeed")
+        .expect("row should exist");
+    assert!(pruned.worktree_path.is_none());
+    assert!(pruned.worktree_pruned_at.is_some());
+    assert!(WorktreeService::new(&repo_root).branch_exists("lulu/old-merged"));
+}
+
+#[test]
+fn startup_retention_only_measures_worktrees_that_need_a_size() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    finished_session(&db, &repo_root, "measured", 30);
+    finished_session(&db, &repo_root, "unmeasured", 30);
+    db.update_worktree_bytes("measured", 1).expect("size should persist");
+    let bytes = |id: &str| {
+        db.get_dashboard_session(id)
+            .expect("dashboard read should succeed")
+            .expect("row should exist")
+            .worktree_bytes
+    };
+
+    let report = apply_startup_worktree_retention(&db).expect("retention should succeed");
+    assert!(report.actions.is_empty(), "no policy, nothing to remove");
+    assert_eq!(bytes("measured"), Some(1), "a stored size is kept without a policy");
+    assert!(bytes("unmeasured").is_some_and(|bytes| bytes > 1));
+
+    db.save_repository_settings(&RepositorySettings {
+        repo_root: repo_root.clone(),
+        retention_max_bytes: Some(i64::MAX),
+        ..Default::default()
+    })
+    .expect("settings should save");
+    apply_startup_worktree_retention(&db).expect("retention should succeed");
+    assert!(bytes("measured").is_some_and(|bytes| bytes > 1), "a policy needs fresh sizes");
+}
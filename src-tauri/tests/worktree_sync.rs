@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+
+use tauri_app_lib::db::{init_database, Database, Session};
+use tauri_app_lib::session::sync::{sync_session_worktree, SyncConflictPolicy, SyncStrategy};
+use tauri_app_lib::session::WorktreeService;
+
+mod common;
+
+use common::{git, init_repo};
+
+fn commit_file(dir: &Path, path: &str, contents: &str, message: &str) {
+    std::fs::write(dir.join(path), contents).expect("file should write");
+    git(dir, &["add", path]);
+    git(dir, &["commit", "-m", message]);
+}
+
+/// A session whose worktree branched off `main` and committed `session.txt`.
+fn session_on_main(db: &Database, repo_root: &str, id: &str) -> PathBuf {
+    let service = WorktreeService::new(repo_root);
+    let base_sha = service.resolve_commit("main").expect("main should resolve");
+    let worktree = service.create_worktree(id, &format!("lulu/{}", id)).expect("worktree");
+
+    let now = chrono::Utc::now().to_rfc3339();
+    db.create_session(&Session {
+        id: id.to_string(),
+        name: id.to_string(),
+        status: "completed".to_string(),
+        working_dir: repo_root.to_string(),
+        created_at: now.clone(),
+        updated_at: now,
+    })
+    .expect("session should persist");
+    db.update_worktree_path(id, Some(&worktree.display().to_string()))
+        .expect("worktree path should persist");
+    db.update_session_base(id, Some("main"), Some(&base_sha)).expect("base should persist");
+
+    commit_file(&worktree, "session.txt", "session work\n", "session work");
+    worktree
+}
+
+#[test]
+fn rebase_sync_replays_session_commits_on_the_moved_base() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    let worktree = session_on_main(&db, &repo_root, "sync-rebase");
+    commit_file(repo.path(), "upstream.txt", "from main\n", "upstream work");
+    let main_tip = git(repo.path(), &["rev-parse", "main"]);
+    std::fs::write(worktree.join("scratch.txt"), "uncommitted\n").expect("scratch should write");
+
+    let (outcome, events) =
+        sync_session_worktree(&db, "sync-rebase", SyncStrategy::Rebase, SyncConflictPolicy::Abort)
+            .expect("sync should succeed");
+
+    assert_eq!(outcome.status, "synced");
+    assert_eq!(outcome.base_sha, main_tip);
+    assert!(outcome.conflicts.is_empty() && outcome.resume_prompt.is_none());
+    assert!(worktree.join("upstream.txt").exists());
+    assert_eq!(git(&worktree, &["rev-parse", "HEAD~1"]), main_tip);
+    assert_eq!(
+        std::fs::read_to_string(worktree.join("scratch.txt")).expect("scratch should survive"),
+        "uncommitted\n"
+    );
+    assert_eq!(
+        db.get_session_base("sync-rebase").expect("base should load").expect("base").base_sha,
+        main_tip
+    );
+    assert_eq!(events.len(), 1);
+    let history = db.list_session_history("sync-rebase").expect("history should load");
+    assert_eq!(history.last().expect("sync event").event_type, "worktree_sync");
+    assert_eq!(history.last().expect("sync event").payload_json["data"]["status"], "synced");
+
+    let (again, _) =
+        sync_session_worktree(&db, "sync-rebase", SyncStrategy::Rebase, SyncConflictPolicy::Abort)
+            .expect("second sync should succeed");
+    assert_eq!(again.status, "up_to_date");
+}
+
+#[test]
+fn conflicting_sync_aborts_cleanly_by_default() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    let worktree = session_on_main(&db, &repo_root, "sync-abort");
+    commit_file(&worktree, "README.md", "# session\n", "session readme");
+    commit_file(repo.path(), "README.md", "# upstream\n", "upstream readme");
+    let head_before = git(&worktree, &["rev-parse", "HEAD"]);
+    let base_before = db.get_session_base("sync-abort").expect("base should load").expect("base");
+
+    let (outcome, _) =
+        sync_session_worktree(&db, "sync-abort", SyncStrategy::Rebase, SyncConflictPolicy::Abort)
+            .expect("sync should report the conflict");
+
+    assert_eq!(outcome.status, "aborted");
+    assert_eq!(outcome.conflicts, vec!["README.md".to_string()]);
+    assert!(outcome.resume_prompt.is_none());
+    assert_eq!(outcome.base_sha, base_before.base_sha);
+    assert_eq!(git(&worktree, &["rev-parse", "HEAD"]), head_before);
+    assert!(git(&worktree, &["status", "--porcelain"]).is_empty());
+    assert_eq!(
+        db.get_session_base("sync-abort").expect("base should load").expect("base"),
+        base_before
+    );
+}
+
+#[test]
+fn conflicting_merge_sync_can_be_left_for_the_next_run() {
+    let (repo, repo_root) = init_repo();
+    let db = init_database(&repo.path().join("lulu.db")).expect("database should initialize");
+    let worktree = session_on_main(&db, &repo_root, "sync-leave");
+    commit_file(&worktree, "README.md", "# session\n", "session readme");
+    commit_file(repo.path(), "README.md", "# upstream\n", "upstream readme");
+
+    let (outcome, _) =
+        sync_session_worktree(&db, "sync-leave", SyncStrategy::Merge, SyncConflictPolicy::Leave)
+            .expect("sync should report the conflict");
+
+    assert_eq!(outcome.status, "conflicted");
+    assert_eq!(outcome.conflicts, vec!["README.md".to_string()]);
+    let prompt = outcome.resume_prompt.expect("a resume prompt should be generated");
+    assert!(prompt.contains("- README.md"), "{}", prompt);
+    assert!(prompt.contains("git commit --no-edit"), "{}", prompt);
+    let readme = std::fs::read_to_string(worktree.join("README.md")).expect("readme should read");
+    assert!(readme.contains("<<<<<<<"), "conflict markers should be left in place: {}", readme);
+
+    let error =
+        sync_session_worktree(&db, "sync-leave", SyncStrategy::Merge, SyncConflictPolicy::Leave)
+            .expect_err("an unfinished merge must block another sync");
+    assert!(error.contains("unfinished"), "unexpected error: {}", error);
+    let base = db.get_session_base("sync-leave").expect("base should load").expect("base");
+    assert_eq!(base.base_sha, outcome.previous_base_sha, "the base moves only once merged");
+
+    std::fs::write(worktree.join("README.md"), "# session and upstream\n").expect("resolve");
+    git(&worktree, &["add", "README.md"]);
+    git(&worktree, &["commit", "--no-edit"]);
+    let (finished, _) =
+        sync_session_worktree(&db, "sync-leave", SyncStrategy::Merge, SyncConflictPolicy::Leave)
+            .expect("sync after the merge should succeed");
+    let main_tip = git(repo.path(), &["rev-parse", "main"]);
+    assert_eq!(finished.status, "up_to_date");
+    assert_eq!(finished.base_sha, main_tip);
+    let base = db.get_session_base("sync-leave").expect("base should load").expect("base");
+    assert_eq!(base.base_sha, main_tip);
+}
@@ -326,6 +326,7 @@ describe("session event isolation", () => {
     });
     expect(invokeMock).toHaveBeenCalledWith("delete_session", {
       id: "to-remove",
+      deleteBranch: false,
     });
     expect(readEvents()["to-remove"]).toBeUndefined();
 
@@ -57,6 +57,7 @@ describe("NewSessionModal", () => {
         "Design Review",
         "Summarize latest changes",
         "/tmp/project",
+        undefined,
       );
       expect(onClose).toHaveBeenCalledTimes(1);
     });
@@ -219,6 +220,7 @@ describe("NewSessionModal", () => {
         "Enter Submit",
         "Run from enter",
         "/tmp/enter",
+        undefined,
       );
       expect(onClose).toHaveBeenCalledTimes(1);
     });
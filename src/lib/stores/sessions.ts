@@ -22,6 +22,12 @@ export interface Session {
   restored?: boolean;
   restored_at?: string | null;
   recovery_hint?: boolean;
+  verification_status?: string | null;
+  branch_name?: string | null;
+  base_ref?: string | null;
+  base_sha?: string | null;
+  integration_summary?: string | null;
+  worktree_bytes?: number | null;
 }
 
 export const sessions = writable<Session[]>([]);
@@ -77,6 +83,12 @@ interface DashboardSessionProjection {
   restored?: boolean;
   restored_at?: string | null;
   recovery_hint?: boolean;
+  verification_status?: string | null;
+  branch_name?: string | null;
+  base_ref?: string | null;
+  base_sha?: string | null;
+  integration_summary?: string | null;
+  worktree_bytes?: number | null;
 }
 
 interface StoredSessionHistoryEvent {
@@ -365,6 +377,10 @@ export const dashboardRows = derived(
         createdAt: session.created_at,
         restored: session.restored ?? false,
         recoveryHint: (session.recovery_hint ?? false) && status === "Running",
+        verificationStatus: session.verification_status ?? undefined,
+        branchName: session.branch_name ?? undefined,
+        integrationSummary: session.integration_summary ?? undefined,
+        worktreeBytes: session.worktree_bytes ?? undefined,
       } satisfies DashboardSessionRow;
     }),
 );
@@ -758,6 +774,12 @@ export async function loadSessions() {
       restored: projection?.restored ?? false,
       restored_at: projection?.restored_at ?? null,
       recovery_hint: projection?.recovery_hint ?? false,
+      verification_status: projection?.verification_status ?? null,
+      branch_name: projection?.branch_name ?? null,
+      base_ref: projection?.base_ref ?? null,
+      base_sha: projection?.base_sha ?? null,
+      integration_summary: projection?.integration_summary ?? null,
+      worktree_bytes: projection?.worktree_bytes ?? null,
     };
   });
 
@@ -1035,6 +1057,7 @@ export async function spawnSession(
   name: string,
   prompt: string,
   workingDir: string,
+  baseRef?: string,
 ) {
   await initSessionListeners();
 
@@ -1050,6 +1073,7 @@ export async function spawnSession(
         prompt,
         workingDir,
         cliPathOverride: loadString("lulu:cli-path-override", "") || null,
+        baseRef: baseRef || null,
       },
       SPAWN_SESSION_TIMEOUT_MS,
     );
@@ -1137,12 +1161,16 @@ export async function spawnSession(
   return id;
 }
 
-export async function removeSession(sessionId: string, status: string) {
+export async function removeSession(
+  sessionId: string,
+  status: string,
+  deleteBranch = false,
+) {
   if (normalizeStatus(status) === "running") {
     await invoke("kill_session", { id: sessionId });
   }
 
-  await invoke("delete_session", { id: sessionId });
+  await invoke("delete_session", { id: sessionId, deleteBranch });
   setSessionOperation(sessionId, null);
   setSessionError(sessionId, null);
   removeSessionLocal(sessionId);
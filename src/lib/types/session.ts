@@ -91,4 +91,8 @@ export interface DashboardSessionRow {
   createdAt: string;
   restored: boolean;
   recoveryHint: boolean;
+  verificationStatus?: string;
+  branchName?: string;
+  integrationSummary?: string;
+  worktreeBytes?: number;
 }